@@ -1,37 +1,44 @@
-// Quick check if HelixDB is running and accessible
+// Quick check if HelixDB is running and serves the queries this build needs
 
-use arxiv_tui::config::HelixDbConfig;
-use arxiv_tui::db::client::DbClient;
+use arxiv_tui::config::Config;
+use arxiv_tui::db::health::{self, HealthStatus};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     println!("=== HelixDB Connection Check ===\n");
 
-    let config = HelixDbConfig {
-        endpoint: "http://localhost".to_string(),
-        port: 6969,
-        api_key: String::new(),
-    };
+    let config = Config::load()?;
+    println!(
+        "Attempting to connect to: {}:{}",
+        config.helixdb.endpoint, config.helixdb.port
+    );
 
-    println!("Attempting to connect to: {}:{}", config.endpoint, config.port);
+    let report = health::check(&config.helixdb).await;
+    println!("{}", report);
 
-    match DbClient::new(&config) {
-        Ok(_client) => {
-            println!("✓ Client initialized successfully");
+    match report.status {
+        HealthStatus::Healthy => {
             println!("\n✓ HelixDB is accessible!");
             println!("\nNext steps:");
-            println!("1. Load schema: cd helix && helix load schema.hx");
-            println!("2. Load queries: helix load queries.hx");
-            println!("3. Run integration: cargo run --example helix_integration");
-            Ok(())
+            println!("1. Ingest papers: cargo run --example ingest_papers");
+            println!("2. Run integration: cargo run --example helix_integration");
         }
-        Err(e) => {
-            println!("✗ Connection failed: {}", e);
+        HealthStatus::Degraded => {
+            println!("\nTroubleshooting:");
+            println!("1. Deploy the queries in helix/ (helix.toml points there)");
+            println!("2. Restart HelixDB so it picks up the new queries");
+        }
+        HealthStatus::Unreachable => {
             println!("\nTroubleshooting:");
             println!("1. Is HelixDB running? Start with: helix serve");
             println!("2. Check port: Default is 6969");
             println!("3. Check endpoint: Default is http://localhost");
-            Err(e)
         }
     }
+
+    if report.is_healthy() {
+        Ok(())
+    } else {
+        anyhow::bail!("HelixDB check failed")
+    }
 }
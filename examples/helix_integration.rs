@@ -4,9 +4,9 @@
 use anyhow::Result;
 use arxiv_tui::arxiv::client::ArxivClient;
 use arxiv_tui::db::client::DbClient;
-use arxiv_tui::embeddings::generator::EmbeddingGenerator;
-use arxiv_tui::config::{Config, HelixDbConfig, EmbeddingsConfig};
-use std::path::PathBuf;
+use arxiv_tui::db::store::PaperStore;
+use arxiv_tui::embeddings::backend;
+use arxiv_tui::config::{Config, HelixDbConfig};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -20,22 +20,13 @@ async fn main() -> Result<()> {
 
     let arxiv_client = ArxivClient::new(3000, 10);
 
-    let helix_config = HelixDbConfig {
-        endpoint: "http://localhost".to_string(),
-        port: 6969,
-        api_key: String::new(),
-    };
+    let helix_config = HelixDbConfig::default();
     let db_client = DbClient::new(&helix_config)?;
     println!("   ✓ Connected to HelixDB at {}:{}", helix_config.endpoint, helix_config.port);
 
-    let embeddings_config = EmbeddingsConfig {
-        model_path: PathBuf::from("./models/all-MiniLM-L6-v2.onnx"),
-        batch_size: 32,
-        device: "cpu".to_string(),
-        dimension: 384,
-    };
-    let embedding_gen = EmbeddingGenerator::new(&embeddings_config)?;
-    println!("   ✓ Embedding generator ready (placeholder mode)");
+    let config = Config::load()?;
+    let embeddings = backend::from_config_with_cache(&config.embeddings, &config.storage)?;
+    println!("   ✓ Embedding backend ready ({})", embeddings.model_name());
 
     // 2. Fetch papers from arXiv
     println!("\n2. Fetching papers from arXiv...");
@@ -50,11 +41,11 @@ async fn main() -> Result<()> {
 
         // Generate embedding from title + abstract
         let text = paper.text_for_embedding();
-        let embedding = embedding_gen.generate_embedding(&text)?;
+        let embedding = embeddings.embed(&text).await?;
         println!("     ✓ Generated {}-dimensional embedding", embedding.len());
 
         // Store paper vector in HelixDB
-        match db_client.add_paper(paper, embedding).await {
+        match db_client.add_paper(paper, embedding, &embeddings.model_info()).await {
             Ok(result) => {
                 println!("     ✓ Stored paper vector in HelixDB");
                 println!("       Response: {}", serde_json::to_string_pretty(&result)?);
@@ -82,13 +73,25 @@ async fn main() -> Result<()> {
     let search_text = "attention mechanism in deep learning";
     println!("   Query: \"{}\"", search_text);
 
-    let query_embedding = embedding_gen.generate_embedding(search_text)?;
+    let query_embedding = embeddings.embed(search_text).await?;
     println!("   ✓ Generated query embedding");
 
-    match db_client.search_papers(query_embedding, 0.5, 10).await {
+    match db_client.search_papers(query_embedding, &embeddings.model_info(), 0.5, 10).await {
         Ok(results) => {
             println!("\n   ✓ Semantic search results:");
-            println!("{}", serde_json::to_string_pretty(&results)?);
+            if results.is_empty() {
+                println!("No matches above the similarity threshold");
+            }
+            for (i, result) in results.iter().enumerate() {
+                println!(
+                    "{}. [{:.3}] {} ({})",
+                    i + 1,
+                    result.similarity_score,
+                    result.paper.title,
+                    result.paper.arxiv_id
+                );
+                println!("   {}", result.paper.authors);
+            }
         }
         Err(e) => {
             println!("\n   ✗ Search failed: {}", e);
@@ -1,13 +1,17 @@
-// Ingest papers from arXiv into HelixDB
+// Ingest papers from arXiv into the paper store (HelixDB or local)
 // This is a practical tool for building your database
 
 use anyhow::Result;
 use arxiv_tui::arxiv::client::ArxivClient;
 use arxiv_tui::arxiv::models::Category;
-use arxiv_tui::db::client::DbClient;
-use arxiv_tui::embeddings::generator::EmbeddingGenerator;
-use arxiv_tui::config::{HelixDbConfig, EmbeddingsConfig};
-use std::path::PathBuf;
+use arxiv_tui::db::models::UpsertReport;
+use arxiv_tui::db::similarity::SimilarityJob;
+use arxiv_tui::db::store;
+use arxiv_tui::embeddings::backend;
+use arxiv_tui::embeddings::chunking::DocumentEmbedder;
+use arxiv_tui::ingest::pipeline::IngestPipeline;
+use arxiv_tui::ingest::source::ArxivSource;
+use arxiv_tui::config::Config;
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -16,20 +20,12 @@ async fn main() -> Result<()> {
     println!("=== arXiv Paper Ingestion Tool ===\n");
 
     // Initialize clients
-    let arxiv_client = ArxivClient::new(3000, 50);
-
-    let db_client = DbClient::new(&HelixDbConfig {
-        endpoint: "http://localhost".to_string(),
-        port: 6969,
-        api_key: String::new(),
-    })?;
-
-    let embedding_gen = EmbeddingGenerator::new(&EmbeddingsConfig {
-        model_path: PathBuf::from("./models/all-MiniLM-L6-v2.onnx"),
-        batch_size: 32,
-        device: "cpu".to_string(),
-        dimension: 384,
-    })?;
+    let config = Config::load()?;
+    let arxiv_client = ArxivClient::from_config(&config.arxiv);
+    let store = store::open(&config).await?;
+    let embeddings = backend::from_config_with_cache(&config.embeddings, &config.storage)?;
+    let model = embeddings.model_info();
+    let embedder = DocumentEmbedder::new(embeddings, &config.embeddings.chunking);
 
     // Categories to ingest
     let categories = vec![
@@ -41,46 +37,54 @@ async fn main() -> Result<()> {
 
     println!("Ingesting latest papers from {} categories\n", categories.len());
 
-    let mut total_ingested = 0;
+    let mut report = UpsertReport::default();
+    let mut changed = Vec::new();
+    let mut failures = Vec::new();
     let papers_per_category = 10;
 
     for (name, category) in categories {
         println!("\n📚 Category: {} ({})", name, category.as_str());
         println!("Fetching {} latest papers...", papers_per_category);
 
-        let papers = arxiv_client
-            .get_latest(category, Some(papers_per_category))
+        // Fetch, embed and store in batches; papers already stored at the
+        // same version are skipped
+        let source = ArxivSource::latest(&arxiv_client, category.as_str());
+        let ingested = IngestPipeline::new(store.as_ref(), &embedder, &config.ingest)
+            .with_page_size(papers_per_category)
+            .with_max_papers(papers_per_category as usize)
+            .run(&source, &mut |progress| {
+                println!(
+                    "  Page {}: {} fetched, {} stored, {} failed",
+                    progress.pages, progress.fetched, progress.stored, progress.failed
+                );
+            })
             .await?;
 
-        println!("Found {} papers", papers.len());
-
-        for (i, paper) in papers.iter().enumerate() {
-            print!("  [{}/{}] Processing: {}... ", i + 1, papers.len(),
-                   paper.title.chars().take(50).collect::<String>());
-
-            // Generate embedding
-            let text = paper.text_for_embedding();
-            let embedding = embedding_gen.generate_embedding(&text)?;
+        println!("{}", ingested);
+        report.inserted += ingested.upserts.inserted;
+        report.updated += ingested.upserts.updated;
+        report.skipped += ingested.upserts.skipped;
+        report.failed += ingested.upserts.failed;
+        changed.extend(ingested.changed);
+        failures.extend(ingested.failures);
+    }
 
-            // Store in HelixDB
-            match db_client.add_paper(paper, embedding).await {
-                Ok(_) => {
-                    // Also store metadata
-                    let _ = db_client.add_paper_metadata(paper).await;
-                    println!("✓");
-                    total_ingested += 1;
-                }
-                Err(e) => {
-                    println!("✗ ({})", e);
-                }
+    // Link new and updated papers to their nearest neighbors
+    println!("\nLinking similar papers for {} papers...", changed.len());
+    let similarity = SimilarityJob::new(store.as_ref(), model, &config.search.similar)
+        .run(&changed, &mut |progress| {
+            if progress.processed % 10 == 0 || progress.processed == progress.total {
+                println!("  Linked {}/{}", progress.processed, progress.total);
             }
-        }
-
-        println!("  Completed {} papers", papers.len());
-    }
+        })
+        .await?;
 
     println!("\n=== Ingestion Complete ===");
-    println!("Total papers ingested: {}", total_ingested);
+    println!("{}", report);
+    println!("Similar papers: {}", similarity);
+    for failure in &failures {
+        println!("  ✗ {} ({}): {}", failure.arxiv_id, failure.stage, failure.error);
+    }
     println!("\nYou can now perform semantic searches!");
 
     Ok(())
@@ -0,0 +1,223 @@
+// Back up the paper library, move it between machines, refresh it from arXiv,
+// import the arXiv papers a BibTeX file cites or export citations
+//
+// Usage:
+//   cargo run --example library -- export library.jsonl
+//   cargo run --example library -- export library.jsonl --no-vectors
+//   cargo run --example library -- import library.jsonl
+//   cargo run --example library -- import library.jsonl --policy merge-tags
+//   cargo run --example library -- refresh
+//   cargo run --example library -- refresh --collection "Reading list"
+//   cargo run --example library -- import-bib references.bib --collection Thesis
+//   cargo run --example library -- cite collection "Reading list" references.bib
+//   cargo run --example library -- cite paper 1706.03762 attention.ris
+//   cargo run --example library -- cite search "diffusion models" notes.md
+//   cargo run --example library -- cite search "cat:cs.LG after:2024-01" refs.json --format csl-json
+//
+// The citation format is taken from --format, else the file extension, else
+// `export.format` in the config.
+
+use anyhow::{bail, Result};
+use arxiv_tui::arxiv::client::ArxivClient;
+use arxiv_tui::arxiv::models::ArxivId;
+use arxiv_tui::arxiv::query::Query;
+use arxiv_tui::config::{Config, ExportFormat};
+use arxiv_tui::db::snapshot::{ConflictPolicy, LibraryExport, LibraryImport};
+use arxiv_tui::db::store::{self, PaperStore};
+use arxiv_tui::embeddings::backend;
+use arxiv_tui::embeddings::chunking::DocumentEmbedder;
+use arxiv_tui::export::bibtex::parse_bibliography;
+use arxiv_tui::export::exporter::{self, load_papers, write_export};
+use arxiv_tui::ingest::by_id::{stored_ids, IdImport, IdImportReport};
+use std::path::Path;
+
+const USAGE: &str = "Usage: library [export <file> [--no-vectors] | \
+                     import <file> [--policy skip|overwrite|merge-tags] | \
+                     refresh [--collection <name>] | \
+                     import-bib <file.bib> [--collection <name>] | \
+                     cite collection|paper|search <name> <file> \
+                     [--format bibtex|ris|csl-json|markdown]]";
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let config = Config::load()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+
+    match args.as_slice() {
+        ["export", path, options @ ..] => {
+            let store = store::open(&config).await?;
+            let mut export = LibraryExport::new(store.as_ref());
+            match options {
+                [] => {}
+                ["--no-vectors"] => export = export.without_vectors(),
+                _ => bail!(USAGE),
+            }
+            let report = export.write_file(Path::new(path)).await?;
+            println!(
+                "Exported {} from {} to {}",
+                report,
+                store.backend_name(),
+                path
+            );
+        }
+        ["import", path, options @ ..] => {
+            let policy: ConflictPolicy = match options {
+                [] => ConflictPolicy::default(),
+                ["--policy", policy] => policy.parse()?,
+                _ => bail!(USAGE),
+            };
+            let store = store::open(&config).await?;
+            // Papers exported without vectors are embedded again
+            let embedder =
+                match backend::from_config_with_cache(&config.embeddings, &config.storage) {
+                    Ok(embeddings) => Some(DocumentEmbedder::new(
+                        embeddings,
+                        &config.embeddings.chunking,
+                    )),
+                    Err(e) => {
+                        println!(
+                            "No embedding model ({:#}); papers without vectors will fail",
+                            e
+                        );
+                        None
+                    }
+                };
+
+            let mut import = LibraryImport::new(store.as_ref(), policy);
+            if let Some(embedder) = &embedder {
+                import = import.with_embedder(embedder);
+            }
+            let report = import.read_file(Path::new(path)).await?;
+            println!(
+                "Imported {} into {} ({} policy)",
+                path,
+                store.backend_name(),
+                policy
+            );
+            println!("{}", report);
+            for (item, error) in &report.failed {
+                println!("  ✗ {}: {}", item, error);
+            }
+        }
+        ["refresh", options @ ..] => {
+            let collection = match options {
+                [] => None,
+                ["--collection", name] => Some(*name),
+                _ => bail!(USAGE),
+            };
+            let store = store::open(&config).await?;
+            let ids = stored_ids(store.as_ref(), collection).await?;
+            println!("Refreshing {} papers from arXiv", ids.len());
+            let report = import_ids(&config, store.as_ref(), &ids, None).await?;
+            print_id_report(&report);
+        }
+        ["import-bib", path, options @ ..] => {
+            let collection = match options {
+                [] => None,
+                ["--collection", name] => Some(*name),
+                _ => bail!(USAGE),
+            };
+            let entries = parse_bibliography(&std::fs::read_to_string(path)?);
+            let mut ids = Vec::new();
+            for entry in &entries {
+                match entry.arxiv_id() {
+                    Some(id) => ids.push(id),
+                    None => println!("  - {}: no arXiv ID", entry.key),
+                }
+            }
+            println!(
+                "{} of {} entries cite arXiv papers",
+                ids.len(),
+                entries.len()
+            );
+
+            let store = store::open(&config).await?;
+            let report = import_ids(&config, store.as_ref(), &ids, collection).await?;
+            print_id_report(&report);
+        }
+        ["cite", kind, name, path, options @ ..] => {
+            let format = match options {
+                [] => Path::new(path)
+                    .extension()
+                    .and_then(|extension| extension.to_str()?.parse().ok())
+                    .unwrap_or(config.export.format),
+                ["--format", format] => format.parse::<ExportFormat>()?,
+                _ => bail!(USAGE),
+            };
+            let exporter = exporter::exporter(format, &config.export)?;
+
+            let papers = match *kind {
+                "collection" => {
+                    let store = store::open(&config).await?;
+                    let entries = store.get_collection_papers(name).await?;
+                    let stored: Vec<_> = entries.into_iter().map(|entry| entry.paper).collect();
+                    load_papers(store.as_ref(), &stored).await?
+                }
+                "paper" => {
+                    // Stored papers are exported as stored, others fetched from arXiv
+                    let store = store::open(&config).await?;
+                    match store.get_paper_by_arxiv_id(name).await? {
+                        Some(paper) => load_papers(store.as_ref(), &[paper]).await?,
+                        None => {
+                            let arxiv = ArxivClient::from_config(&config.arxiv);
+                            vec![arxiv.get_by_id(name).await?]
+                        }
+                    }
+                }
+                "search" => {
+                    let arxiv = ArxivClient::from_config(&config.arxiv);
+                    let query: Query = name.parse()?;
+                    arxiv.search_query(&query, None).await?
+                }
+                _ => bail!(USAGE),
+            };
+
+            write_export(exporter.as_ref(), &papers, Path::new(path))?;
+            println!("Wrote {} papers as {} to {}", papers.len(), format, path);
+        }
+        _ => bail!(USAGE),
+    }
+
+    Ok(())
+}
+
+/// Fetches `ids` from arXiv and stores them, optionally into a collection.
+async fn import_ids(
+    config: &Config,
+    store: &dyn PaperStore,
+    ids: &[ArxivId],
+    collection: Option<&str>,
+) -> Result<IdImportReport> {
+    let arxiv = ArxivClient::from_config(&config.arxiv);
+    let embeddings = backend::from_config_with_cache(&config.embeddings, &config.storage)?;
+    let embedder = DocumentEmbedder::new(embeddings, &config.embeddings.chunking);
+
+    let mut import = IdImport::new(&arxiv, store, &embedder, &config.ingest);
+    if let Some(name) = collection {
+        import = import.into_collection(name);
+    }
+    import
+        .run(ids, &mut |progress| {
+            println!(
+                "  {} fetched, {} stored, {} failed",
+                progress.fetched, progress.stored, progress.failed
+            );
+        })
+        .await
+}
+
+fn print_id_report(report: &IdImportReport) {
+    println!("{}", report);
+    for id in &report.missing {
+        println!("  ✗ {}: not found on arXiv", id);
+    }
+    for failure in &report.ingest.failures {
+        println!(
+            "  ✗ {} ({}): {}",
+            failure.arxiv_id, failure.stage, failure.error
+        );
+    }
+}
@@ -0,0 +1,76 @@
+// Manage local embedding models
+//
+// Usage:
+//   cargo run --example models -- list
+//   cargo run --example models -- install all-MiniLM-L6-v2
+//   cargo run --example models -- install all-MiniLM-L6-v2 --allow-unverified
+//   cargo run --example models -- verify all-MiniLM-L6-v2
+//   cargo run --example models -- remove all-MiniLM-L6-v2
+
+use anyhow::{bail, Result};
+use arxiv_tui::config::Config;
+use arxiv_tui::embeddings::models::ModelManager;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let config = Config::load()?;
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let allow_unverified = args.iter().any(|arg| arg == "--allow-unverified");
+    args.retain(|arg| arg != "--allow-unverified");
+    let manager = ModelManager::from_config(&config.embeddings).allow_unverified(allow_unverified);
+
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] | ["list"] => {
+            println!("Models in {:?}:\n", manager.models_dir());
+            for status in manager.list()? {
+                let marker = match &status.installed {
+                    Some(_) if config.embeddings.model.as_deref() == Some(status.spec.name) => "*",
+                    Some(_) => "+",
+                    None => " ",
+                };
+                println!(
+                    " {} {:<20} {:>4} dims  {:>3} tokens  {}",
+                    marker,
+                    status.spec.name,
+                    status.spec.dimension,
+                    status.spec.max_seq_len,
+                    status.spec.repo
+                );
+            }
+            println!("\n* active  + installed");
+        }
+        ["install", name] => {
+            let installed = manager.install(name).await?;
+            println!(
+                "Installed {} ({} dimensions)",
+                installed.name, installed.dimension
+            );
+            for (file, sha256) in &installed.files {
+                println!("  {}  {}", sha256, file);
+            }
+        }
+        ["verify", name] => {
+            manager.verify(name)?;
+            println!("{} matches its recorded checksums", name);
+        }
+        ["remove", name] => {
+            if manager.remove(name)? {
+                println!("Removed {}", name);
+            } else {
+                println!("{} is not installed", name);
+            }
+        }
+        _ => bail!(
+            "Usage: models [list | install <name> [--allow-unverified] | verify <name> | remove <name>]"
+        ),
+    }
+
+    Ok(())
+}
@@ -0,0 +1,64 @@
+// Migrate stored paper vectors to the currently configured embedding model
+//
+// Safe to interrupt: rerunning continues with the papers that still carry
+// vectors from the old model.
+
+use anyhow::Result;
+use arxiv_tui::config::Config;
+use arxiv_tui::db::reembed::ReembedJob;
+use arxiv_tui::db::similarity::SimilarityJob;
+use arxiv_tui::db::store;
+use arxiv_tui::embeddings::backend;
+use arxiv_tui::embeddings::chunking::DocumentEmbedder;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    tracing_subscriber::fmt::init();
+
+    println!("=== Re-embed Stored Papers ===\n");
+
+    let config = Config::load()?;
+    let store = store::open(&config).await?;
+    let embeddings = backend::from_config_with_cache(&config.embeddings, &config.storage)?;
+    let embedder = DocumentEmbedder::new(embeddings, &config.embeddings.chunking);
+
+    let job = ReembedJob::new(store.as_ref(), &embedder);
+    let model = job.target_model();
+
+    let coverage = store.embedding_coverage(&model).await?;
+    println!("Target model: {}", model);
+    println!(
+        "{} of {} papers already use it, {} to migrate\n",
+        coverage.matching,
+        coverage.total,
+        coverage.stale()
+    );
+    if coverage.is_complete() {
+        println!("Nothing to do.");
+        return Ok(());
+    }
+
+    let report = job
+        .run(&mut |progress| {
+            println!(
+                "  Re-embedded {} papers ({} failed, {} remaining)",
+                progress.reembedded, progress.failed, progress.remaining
+            );
+        })
+        .await?;
+
+    println!("\n=== Re-embed Complete ===");
+    println!("Papers migrated: {}", report.reembedded);
+    for (arxiv_id, error) in &report.failed {
+        println!("  ✗ {}: {}", arxiv_id, error);
+    }
+
+    // Old similar-paper links compared vectors from the previous model
+    println!("\nRecomputing similar papers...");
+    let similarity = SimilarityJob::new(store.as_ref(), model, &config.search.similar)
+        .run_all(&mut |_| {})
+        .await?;
+    println!("Similar papers: {}", similarity);
+
+    Ok(())
+}
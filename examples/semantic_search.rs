@@ -1,10 +1,9 @@
-// Semantic search example using HelixDB
+// Semantic search example using the configured paper store
 
 use anyhow::Result;
-use arxiv_tui::db::client::DbClient;
-use arxiv_tui::embeddings::generator::EmbeddingGenerator;
-use arxiv_tui::config::{HelixDbConfig, EmbeddingsConfig};
-use std::path::PathBuf;
+use arxiv_tui::db::store;
+use arxiv_tui::embeddings::backend;
+use arxiv_tui::config::Config;
 use std::io::{self, Write};
 
 #[tokio::main]
@@ -14,20 +13,11 @@ async fn main() -> Result<()> {
     println!("=== Semantic Paper Search ===\n");
 
     // Initialize
-    let db_client = DbClient::new(&HelixDbConfig {
-        endpoint: "http://localhost".to_string(),
-        port: 6969,
-        api_key: String::new(),
-    })?;
-
-    let embedding_gen = EmbeddingGenerator::new(&EmbeddingsConfig {
-        model_path: PathBuf::from("./models/all-MiniLM-L6-v2.onnx"),
-        batch_size: 32,
-        device: "cpu".to_string(),
-        dimension: 384,
-    })?;
-
-    println!("Connected to HelixDB");
+    let config = Config::load()?;
+    let store = store::open(&config).await?;
+    let embeddings = backend::from_config_with_cache(&config.embeddings, &config.storage)?;
+
+    println!("Connected to {} store", store.backend_name());
     println!("Ready for semantic search!\n");
 
     // Example queries
@@ -77,14 +67,26 @@ async fn main() -> Result<()> {
         println!("\nSearching for: \"{}\"", query);
 
         // Generate query embedding
-        let embedding = embedding_gen.generate_embedding(query)?;
+        let embedding = embeddings.embed(query).await?;
         println!("Generated embedding ({} dimensions)", embedding.len());
 
-        // Search HelixDB
-        match db_client.search_papers(embedding, 0.5, 10).await {
+        // Search the paper store
+        match store.search_papers(embedding, &embeddings.model_info(), 0.5, 10).await {
             Ok(results) => {
                 println!("\n📄 Search Results:");
-                println!("{}\n", serde_json::to_string_pretty(&results)?);
+                if results.is_empty() {
+                    println!("No matches above the similarity threshold");
+                }
+                for (i, result) in results.iter().enumerate() {
+                    println!(
+                        "{}. [{:.3}] {} ({})",
+                        i + 1,
+                        result.similarity_score,
+                        result.paper.title,
+                        result.paper.arxiv_id
+                    );
+                    println!("   {}", result.paper.authors);
+                }
             }
             Err(e) => {
                 println!("\n❌ Search failed: {}", e);
@@ -6,24 +6,92 @@
 // Add a new paper with embedding
 QUERY AddPaper (
     arxiv_id: String,
+    version: U32,
     title: String,
     authors: String,
     published: String,
+    updated: String,
     categories: String,
     abstract_preview: String,
-    embedding: Vec<F32>
+    embedding: Vec<F32>,
+    embedding_model: String,
+    embedding_version: String,
+    embedding_dim: U32
 ) =>
     paper <- AddV<Paper>({
         arxiv_id: arxiv_id,
+        version: version,
         title: title,
         authors: authors,
         published: published,
+        updated: updated,
         categories: categories,
         abstract_preview: abstract_preview,
-        embedding: embedding
+        embedding: embedding,
+        embedding_model: embedding_model,
+        embedding_version: embedding_version,
+        embedding_dim: embedding_dim
     })
     RETURN paper
 
+// Overwrite a stored paper with a newer arXiv version
+QUERY UpdatePaper (
+    arxiv_id: String,
+    version: U32,
+    title: String,
+    authors: String,
+    published: String,
+    updated: String,
+    categories: String,
+    abstract_preview: String,
+    embedding: Vec<F32>,
+    embedding_model: String,
+    embedding_version: String,
+    embedding_dim: U32
+) =>
+    paper <- MATCH (v:Paper)
+    WHERE v.arxiv_id = arxiv_id
+    SET v.version = version,
+        v.title = title,
+        v.authors = authors,
+        v.published = published,
+        v.updated = updated,
+        v.categories = categories,
+        v.abstract_preview = abstract_preview,
+        v.embedding = embedding,
+        v.embedding_model = embedding_model,
+        v.embedding_version = embedding_version,
+        v.embedding_dim = embedding_dim
+    RETURN v
+
+// Add one chunk vector of a paper's abstract
+QUERY AddPaperChunk (
+    arxiv_id: String,
+    chunk_index: U32,
+    text: String,
+    embedding: Vec<F32>,
+    embedding_model: String,
+    embedding_version: String,
+    embedding_dim: U32
+) =>
+    chunk <- AddV<PaperChunk>({
+        arxiv_id: arxiv_id,
+        chunk_index: chunk_index,
+        text: text,
+        embedding: embedding,
+        embedding_model: embedding_model,
+        embedding_version: embedding_version,
+        embedding_dim: embedding_dim
+    })
+    RETURN chunk
+
+// Drop all chunk vectors of a paper, before storing new ones
+QUERY DeletePaperChunks (arxiv_id: String) =>
+    chunks <- MATCH (c:PaperChunk)
+    WHERE c.arxiv_id = arxiv_id
+    DELETE c
+    RETURN chunks
+
 // Add full metadata for a paper
 QUERY AddPaperMetadata (
     arxiv_id: String,
@@ -43,11 +111,32 @@ QUERY AddPaperMetadata (
         pdf_url: pdf_url,
         saved: false,
         read: false,
+        read_state: "unread",
+        reading_started_at: "",
+        read_at: "",
         tags: "",
         created_at: created_at
     })
     RETURN metadata
 
+// Refresh the arXiv-provided metadata fields, keeping saved/read/tags
+QUERY UpdatePaperMetadata (
+    arxiv_id: String,
+    abstract: String,
+    comment: String,
+    journal_ref: String,
+    doi: String,
+    pdf_url: String
+) =>
+    metadata <- MATCH (m:PaperMetadata)
+    WHERE m.arxiv_id = arxiv_id
+    SET m.abstract = abstract,
+        m.comment = comment,
+        m.journal_ref = journal_ref,
+        m.doi = doi,
+        m.pdf_url = pdf_url
+    RETURN m
+
 // Link paper vector to its metadata
 QUERY LinkPaperMetadata (paper_id: String, metadata_id: String) =>
     edge <- AddE<HasMetadata>({
@@ -59,10 +148,34 @@ QUERY LinkPaperMetadata (paper_id: String, metadata_id: String) =>
 
 // ===== Search Operations =====
 
-// Semantic search for papers by embedding similarity
-QUERY SearchPapers (query_embedding: Vec<F32>, min_similarity: F32, limit: U32) =>
+// Semantic search for papers by embedding similarity, restricted to vectors
+// produced by the same model as the query
+QUERY SearchPapers (
+    query_embedding: Vec<F32>,
+    embedding_model: String,
+    embedding_version: String,
+    min_similarity: F32,
+    limit: U32
+) =>
     results <- SEARCH V::Paper
-    WHERE SIMILARITY(embedding, query_embedding) > min_similarity
+    WHERE embedding_model = embedding_model
+        AND embedding_version = embedding_version
+        AND SIMILARITY(embedding, query_embedding) > min_similarity
+    LIMIT limit
+    RETURN results
+
+// Semantic search over individual abstract chunks
+QUERY SearchPaperChunks (
+    query_embedding: Vec<F32>,
+    embedding_model: String,
+    embedding_version: String,
+    min_similarity: F32,
+    limit: U32
+) =>
+    results <- SEARCH V::PaperChunk
+    WHERE embedding_model = embedding_model
+        AND embedding_version = embedding_version
+        AND SIMILARITY(embedding, query_embedding) > min_similarity
     LIMIT limit
     RETURN results
 
@@ -78,34 +191,90 @@ QUERY GetPaperWithMetadata (arxiv_id: String) =>
     WHERE v.arxiv_id = arxiv_id
     RETURN v, m
 
+// ===== Embedding Migrations =====
+
+// Count paper vectors produced by a given model
+QUERY CountPapersWithModel (embedding_model: String, embedding_version: String) =>
+    count <- MATCH (p:Paper)
+    WHERE p.embedding_model = embedding_model AND p.embedding_version = embedding_version
+    RETURN COUNT(p)
+
+// Page through papers whose vectors came from a different model
+QUERY ListStalePapers (
+    embedding_model: String,
+    embedding_version: String,
+    offset: U32,
+    limit: U32
+) =>
+    result <- MATCH (v:Paper)-[:HasMetadata]->(m:PaperMetadata)
+    WHERE v.embedding_model != embedding_model OR v.embedding_version != embedding_version
+    ORDER BY v.arxiv_id ASC
+    SKIP offset
+    LIMIT limit
+    RETURN v, m
+
+// Swap a paper's vector for one from another model, keeping its edges
+QUERY ReplacePaperEmbedding (
+    arxiv_id: String,
+    embedding: Vec<F32>,
+    embedding_model: String,
+    embedding_version: String,
+    embedding_dim: U32
+) =>
+    paper <- MATCH (v:Paper)
+    WHERE v.arxiv_id = arxiv_id
+    SET v.embedding = embedding,
+        v.embedding_model = embedding_model,
+        v.embedding_version = embedding_version,
+        v.embedding_dim = embedding_dim
+    RETURN v
+
 // ===== Similar Papers =====
 
-// Find similar papers (via pre-computed similarity edges)
+// Get precomputed similar papers, most similar first
 QUERY FindSimilarPapers (arxiv_id: String, limit: U32) =>
-    similar <- MATCH (p1:Paper)-[:SimilarTo]->(p2:Paper)
+    similar <- MATCH (p1:Paper)-[e:SimilarTo]->(p2:Paper)
     WHERE p1.arxiv_id = arxiv_id
-    ORDER BY SimilarTo.similarity_score DESC
+    ORDER BY e.similarity_score DESC
     LIMIT limit
     RETURN p2
 
+// Get precomputed similar papers with their scores
+QUERY GetSimilarities (arxiv_id: String) =>
+    similar <- MATCH (p1:Paper)-[e:SimilarTo]->(p2:Paper)
+    WHERE p1.arxiv_id = arxiv_id
+    ORDER BY e.similarity_score DESC
+    RETURN p2, e
+
 // Create similarity edge between two papers
-QUERY AddSimilarity (from_id: String, to_id: String, score: F32) =>
-    edge <- AddE<SimilarTo>({
-        from: from_id,
-        to: to_id,
-        properties: {
-            similarity_score: score
-        }
-    })
+QUERY AddSimilarity (from_arxiv_id: String, to_arxiv_id: String, score: F32) =>
+    edge <- MATCH (p1:Paper), (p2:Paper)
+    WHERE p1.arxiv_id = from_arxiv_id AND p2.arxiv_id = to_arxiv_id
+    CREATE (p1)-[:SimilarTo {similarity_score: score}]->(p2)
     RETURN edge
 
+// Remove all similarity edges leaving a paper
+QUERY DeleteSimilarities (arxiv_id: String) =>
+    result <- MATCH (p1:Paper)-[e:SimilarTo]->(p2:Paper)
+    WHERE p1.arxiv_id = arxiv_id
+    DELETE e
+    RETURN result
+
+// Get the stored vector of a paper
+QUERY GetPaperVector (arxiv_id: String) =>
+    paper <- MATCH (v:Paper)
+    WHERE v.arxiv_id = arxiv_id
+    RETURN v
+
 // ===== Collections =====
 
 // Create a new collection
-QUERY CreateCollection (name: String, description: String, created_at: String) =>
+QUERY CreateCollection (name: String, description: String, parent: String, filter: String, created_at: String) =>
     collection <- AddN<Collection>({
         name: name,
         description: description,
+        parent: parent,
+        filter: filter,
         created_at: created_at
     })
     RETURN collection
@@ -115,30 +284,62 @@ QUERY GetAllCollections () =>
     collections <- MATCH (c:Collection)
     RETURN c
 
-// Get papers in a collection
+// Get a collection by name
+QUERY GetCollection (name: String) =>
+    collection <- MATCH (c:Collection)
+    WHERE c.name = name
+    RETURN c
+
+// Rename a collection
+QUERY RenameCollection (name: String, new_name: String) =>
+    collection <- MATCH (c:Collection)
+    WHERE c.name = name
+    SET c.name = new_name
+    RETURN c
+
+// Move every child of one collection under another
+QUERY ReparentCollections (parent: String, new_parent: String) =>
+    children <- MATCH (c:Collection)
+    WHERE c.parent = parent
+    SET c.parent = new_parent
+    RETURN c
+
+// Delete a collection and its membership edges; papers are kept
+QUERY DeleteCollection (name: String) =>
+    result <- MATCH (c:Collection)
+    WHERE c.name = name
+    DETACH DELETE c
+    RETURN result
+
+// Get papers in a collection with the time each was added
 QUERY GetCollectionPapers (collection_name: String) =>
-    papers <- MATCH (p:Paper)-[:InCollection]->(c:Collection)
+    papers <- MATCH (p:Paper)-[e:InCollection]->(c:Collection)
     WHERE c.name = collection_name
-    RETURN p
+    ORDER BY e.added_at
+    RETURN p, e
 
 // Add paper to collection
-QUERY AddPaperToCollection (paper_id: String, collection_id: String, added_at: String) =>
-    edge <- AddE<InCollection>({
-        from: paper_id,
-        to: collection_id,
-        properties: {
-            added_at: added_at
-        }
-    })
+QUERY AddPaperToCollection (arxiv_id: String, collection_name: String, added_at: String) =>
+    edge <- MATCH (p:Paper), (c:Collection)
+    WHERE p.arxiv_id = arxiv_id AND c.name = collection_name
+    CREATE (p)-[:InCollection {added_at: added_at}]->(c)
     RETURN edge
 
 // Remove paper from collection
-QUERY RemovePaperFromCollection (paper_id: String, collection_id: String) =>
+QUERY RemovePaperFromCollection (arxiv_id: String, collection_name: String) =>
     result <- MATCH (p:Paper)-[e:InCollection]->(c:Collection)
-    WHERE p.id = paper_id AND c.id = collection_id
+    WHERE p.arxiv_id = arxiv_id AND c.name = collection_name
     DELETE e
     RETURN result
 
+// Page through all papers with metadata, for evaluating smart collections
+QUERY ListPapers (offset: U32, limit: U32) =>
+    papers <- MATCH (v:Paper)-[:HasMetadata]->(m:PaperMetadata)
+    ORDER BY v.arxiv_id
+    SKIP offset
+    LIMIT limit
+    RETURN v, m
+
 // ===== Paper Status =====
 
 // Mark paper as saved
@@ -155,6 +356,28 @@ QUERY MarkPaperRead (arxiv_id: String, read: Bool) =>
     SET m.read = read
     RETURN m
 
+// Move paper between unread, reading and read
+QUERY SetReadState (
+    arxiv_id: String,
+    read: Bool,
+    read_state: String,
+    reading_started_at: String,
+    read_at: String
+) =>
+    result <- MATCH (m:PaperMetadata)
+    WHERE m.arxiv_id = arxiv_id
+    SET m.read = read,
+        m.read_state = read_state,
+        m.reading_started_at = reading_started_at,
+        m.read_at = read_at
+    RETURN m
+
+// Get the metadata of a paper
+QUERY GetPaperMetadata (arxiv_id: String) =>
+    result <- MATCH (m:PaperMetadata)
+    WHERE m.arxiv_id = arxiv_id
+    RETURN m
+
 // Update paper tags
 QUERY UpdatePaperTags (arxiv_id: String, tags: String) =>
     result <- MATCH (m:PaperMetadata)
@@ -171,17 +394,31 @@ QUERY AddCitation (from_arxiv_id: String, to_arxiv_id: String, context: String)
     CREATE (p1)-[:Cites {context: context}]->(p2)
     RETURN edge
 
-// Get papers cited by a paper
+// Update the context of an existing citation
+QUERY UpdateCitation (from_arxiv_id: String, to_arxiv_id: String, context: String) =>
+    edge <- MATCH (p1:Paper)-[e:Cites]->(p2:Paper)
+    WHERE p1.arxiv_id = from_arxiv_id AND p2.arxiv_id = to_arxiv_id
+    SET e.context = context
+    RETURN e
+
+// Remove all citations made by a paper
+QUERY DeleteCitations (arxiv_id: String) =>
+    result <- MATCH (p1:Paper)-[e:Cites]->(p2:Paper)
+    WHERE p1.arxiv_id = arxiv_id
+    DELETE e
+    RETURN result
+
+// Get papers cited by a paper, with the citing context
 QUERY GetCitedPapers (arxiv_id: String) =>
-    cited <- MATCH (p1:Paper)-[:Cites]->(p2:Paper)
+    cited <- MATCH (p1:Paper)-[e:Cites]->(p2:Paper)
     WHERE p1.arxiv_id = arxiv_id
-    RETURN p2
+    RETURN p2, e
 
-// Get papers that cite a paper
+// Get papers that cite a paper, with the citing context
 QUERY GetCitingPapers (arxiv_id: String) =>
-    citing <- MATCH (p1:Paper)-[:Cites]->(p2:Paper)
+    citing <- MATCH (p1:Paper)-[e:Cites]->(p2:Paper)
     WHERE p2.arxiv_id = arxiv_id
-    RETURN p1
+    RETURN p1, e
 
 // ===== Statistics =====
 
@@ -195,3 +432,19 @@ QUERY CountSavedPapers () =>
     count <- MATCH (m:PaperMetadata)
     WHERE m.saved = true
     RETURN COUNT(m)
+
+// ===== Schema =====
+
+// Get every applied schema version
+QUERY GetSchemaVersion () =>
+    versions <- MATCH (s:SchemaInfo)
+    RETURN s
+
+// Record an applied schema version
+QUERY SetSchemaVersion (version: U32, description: String, applied_at: String) =>
+    info <- AddN<SchemaInfo>({
+        version: version,
+        description: description,
+        applied_at: applied_at
+    })
+    RETURN info
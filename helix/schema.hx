@@ -1,14 +1,35 @@
 // HelixDB Schema for arXiv TUI
 // Defines the data model for papers, metadata, and relationships
+//
+// Schema version: 3
+// Bump together with SCHEMA_VERSION in src/db/schema.rs and add a
+// migration in src/db/migrations.rs when changing stored data.
 
 // Paper vector - stores embeddings for semantic search
 V::Paper {
+    // arXiv ID without version suffix; unique
     arxiv_id: String,
+    version: U32,
     title: String,
     authors: String,
     published: String,
+    updated: String,
     categories: String,
-    abstract_preview: String
+    abstract_preview: String,
+    // Provenance of the embedding; vectors from different models are not comparable
+    embedding_model: String,
+    embedding_version: String,
+    embedding_dim: U32
+}
+
+// Passage of a long abstract - one vector per chunk for passage-level search
+V::PaperChunk {
+    arxiv_id: String,
+    chunk_index: U32,
+    text: String,
+    embedding_model: String,
+    embedding_version: String,
+    embedding_dim: U32
 }
 
 // Full paper metadata node
@@ -21,6 +42,11 @@ N::PaperMetadata {
     pdf_url: String,
     saved: Bool,
     read: Bool,
+    // unread, reading or read
+    read_state: String,
+    reading_started_at: String,
+    read_at: String,
+    // Comma-separated normalized tags
     tags: String,
     created_at: String
 }
@@ -29,6 +55,10 @@ N::PaperMetadata {
 N::Collection {
     name: String,
     description: String,
+    // Name of the enclosing collection, empty for top-level collections
+    parent: String,
+    // JSON smart-collection filter, empty for manual collections
+    filter: String,
     created_at: String
 }
 
@@ -65,3 +95,10 @@ E::Cites {
         context: String
     }
 }
+
+// Applied schema versions, one node per migration
+N::SchemaInfo {
+    version: U32,
+    description: String,
+    applied_at: String
+}
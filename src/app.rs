@@ -6,38 +6,60 @@ use crossterm::{
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
 use std::io;
+use std::sync::Arc;
 use std::time::Duration;
 
+use crate::arxiv::client::ArxivClient;
+use crate::arxiv::pager::SearchPager;
+use crate::arxiv::query::Query;
 use crate::config::Config;
-use crate::core::state::AppState;
-use crate::db::client::DbClient;
-use crate::embeddings::generator::EmbeddingGenerator;
+use crate::core::state::{AppState, View};
+use crate::db::store::{self, PaperStore};
+use crate::embeddings::backend;
+use crate::export::exporter::{self, export_path, write_export};
 use crate::ui::app::Ui;
 
 pub struct App {
     config: Config,
     state: AppState,
-    db_client: DbClient,
-    embedding_gen: EmbeddingGenerator,
+    store: Arc<dyn PaperStore>,
+    arxiv: ArxivClient,
     should_quit: bool,
 }
 
 impl App {
     pub async fn new(config: Config) -> Result<Self> {
-        // Initialize database client
-        let db_client = DbClient::new(&config.helixdb)?;
+        // Open HelixDB, or the local store when it is unavailable
+        let store = store::open(&config).await?;
 
-        // Initialize embedding generator
-        let embedding_gen = EmbeddingGenerator::new(&config.embeddings)?;
+        // Initialize embedding backend
+        let embeddings = backend::from_config_with_cache(&config.embeddings, &config.storage)?;
+
+        // Vectors from another model are excluded from search until migrated
+        let model = embeddings.model_info();
+        match store.embedding_coverage(&model).await {
+            Ok(coverage) if !coverage.is_complete() => tracing::warn!(
+                "{} of {} stored papers were embedded with a model other than {}; \
+                 run `cargo run --example reembed` to migrate them",
+                coverage.stale(),
+                coverage.total,
+                model
+            ),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Could not check stored embedding models: {}", e),
+        }
+
+        let arxiv = ArxivClient::from_config(&config.arxiv);
 
         // Initialize application state
-        let state = AppState::new();
+        let mut state = AppState::new();
+        state.export_format = config.export.format;
 
         Ok(Self {
             config,
             state,
-            db_client,
-            embedding_gen,
+            store,
+            arxiv,
             should_quit: false,
         })
     }
@@ -63,7 +85,7 @@ impl App {
             if event::poll(tick_rate)? {
                 if let Event::Key(key) = event::read()? {
                     match key.code {
-                        KeyCode::Char('q') => {
+                        KeyCode::Char('q') if !self.state.search_editing => {
                             self.should_quit = true;
                         }
                         _ => {
@@ -89,11 +111,108 @@ impl App {
         )?;
         terminal.show_cursor()?;
 
+        // Save any library writes still buffered by the store
+        self.store.flush().await?;
+
         Ok(())
     }
 
-    async fn handle_key_event(&mut self, _key: KeyCode) -> Result<()> {
-        // TODO: Implement key event handling
+    async fn handle_key_event(&mut self, key: KeyCode) -> Result<()> {
+        if self.state.search_editing {
+            match key {
+                KeyCode::Char(c) => self.state.search_query.push(c),
+                KeyCode::Backspace => {
+                    self.state.search_query.pop();
+                }
+                KeyCode::Esc => self.state.search_editing = false,
+                KeyCode::Enter => {
+                    self.state.search_editing = false;
+                    self.run_search().await;
+                }
+                _ => {}
+            }
+            return Ok(());
+        }
+
+        match key {
+            KeyCode::Char('/') => {
+                self.state.switch_view(View::Search);
+                self.state.search_editing = true;
+                self.state.status_message = Some(
+                    "Search: words, \"phrases\", au: ti: abs: cat:, after:YYYY-MM, OR, -word"
+                        .to_string(),
+                );
+            }
+            KeyCode::Char('j') | KeyCode::Down => self.state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => self.state.select_previous(),
+            KeyCode::Char('x') => {
+                self.state.export_format = self.state.export_format.next();
+                self.state.status_message =
+                    Some(format!("Export format: {}", self.state.export_format));
+            }
+            KeyCode::Char('e') => {
+                self.state.status_message = Some(match self.export_current() {
+                    Ok(Some(message)) => message,
+                    Ok(None) => "Nothing to export".to_string(),
+                    Err(e) => format!("Export failed: {:#}", e),
+                });
+            }
+            // TODO: Implement remaining key event handling
+            _ => {}
+        }
         Ok(())
     }
+
+    /// Parses the search bar and fetches matching papers from arXiv.
+    async fn run_search(&mut self) {
+        let query: Query = match self.state.search_query.parse() {
+            Ok(query) => query,
+            Err(e) => {
+                self.state.status_message = Some(format!("{:#}", e));
+                return;
+            }
+        };
+
+        // First page only, with the total number of matches
+        let mut pager = SearchPager::new(&self.arxiv, &query.to_string())
+            .with_page_size(self.config.arxiv.max_results);
+        let result = pager.next_page().await;
+        let total = pager.total_results().unwrap_or(0);
+
+        self.state.status_message = Some(match result {
+            Ok(page) => {
+                let papers = page.map(|page| page.papers).unwrap_or_default();
+                let message = format!("{} of {} papers for {}", papers.len(), total, query);
+                self.state.search_results = papers;
+                self.state.selected_index = 0;
+                self.state.selected_paper = None;
+                message
+            }
+            Err(e) => format!("Search failed: {:#}", e),
+        });
+    }
+
+    /// Exports the selected paper, or all search results when none is
+    /// selected, to the export directory.
+    fn export_current(&self) -> Result<Option<String>> {
+        let format = self.state.export_format;
+        let (papers, name) = match &self.state.selected_paper {
+            Some(paper) => (vec![paper.clone()], paper.base_id().to_string()),
+            None if !self.state.search_results.is_empty() => (
+                self.state.search_results.clone(),
+                format!("search {}", self.state.search_query),
+            ),
+            None => return Ok(None),
+        };
+
+        let exporter = exporter::exporter(format, &self.config.export)?;
+        let path = export_path(&self.config.export.dir, &name, format);
+        write_export(exporter.as_ref(), &papers, &path)?;
+        Ok(Some(format!(
+            "Exported {} papers as {} to {}",
+            papers.len(),
+            format,
+            path.display()
+        )))
+    }
 }
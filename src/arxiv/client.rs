@@ -1,101 +1,343 @@
 use anyhow::Result;
-use reqwest::Client;
+use reqwest::header::RETRY_AFTER;
+use reqwest::{Client, StatusCode};
+use std::collections::{HashMap, HashSet};
 use std::time::Duration;
 
-use super::models::{Category, Paper};
-use super::parser::parse_arxiv_response;
+use super::error::{backoff, parse_retry_after, ArxivError};
+use super::models::{ArxivId, Category, Paper};
+use super::pager::SearchPager;
+use super::parser::{parse_feed, FeedPage};
+use super::query::Query;
+use super::rate_limit::{Budget, RateLimiter};
+use crate::config::ArxivConfig;
 
+/// IDs per `id_list` request, well below the length arXiv accepts in a URL
+pub const ID_LIST_CHUNK: usize = 100;
+
+/// Papers fetched by [`ArxivClient::get_by_ids`].
+#[derive(Debug, Default)]
+pub struct FetchedPapers {
+    pub papers: Vec<Paper>,
+    pub missing: Vec<ArxivId>,
+}
+
+const API_URL: &str = "http://export.arxiv.org/api/query";
+
+/// Process-wide limiters, so every client stays within arXiv's policy
+const API_LIMITER: &str = "arxiv-api";
+const PDF_LIMITER: &str = "arxiv-pdf";
+
+#[derive(Clone)]
 pub struct ArxivClient {
     client: Client,
-    rate_limit_delay: Duration,
+    api_url: String,
+    api_limiter: RateLimiter,
+    pdf_limiter: RateLimiter,
     max_results: u32,
+    max_retries: u32,
+    retry_backoff: Duration,
+    max_retry_after: Duration,
 }
 
 impl ArxivClient {
+    /// API calls and PDF downloads each get one request per
+    /// `rate_limit_delay_ms`, shared with every other client. Timeouts and
+    /// retries use the config defaults.
     pub fn new(rate_limit_delay_ms: u64, max_results: u32) -> Self {
-        Self {
-            client: Client::new(),
-            rate_limit_delay: Duration::from_millis(rate_limit_delay_ms),
+        Self::from_config(&ArxivConfig {
+            rate_limit_delay_ms,
+            pdf_rate_limit_delay_ms: rate_limit_delay_ms,
             max_results,
+            ..ArxivConfig::default()
+        })
+    }
+
+    /// A client with the budgets, timeouts and retries from the config.
+    pub fn from_config(config: &ArxivConfig) -> Self {
+        let api = Budget::every(Duration::from_millis(config.rate_limit_delay_ms))
+            .with_burst(config.rate_limit_burst);
+        let pdf = Budget::every(Duration::from_millis(config.pdf_rate_limit_delay_ms))
+            .with_burst(config.pdf_rate_limit_burst);
+        // Like `Client::new`, only fails when no TLS backend is available
+        let client = Client::builder()
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+            .build()
+            .expect("Failed to build HTTP client");
+
+        Self {
+            client,
+            api_url: API_URL.to_string(),
+            api_limiter: RateLimiter::shared(API_LIMITER, api),
+            pdf_limiter: RateLimiter::shared(PDF_LIMITER, pdf),
+            max_results: config.max_results,
+            max_retries: config.max_retries,
+            retry_backoff: Duration::from_millis(config.retry_backoff_ms),
+            max_retry_after: Duration::from_millis(config.max_retry_after_ms),
         }
     }
 
-    pub async fn search(&self, query: &str, max_results: Option<u32>) -> Result<Vec<Paper>> {
-        let max_results = max_results.unwrap_or(self.max_results);
+    /// Sends API queries to a mirror or test server instead of arXiv.
+    pub fn with_api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.to_string();
+        self
+    }
 
-        let url = format!(
-            "http://export.arxiv.org/api/query?search_query={}&max_results={}",
-            urlencoding::encode(query),
-            max_results
-        );
+    /// Uses the given limiters instead of the process-wide ones.
+    pub fn with_rate_limiters(mut self, api: RateLimiter, pdf: RateLimiter) -> Self {
+        self.api_limiter = api;
+        self.pdf_limiter = pdf;
+        self
+    }
 
-        tracing::info!("Searching arXiv: {}", query);
-        let response = self.client.get(&url).send().await?;
-        let body = response.text().await?;
+    pub async fn search(
+        &self,
+        query: &str,
+        max_results: Option<u32>,
+    ) -> Result<Vec<Paper>, ArxivError> {
+        let max_results = max_results.unwrap_or(self.max_results);
 
-        let papers = parse_arxiv_response(&body)?;
+        tracing::info!("Searching arXiv: {}", query);
+        SearchPager::new(self, query)
+            .with_page_size(max_results)
+            .with_limit(max_results as usize)
+            .collect_papers()
+            .await
+    }
 
-        // Respect rate limiting
-        tokio::time::sleep(self.rate_limit_delay).await;
+    /// Searches with a structured query, see [`Query`] for the search bar
+    /// syntax it parses.
+    pub async fn search_query(
+        &self,
+        query: &Query,
+        max_results: Option<u32>,
+    ) -> Result<Vec<Paper>, ArxivError> {
+        self.search(&query.to_string(), max_results).await
+    }
 
-        Ok(papers)
+    /// One page of results for `search_query`, starting at result `start`.
+    /// A page shorter than `max_results` is the last one.
+    pub async fn search_page(
+        &self,
+        search_query: &str,
+        start: usize,
+        max_results: u32,
+        newest_first: bool,
+    ) -> Result<Vec<Paper>, ArxivError> {
+        Ok(self
+            .search_feed(search_query, start, max_results, newest_first)
+            .await?
+            .papers)
     }
 
-    pub async fn get_by_id(&self, arxiv_id: &str) -> Result<Paper> {
-        let url = format!(
-            "http://export.arxiv.org/api/query?id_list={}",
-            arxiv_id
+    /// Like [`search_page`](Self::search_page), with the feed's result counts.
+    pub async fn search_feed(
+        &self,
+        search_query: &str,
+        start: usize,
+        max_results: u32,
+        newest_first: bool,
+    ) -> Result<FeedPage, ArxivError> {
+        let mut url = format!(
+            "{}?search_query={}&start={}&max_results={}",
+            self.api_url,
+            urlencoding::encode(search_query),
+            start,
+            max_results
         );
+        if newest_first {
+            url.push_str("&sortBy=submittedDate&sortOrder=descending");
+        }
 
-        tracing::info!("Fetching paper: {}", arxiv_id);
-        let response = self.client.get(&url).send().await?;
-        let body = response.text().await?;
-
-        let papers = parse_arxiv_response(&body)?;
+        tracing::debug!("Fetching arXiv page: {} from {}", search_query, start);
+        let body = self.fetch(&url, &self.api_limiter).await?;
+        parse_body(&body)
+    }
 
-        // Respect rate limiting
-        tokio::time::sleep(self.rate_limit_delay).await;
+    pub async fn get_by_id(&self, arxiv_id: &str) -> Result<Paper, ArxivError> {
+        let id: ArxivId = arxiv_id
+            .parse()
+            .map_err(|_| ArxivError::InvalidId(arxiv_id.to_string()))?;
 
-        papers
-            .into_iter()
-            .next()
-            .ok_or_else(|| anyhow::anyhow!("Paper not found: {}", arxiv_id))
+        tracing::info!("Fetching paper: {}", id);
+        self.get_by_ids(&[id])
+            .await?
+            .papers
+            .pop()
+            .ok_or_else(|| ArxivError::NotFound(arxiv_id.to_string()))
     }
 
-    pub async fn get_latest(&self, category: Category, max_results: Option<u32>) -> Result<Vec<Paper>> {
-        let max_results = max_results.unwrap_or(self.max_results);
-        let category_str = category.as_str();
+    /// Fetches papers in `id_list` requests of [`ID_LIST_CHUNK`] IDs.
+    /// Papers come back in the order of `ids`, each requested ID once: the
+    /// version asked for, or the latest one for IDs without a version. IDs
+    /// arXiv did not return are listed as missing.
+    pub async fn get_by_ids(&self, ids: &[ArxivId]) -> Result<FetchedPapers, ArxivError> {
+        let mut unique: Vec<&ArxivId> = Vec::with_capacity(ids.len());
+        let mut seen = HashSet::new();
+        for id in ids {
+            if seen.insert(id) {
+                unique.push(id);
+            }
+        }
 
-        let url = format!(
-            "http://export.arxiv.org/api/query?search_query=cat:{}&sortBy=submittedDate&sortOrder=descending&max_results={}",
-            category_str,
-            max_results
-        );
+        // Every version arXiv returned, by ID without version
+        let mut found: HashMap<String, Vec<Paper>> = HashMap::new();
+        for chunk in unique.chunks(ID_LIST_CHUNK) {
+            let id_list: Vec<String> = chunk
+                .iter()
+                .map(|id| urlencoding::encode(&id.to_string()).into_owned())
+                .collect();
+            let url = format!(
+                "{}?id_list={}&max_results={}",
+                self.api_url,
+                id_list.join(","),
+                chunk.len()
+            );
 
-        tracing::info!("Fetching latest papers for category: {}", category_str);
-        let response = self.client.get(&url).send().await?;
-        let body = response.text().await?;
+            tracing::debug!("Fetching {} papers by ID", chunk.len());
+            let body = self.fetch(&url, &self.api_limiter).await?;
+            for paper in parse_body(&body)?.papers {
+                found
+                    .entry(paper.base_id().to_string())
+                    .or_default()
+                    .push(paper);
+            }
+        }
 
-        let papers = parse_arxiv_response(&body)?;
+        let mut fetched = FetchedPapers::default();
+        for id in unique {
+            let versions = found.get(id.base()).map(Vec::as_slice).unwrap_or_default();
+            let paper = match id.version() {
+                Some(version) => versions.iter().find(|p| p.version() == Some(version)),
+                None => versions.iter().max_by_key(|p| p.version()),
+            };
+            match paper {
+                Some(paper) => fetched.papers.push(paper.clone()),
+                None => fetched.missing.push(id.clone()),
+            }
+        }
+        Ok(fetched)
+    }
 
-        // Respect rate limiting
-        tokio::time::sleep(self.rate_limit_delay).await;
+    pub async fn get_latest(
+        &self,
+        category: Category,
+        max_results: Option<u32>,
+    ) -> Result<Vec<Paper>, ArxivError> {
+        let max_results = max_results.unwrap_or(self.max_results);
+        let category_str = category.as_str();
 
-        Ok(papers)
+        tracing::info!("Fetching latest papers for category: {}", category_str);
+        SearchPager::new(self, &format!("cat:{}", category_str))
+            .newest_first()
+            .with_page_size(max_results)
+            .with_limit(max_results as usize)
+            .collect_papers()
+            .await
     }
 
     pub async fn download_pdf(&self, arxiv_id: &str, output_path: &std::path::Path) -> Result<()> {
         let pdf_url = format!("https://arxiv.org/pdf/{}.pdf", arxiv_id);
 
         tracing::info!("Downloading PDF: {}", arxiv_id);
-        let response = self.client.get(&pdf_url).send().await?;
-        let bytes = response.bytes().await?;
+        let bytes = self.fetch(&pdf_url, &self.pdf_limiter).await?;
+        if !bytes.starts_with(b"%PDF") {
+            return Err(ArxivError::Parse(format!("{} is not a PDF", pdf_url)).into());
+        }
 
         std::fs::write(output_path, bytes)?;
 
-        // Respect rate limiting
-        tokio::time::sleep(self.rate_limit_delay).await;
-
         Ok(())
     }
+
+    /// GETs `url` within `limiter`'s budget and returns the body. Failures
+    /// that may pass are retried with jittered exponential backoff, or
+    /// after the delay arXiv asks for unless it is longer than
+    /// `max_retry_after`.
+    async fn fetch(&self, url: &str, limiter: &RateLimiter) -> Result<Vec<u8>, ArxivError> {
+        let mut attempt = 0;
+        loop {
+            limiter.acquire().await;
+            match self.fetch_once(url).await {
+                Ok(body) => return Ok(body),
+                Err(e) if e.retry_after() > Some(self.max_retry_after) => return Err(e),
+                Err(e) if e.is_retryable() && attempt < self.max_retries => {
+                    let delay = e
+                        .retry_after()
+                        .unwrap_or_else(|| backoff(self.retry_backoff, attempt));
+                    tracing::warn!("arXiv request failed ({}); retrying in {:?}", e, delay);
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    async fn fetch_once(&self, url: &str) -> Result<Vec<u8>, ArxivError> {
+        let response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .map_err(ArxivError::Network)?;
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_retry_after);
+
+        // arXiv throttles with 503 and a Retry-After header
+        if status == StatusCode::TOO_MANY_REQUESTS
+            || (status == StatusCode::SERVICE_UNAVAILABLE && retry_after.is_some())
+        {
+            return Err(ArxivError::RateLimited { retry_after });
+        }
+
+        let body = response.bytes().await.map_err(ArxivError::Network)?;
+        if status == StatusCode::NOT_FOUND {
+            return Err(ArxivError::NotFound(url.to_string()));
+        }
+        if !status.is_success() {
+            return Err(ArxivError::Status {
+                status: status.as_u16(),
+                message: error_message(&String::from_utf8_lossy(&body)),
+            });
+        }
+        Ok(body.to_vec())
+    }
+}
+
+/// Parses an Atom feed, telling HTML error pages apart from broken feeds.
+fn parse_body(body: &[u8]) -> Result<FeedPage, ArxivError> {
+    let body = String::from_utf8_lossy(body);
+    if !body.contains("<feed") {
+        return Err(ArxivError::Parse(format!(
+            "expected an Atom feed, got {:?}",
+            preview(&body)
+        )));
+    }
+    parse_feed(&body).map_err(|e| ArxivError::Parse(format!("{:#}", e)))
+}
+
+/// The message of an arXiv error feed, or the start of any other body.
+fn error_message(body: &str) -> String {
+    let summary = body
+        .split_once("<summary>")
+        .and_then(|(_, rest)| rest.split_once("</summary>"))
+        .map(|(summary, _)| summary.trim());
+    match summary {
+        Some(summary) => summary.to_string(),
+        None => preview(body),
+    }
+}
+
+fn preview(body: &str) -> String {
+    let body = body.split_whitespace().collect::<Vec<_>>().join(" ");
+    match body.char_indices().nth(200) {
+        Some((end, _)) => format!("{}...", &body[..end]),
+        None => body,
+    }
 }
@@ -0,0 +1,70 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+
+/// Why a request to arXiv failed.
+#[derive(Debug, thiserror::Error)]
+pub enum ArxivError {
+    #[error("cannot reach arXiv: {0}")]
+    Network(#[source] reqwest::Error),
+    #[error("arXiv returned HTTP {status}: {message}")]
+    Status { status: u16, message: String },
+    #[error("arXiv is rate limiting requests{}", retry_after_hint(.retry_after))]
+    RateLimited { retry_after: Option<Duration> },
+    #[error("invalid response from arXiv: {0}")]
+    Parse(String),
+    #[error("not found on arXiv: {0}")]
+    NotFound(String),
+    #[error("not an arXiv ID: {0}")]
+    InvalidId(String),
+}
+
+impl ArxivError {
+    /// Whether sending the same request again may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            // Every arXiv request is a GET, so repeating one is harmless.
+            // A failed DNS lookup or connection means we are offline, which
+            // waiting a few seconds will not fix.
+            ArxivError::Network(e) => !e.is_connect(),
+            ArxivError::RateLimited { .. } => true,
+            ArxivError::Status { status, .. } => matches!(status, 500 | 502..=504),
+            ArxivError::Parse(_) | ArxivError::NotFound(_) | ArxivError::InvalidId(_) => false,
+        }
+    }
+
+    /// How long arXiv asked us to wait, from `Retry-After`.
+    pub fn retry_after(&self) -> Option<Duration> {
+        match self {
+            ArxivError::RateLimited { retry_after } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+fn retry_after_hint(retry_after: &Option<Duration>) -> String {
+    match retry_after {
+        Some(delay) => format!(", retry after {:?}", delay),
+        None => String::new(),
+    }
+}
+
+/// Reads `Retry-After`, given in seconds or as an HTTP date.
+pub fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let delay = date.with_timezone(&chrono::Utc) - chrono::Utc::now();
+    Some(delay.to_std().unwrap_or(Duration::ZERO))
+}
+
+/// Exponential backoff from `base`, doubled per attempt, with a random
+/// cut of up to half so that clients failing together retry apart.
+pub fn backoff(base: Duration, attempt: u32) -> Duration {
+    let delay = base * 2u32.saturating_pow(attempt);
+    let random = RandomState::new().build_hasher().finish();
+    let jitter = (random % 1_000) as f64 / 2_000.0;
+    delay.mul_f64(1.0 - jitter)
+}
@@ -1,3 +1,7 @@
 pub mod client;
+pub mod error;
 pub mod models;
+pub mod pager;
 pub mod parser;
+pub mod query;
+pub mod rate_limit;
@@ -37,6 +37,114 @@ impl Paper {
     pub fn text_for_embedding(&self) -> String {
         format!("{} {}", self.title, self.abstract_text)
     }
+
+    /// arXiv ID without the version suffix, used as the storage key.
+    pub fn base_id(&self) -> &str {
+        split_version(&self.arxiv_id).0
+    }
+
+    /// Version number from the ID, e.g. 2 for `2401.00001v2`.
+    pub fn version(&self) -> Option<u32> {
+        split_version(&self.arxiv_id).1
+    }
+}
+
+/// Splits `2401.00001v2` into `("2401.00001", Some(2))`. IDs without a
+/// version suffix are returned unchanged with `None`.
+pub fn split_version(arxiv_id: &str) -> (&str, Option<u32>) {
+    if let Some(pos) = arxiv_id.rfind('v') {
+        let digits = &arxiv_id[pos + 1..];
+        if pos > 0 && !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+            if let Ok(version) = digits.parse() {
+                return (&arxiv_id[..pos], Some(version));
+            }
+        }
+    }
+    (arxiv_id, None)
+}
+
+/// A validated arXiv identifier: new style `2401.00001` or old style
+/// `hep-th/9901001`, with an optional version.
+///
+/// Parsing accepts the forms IDs are usually pasted in: `arXiv:2401.00001`,
+/// abstract and PDF URLs, and old-style IDs with a subject class such as
+/// `math.GT/0309136`, which arXiv files as `math/0309136`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ArxivId {
+    id: String,
+    version: Option<u32>,
+}
+
+impl ArxivId {
+    /// The ID without its version, as papers are stored.
+    pub fn base(&self) -> &str {
+        &self.id
+    }
+
+    pub fn version(&self) -> Option<u32> {
+        self.version
+    }
+}
+
+impl std::fmt::Display for ArxivId {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self.version {
+            Some(version) => write!(f, "{}v{}", self.id, version),
+            None => f.write_str(&self.id),
+        }
+    }
+}
+
+impl std::str::FromStr for ArxivId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let mut id = s.trim();
+        for prefix in ["https://", "http://", "www.", "export."] {
+            id = strip_prefix_ignore_case(id, prefix);
+        }
+        for prefix in ["arxiv.org/abs/", "arxiv.org/pdf/", "arxiv:"] {
+            id = strip_prefix_ignore_case(id, prefix);
+        }
+        let id = id.trim_end_matches('/').trim_end_matches(".pdf");
+
+        let (base, version) = split_version(id);
+        let base = match base.split_once('/') {
+            // Old style: archive, optional subject class, YYMMNNN
+            Some((archive, number)) => {
+                let archive = archive.split('.').next().unwrap_or_default();
+                let valid = !archive.is_empty()
+                    && archive.chars().all(|c| c.is_ascii_lowercase() || c == '-')
+                    && number.len() == 7
+                    && number.chars().all(|c| c.is_ascii_digit());
+                valid.then(|| format!("{}/{}", archive, number))
+            }
+            // New style: YYMM.NNNN or YYMM.NNNNN
+            None => {
+                let valid = base.split_once('.').is_some_and(|(month, number)| {
+                    month.len() == 4
+                        && (4..=5).contains(&number.len())
+                        && month
+                            .chars()
+                            .chain(number.chars())
+                            .all(|c| c.is_ascii_digit())
+                });
+                valid.then(|| base.to_string())
+            }
+        };
+
+        match base {
+            Some(id) => Ok(Self { id, version }),
+            None => anyhow::bail!("Not an arXiv ID: {:?}", s),
+        }
+    }
+}
+
+fn strip_prefix_ignore_case<'a>(text: &'a str, prefix: &str) -> &'a str {
+    match text.get(..prefix.len()) {
+        Some(head) if head.eq_ignore_ascii_case(prefix) => &text[prefix.len()..],
+        _ => text,
+    }
 }
 
 #[derive(Debug, Clone, Copy)]
@@ -0,0 +1,123 @@
+use futures::stream::{self, Stream, TryStreamExt};
+
+use super::client::ArxivClient;
+use super::error::ArxivError;
+use super::models::Paper;
+use super::parser::FeedPage;
+
+/// Most results arXiv returns for one request
+pub const MAX_PAGE_SIZE: u32 = 2000;
+/// Deepest result arXiv serves for a query; later pages come back empty
+pub const MAX_SEARCH_RESULTS: usize = 30_000;
+
+/// Walks the pages of an arXiv search with `start`/`max_results`.
+///
+/// Stops after a short or empty page, at the query's `totalResults`, at
+/// [`MAX_SEARCH_RESULTS`] or at the limit set with `with_limit`.
+pub struct SearchPager<'a> {
+    client: &'a ArxivClient,
+    query: String,
+    newest_first: bool,
+    page_size: u32,
+    limit: usize,
+    start: usize,
+    total_results: Option<usize>,
+    done: bool,
+}
+
+impl<'a> SearchPager<'a> {
+    /// Pages of `query` in relevance order, 100 papers each.
+    pub fn new(client: &'a ArxivClient, query: &str) -> Self {
+        Self {
+            client,
+            query: query.to_string(),
+            newest_first: false,
+            page_size: 100,
+            limit: MAX_SEARCH_RESULTS,
+            start: 0,
+            total_results: None,
+            done: false,
+        }
+    }
+
+    /// Newest submissions first.
+    pub fn newest_first(mut self) -> Self {
+        self.newest_first = true;
+        self
+    }
+
+    /// Capped at [`MAX_PAGE_SIZE`].
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.clamp(1, MAX_PAGE_SIZE);
+        self
+    }
+
+    /// Stops after `limit` papers.
+    pub fn with_limit(mut self, limit: usize) -> Self {
+        self.limit = limit.min(MAX_SEARCH_RESULTS);
+        self
+    }
+
+    /// Begins at result `start` instead of the first one.
+    pub fn starting_at(mut self, start: usize) -> Self {
+        self.start = start;
+        self.limit = self.limit.saturating_add(start).min(MAX_SEARCH_RESULTS);
+        self
+    }
+
+    /// Matches for the whole query, known once a page has been fetched.
+    pub fn total_results(&self) -> Option<usize> {
+        self.total_results
+    }
+
+    /// Offset of the next page.
+    pub fn position(&self) -> usize {
+        self.start
+    }
+
+    /// The next page, or `None` once the results are exhausted.
+    pub async fn next_page(&mut self) -> Result<Option<FeedPage>, ArxivError> {
+        let end = self
+            .total_results
+            .map_or(self.limit, |total| total.min(self.limit));
+        if self.done || self.start >= end {
+            self.done = true;
+            return Ok(None);
+        }
+
+        let size = (self.page_size as usize).min(end - self.start) as u32;
+        let page = self
+            .client
+            .search_feed(&self.query, self.start, size, self.newest_first)
+            .await?;
+
+        self.total_results = Some(page.total_results);
+        self.start += page.papers.len();
+        if page.papers.len() < size as usize {
+            self.done = true;
+        }
+        if page.papers.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(page))
+    }
+
+    /// Every remaining paper, one page request at a time.
+    pub fn papers(self) -> impl Stream<Item = Result<Paper, ArxivError>> + 'a {
+        stream::try_unfold(self, |mut pager| async move {
+            let page = pager.next_page().await?;
+            let papers = page.map(|page| page.papers.into_iter().map(Ok::<_, ArxivError>));
+            Ok::<_, ArxivError>(papers.map(|papers| (stream::iter(papers), pager)))
+        })
+        .try_flatten()
+    }
+
+    /// Fetches every remaining page.
+    pub async fn collect_papers(mut self) -> Result<Vec<Paper>, ArxivError> {
+        let mut papers = Vec::new();
+        while let Some(page) = self.next_page().await? {
+            papers.extend(page.papers);
+        }
+        Ok(papers)
+    }
+}
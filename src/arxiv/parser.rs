@@ -7,26 +7,62 @@ use super::models::Paper;
 
 #[derive(Debug, Deserialize)]
 struct Feed {
+    // Elements are matched by local name: `opensearch:totalResults` and
+    // `arxiv:comment` arrive as `totalResults` and `comment`
+    #[serde(rename = "totalResults", default)]
+    total_results: Option<OpenSearchValue>,
+    #[serde(rename = "startIndex", default)]
+    start_index: Option<OpenSearchValue>,
+    #[serde(rename = "itemsPerPage", default)]
+    items_per_page: Option<OpenSearchValue>,
+    #[serde(default)]
     entry: Vec<Entry>,
 }
 
+#[derive(Debug, Deserialize)]
+struct OpenSearchValue {
+    #[serde(rename = "$text")]
+    value: usize,
+}
+
+/// One page of an arXiv feed with its OpenSearch counts.
+#[derive(Debug, Clone)]
+pub struct FeedPage {
+    /// Matches for the whole query, not just this page
+    pub total_results: usize,
+    /// Offset of the first paper, the request's `start`
+    pub start_index: usize,
+    /// Papers requested per page, the request's `max_results`
+    pub items_per_page: usize,
+    pub papers: Vec<Paper>,
+}
+
+/// Fields are optional so that error entries, which arXiv returns for IDs
+/// it does not know, parse and can be skipped.
 #[derive(Debug, Deserialize)]
 struct Entry {
     id: String,
+    #[serde(default)]
     title: String,
+    #[serde(default)]
     summary: String,
+    #[serde(default)]
     published: String,
+    #[serde(default)]
     updated: String,
+    #[serde(default)]
     author: Vec<Author>,
+    #[serde(default)]
     category: Vec<CategoryAttr>,
+    #[serde(default)]
     link: Vec<Link>,
-    #[serde(rename = "arxiv:comment", default)]
+    #[serde(default)]
     comment: Option<String>,
-    #[serde(rename = "arxiv:journal_ref", default)]
+    #[serde(default)]
     journal_ref: Option<String>,
-    #[serde(rename = "arxiv:doi", default)]
+    #[serde(default)]
     doi: Option<String>,
-    #[serde(rename = "arxiv:primary_category", default)]
+    #[serde(default)]
     primary_category: Option<PrimaryCategory>,
 }
 
@@ -56,11 +92,31 @@ struct PrimaryCategory {
 }
 
 pub fn parse_arxiv_response(xml: &str) -> Result<Vec<Paper>> {
+    Ok(parse_feed(xml)?.papers)
+}
+
+/// Parses a feed with its counts. Feeds without OpenSearch elements count
+/// only their own entries.
+pub fn parse_feed(xml: &str) -> Result<FeedPage> {
     let feed: Feed = from_str(xml)?;
+    let entries = feed.entry.len();
+    let count = |value: Option<OpenSearchValue>, default: usize| {
+        value.map(|v| v.value).unwrap_or(default)
+    };
+    let start_index = count(feed.start_index, 0);
+    let total_results = count(feed.total_results, start_index + entries);
+    let items_per_page = count(feed.items_per_page, entries);
 
     let papers = feed
         .entry
         .into_iter()
+        .filter(|entry| {
+            let paper = !entry.published.is_empty();
+            if !paper {
+                tracing::debug!("Skipping arXiv entry {}: {}", entry.id, entry.summary.trim());
+            }
+            paper
+        })
         .map(|entry| -> Result<Paper> {
             let arxiv_id = extract_arxiv_id(&entry.id);
             let authors: Vec<String> = entry.author.into_iter().map(|a| a.name).collect();
@@ -93,13 +149,18 @@ pub fn parse_arxiv_response(xml: &str) -> Result<Vec<Paper>> {
         })
         .collect::<Result<Vec<_>>>()?;
 
-    Ok(papers)
+    Ok(FeedPage {
+        total_results,
+        start_index,
+        items_per_page,
+        papers,
+    })
 }
 
 fn extract_arxiv_id(url: &str) -> String {
-    url.split('/')
-        .last()
-        .unwrap_or(url)
-        .trim_start_matches("abs/")
-        .to_string()
+    // Old-style IDs keep their archive, as in `abs/math/0309136v1`
+    match url.split_once("/abs/") {
+        Some((_, id)) => id.to_string(),
+        None => url.rsplit('/').next().unwrap_or(url).to_string(),
+    }
 }
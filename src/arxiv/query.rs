@@ -0,0 +1,495 @@
+use anyhow::{anyhow, bail, Result};
+use chrono::{Duration, NaiveDate, NaiveDateTime, NaiveTime};
+use std::fmt;
+
+/// A field of the arXiv search API.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    Title,
+    Author,
+    Abstract,
+    Comment,
+    JournalRef,
+    Category,
+    ReportNumber,
+    Id,
+    All,
+}
+
+impl Field {
+    pub const ALL: [Field; 9] = [
+        Field::Title,
+        Field::Author,
+        Field::Abstract,
+        Field::Comment,
+        Field::JournalRef,
+        Field::Category,
+        Field::ReportNumber,
+        Field::Id,
+        Field::All,
+    ];
+
+    /// The prefix arXiv expects, e.g. `ti` in `ti:transformer`.
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Field::Title => "ti",
+            Field::Author => "au",
+            Field::Abstract => "abs",
+            Field::Comment => "co",
+            Field::JournalRef => "jr",
+            Field::Category => "cat",
+            Field::ReportNumber => "rn",
+            Field::Id => "id",
+            Field::All => "all",
+        }
+    }
+
+    /// Accepts arXiv prefixes and spelled-out names such as `author`.
+    pub fn from_name(name: &str) -> Option<Field> {
+        let name = name.to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|field| field.prefix() == name)
+            .or(match name.as_str() {
+                "title" => Some(Field::Title),
+                "author" => Some(Field::Author),
+                "abstract" => Some(Field::Abstract),
+                "comment" => Some(Field::Comment),
+                "journal" => Some(Field::JournalRef),
+                "category" => Some(Field::Category),
+                _ => None,
+            })
+    }
+}
+
+/// First submission time arXiv accepts in a range
+const EARLIEST: &str = "199101010000";
+/// Stands in for an open upper bound
+const LATEST: &str = "999912312359";
+
+/// An arXiv `search_query`, built from field terms, boolean operators and
+/// submission date ranges. `Display` renders the string the API expects,
+/// e.g. `(au:hinton AND cat:cs.LG) ANDNOT ti:survey`; nested groups are
+/// parenthesized.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Query {
+    /// A word, or a phrase when it contains spaces
+    Term(Field, String),
+    /// Submitted within the range, both ends inclusive, in UTC
+    Submitted {
+        from: Option<NaiveDateTime>,
+        to: Option<NaiveDateTime>,
+    },
+    And(Vec<Query>),
+    Or(Vec<Query>),
+    AndNot(Box<Query>, Box<Query>),
+}
+
+impl Query {
+    pub fn term(field: Field, value: &str) -> Self {
+        Query::Term(field, value.trim().to_string())
+    }
+
+    pub fn title(value: &str) -> Self {
+        Self::term(Field::Title, value)
+    }
+
+    pub fn author(value: &str) -> Self {
+        Self::term(Field::Author, value)
+    }
+
+    pub fn abstract_text(value: &str) -> Self {
+        Self::term(Field::Abstract, value)
+    }
+
+    pub fn category(value: &str) -> Self {
+        Self::term(Field::Category, value)
+    }
+
+    pub fn all(value: &str) -> Self {
+        Self::term(Field::All, value)
+    }
+
+    pub fn submitted_between(from: NaiveDateTime, to: NaiveDateTime) -> Self {
+        Query::Submitted {
+            from: Some(from),
+            to: Some(to),
+        }
+    }
+
+    /// Submitted on or after the start of `date`.
+    pub fn submitted_after(date: NaiveDate) -> Self {
+        Query::Submitted {
+            from: Some(date.and_time(NaiveTime::MIN)),
+            to: None,
+        }
+    }
+
+    /// Submitted before the start of `date`.
+    pub fn submitted_before(date: NaiveDate) -> Self {
+        Query::Submitted {
+            from: None,
+            to: Some(date.and_time(NaiveTime::MIN) - Duration::minutes(1)),
+        }
+    }
+
+    pub fn and(self, other: Query) -> Self {
+        match self {
+            Query::And(mut queries) => {
+                queries.push(other);
+                Query::And(queries)
+            }
+            query => Query::And(vec![query, other]),
+        }
+    }
+
+    pub fn or(self, other: Query) -> Self {
+        match self {
+            Query::Or(mut queries) => {
+                queries.push(other);
+                Query::Or(queries)
+            }
+            query => Query::Or(vec![query, other]),
+        }
+    }
+
+    pub fn and_not(self, other: Query) -> Self {
+        Query::AndNot(Box::new(self), Box::new(other))
+    }
+
+    fn is_compound(&self) -> bool {
+        match self {
+            Query::And(queries) | Query::Or(queries) => queries.len() > 1,
+            Query::AndNot(..) => true,
+            _ => false,
+        }
+    }
+
+    fn fmt_operand(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_compound() {
+            write!(f, "({})", self)
+        } else {
+            write!(f, "{}", self)
+        }
+    }
+}
+
+impl fmt::Display for Query {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Query::Term(field, value) => {
+                let value = value.replace('"', "");
+                if value.contains(char::is_whitespace) {
+                    write!(f, "{}:\"{}\"", field.prefix(), value)
+                } else {
+                    write!(f, "{}:{}", field.prefix(), value)
+                }
+            }
+            Query::Submitted { from, to } => {
+                let bound = |date: &Option<NaiveDateTime>, open: &str| {
+                    date.map(|date| date.format("%Y%m%d%H%M").to_string())
+                        .unwrap_or_else(|| open.to_string())
+                };
+                write!(
+                    f,
+                    "submittedDate:[{} TO {}]",
+                    bound(from, EARLIEST),
+                    bound(to, LATEST)
+                )
+            }
+            Query::And(queries) | Query::Or(queries) => {
+                let operator = if matches!(self, Query::And(_)) {
+                    " AND "
+                } else {
+                    " OR "
+                };
+                for (i, query) in queries.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(operator)?;
+                    }
+                    if queries.len() > 1 {
+                        query.fmt_operand(f)?;
+                    } else {
+                        write!(f, "{}", query)?;
+                    }
+                }
+                Ok(())
+            }
+            Query::AndNot(include, exclude) => {
+                include.fmt_operand(f)?;
+                f.write_str(" ANDNOT ")?;
+                exclude.fmt_operand(f)
+            }
+        }
+    }
+}
+
+/// Parses the search bar syntax:
+///
+/// - bare words and `"quoted phrases"` search all fields
+/// - `field:value` or `field:"a phrase"` with `ti`, `au`, `abs`, `co`, `jr`,
+///   `cat`, `rn`, `id`, `all` or `title`, `author`, `abstract`, `category`
+/// - `after:2024-01` and `before:2024-06-15` limit the submission date;
+///   dates may be a year, a month or a day, and `before` excludes that date
+/// - terms are ANDed; `OR`, `AND`, `NOT` or a leading `-` and parentheses
+///   combine them, with NOT binding tightest and OR loosest
+impl std::str::FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let tokens = tokenize(s)?;
+        if tokens.is_empty() {
+            bail!("Empty search query");
+        }
+        let mut parser = Parser { tokens, pos: 0 };
+        let query = parser.parse_or()?;
+        match parser.tokens.get(parser.pos) {
+            None => Ok(query),
+            Some(Token::Close) => bail!("Unmatched ) in search query"),
+            Some(token) => bail!("Unexpected {} in search query", token),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Open,
+    Close,
+    And,
+    Or,
+    Not,
+    /// `field:value`, with no field for bare words and phrases
+    Word(Option<String>, String),
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::Open => f.write_str("("),
+            Token::Close => f.write_str(")"),
+            Token::And => f.write_str("AND"),
+            Token::Or => f.write_str("OR"),
+            Token::Not => f.write_str("NOT"),
+            Token::Word(Some(field), value) => write!(f, "{}:{}", field, value),
+            Token::Word(None, value) => f.write_str(value),
+        }
+    }
+}
+
+fn tokenize(input: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = input.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '(' => {
+                chars.next();
+                tokens.push(Token::Open);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(Token::Close);
+            }
+            '-' => {
+                chars.next();
+                tokens.push(Token::Not);
+            }
+            _ => {
+                let mut field = None;
+                let mut value = String::new();
+                let mut quoted = false;
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || c == '(' || c == ')' {
+                        break;
+                    }
+                    chars.next();
+                    match c {
+                        '"' => {
+                            quoted = true;
+                            loop {
+                                match chars.next() {
+                                    Some('"') => break,
+                                    Some(c) => value.push(c),
+                                    None => bail!("Unclosed quote in search query"),
+                                }
+                            }
+                        }
+                        ':' if field.is_none() && !quoted && !value.is_empty() => {
+                            field = Some(std::mem::take(&mut value));
+                        }
+                        c => value.push(c),
+                    }
+                }
+                let token = match (field.is_none() && !quoted, value.as_str()) {
+                    (true, "AND") => Token::And,
+                    (true, "OR") => Token::Or,
+                    (true, "NOT") | (true, "ANDNOT") => Token::Not,
+                    _ => Token::Word(field, value),
+                };
+                tokens.push(token);
+            }
+        }
+    }
+
+    Ok(tokens)
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn parse_or(&mut self) -> Result<Query> {
+        let mut query = self.parse_and()?;
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            query = query.or(self.parse_and()?);
+        }
+        Ok(query)
+    }
+
+    /// Terms joined by AND or juxtaposition; negated ones are collected and
+    /// excluded from the rest.
+    fn parse_and(&mut self) -> Result<Query> {
+        let mut include = Vec::new();
+        let mut exclude = Vec::new();
+        loop {
+            match self.peek() {
+                None | Some(Token::Close) | Some(Token::Or) => break,
+                Some(Token::And) => {
+                    self.pos += 1;
+                }
+                Some(Token::Not) => {
+                    self.pos += 1;
+                    exclude.push(self.parse_operand()?);
+                }
+                Some(_) => include.push(self.parse_operand()?),
+            }
+        }
+
+        let Some(include) = combine_and(include) else {
+            if exclude.is_empty() {
+                bail!("Expected a search term");
+            }
+            bail!("NOT needs a term to exclude from, e.g. `transformer -vision`");
+        };
+        Ok(match exclude.len() {
+            0 => include,
+            1 => include.and_not(exclude.remove(0)),
+            _ => include.and_not(Query::Or(exclude)),
+        })
+    }
+
+    fn parse_operand(&mut self) -> Result<Query> {
+        let token = self
+            .tokens
+            .get(self.pos)
+            .cloned()
+            .ok_or_else(|| anyhow!("Search query ends after an operator"))?;
+        self.pos += 1;
+        match token {
+            Token::Open => {
+                let query = self.parse_or()?;
+                if self.peek() != Some(&Token::Close) {
+                    bail!("Unclosed ( in search query");
+                }
+                self.pos += 1;
+                Ok(query)
+            }
+            Token::Word(field, value) => word(field.as_deref(), &value),
+            Token::Not => bail!("Unexpected NOT in search query"),
+            token => bail!("Unexpected {} in search query", token),
+        }
+    }
+}
+
+fn word(field: Option<&str>, value: &str) -> Result<Query> {
+    if value.trim().is_empty() {
+        bail!("Missing value after {}:", field.unwrap_or_default());
+    }
+    let Some(field) = field else {
+        return Ok(Query::all(value));
+    };
+    match field.to_lowercase().as_str() {
+        "after" | "since" => Ok(Query::submitted_after(parse_date(value)?)),
+        "before" | "until" => Ok(Query::submitted_before(parse_date(value)?)),
+        name => Field::from_name(name)
+            .map(|field| Query::term(field, value))
+            .ok_or_else(|| {
+                anyhow!(
+                    "Unknown search field {:?} (expected ti, au, abs, co, jr, cat, rn, id, all, \
+                     after or before)",
+                    name
+                )
+            }),
+    }
+}
+
+/// `2024`, `2024-01` or `2024-01-15`, as the first day of that period.
+fn parse_date(value: &str) -> Result<NaiveDate> {
+    let parts: Vec<&str> = value.split('-').collect();
+    let number = |part: Option<&&str>, default: u32| -> Result<u32> {
+        part.map(|part| part.parse::<u32>())
+            .transpose()
+            .map(|n| n.unwrap_or(default))
+            .map_err(|_| {
+                anyhow!(
+                    "Invalid date {:?} (expected YYYY, YYYY-MM or YYYY-MM-DD)",
+                    value
+                )
+            })
+    };
+    if parts.len() > 3 || parts[0].len() != 4 {
+        bail!(
+            "Invalid date {:?} (expected YYYY, YYYY-MM or YYYY-MM-DD)",
+            value
+        );
+    }
+    let year = number(parts.first(), 1)? as i32;
+    let month = number(parts.get(1), 1)?;
+    let day = number(parts.get(2), 1)?;
+    NaiveDate::from_ymd_opt(year, month, day).ok_or_else(|| anyhow!("Invalid date {:?}", value))
+}
+
+/// ANDs the terms, merging submission date limits into a single range.
+fn combine_and(queries: Vec<Query>) -> Option<Query> {
+    let mut combined: Option<Query> = None;
+    let mut range: Option<(Option<NaiveDateTime>, Option<NaiveDateTime>)> = None;
+    for query in queries {
+        match query {
+            Query::Submitted { from, to } => {
+                let (old_from, old_to) = range.unwrap_or_default();
+                range = Some((old_from.max(from), min_bound(old_to, to)));
+            }
+            query => {
+                combined = Some(match combined {
+                    Some(combined) => combined.and(query),
+                    None => query,
+                })
+            }
+        }
+    }
+    if let Some((from, to)) = range {
+        let submitted = Query::Submitted { from, to };
+        combined = Some(match combined {
+            Some(combined) => combined.and(submitted),
+            None => submitted,
+        });
+    }
+    combined
+}
+
+fn min_bound(a: Option<NaiveDateTime>, b: Option<NaiveDateTime>) -> Option<NaiveDateTime> {
+    match (a, b) {
+        (Some(a), Some(b)) => Some(a.min(b)),
+        (a, b) => a.or(b),
+    }
+}
@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// How often requests may be sent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Budget {
+    /// Time for one request's allowance to come back
+    pub interval: Duration,
+    /// Requests that may go out back to back after a quiet period
+    pub burst: u32,
+}
+
+impl Budget {
+    /// One request per `interval`, no bursts.
+    pub fn every(interval: Duration) -> Self {
+        Self { interval, burst: 1 }
+    }
+
+    pub fn with_burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// The tighter of two budgets: the longer interval and smaller burst.
+    pub fn strictest(self, other: Budget) -> Budget {
+        Budget {
+            interval: self.interval.max(other.interval),
+            burst: self.burst.min(other.burst),
+        }
+    }
+}
+
+/// A token bucket. Clones share the same bucket, so a limiter handed to
+/// several tasks or clients keeps all of them within one budget.
+///
+/// Callers wait in [`acquire`](Self::acquire) *before* sending: each call
+/// reserves the next free slot, so concurrent callers are spaced out in
+/// the order they asked, and an idle limiter lets the first request through
+/// at once.
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    bucket: Arc<Mutex<Bucket>>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    budget: Budget,
+    /// Negative while requests are queued for later slots
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Bucket {
+    fn refill(&mut self, now: Instant) {
+        let capacity = self.budget.burst as f64;
+        if self.budget.interval.is_zero() {
+            self.tokens = capacity;
+        } else {
+            let earned =
+                now.duration_since(self.updated).as_secs_f64() / self.budget.interval.as_secs_f64();
+            self.tokens = (self.tokens + earned).min(capacity);
+        }
+        self.updated = now;
+    }
+}
+
+impl RateLimiter {
+    /// A limiter of its own, starting with a full bucket.
+    pub fn new(budget: Budget) -> Self {
+        let budget = budget.with_burst(budget.burst);
+        Self {
+            bucket: Arc::new(Mutex::new(Bucket {
+                budget,
+                tokens: budget.burst as f64,
+                updated: Instant::now(),
+            })),
+        }
+    }
+
+    /// The process-wide limiter called `name`, created on first use.
+    /// Later calls share it and may only tighten its budget, so a client
+    /// configured more loosely cannot lift the limit for every other one.
+    pub fn shared(name: &str, budget: Budget) -> Self {
+        static LIMITERS: OnceLock<Mutex<HashMap<String, RateLimiter>>> = OnceLock::new();
+
+        let mut limiters = LIMITERS
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        let limiter = limiters
+            .entry(name.to_string())
+            .or_insert_with(|| RateLimiter::new(budget))
+            .clone();
+        let current = limiter.budget();
+        let strictest = current.strictest(budget.with_burst(budget.burst));
+        if strictest != current {
+            limiter.set_budget(strictest);
+        }
+        limiter
+    }
+
+    pub fn budget(&self) -> Budget {
+        self.lock().budget
+    }
+
+    pub fn set_budget(&self, budget: Budget) {
+        let mut bucket = self.lock();
+        bucket.refill(Instant::now());
+        bucket.budget = budget.with_burst(budget.burst);
+        bucket.tokens = bucket.tokens.min(bucket.budget.burst as f64);
+    }
+
+    /// Waits until a request may be sent.
+    pub async fn acquire(&self) {
+        let wait = self.reserve();
+        if !wait.is_zero() {
+            tracing::debug!("Rate limited, waiting {:?}", wait);
+            tokio::time::sleep(wait).await;
+        }
+    }
+
+    /// Takes the next slot and returns how long until it starts.
+    fn reserve(&self) -> Duration {
+        let mut bucket = self.lock();
+        bucket.refill(Instant::now());
+        bucket.tokens -= 1.0;
+        if bucket.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            bucket.budget.interval.mul_f64(-bucket.tokens)
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Bucket> {
+        // The bucket holds plain numbers, always usable after a panic
+        self.bucket
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
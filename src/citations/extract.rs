@@ -0,0 +1,437 @@
+use serde::{Deserialize, Serialize};
+
+/// Longest citation context kept per reference, in characters.
+const MAX_CONTEXT_LEN: usize = 300;
+
+/// One entry of a paper's bibliography.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Reference {
+    /// The entry as it appears in the bibliography
+    pub raw: String,
+    /// Label the body cites it by: `12` for `[12]`, the key for `\cite{key}`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    /// arXiv ID without version, when the entry names one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub arxiv_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub doi: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Sentence in the citing paper where the reference is cited
+    #[serde(default)]
+    pub context: String,
+}
+
+impl Reference {
+    /// Builds a reference from bibliography text, picking out identifiers.
+    pub fn from_raw(raw: &str) -> Self {
+        let raw = collapse_whitespace(raw);
+        Self {
+            arxiv_id: find_arxiv_ids(&raw).into_iter().next(),
+            doi: find_doi(&raw),
+            title: guess_title(&raw),
+            raw,
+            ..Self::default()
+        }
+    }
+}
+
+/// Extracts the bibliography from the full text of a paper, either LaTeX
+/// source (`\bibitem` entries, `\cite` commands) or text pulled out of a
+/// PDF (a "References" section with `[n]` or `n.` entries).
+pub fn extract_references(text: &str) -> Vec<Reference> {
+    if text.contains("\\bibitem") {
+        return extract_latex(text);
+    }
+
+    let Some((body, section)) = split_reference_section(text) else {
+        return Vec::new();
+    };
+
+    let mut references: Vec<Reference> = split_entries(section)
+        .into_iter()
+        .map(|(label, entry)| Reference {
+            label,
+            ..Reference::from_raw(&entry)
+        })
+        .filter(|reference| !reference.raw.is_empty())
+        .collect();
+
+    for reference in &mut references {
+        if let Some(label) = &reference.label {
+            reference.context = find_numbered_context(body, label).unwrap_or_default();
+        }
+    }
+    references
+}
+
+/// Every arXiv identifier in `text`, without version suffix, in order of
+/// first appearance. Recognizes new-style (`2401.01234`) and old-style
+/// (`hep-th/9901001`) identifiers.
+pub fn find_arxiv_ids(text: &str) -> Vec<String> {
+    let bytes = text.as_bytes();
+    let mut ids: Vec<String> = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if let Some((id, end)) = new_style_id_at(bytes, i).or_else(|| old_style_id_at(bytes, i)) {
+            if !ids.contains(&id) {
+                ids.push(id);
+            }
+            i = end;
+        } else {
+            i += 1;
+        }
+    }
+    ids
+}
+
+/// `YYMM.NNNN` or `YYMM.NNNNN` starting at `i`, optionally followed by a
+/// version, not embedded in a longer number.
+fn new_style_id_at(bytes: &[u8], i: usize) -> Option<(String, usize)> {
+    // Allow the arXiv DOI form `10.48550/arXiv.2401.01234`
+    let after_doi_prefix = i >= 6 && bytes[i - 6..i].eq_ignore_ascii_case(b"arxiv.");
+    if i > 0 && matches!(bytes[i - 1], b'0'..=b'9' | b'.') && !after_doi_prefix {
+        return None;
+    }
+    let digits = |from: usize| {
+        bytes[from.min(bytes.len())..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count()
+    };
+
+    if digits(i) != 4 || bytes.get(i + 4) != Some(&b'.') {
+        return None;
+    }
+    let month: u32 = std::str::from_utf8(&bytes[i + 2..i + 4])
+        .ok()?
+        .parse()
+        .ok()?;
+    if !(1..=12).contains(&month) {
+        return None;
+    }
+    let number = digits(i + 5);
+    if number != 4 && number != 5 {
+        return None;
+    }
+
+    let id_end = i + 5 + number;
+    let mut end = id_end;
+    if bytes.get(end) == Some(&b'v') && digits(end + 1) > 0 {
+        end += 1 + digits(end + 1);
+    }
+    let id = std::str::from_utf8(&bytes[i..id_end]).ok()?.to_string();
+    Some((id, end))
+}
+
+/// `archive[.XX]/YYMMNNN` starting at `i`.
+fn old_style_id_at(bytes: &[u8], i: usize) -> Option<(String, usize)> {
+    if i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'-') {
+        return None;
+    }
+    let archive_len = bytes[i..]
+        .iter()
+        .take_while(|b| b.is_ascii_lowercase() || **b == b'-')
+        .count();
+    if archive_len < 2 {
+        return None;
+    }
+    let mut j = i + archive_len;
+    // Optional subject class such as `.CO` in `math.CO/0501001`
+    if bytes.get(j) == Some(&b'.')
+        && bytes.get(j + 1).is_some_and(|b| b.is_ascii_uppercase())
+        && bytes.get(j + 2).is_some_and(|b| b.is_ascii_uppercase())
+    {
+        j += 3;
+    }
+    if bytes.get(j) != Some(&b'/') {
+        return None;
+    }
+    let digits = bytes[j + 1..]
+        .iter()
+        .take_while(|b| b.is_ascii_digit())
+        .count();
+    if digits != 7 {
+        return None;
+    }
+
+    let id_end = j + 8;
+    let mut end = id_end;
+    if bytes.get(end) == Some(&b'v') {
+        end += 1 + bytes[end + 1..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+    }
+    let id = std::str::from_utf8(&bytes[i..id_end]).ok()?.to_string();
+    Some((id, end))
+}
+
+/// First DOI (`10.NNNN/...`) in `text`, lowercased.
+pub fn find_doi(text: &str) -> Option<String> {
+    let start = text.find("10.")?;
+    let rest = &text[start..];
+    let registrant = rest[3..].chars().take_while(|c| c.is_ascii_digit()).count();
+    if registrant < 4 || !rest[3 + registrant..].starts_with('/') {
+        return find_doi(&text[start + 3..]);
+    }
+
+    let doi: String = rest
+        .chars()
+        .take_while(|c| !c.is_whitespace() && !matches!(c, '"' | '<' | '>' | '}' | ','))
+        .collect();
+    let doi = doi.trim_end_matches(['.', ';', ')', ']']);
+    Some(doi.to_lowercase())
+}
+
+/// The title of a bibliography entry when it is quoted, as most styles do
+/// in text extracted from PDFs and LaTeX alike.
+fn guess_title(entry: &str) -> Option<String> {
+    for (open, close) in [("\u{201c}", "\u{201d}"), ("``", "''"), ("\"", "\"")] {
+        if let Some(start) = entry.find(open) {
+            let rest = &entry[start + open.len()..];
+            if let Some(end) = rest.find(close) {
+                let title = rest[..end].trim().trim_end_matches(',').trim();
+                if title.split_whitespace().count() >= 2 {
+                    return Some(title.to_string());
+                }
+            }
+        }
+    }
+    None
+}
+
+/// Splits text at the last "References" or "Bibliography" heading.
+fn split_reference_section(text: &str) -> Option<(&str, &str)> {
+    let mut offset = 0;
+    let mut heading = None;
+    for line in text.split_inclusive('\n') {
+        let title = line
+            .trim()
+            .trim_start_matches(|c: char| c.is_ascii_digit() || c == '.' || c.is_whitespace())
+            .to_lowercase();
+        if matches!(
+            title.as_str(),
+            "references" | "bibliography" | "works cited"
+        ) {
+            heading = Some((offset, offset + line.len()));
+        }
+        offset += line.len();
+    }
+    let (start, end) = heading?;
+    Some((&text[..start], &text[end..]))
+}
+
+/// Splits a reference section into entries, keeping their `[n]` or `n.`
+/// labels where present and falling back to blank-line separated blocks.
+fn split_entries(section: &str) -> Vec<(Option<String>, String)> {
+    let mut entries: Vec<(Option<String>, String)> = Vec::new();
+    let mut labelled = false;
+
+    for line in section.lines() {
+        let trimmed = line.trim();
+        if let Some((label, rest)) = entry_label(trimmed) {
+            labelled = true;
+            entries.push((Some(label), rest.to_string()));
+        } else if trimmed.is_empty() {
+            if !labelled {
+                entries.push((None, String::new()));
+            }
+        } else if let Some((_, entry)) = entries.last_mut() {
+            entry.push(' ');
+            entry.push_str(trimmed);
+        } else {
+            entries.push((None, trimmed.to_string()));
+        }
+    }
+
+    entries.retain(|(_, entry)| !entry.trim().is_empty());
+    entries
+}
+
+/// Recognizes `[12] ...` and `12. ...` at the start of a line.
+fn entry_label(line: &str) -> Option<(String, &str)> {
+    if let Some(rest) = line.strip_prefix('[') {
+        let end = rest.find(']')?;
+        let label = &rest[..end];
+        if !label.is_empty() && label.len() <= 12 && !label.contains(' ') {
+            return Some((label.to_string(), rest[end + 1..].trim_start()));
+        }
+        return None;
+    }
+
+    let digits = line.chars().take_while(|c| c.is_ascii_digit()).count();
+    if (1..=3).contains(&digits) && line[digits..].starts_with(". ") {
+        return Some((line[..digits].to_string(), line[digits + 2..].trim_start()));
+    }
+    None
+}
+
+/// The sentence in `body` around the first `[label]` style citation,
+/// including grouped ones like `[3, 12]` and ranges like `[10-14]`.
+fn find_numbered_context(body: &str, label: &str) -> Option<String> {
+    let mut search = 0;
+    while let Some(open) = body[search..].find('[').map(|i| search + i) {
+        let close = body[open..].find(']').map(|i| open + i)?;
+        let inner = &body[open + 1..close];
+        if cites_label(inner, label) {
+            return Some(sentence_around(body, open, close + 1));
+        }
+        search = close;
+    }
+    None
+}
+
+fn cites_label(group: &str, label: &str) -> bool {
+    let wanted: Option<u32> = label.parse().ok();
+    group.split([',', ';']).map(str::trim).any(|part| {
+        if part == label {
+            return true;
+        }
+        let (Some(n), Some((from, to))) = (wanted, part.split_once(['-', '\u{2013}'])) else {
+            return false;
+        };
+        match (from.trim().parse::<u32>(), to.trim().parse::<u32>()) {
+            (Ok(from), Ok(to)) => from <= n && n <= to,
+            _ => false,
+        }
+    })
+}
+
+fn extract_latex(text: &str) -> Vec<Reference> {
+    let (body, bibliography) = match text.find("\\begin{thebibliography}") {
+        Some(start) => (&text[..start], &text[start..]),
+        None => {
+            let start = text.find("\\bibitem").unwrap_or(0);
+            (&text[..start], &text[start..])
+        }
+    };
+    let bibliography = bibliography
+        .split("\\end{thebibliography}")
+        .next()
+        .unwrap_or(bibliography);
+
+    bibliography
+        .split("\\bibitem")
+        .skip(1)
+        .filter_map(|item| {
+            let mut rest = item.trim_start();
+            // Optional `[label]` before the key
+            if rest.starts_with('[') {
+                rest = &rest[rest.find(']')? + 1..];
+            }
+            let rest = rest.trim_start().strip_prefix('{')?;
+            let close = rest.find('}')?;
+            let key = rest[..close].trim().to_string();
+            let entry = strip_latex(&rest[close + 1..]);
+            Some(Reference {
+                context: find_latex_context(body, &key).unwrap_or_default(),
+                label: Some(key),
+                ..Reference::from_raw(&entry)
+            })
+        })
+        .collect()
+}
+
+/// The sentence around the first `\cite`-family command naming `key`.
+fn find_latex_context(body: &str, key: &str) -> Option<String> {
+    let mut search = 0;
+    while let Some(start) = body[search..].find("\\cite").map(|i| search + i) {
+        let open = body[start..].find('{').map(|i| start + i)?;
+        let close = body[open..].find('}').map(|i| open + i)?;
+        if body[open + 1..close].split(',').any(|k| k.trim() == key) {
+            return Some(strip_latex(&sentence_around(body, start, close + 1)));
+        }
+        search = close;
+    }
+    None
+}
+
+/// Removes common LaTeX markup from a bibliography entry or sentence.
+fn strip_latex(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                let mut command = String::new();
+                while let Some(&next) = chars.peek() {
+                    if next.is_ascii_alphabetic() {
+                        command.push(next);
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+                if command.is_empty() {
+                    // Escaped character such as `\&`
+                    if let Some(next) = chars.next() {
+                        out.push(next);
+                    }
+                } else if command.starts_with("cite") {
+                    // Replace the citation and its keys with a marker
+                    while chars.peek() == Some(&'[') {
+                        chars.by_ref().find(|&c| c == ']');
+                    }
+                    if chars.peek() == Some(&'{') {
+                        chars.by_ref().find(|&c| c == '}');
+                    }
+                    out.push_str("[…]");
+                } else if command == "newblock" {
+                    out.push(' ');
+                }
+            }
+            '~' => out.push(' '),
+            '{' | '}' => {}
+            '%' => {
+                // Comment to end of line
+                for next in chars.by_ref() {
+                    if next == '\n' {
+                        break;
+                    }
+                }
+            }
+            c => out.push(c),
+        }
+    }
+    collapse_whitespace(&out)
+}
+
+/// The sentence containing `text[start..end]`, bounded to
+/// [`MAX_CONTEXT_LEN`] characters.
+fn sentence_around(text: &str, start: usize, end: usize) -> String {
+    // Sentence ends are punctuation followed by whitespace, or a blank line
+    let ends_sentence = |i: usize, c: char| match c {
+        '.' | '?' | '!' => {
+            text[i + 1..].is_empty() || text[i + 1..].starts_with(char::is_whitespace)
+        }
+        '\n' => text[..i].ends_with('\n'),
+        _ => false,
+    };
+    let from = text[..start]
+        .char_indices()
+        .rev()
+        .find(|&(i, c)| ends_sentence(i, c))
+        .map(|(i, _)| i + 1)
+        .unwrap_or(0);
+    let to = text[end..]
+        .char_indices()
+        .find(|&(i, c)| c != '\n' && ends_sentence(end + i, c))
+        .map(|(i, _)| end + i + 1)
+        .unwrap_or(text.len());
+
+    let sentence = collapse_whitespace(&text[from..to]);
+    if sentence.chars().count() <= MAX_CONTEXT_LEN {
+        sentence
+    } else {
+        let mut truncated: String = sentence.chars().take(MAX_CONTEXT_LEN).collect();
+        truncated.push('…');
+        truncated
+    }
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
@@ -0,0 +1,143 @@
+use anyhow::Result;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::arxiv::models::split_version;
+use crate::db::models::{CitationLink, StoredPaper};
+use crate::db::store::PaperStore;
+
+/// Which citation edges a traversal follows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    /// From a paper to the papers it cites
+    References,
+    /// From a paper to the papers citing it
+    CitedBy,
+    Both,
+}
+
+/// A paper related to another through shared citations.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Related {
+    pub paper: StoredPaper,
+    /// Number of shared citing papers or shared references
+    pub strength: usize,
+}
+
+/// A paper reached by [`neighborhood`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Neighbor {
+    pub paper: StoredPaper,
+    /// Number of citation hops from the starting paper
+    pub distance: usize,
+}
+
+/// Papers cited by `arxiv_id`.
+pub async fn references(store: &dyn PaperStore, arxiv_id: &str) -> Result<Vec<CitationLink>> {
+    store.get_references(arxiv_id).await
+}
+
+/// Papers citing `arxiv_id`.
+pub async fn cited_by(store: &dyn PaperStore, arxiv_id: &str) -> Result<Vec<CitationLink>> {
+    store.get_cited_by(arxiv_id).await
+}
+
+/// Papers most often cited alongside `arxiv_id`: for every paper citing it,
+/// each other reference of that paper counts once.
+pub async fn co_cited(
+    store: &dyn PaperStore,
+    arxiv_id: &str,
+    limit: usize,
+) -> Result<Vec<Related>> {
+    let arxiv_id = split_version(arxiv_id).0;
+    let mut counts: HashMap<String, Related> = HashMap::new();
+    for citing in store.get_cited_by(arxiv_id).await? {
+        for link in store.get_references(&citing.paper.arxiv_id).await? {
+            if link.paper.arxiv_id != arxiv_id {
+                count(&mut counts, link.paper);
+            }
+        }
+    }
+    Ok(ranked(counts, limit))
+}
+
+/// Papers sharing the most references with `arxiv_id` (bibliographic
+/// coupling).
+pub async fn co_referencing(
+    store: &dyn PaperStore,
+    arxiv_id: &str,
+    limit: usize,
+) -> Result<Vec<Related>> {
+    let arxiv_id = split_version(arxiv_id).0;
+    let mut counts: HashMap<String, Related> = HashMap::new();
+    for reference in store.get_references(arxiv_id).await? {
+        for link in store.get_cited_by(&reference.paper.arxiv_id).await? {
+            if link.paper.arxiv_id != arxiv_id {
+                count(&mut counts, link.paper);
+            }
+        }
+    }
+    Ok(ranked(counts, limit))
+}
+
+/// Every paper within `hops` citation steps of `arxiv_id` along
+/// `direction`, nearest first, stopping after `max_papers`.
+pub async fn neighborhood(
+    store: &dyn PaperStore,
+    arxiv_id: &str,
+    hops: usize,
+    direction: Direction,
+    max_papers: usize,
+) -> Result<Vec<Neighbor>> {
+    let start = split_version(arxiv_id).0.to_string();
+    let mut seen: HashSet<String> = HashSet::from([start.clone()]);
+    let mut queue: VecDeque<(String, usize)> = VecDeque::from([(start, 0)]);
+    let mut neighbors = Vec::new();
+
+    while let Some((current, distance)) = queue.pop_front() {
+        if distance == hops {
+            continue;
+        }
+
+        let mut links = Vec::new();
+        if direction != Direction::CitedBy {
+            links.extend(store.get_references(&current).await?);
+        }
+        if direction != Direction::References {
+            links.extend(store.get_cited_by(&current).await?);
+        }
+
+        for link in links {
+            if !seen.insert(link.paper.arxiv_id.clone()) {
+                continue;
+            }
+            queue.push_back((link.paper.arxiv_id.clone(), distance + 1));
+            neighbors.push(Neighbor {
+                paper: link.paper,
+                distance: distance + 1,
+            });
+            if neighbors.len() >= max_papers {
+                return Ok(neighbors);
+            }
+        }
+    }
+
+    Ok(neighbors)
+}
+
+fn count(counts: &mut HashMap<String, Related>, paper: StoredPaper) {
+    counts
+        .entry(paper.arxiv_id.clone())
+        .or_insert(Related { paper, strength: 0 })
+        .strength += 1;
+}
+
+fn ranked(counts: HashMap<String, Related>, limit: usize) -> Vec<Related> {
+    let mut related: Vec<Related> = counts.into_values().collect();
+    related.sort_by(|a, b| {
+        b.strength
+            .cmp(&a.strength)
+            .then_with(|| a.paper.arxiv_id.cmp(&b.paper.arxiv_id))
+    });
+    related.truncate(limit);
+    related
+}
@@ -0,0 +1,116 @@
+use anyhow::Result;
+use std::collections::BTreeSet;
+
+use super::provider::ReferenceProvider;
+use super::resolve::{ReferenceResolver, Resolution};
+use crate::arxiv::models::Paper;
+use crate::db::store::PaperStore;
+
+#[derive(Debug, Default)]
+pub struct CitationReport {
+    /// Papers whose references were indexed
+    pub papers: usize,
+    pub references: usize,
+    /// `Cites` edges written
+    pub linked: usize,
+    /// Cited arXiv papers that are not in the library yet, worth ingesting
+    pub not_in_library: BTreeSet<String>,
+    /// References that could not be tied to an arXiv paper
+    pub unresolved: usize,
+    /// arXiv IDs whose references could not be indexed, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+impl std::fmt::Display for CitationReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} papers, {} references: {} linked, {} not in library, {} unresolved, {} failed",
+            self.papers,
+            self.references,
+            self.linked,
+            self.not_in_library.len(),
+            self.unresolved,
+            self.failed.len()
+        )
+    }
+}
+
+/// Builds `Cites` edges from the references a [`ReferenceProvider`] finds
+/// for each paper.
+///
+/// Indexing a paper replaces the citations it had before, so the job can be
+/// re-run after more papers are ingested to link references that were not
+/// in the library the first time.
+pub struct CitationIndexer<'a> {
+    store: &'a dyn PaperStore,
+    provider: &'a dyn ReferenceProvider,
+}
+
+impl<'a> CitationIndexer<'a> {
+    pub fn new(store: &'a dyn PaperStore, provider: &'a dyn ReferenceProvider) -> Self {
+        Self { store, provider }
+    }
+
+    /// Indexes the given papers, which should already be stored.
+    pub async fn index_papers(&self, papers: &[Paper]) -> Result<CitationReport> {
+        let resolver = ReferenceResolver::from_store(self.store).await?;
+        let mut report = CitationReport::default();
+        for paper in papers {
+            if let Err(e) = self.index_paper(paper, &resolver, &mut report).await {
+                tracing::warn!("Failed to index citations of {}: {}", paper.arxiv_id, e);
+                report.failed.push((paper.arxiv_id.clone(), e.to_string()));
+            }
+        }
+        Ok(report)
+    }
+
+    /// Indexes every paper in the library.
+    pub async fn index_library(&self) -> Result<CitationReport> {
+        let papers: Vec<Paper> = self
+            .store
+            .list_papers()
+            .await?
+            .iter()
+            .map(|(paper, metadata)| paper.to_paper(metadata))
+            .collect();
+        self.index_papers(&papers).await
+    }
+
+    async fn index_paper(
+        &self,
+        paper: &Paper,
+        resolver: &ReferenceResolver,
+        report: &mut CitationReport,
+    ) -> Result<()> {
+        let references = self.provider.references(paper).await?;
+        let from = paper.base_id();
+
+        self.store.delete_citations(from).await?;
+        for reference in &references {
+            match resolver.resolve(reference) {
+                Resolution::InLibrary(to) if to != from => {
+                    self.store
+                        .add_citation(from, &to, &reference.context)
+                        .await?;
+                    report.linked += 1;
+                }
+                Resolution::InLibrary(_) => {}
+                Resolution::NotInLibrary(to) => {
+                    report.not_in_library.insert(to);
+                }
+                Resolution::Unresolved => report.unresolved += 1,
+            }
+        }
+
+        tracing::debug!(
+            "Indexed {} references of {} from {}",
+            references.len(),
+            from,
+            self.provider.name()
+        );
+        report.papers += 1;
+        report.references += references.len();
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+pub mod extract;
+pub mod graph;
+pub mod index;
+pub mod provider;
+pub mod resolve;
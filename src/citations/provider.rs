@@ -0,0 +1,77 @@
+use anyhow::{Context, Result};
+use async_trait::async_trait;
+use std::path::{Path, PathBuf};
+
+use super::extract::{extract_references, Reference};
+use crate::arxiv::models::Paper;
+
+/// Source of a paper's bibliography.
+#[async_trait]
+pub trait ReferenceProvider: Send + Sync {
+    /// Short name of the provider, for logs.
+    fn name(&self) -> &str;
+
+    /// The references of `paper`, or an empty list if the provider knows
+    /// nothing about it.
+    async fn references(&self, paper: &Paper) -> Result<Vec<Reference>>;
+}
+
+/// Reads references from a local directory, one file per paper named by
+/// its arXiv ID without version (old-style IDs use `_` for `/`):
+///
+/// - `<id>.json`: a list of [`Reference`] records
+/// - `<id>.tex`: LaTeX source, parsed for `\bibitem` entries
+/// - `<id>.txt`: text extracted from the PDF, parsed for a references
+///   section
+///
+/// Useful for tests and for bibliographies prepared offline.
+pub struct FixtureProvider {
+    dir: PathBuf,
+}
+
+impl FixtureProvider {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn file(&self, arxiv_id: &str, extension: &str) -> PathBuf {
+        self.dir
+            .join(format!("{}.{}", arxiv_id.replace('/', "_"), extension))
+    }
+}
+
+#[async_trait]
+impl ReferenceProvider for FixtureProvider {
+    fn name(&self) -> &str {
+        "fixture"
+    }
+
+    async fn references(&self, paper: &Paper) -> Result<Vec<Reference>> {
+        let arxiv_id = paper.base_id();
+
+        let json = self.file(arxiv_id, "json");
+        if json.exists() {
+            let content = tokio::fs::read_to_string(&json)
+                .await
+                .with_context(|| format!("Failed to read {}", json.display()))?;
+            return serde_json::from_str(&content)
+                .with_context(|| format!("Invalid references in {}", json.display()));
+        }
+
+        for extension in ["tex", "txt"] {
+            let path = self.file(arxiv_id, extension);
+            if path.exists() {
+                let text = tokio::fs::read_to_string(&path)
+                    .await
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                return Ok(extract_references(&text));
+            }
+        }
+
+        Ok(Vec::new())
+    }
+}
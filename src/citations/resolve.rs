@@ -0,0 +1,95 @@
+use anyhow::Result;
+use std::collections::HashMap;
+
+use super::extract::{find_arxiv_ids, Reference};
+use crate::arxiv::models::split_version;
+use crate::db::store::PaperStore;
+
+/// What a reference points at.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// A paper stored in the library
+    InLibrary(String),
+    /// An arXiv paper that has not been ingested yet
+    NotInLibrary(String),
+    /// Not an arXiv paper, or not enough information to tell
+    Unresolved,
+}
+
+/// Maps references to arXiv IDs using the identifiers they carry and, for
+/// references without one, the DOIs and titles of papers in the library.
+pub struct ReferenceResolver {
+    arxiv_ids: std::collections::HashSet<String>,
+    by_doi: HashMap<String, String>,
+    by_title: HashMap<String, String>,
+}
+
+impl ReferenceResolver {
+    /// Indexes every paper currently in `store`.
+    pub async fn from_store(store: &dyn PaperStore) -> Result<Self> {
+        let mut resolver = Self {
+            arxiv_ids: Default::default(),
+            by_doi: HashMap::new(),
+            by_title: HashMap::new(),
+        };
+        for (paper, metadata) in store.list_papers().await? {
+            if !metadata.doi.is_empty() {
+                resolver
+                    .by_doi
+                    .insert(metadata.doi.to_lowercase(), paper.arxiv_id.clone());
+            }
+            resolver
+                .by_title
+                .insert(title_key(&paper.title), paper.arxiv_id.clone());
+            resolver.arxiv_ids.insert(paper.arxiv_id);
+        }
+        Ok(resolver)
+    }
+
+    pub fn resolve(&self, reference: &Reference) -> Resolution {
+        let arxiv_id = reference
+            .arxiv_id
+            .as_deref()
+            .map(|id| split_version(id).0.to_string())
+            .or_else(|| find_arxiv_ids(&reference.raw).into_iter().next())
+            .or_else(|| {
+                reference
+                    .doi
+                    .as_ref()
+                    .and_then(|doi| self.by_doi.get(&doi.to_lowercase()).cloned())
+            })
+            .or_else(|| self.match_title(reference));
+
+        match arxiv_id {
+            Some(id) if self.arxiv_ids.contains(&id) => Resolution::InLibrary(id),
+            Some(id) => Resolution::NotInLibrary(id),
+            None => Resolution::Unresolved,
+        }
+    }
+
+    /// Looks up the quoted title if there is one, otherwise any library
+    /// title that appears verbatim in the entry.
+    fn match_title(&self, reference: &Reference) -> Option<String> {
+        if let Some(title) = &reference.title {
+            return self.by_title.get(&title_key(title)).cloned();
+        }
+        let raw = title_key(&reference.raw);
+        self.by_title
+            .iter()
+            // Short titles match too much by accident
+            .filter(|(title, _)| title.split(' ').count() >= 4 && raw.contains(title.as_str()))
+            .max_by_key(|(title, _)| title.len())
+            .map(|(_, arxiv_id)| arxiv_id.clone())
+    }
+}
+
+/// Lowercase alphanumeric words separated by single spaces, so titles
+/// compare equal across punctuation and LaTeX differences.
+fn title_key(title: &str) -> String {
+    title
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
@@ -10,14 +10,63 @@ pub struct Config {
     pub ui: UiConfig,
     pub storage: StorageConfig,
     pub search: SearchConfig,
+    #[serde(default)]
+    pub ingest: IngestConfig,
+    #[serde(default)]
+    pub export: ExportConfig,
     pub logging: LoggingConfig,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ArxivConfig {
+    /// Time between API requests, shared by every client in the process
     pub rate_limit_delay_ms: u64,
+    /// API requests that may go out back to back after a quiet period
+    #[serde(default = "default_rate_limit_burst")]
+    pub rate_limit_burst: u32,
+    /// Time between PDF downloads, budgeted apart from API requests
+    #[serde(default = "default_pdf_rate_limit_delay_ms")]
+    pub pdf_rate_limit_delay_ms: u64,
+    #[serde(default = "default_rate_limit_burst")]
+    pub pdf_rate_limit_burst: u32,
     pub max_results: u32,
     pub default_categories: Vec<String>,
+    /// Time allowed for one request, including reading the response
+    #[serde(default = "default_arxiv_timeout_ms")]
+    pub timeout_ms: u64,
+    #[serde(default = "default_arxiv_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// Retries for timeouts, 5xx responses and rate limiting
+    #[serde(default = "default_arxiv_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each further one and
+    /// jittered; a `Retry-After` from arXiv takes precedence
+    #[serde(default = "default_arxiv_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    /// Longest `Retry-After` worth waiting for; a longer one fails the
+    /// request as rate limited
+    #[serde(default = "default_arxiv_max_retry_after_ms")]
+    pub max_retry_after_ms: u64,
+}
+
+impl Default for ArxivConfig {
+    fn default() -> Self {
+        Self {
+            rate_limit_delay_ms: 3_000,
+            rate_limit_burst: default_rate_limit_burst(),
+            pdf_rate_limit_delay_ms: default_pdf_rate_limit_delay_ms(),
+            pdf_rate_limit_burst: default_rate_limit_burst(),
+            max_results: 100,
+            default_categories: ["cs.AI", "cs.LG", "cs.CL", "cs.CV"]
+                .map(String::from)
+                .to_vec(),
+            timeout_ms: default_arxiv_timeout_ms(),
+            connect_timeout_ms: default_arxiv_connect_timeout_ms(),
+            max_retries: default_arxiv_max_retries(),
+            retry_backoff_ms: default_arxiv_retry_backoff_ms(),
+            max_retry_after_ms: default_arxiv_max_retry_after_ms(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -25,14 +74,154 @@ pub struct HelixDbConfig {
     pub endpoint: String,
     pub port: u16,
     pub api_key: String,
+    /// Time allowed for one query, including reading the response
+    #[serde(default = "default_helix_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Time allowed to open a connection, also used for health checks
+    #[serde(default = "default_helix_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// Retries for queries that fail before reaching HelixDB
+    #[serde(default = "default_helix_max_retries")]
+    pub max_retries: u32,
+    /// Delay before the first retry, doubled for each further one
+    #[serde(default = "default_helix_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    #[serde(default)]
+    pub offline: OfflineConfig,
+}
+
+impl Default for HelixDbConfig {
+    fn default() -> Self {
+        Self {
+            endpoint: "http://localhost".to_string(),
+            port: 6969,
+            api_key: String::new(),
+            timeout_ms: default_helix_timeout_ms(),
+            connect_timeout_ms: default_helix_connect_timeout_ms(),
+            max_retries: default_helix_max_retries(),
+            retry_backoff_ms: default_helix_retry_backoff_ms(),
+            offline: OfflineConfig::default(),
+        }
+    }
+}
+
+/// Offline mode, see `db::offline`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OfflineConfig {
+    /// Serve reads from a local cache and queue writes while HelixDB is down
+    pub enabled: bool,
+    /// Holds the cache and the queue of writes to replay
+    pub dir: PathBuf,
+    /// Seconds between reconnection attempts while offline
+    pub reconnect_interval_secs: u64,
+}
+
+impl Default for OfflineConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dir: PathBuf::from("~/.local/share/arxiv-tui/offline"),
+            reconnect_interval_secs: 30,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingsConfig {
+    #[serde(default)]
+    pub backend: EmbeddingBackendKind,
+    /// Managed model installed under `models_dir`; overrides `model_path`
+    #[serde(default)]
+    pub model: Option<String>,
     pub model_path: PathBuf,
+    /// Defaults to `tokenizer.json` next to `model_path`
+    #[serde(default)]
+    pub tokenizer_path: Option<PathBuf>,
     pub batch_size: usize,
     pub device: String,
-    pub dimension: usize,
+    /// Taken from the installed model when `model` is set
+    #[serde(default)]
+    pub dimension: Option<usize>,
+    #[serde(default = "default_models_dir")]
+    pub models_dir: PathBuf,
+    /// Where managed models are downloaded from
+    #[serde(default = "default_model_base_url")]
+    pub model_base_url: String,
+    #[serde(default)]
+    pub openai: OpenAiEmbeddingsConfig,
+    #[serde(default)]
+    pub chunking: ChunkingConfig,
+    /// Reuse vectors from the on-disk embedding cache
+    #[serde(default = "default_true")]
+    pub cache: bool,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingBackendKind {
+    /// Local ONNX model at `model_path`
+    #[default]
+    Onnx,
+    /// Deterministic hash vectors, for tests only
+    Hash,
+    /// OpenAI-compatible `/v1/embeddings` endpoint
+    OpenAi,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OpenAiEmbeddingsConfig {
+    pub base_url: String,
+    pub model: String,
+    pub api_key: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChunkingConfig {
+    /// Tokens per chunk, capped at what the model can read
+    pub max_tokens: usize,
+    /// Tokens shared between consecutive chunks
+    pub overlap: usize,
+    pub pooling: PoolingStrategy,
+    /// Weight of the title vector when `pooling = "title_weighted"`
+    pub title_weight: f32,
+    /// Keep per-chunk vectors so search can match individual passages
+    pub store_chunks: bool,
+}
+
+impl Default for ChunkingConfig {
+    fn default() -> Self {
+        Self {
+            max_tokens: 256,
+            overlap: 32,
+            pooling: PoolingStrategy::Mean,
+            title_weight: 1.0,
+            store_chunks: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PoolingStrategy {
+    /// Average of all chunk vectors over title + abstract
+    #[default]
+    Mean,
+    /// Title embedded on its own and added, weighted, to the mean of the
+    /// abstract chunks
+    TitleWeighted,
+}
+
+impl Default for OpenAiEmbeddingsConfig {
+    fn default() -> Self {
+        Self {
+            base_url: "http://localhost:8080".to_string(),
+            model: String::new(),
+            api_key: String::new(),
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -49,6 +238,23 @@ pub struct StorageConfig {
     pub cache_dir: PathBuf,
     pub download_dir: PathBuf,
     pub max_cache_size_mb: u64,
+    #[serde(default)]
+    pub backend: StoreBackendKind,
+    /// Single-file database used by the local store
+    #[serde(default = "default_library_path")]
+    pub library_path: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreBackendKind {
+    /// HelixDB when configured and reachable, the local store otherwise
+    #[default]
+    Auto,
+    /// Always HelixDB; fail if it is not reachable
+    Helix,
+    /// Always the embedded local store
+    Local,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -56,6 +262,161 @@ pub struct SearchConfig {
     pub min_similarity: f32,
     pub max_results: usize,
     pub enable_fuzzy: bool,
+    #[serde(default)]
+    pub similar: SimilarPapersConfig,
+}
+
+/// Precomputed "similar papers" links, see `db::similarity`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SimilarPapersConfig {
+    /// Neighbors linked per paper
+    pub top_k: usize,
+    /// Pairs scoring below this are never linked
+    pub min_similarity: f32,
+}
+
+impl Default for SimilarPapersConfig {
+    fn default() -> Self {
+        Self {
+            top_k: 10,
+            min_similarity: 0.5,
+        }
+    }
+}
+
+/// Batched ingestion, see `ingest::pipeline`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IngestConfig {
+    /// Papers requested from arXiv per page
+    pub page_size: u32,
+    /// Papers embedded together
+    pub batch_size: usize,
+    /// Batches embedded and written at the same time
+    pub concurrency: usize,
+    /// Store writes in flight per batch
+    pub write_concurrency: usize,
+    /// Progress of interrupted runs, so they can resume
+    pub checkpoint_path: PathBuf,
+}
+
+impl Default for IngestConfig {
+    fn default() -> Self {
+        Self {
+            page_size: 100,
+            batch_size: 32,
+            concurrency: 2,
+            write_concurrency: 8,
+            checkpoint_path: PathBuf::from("~/.local/share/arxiv-tui/ingest-checkpoint.json"),
+        }
+    }
+}
+
+/// Citation exports, see `export::exporter`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportConfig {
+    /// Format the TUI exports to until another one is picked
+    pub format: ExportFormat,
+    /// Directory the TUI writes exports to
+    pub dir: PathBuf,
+    /// Template for each paper in Markdown exports, see `export::markdown`
+    pub markdown_template: String,
+}
+
+impl Default for ExportConfig {
+    fn default() -> Self {
+        Self {
+            format: ExportFormat::default(),
+            dir: PathBuf::from("~/Documents/arxiv-papers/exports"),
+            markdown_template: DEFAULT_MARKDOWN_TEMPLATE.to_string(),
+        }
+    }
+}
+
+pub const DEFAULT_MARKDOWN_TEMPLATE: &str = "## {title}
+
+- **Authors:** {authors}
+- **Published:** {published}
+- **arXiv:** [{arxiv_id}]({url})
+- **Categories:** {categories}
+
+{abstract}
+";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ExportFormat {
+    #[default]
+    Bibtex,
+    /// Research Information Systems, read by Zotero, EndNote and Mendeley
+    Ris,
+    /// Citation Style Language JSON, read by Zotero and pandoc
+    CslJson,
+    /// Notes for Obsidian and other Markdown editors
+    Markdown,
+}
+
+impl ExportFormat {
+    pub const ALL: [ExportFormat; 4] = [
+        ExportFormat::Bibtex,
+        ExportFormat::Ris,
+        ExportFormat::CslJson,
+        ExportFormat::Markdown,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ExportFormat::Bibtex => "bibtex",
+            ExportFormat::Ris => "ris",
+            ExportFormat::CslJson => "csl-json",
+            ExportFormat::Markdown => "markdown",
+        }
+    }
+
+    /// File extension, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Bibtex => "bib",
+            ExportFormat::Ris => "ris",
+            ExportFormat::CslJson => "json",
+            ExportFormat::Markdown => "md",
+        }
+    }
+
+    /// The format after this one, wrapping around, for cycling in the TUI.
+    pub fn next(self) -> Self {
+        let index = Self::ALL.iter().position(|f| *f == self).unwrap_or(0);
+        Self::ALL[(index + 1) % Self::ALL.len()]
+    }
+}
+
+impl std::fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ExportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim().to_lowercase();
+        Self::ALL
+            .into_iter()
+            .find(|format| format.as_str() == s || format.extension() == s)
+            .or(match s.as_str() {
+                "csl" | "csljson" => Some(ExportFormat::CslJson),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unknown export format {:?} (expected bibtex, ris, csl-json or markdown)",
+                    s
+                )
+            })
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -77,6 +438,11 @@ impl Config {
         // Expand home directory paths
         cfg.storage.cache_dir = expand_tilde(&cfg.storage.cache_dir);
         cfg.storage.download_dir = expand_tilde(&cfg.storage.download_dir);
+        cfg.storage.library_path = expand_tilde(&cfg.storage.library_path);
+        cfg.helixdb.offline.dir = expand_tilde(&cfg.helixdb.offline.dir);
+        cfg.ingest.checkpoint_path = expand_tilde(&cfg.ingest.checkpoint_path);
+        cfg.export.dir = expand_tilde(&cfg.export.dir);
+        cfg.embeddings.models_dir = expand_tilde(&cfg.embeddings.models_dir);
         cfg.logging.log_file = expand_tilde(&cfg.logging.log_file);
 
         // Create directories if they don't exist
@@ -87,6 +453,66 @@ impl Config {
     }
 }
 
+fn default_library_path() -> PathBuf {
+    PathBuf::from("~/.local/share/arxiv-tui/library.json")
+}
+
+fn default_rate_limit_burst() -> u32 {
+    1
+}
+
+fn default_pdf_rate_limit_delay_ms() -> u64 {
+    1_000
+}
+
+fn default_arxiv_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_arxiv_connect_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_arxiv_max_retries() -> u32 {
+    3
+}
+
+fn default_arxiv_retry_backoff_ms() -> u64 {
+    3_000
+}
+
+fn default_arxiv_max_retry_after_ms() -> u64 {
+    60_000
+}
+
+fn default_helix_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_helix_connect_timeout_ms() -> u64 {
+    2_000
+}
+
+fn default_helix_max_retries() -> u32 {
+    3
+}
+
+fn default_helix_retry_backoff_ms() -> u64 {
+    200
+}
+
+fn default_models_dir() -> PathBuf {
+    PathBuf::from("./models")
+}
+
+fn default_model_base_url() -> String {
+    "https://huggingface.co".to_string()
+}
+
+fn default_true() -> bool {
+    true
+}
+
 fn expand_tilde(path: &PathBuf) -> PathBuf {
     if let Some(path_str) = path.to_str() {
         if path_str.starts_with("~/") {
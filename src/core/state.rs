@@ -1,4 +1,5 @@
 use crate::arxiv::models::Paper;
+use crate::config::ExportFormat;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum View {
@@ -14,6 +15,12 @@ pub struct AppState {
     pub search_results: Vec<Paper>,
     pub selected_paper: Option<Paper>,
     pub selected_index: usize,
+    /// Keys go to the search bar while it is focused
+    pub search_editing: bool,
+    /// Citation format used by the export key
+    pub export_format: ExportFormat,
+    /// Outcome of the last action, shown in the footer
+    pub status_message: Option<String>,
 }
 
 impl AppState {
@@ -24,6 +31,9 @@ impl AppState {
             search_results: Vec::new(),
             selected_paper: None,
             selected_index: 0,
+            search_editing: false,
+            export_format: ExportFormat::default(),
+            status_message: None,
         }
     }
 
@@ -33,7 +43,10 @@ impl AppState {
 
     pub fn select_next(&mut self) {
         if !self.search_results.is_empty() {
-            self.selected_index = (self.selected_index + 1) % self.search_results.len();
+            // The first move after a search selects the top result
+            if self.selected_paper.is_some() {
+                self.selected_index = (self.selected_index + 1) % self.search_results.len();
+            }
             self.selected_paper = Some(self.search_results[self.selected_index].clone());
         }
     }
@@ -1,114 +1,1047 @@
-use anyhow::Result;
-use helix_rs::{HelixDB, HelixDBClient};
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use serde::Deserialize;
 use serde_json::{json, Value};
+use std::time::Duration;
 
+use super::migrations;
+use super::models::{
+    decode_count, decode_one, decode_rows, decode_scored, ChunkSearchResult, CitationLink,
+    Collection, CollectionPaper, EmbeddingCoverage, NewCollection, PaperMetadata, ReadState,
+    SearchResult, StoredChunk, StoredPaper, UpsertOutcome,
+};
+use super::schema::{self, QueryIssue, SchemaReport, SCHEMA_VERSION};
+use super::store::{check_manual, check_vector, prepare_collection, PaperStore};
+use super::tags::encode_tags;
+use crate::arxiv::models::{split_version, Paper};
 use crate::config::HelixDbConfig;
-use crate::arxiv::models::Paper;
+use crate::embeddings::backend::ModelInfo;
+use crate::embeddings::chunking::ChunkEmbedding;
 
+/// How many papers to fetch per query when evaluating smart collections.
+const SCAN_PAGE_SIZE: u32 = 256;
+
+/// Client for the HQL queries in `helix/queries.hx`, each served by
+/// HelixDB as `POST /<QueryName>` with a JSON object of parameters.
+#[derive(Clone)]
 pub struct DbClient {
-    client: HelixDB,
+    /// `endpoint:port`
+    base_url: String,
+    api_key: Option<String>,
+    http: reqwest::Client,
+    timeout: Duration,
+    connect_timeout: Duration,
+    max_retries: u32,
+    retry_backoff: Duration,
+}
+
+/// Why a request to HelixDB failed.
+#[derive(Debug, thiserror::Error)]
+pub enum DbError {
+    #[error("cannot reach HelixDB at {address}: {source}")]
+    Unreachable {
+        address: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    #[error("HelixDB did not answer within {0:?}")]
+    Timeout(Duration),
+    #[error("HelixDB returned HTTP {status}: {body}")]
+    Status { status: u16, body: String },
+}
+
+impl DbError {
+    /// Whether the request certainly was not applied, so sending it again
+    /// is safe even for writes.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            DbError::Unreachable { source, .. } => source.is_connect(),
+            DbError::Timeout(_) => false,
+            DbError::Status { status, .. } => matches!(status, 429 | 502..=504),
+        }
+    }
+
+    /// Whether HelixDB is down, as opposed to rejecting the request.
+    pub fn is_unavailable(&self) -> bool {
+        match self {
+            DbError::Unreachable { .. } | DbError::Timeout(_) => true,
+            DbError::Status { status, .. } => matches!(status, 502..=504),
+        }
+    }
+}
+
+/// Whether `error` was caused by HelixDB being unavailable.
+pub fn is_unavailable(error: &anyhow::Error) -> bool {
+    error.chain().any(|cause| {
+        cause
+            .downcast_ref::<DbError>()
+            .is_some_and(DbError::is_unavailable)
+    })
+}
+
+/// An `N::SchemaInfo` record.
+#[derive(Deserialize)]
+struct SchemaInfo {
+    version: u32,
+}
+
+/// Vector of a `V::Paper` record.
+#[derive(Deserialize)]
+struct PaperVector {
+    #[serde(alias = "embedding", alias = "vector")]
+    data: Vec<f32>,
+}
+
+/// Properties of an `E::SimilarTo` edge.
+#[derive(Deserialize)]
+struct SimilarToEdge {
+    similarity_score: f32,
+}
+
+/// Properties of an `E::Cites` edge.
+#[derive(Deserialize)]
+struct CitesEdge {
+    #[serde(default)]
+    context: String,
+}
+
+/// Properties of an `E::InCollection` edge.
+#[derive(Deserialize)]
+struct Membership {
+    #[serde(default)]
+    added_at: String,
 }
 
 impl DbClient {
     pub fn new(config: &HelixDbConfig) -> Result<Self> {
-        let api_key = if config.api_key.is_empty() {
-            None
-        } else {
-            Some(config.api_key.as_str())
+        let timeout = Duration::from_millis(config.timeout_ms);
+        let connect_timeout = Duration::from_millis(config.connect_timeout_ms);
+        let http = reqwest::Client::builder()
+            .timeout(timeout)
+            .connect_timeout(connect_timeout)
+            .build()
+            .context("Failed to build HTTP client")?;
+
+        Ok(Self {
+            base_url: format!("{}:{}", config.endpoint, config.port),
+            api_key: Some(config.api_key.clone()).filter(|key| !key.is_empty()),
+            http,
+            timeout,
+            connect_timeout,
+            max_retries: config.max_retries,
+            retry_backoff: Duration::from_millis(config.retry_backoff_ms),
+        })
+    }
+
+    /// `endpoint:port` of the server.
+    pub fn address(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Runs a trivial query once, without retries, and returns how long it
+    /// took. Fails if HelixDB does not answer within the connect timeout.
+    pub async fn ping(&self) -> Result<Duration> {
+        let started = std::time::Instant::now();
+        let payload = json!({});
+        let probe = self.post("CountPapers", &payload);
+        let response = tokio::time::timeout(self.connect_timeout, probe)
+            .await
+            .map_err(|_| DbError::Timeout(self.connect_timeout))??;
+        check_status(response).await?;
+        Ok(started.elapsed())
+    }
+
+    pub async fn count_papers(&self) -> Result<u64> {
+        let result = self.query("CountPapers", &json!({})).await?;
+        decode_count("CountPapers", &result)
+    }
+
+    /// Highest schema version recorded on the server, `None` for a
+    /// database that predates versioning.
+    pub async fn schema_version(&self) -> Result<Option<u32>> {
+        let result = self.query("GetSchemaVersion", &json!({})).await?;
+        let versions: Vec<SchemaInfo> = decode_rows("GetSchemaVersion", &result, "s")?;
+        Ok(versions.iter().map(|info| info.version).max())
+    }
+
+    /// Checks that the registered read-only queries are deployed with the
+    /// expected parameters, and reads the server's schema version.
+    ///
+    /// Each read-only query is called with no parameters: a 404 means it is
+    /// missing, while a query that expects parameters but accepts the call,
+    /// or that complains about a parameter the registry does not know, is
+    /// mismatched. Write queries are never called, since a call the server
+    /// accepts would change the database; they are only checked against
+    /// `helix/queries.hx` by [`schema::check_hql`].
+    pub async fn check_schema(&self) -> Result<SchemaReport> {
+        let mut report = SchemaReport::default();
+
+        for spec in schema::QUERIES {
+            if !spec.read_only {
+                report.unchecked += 1;
+                continue;
+            }
+            let response = self.post(spec.name, &json!({})).await?;
+            let status = response.status();
+            let body = response.text().await.unwrap_or_default();
+            if let Some(issue) = schema::probe_issue(spec, status.as_u16(), &body) {
+                report.issues.push(issue);
+            }
+        }
+
+        let schema_query_missing = report
+            .issues
+            .iter()
+            .any(|issue| matches!(issue, QueryIssue::Missing(name) if name == "GetSchemaVersion"));
+        if !schema_query_missing {
+            report.server_version = self.schema_version().await?;
+        }
+        Ok(report)
+    }
+
+    /// Applies pending migrations and records each in `N::SchemaInfo`.
+    ///
+    /// An empty database without a recorded version is stamped with the
+    /// current version; one holding papers is assumed to be at version 1.
+    /// Returns the versions applied.
+    pub async fn migrate(&self) -> Result<Vec<u32>> {
+        let current = match self.schema_version().await? {
+            Some(version) => version,
+            None => {
+                if self.count_papers().await? == 0 {
+                    self.record_version(SCHEMA_VERSION, "initial schema")
+                        .await?;
+                    return Ok(Vec::new());
+                }
+                1
+            }
         };
+        if current > SCHEMA_VERSION {
+            bail!(
+                "HelixDB schema is at version {}, newer than this build's {}",
+                current,
+                SCHEMA_VERSION
+            );
+        }
 
-        let client = HelixDB::new(
-            Some(&config.endpoint),
-            Some(config.port),
-            api_key,
-        );
+        let mut applied = Vec::new();
+        for migration in migrations::pending(current) {
+            tracing::info!(
+                "Migrating HelixDB schema to v{}: {}",
+                migration.version,
+                migration.description
+            );
+            (migration.apply)(self)
+                .await
+                .with_context(|| format!("Migration to schema v{} failed", migration.version))?;
+            self.record_version(migration.version, migration.description)
+                .await?;
+            applied.push(migration.version);
+        }
+        Ok(applied)
+    }
 
-        Ok(Self { client })
+    async fn record_version(&self, version: u32, description: &str) -> Result<()> {
+        let payload = json!({
+            "version": version,
+            "description": description,
+            "applied_at": chrono::Utc::now().to_rfc3339(),
+        });
+
+        self.query("SetSchemaVersion", &payload).await?;
+        Ok(())
     }
 
-    pub async fn add_paper(&self, paper: &Paper, embedding: Vec<f32>) -> Result<Value> {
+    async fn require_collection(&self, name: &str) -> Result<Collection> {
+        self.get_collection(name)
+            .await?
+            .ok_or_else(|| anyhow!("Collection {:?} does not exist", name))
+    }
+
+    /// Runs a citation query returning papers under `key` and their
+    /// `E::Cites` edges under `e`.
+    async fn citation_links(
+        &self,
+        query: &str,
+        arxiv_id: &str,
+        key: &str,
+    ) -> Result<Vec<CitationLink>> {
         let payload = json!({
-            "arxiv_id": paper.arxiv_id,
-            "title": paper.title,
-            "authors": paper.authors_string(),
-            "published": paper.published.to_rfc3339(),
-            "categories": paper.categories_string(),
-            "abstract_preview": paper.abstract_preview(200),
-            "embedding": embedding,
+            "arxiv_id": split_version(arxiv_id).0,
+        });
+
+        let result = self.query(query, &payload).await?;
+        let papers: Vec<StoredPaper> = decode_rows(query, &result, key)?;
+        let edges: Vec<CitesEdge> = decode_rows(query, &result, "e")?;
+        if papers.len() != edges.len() {
+            bail!(
+                "HelixDB {} returned {} papers but {} edges",
+                query,
+                papers.len(),
+                edges.len()
+            );
+        }
+
+        Ok(papers
+            .into_iter()
+            .zip(edges)
+            .map(|(paper, edge)| CitationLink {
+                paper,
+                context: edge.context,
+            })
+            .collect())
+    }
+
+    /// Runs a registered query, retrying with exponential backoff while
+    /// the failure is one that left the database untouched.
+    async fn query(&self, name: &str, payload: &Value) -> Result<Value> {
+        schema::check_call(name, payload)?;
+
+        let mut attempt = 0;
+        let response = loop {
+            let result = match self.post(name, payload).await {
+                Ok(response) => check_status(response).await,
+                Err(e) => Err(e),
+            };
+            match result {
+                Ok(response) => break response,
+                Err(e) if e.is_retryable() && attempt < self.max_retries => {
+                    let delay = self.retry_backoff * 2u32.saturating_pow(attempt);
+                    tracing::debug!(
+                        "HelixDB query {} failed ({}); retrying in {:?}",
+                        name,
+                        e,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+                Err(e) => {
+                    return Err(anyhow::Error::new(e))
+                        .with_context(|| format!("HelixDB query {} failed", name))
+                }
+            }
+        };
+
+        response
+            .json()
+            .await
+            .with_context(|| format!("HelixDB query {} returned invalid JSON", name))
+    }
+
+    /// Sends one request for `name`, whatever the response status.
+    async fn post(&self, name: &str, payload: &Value) -> Result<reqwest::Response, DbError> {
+        let mut request = self
+            .http
+            .post(format!("{}/{}", self.base_url, name))
+            .json(payload);
+        if let Some(api_key) = &self.api_key {
+            request = request.header("x-api-key", api_key);
+        }
+        request.send().await.map_err(|e| {
+            if e.is_timeout() {
+                DbError::Timeout(self.timeout)
+            } else {
+                DbError::Unreachable {
+                    address: self.base_url.clone(),
+                    source: e,
+                }
+            }
+        })
+    }
+}
+
+#[async_trait]
+impl PaperStore for DbClient {
+    fn backend_name(&self) -> &str {
+        "helixdb"
+    }
+
+    async fn add_paper(
+        &self,
+        paper: &Paper,
+        embedding: Vec<f32>,
+        model: &ModelInfo,
+    ) -> Result<StoredPaper> {
+        let payload = paper_payload(paper, embedding, model)?;
+        let result = self.query("AddPaper", &payload).await?;
+        decode_one("AddPaper", &result, "paper")
+    }
+
+    async fn upsert_paper(
+        &self,
+        paper: &Paper,
+        embedding: Vec<f32>,
+        chunks: &[ChunkEmbedding],
+        model: &ModelInfo,
+    ) -> Result<UpsertOutcome> {
+        let arxiv_id = paper.base_id();
+
+        let outcome = match self.get_paper_by_arxiv_id(arxiv_id).await? {
+            None => {
+                self.add_paper(paper, embedding, model).await?;
+                self.add_paper_metadata(paper).await?;
+                UpsertOutcome::Inserted
+            }
+            Some(existing) if existing.is_outdated_by(paper) => {
+                let payload = paper_payload(paper, embedding, model)?;
+                self.query("UpdatePaper", &payload).await?;
+
+                let result = self
+                    .query("UpdatePaperMetadata", &metadata_payload(paper))
+                    .await?;
+                let updated: Vec<PaperMetadata> =
+                    decode_rows("UpdatePaperMetadata", &result, "metadata")?;
+                if updated.is_empty() {
+                    // Stored before metadata was written alongside the vector
+                    self.add_paper_metadata(paper).await?;
+                }
+
+                self.delete_paper_chunks(arxiv_id).await?;
+                UpsertOutcome::Updated
+            }
+            Some(_) => return Ok(UpsertOutcome::Skipped),
+        };
+
+        self.add_paper_chunks(arxiv_id, chunks, model).await?;
+        Ok(outcome)
+    }
+
+    async fn add_paper_chunks(
+        &self,
+        arxiv_id: &str,
+        chunks: &[ChunkEmbedding],
+        model: &ModelInfo,
+    ) -> Result<Vec<StoredChunk>> {
+        let mut results = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            check_vector(&chunk.embedding, model)?;
+
+            let payload = json!({
+                "arxiv_id": split_version(arxiv_id).0,
+                "chunk_index": chunk.index,
+                "text": chunk.text,
+                "embedding": chunk.embedding,
+                "embedding_model": model.name,
+                "embedding_version": model.version,
+                "embedding_dim": model.dimension,
+            });
+
+            let result = self.query("AddPaperChunk", &payload).await?;
+            results.push(decode_one("AddPaperChunk", &result, "chunk")?);
+        }
+        Ok(results)
+    }
+
+    async fn delete_paper_chunks(&self, arxiv_id: &str) -> Result<()> {
+        let payload = json!({
+            "arxiv_id": split_version(arxiv_id).0,
         });
 
-        let result = self.client.query("AddPaper", &payload).await?;
-        Ok(result)
+        self.query("DeletePaperChunks", &payload).await?;
+        Ok(())
     }
 
-    pub async fn add_paper_metadata(&self, paper: &Paper) -> Result<Value> {
+    /// Also links the metadata to the paper's vector when it is stored.
+    async fn add_paper_metadata(&self, paper: &Paper) -> Result<PaperMetadata> {
+        let mut payload = metadata_payload(paper);
+        payload["created_at"] = json!(chrono::Utc::now().to_rfc3339());
+
+        let result = self.query("AddPaperMetadata", &payload).await?;
+        let metadata: PaperMetadata = decode_one("AddPaperMetadata", &result, "metadata")?;
+
+        if let Some(stored) = self.get_paper_by_arxiv_id(paper.base_id()).await? {
+            let payload = json!({
+                "paper_id": stored.id,
+                "metadata_id": metadata.id,
+            });
+            self.query("LinkPaperMetadata", &payload).await?;
+        }
+
+        Ok(metadata)
+    }
+
+    /// Also fails if the server returns vectors from another model.
+    async fn search_papers(
+        &self,
+        query_embedding: Vec<f32>,
+        model: &ModelInfo,
+        min_similarity: f32,
+        limit: u32,
+    ) -> Result<Vec<SearchResult>> {
+        check_vector(&query_embedding, model)?;
+
         let payload = json!({
-            "arxiv_id": paper.arxiv_id,
-            "abstract": paper.abstract_text,
-            "comment": paper.comment.as_deref().unwrap_or(""),
-            "journal_ref": paper.journal_ref.as_deref().unwrap_or(""),
-            "doi": paper.doi.as_deref().unwrap_or(""),
-            "pdf_url": paper.pdf_url,
-            "created_at": chrono::Utc::now().to_rfc3339(),
+            "query_embedding": query_embedding,
+            "embedding_model": model.name,
+            "embedding_version": model.version,
+            "min_similarity": min_similarity,
+            "limit": limit,
         });
 
-        let result = self.client.query("AddPaperMetadata", &payload).await?;
-        Ok(result)
+        let result = self.query("SearchPapers", &payload).await?;
+        let hits: Vec<(StoredPaper, f32)> = decode_scored("SearchPapers", &result, "results")?;
+        check_provenance(
+            model,
+            hits.iter()
+                .filter(|(paper, _)| !paper.embedded_with(model))
+                .map(|(paper, _)| (&paper.arxiv_id, &paper.embedding_model)),
+        )?;
+
+        let mut results: Vec<SearchResult> = hits
+            .into_iter()
+            .map(|(paper, similarity_score)| SearchResult {
+                paper,
+                similarity_score,
+            })
+            .collect();
+        results.sort_by(|a, b| b.similarity_score.total_cmp(&a.similarity_score));
+        Ok(results)
     }
 
-    pub async fn search_papers(
+    async fn search_paper_chunks(
         &self,
         query_embedding: Vec<f32>,
+        model: &ModelInfo,
         min_similarity: f32,
         limit: u32,
-    ) -> Result<Value> {
+    ) -> Result<Vec<ChunkSearchResult>> {
+        check_vector(&query_embedding, model)?;
+
         let payload = json!({
             "query_embedding": query_embedding,
+            "embedding_model": model.name,
+            "embedding_version": model.version,
             "min_similarity": min_similarity,
             "limit": limit,
         });
 
-        let result = self.client.query("SearchPapers", &payload).await?;
-        Ok(result)
+        let result = self.query("SearchPaperChunks", &payload).await?;
+        let hits: Vec<(StoredChunk, f32)> = decode_scored("SearchPaperChunks", &result, "results")?;
+        check_provenance(
+            model,
+            hits.iter()
+                .filter(|(chunk, _)| !chunk.embedded_with(model))
+                .map(|(chunk, _)| (&chunk.arxiv_id, &chunk.embedding_model)),
+        )?;
+
+        let mut results: Vec<ChunkSearchResult> = hits
+            .into_iter()
+            .map(|(chunk, similarity_score)| ChunkSearchResult {
+                chunk,
+                similarity_score,
+            })
+            .collect();
+        results.sort_by(|a, b| b.similarity_score.total_cmp(&a.similarity_score));
+        Ok(results)
     }
 
-    pub async fn get_paper_by_arxiv_id(&self, arxiv_id: &str) -> Result<Value> {
+    async fn embedding_coverage(&self, model: &ModelInfo) -> Result<EmbeddingCoverage> {
         let payload = json!({
-            "arxiv_id": arxiv_id,
+            "embedding_model": model.name,
+            "embedding_version": model.version,
         });
+        let matching = self.query("CountPapersWithModel", &payload).await?;
+        let total = self.query("CountPapers", &json!({})).await?;
 
-        let result = self.client.query("GetPaperByArxivId", &payload).await?;
-        Ok(result)
+        Ok(EmbeddingCoverage {
+            matching: decode_count("CountPapersWithModel", &matching)?,
+            total: decode_count("CountPapers", &total)?,
+        })
     }
 
-    pub async fn find_similar_papers(&self, arxiv_id: &str, limit: u32) -> Result<Value> {
+    async fn list_stale_papers(
+        &self,
+        model: &ModelInfo,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<(StoredPaper, PaperMetadata)>> {
         let payload = json!({
-            "arxiv_id": arxiv_id,
+            "embedding_model": model.name,
+            "embedding_version": model.version,
+            "offset": offset,
+            "limit": limit,
+        });
+
+        let result = self.query("ListStalePapers", &payload).await?;
+        let papers: Vec<StoredPaper> = decode_rows("ListStalePapers", &result, "v")?;
+        let metadata: Vec<PaperMetadata> = decode_rows("ListStalePapers", &result, "m")?;
+        if papers.len() != metadata.len() {
+            bail!(
+                "HelixDB ListStalePapers returned {} papers but {} metadata records",
+                papers.len(),
+                metadata.len()
+            );
+        }
+
+        Ok(papers.into_iter().zip(metadata).collect())
+    }
+
+    async fn replace_paper_embedding(
+        &self,
+        arxiv_id: &str,
+        embedding: Vec<f32>,
+        model: &ModelInfo,
+    ) -> Result<StoredPaper> {
+        check_vector(&embedding, model)?;
+
+        let payload = json!({
+            "arxiv_id": split_version(arxiv_id).0,
+            "embedding": embedding,
+            "embedding_model": model.name,
+            "embedding_version": model.version,
+            "embedding_dim": model.dimension,
+        });
+
+        let result = self.query("ReplacePaperEmbedding", &payload).await?;
+        decode_one("ReplacePaperEmbedding", &result, "v")
+    }
+
+    async fn overwrite_paper(&self, paper: &Paper) -> Result<StoredPaper> {
+        let arxiv_id = paper.base_id();
+        let (Some(existing), Some(embedding)) = (
+            self.get_paper_by_arxiv_id(arxiv_id).await?,
+            self.get_paper_vector(arxiv_id).await?,
+        ) else {
+            bail!("Paper {} is not in the library", arxiv_id);
+        };
+
+        // UpdatePaper sets the vector too, so the stored one is sent back
+        let model = ModelInfo {
+            name: existing.embedding_model,
+            version: existing.embedding_version,
+            dimension: embedding.len(),
+        };
+        let payload = paper_payload(paper, embedding, &model)?;
+        let result = self.query("UpdatePaper", &payload).await?;
+        let stored = decode_one("UpdatePaper", &result, "v")?;
+
+        let result = self
+            .query("UpdatePaperMetadata", &metadata_payload(paper))
+            .await?;
+        let updated: Vec<PaperMetadata> = decode_rows("UpdatePaperMetadata", &result, "metadata")?;
+        if updated.is_empty() {
+            self.add_paper_metadata(paper).await?;
+        }
+        Ok(stored)
+    }
+
+    async fn get_paper_by_arxiv_id(&self, arxiv_id: &str) -> Result<Option<StoredPaper>> {
+        let payload = json!({
+            "arxiv_id": split_version(arxiv_id).0,
+        });
+
+        let result = self.query("GetPaperByArxivId", &payload).await?;
+        Ok(decode_rows("GetPaperByArxivId", &result, "v")?
+            .into_iter()
+            .next())
+    }
+
+    async fn find_similar_papers(&self, arxiv_id: &str, limit: u32) -> Result<Vec<StoredPaper>> {
+        let payload = json!({
+            "arxiv_id": split_version(arxiv_id).0,
             "limit": limit,
         });
 
-        let result = self.client.query("FindSimilarPapers", &payload).await?;
-        Ok(result)
+        let result = self.query("FindSimilarPapers", &payload).await?;
+        decode_rows("FindSimilarPapers", &result, "p2")
+    }
+
+    async fn get_paper_vector(&self, arxiv_id: &str) -> Result<Option<Vec<f32>>> {
+        let payload = json!({
+            "arxiv_id": split_version(arxiv_id).0,
+        });
+
+        let result = self.query("GetPaperVector", &payload).await?;
+        let vectors: Vec<PaperVector> = decode_rows("GetPaperVector", &result, "v")?;
+        Ok(vectors.into_iter().next().map(|vector| vector.data))
+    }
+
+    async fn get_similarities(&self, arxiv_id: &str) -> Result<Vec<SearchResult>> {
+        let payload = json!({
+            "arxiv_id": split_version(arxiv_id).0,
+        });
+
+        let result = self.query("GetSimilarities", &payload).await?;
+        let papers: Vec<StoredPaper> = decode_rows("GetSimilarities", &result, "p2")?;
+        let edges: Vec<SimilarToEdge> = decode_rows("GetSimilarities", &result, "e")?;
+        if papers.len() != edges.len() {
+            bail!(
+                "HelixDB GetSimilarities returned {} papers but {} edges",
+                papers.len(),
+                edges.len()
+            );
+        }
+
+        let mut results: Vec<SearchResult> = papers
+            .into_iter()
+            .zip(edges)
+            .map(|(paper, edge)| SearchResult {
+                paper,
+                similarity_score: edge.similarity_score,
+            })
+            .collect();
+        results.sort_by(|a, b| b.similarity_score.total_cmp(&a.similarity_score));
+        Ok(results)
+    }
+
+    async fn replace_similarities(&self, arxiv_id: &str, similar: &[(String, f32)]) -> Result<()> {
+        let arxiv_id = split_version(arxiv_id).0;
+        let payload = json!({
+            "arxiv_id": arxiv_id,
+        });
+        self.query("DeleteSimilarities", &payload).await?;
+
+        for (to_arxiv_id, score) in similar {
+            let payload = json!({
+                "from_arxiv_id": arxiv_id,
+                "to_arxiv_id": to_arxiv_id,
+                "score": score,
+            });
+            self.query("AddSimilarity", &payload).await?;
+        }
+        Ok(())
+    }
+
+    async fn create_collection(&self, collection: &NewCollection) -> Result<Collection> {
+        let existing = self.get_all_collections().await?;
+        let collection = prepare_collection(collection, &existing)?;
+        let payload = json!({
+            "name": collection.name,
+            "description": collection.description,
+            "parent": collection.parent,
+            "filter": collection.filter,
+            "created_at": collection.created_at,
+        });
+
+        let result = self.query("CreateCollection", &payload).await?;
+        decode_one("CreateCollection", &result, "collection")
+    }
+
+    async fn get_all_collections(&self) -> Result<Vec<Collection>> {
+        let result = self.query("GetAllCollections", &json!({})).await?;
+        decode_rows("GetAllCollections", &result, "c")
     }
 
-    pub async fn create_collection(&self, name: &str, description: &str) -> Result<Value> {
+    async fn get_collection(&self, name: &str) -> Result<Option<Collection>> {
         let payload = json!({
             "name": name,
-            "description": description,
-            "created_at": chrono::Utc::now().to_rfc3339(),
         });
 
-        let result = self.client.query("CreateCollection", &payload).await?;
-        Ok(result)
+        let result = self.query("GetCollection", &payload).await?;
+        Ok(decode_rows("GetCollection", &result, "c")?
+            .into_iter()
+            .next())
     }
 
-    pub async fn mark_paper_saved(&self, arxiv_id: &str, saved: bool) -> Result<Value> {
+    async fn rename_collection(&self, name: &str, new_name: &str) -> Result<Collection> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            bail!("Collection name cannot be empty");
+        }
+        self.require_collection(name).await?;
+        if name == new_name {
+            return self.require_collection(name).await;
+        }
+        if self.get_collection(new_name).await?.is_some() {
+            bail!("Collection {:?} already exists", new_name);
+        }
+
+        let payload = json!({
+            "name": name,
+            "new_name": new_name,
+        });
+        let result = self.query("RenameCollection", &payload).await?;
+        let collection = decode_one("RenameCollection", &result, "c")?;
+
+        let payload = json!({
+            "parent": name,
+            "new_parent": new_name,
+        });
+        self.query("ReparentCollections", &payload).await?;
+        Ok(collection)
+    }
+
+    async fn delete_collection(&self, name: &str) -> Result<()> {
+        let collection = self.require_collection(name).await?;
+
+        let payload = json!({
+            "parent": collection.name,
+            "new_parent": collection.parent,
+        });
+        self.query("ReparentCollections", &payload).await?;
+
+        let payload = json!({
+            "name": name,
+        });
+        self.query("DeleteCollection", &payload).await?;
+        Ok(())
+    }
+
+    async fn get_collection_papers(&self, collection_name: &str) -> Result<Vec<CollectionPaper>> {
+        let collection = self.require_collection(collection_name).await?;
+        if let Some(filter) = collection.smart_filter()? {
+            return Ok(self
+                .list_papers()
+                .await?
+                .into_iter()
+                .filter(|(paper, metadata)| filter.matches(paper, Some(metadata)))
+                .map(|(paper, _)| CollectionPaper {
+                    paper,
+                    added_at: None,
+                })
+                .collect());
+        }
+
+        let payload = json!({
+            "collection_name": collection_name,
+        });
+
+        let result = self.query("GetCollectionPapers", &payload).await?;
+        let papers: Vec<StoredPaper> = decode_rows("GetCollectionPapers", &result, "p")?;
+        let edges: Vec<Membership> = decode_rows("GetCollectionPapers", &result, "e")?;
+        if papers.len() != edges.len() {
+            bail!(
+                "HelixDB GetCollectionPapers returned {} papers but {} edges",
+                papers.len(),
+                edges.len()
+            );
+        }
+
+        Ok(papers
+            .into_iter()
+            .zip(edges)
+            .map(|(paper, edge)| CollectionPaper {
+                paper,
+                added_at: Some(edge.added_at).filter(|added_at| !added_at.is_empty()),
+            })
+            .collect())
+    }
+
+    async fn add_paper_to_collection(&self, arxiv_id: &str, collection_name: &str) -> Result<()> {
+        let collection = self.require_collection(collection_name).await?;
+        check_manual(&collection)?;
+
+        let arxiv_id = split_version(arxiv_id).0;
+        let members = self.get_collection_papers(collection_name).await?;
+        if members
+            .iter()
+            .any(|member| member.paper.arxiv_id == arxiv_id)
+        {
+            return Ok(());
+        }
+
         let payload = json!({
             "arxiv_id": arxiv_id,
+            "collection_name": collection_name,
+            "added_at": chrono::Utc::now().to_rfc3339(),
+        });
+
+        self.query("AddPaperToCollection", &payload).await?;
+        Ok(())
+    }
+
+    async fn remove_paper_from_collection(
+        &self,
+        arxiv_id: &str,
+        collection_name: &str,
+    ) -> Result<()> {
+        let collection = self.require_collection(collection_name).await?;
+        check_manual(&collection)?;
+
+        let payload = json!({
+            "arxiv_id": split_version(arxiv_id).0,
+            "collection_name": collection_name,
+        });
+
+        self.query("RemovePaperFromCollection", &payload).await?;
+        Ok(())
+    }
+
+    async fn mark_paper_saved(&self, arxiv_id: &str, saved: bool) -> Result<PaperMetadata> {
+        let payload = json!({
+            "arxiv_id": split_version(arxiv_id).0,
             "saved": saved,
         });
 
-        let result = self.client.query("MarkPaperSaved", &payload).await?;
-        Ok(result)
+        let result = self.query("MarkPaperSaved", &payload).await?;
+        decode_one("MarkPaperSaved", &result, "m")
+    }
+
+    async fn list_papers(&self) -> Result<Vec<(StoredPaper, PaperMetadata)>> {
+        let mut all = Vec::new();
+        loop {
+            let payload = json!({
+                "offset": all.len(),
+                "limit": SCAN_PAGE_SIZE,
+            });
+            let result = self.query("ListPapers", &payload).await?;
+            let papers: Vec<StoredPaper> = decode_rows("ListPapers", &result, "v")?;
+            let metadata: Vec<PaperMetadata> = decode_rows("ListPapers", &result, "m")?;
+            if papers.len() != metadata.len() {
+                bail!(
+                    "HelixDB ListPapers returned {} papers but {} metadata records",
+                    papers.len(),
+                    metadata.len()
+                );
+            }
+            let done = papers.len() < SCAN_PAGE_SIZE as usize;
+            all.extend(papers.into_iter().zip(metadata));
+            if done {
+                return Ok(all);
+            }
+        }
+    }
+
+    async fn get_paper_metadata(&self, arxiv_id: &str) -> Result<Option<PaperMetadata>> {
+        let payload = json!({
+            "arxiv_id": split_version(arxiv_id).0,
+        });
+
+        let result = self.query("GetPaperMetadata", &payload).await?;
+        Ok(decode_rows("GetPaperMetadata", &result, "m")?
+            .into_iter()
+            .next())
+    }
+
+    async fn set_read_state(&self, arxiv_id: &str, state: ReadState) -> Result<PaperMetadata> {
+        let mut metadata = self.require_metadata(arxiv_id).await?;
+        metadata.set_state(state, chrono::Utc::now());
+
+        let payload = json!({
+            "arxiv_id": metadata.arxiv_id,
+            "read": metadata.read,
+            "read_state": metadata.read_state,
+            "reading_started_at": metadata.reading_started_at,
+            "read_at": metadata.read_at,
+        });
+
+        let result = self.query("SetReadState", &payload).await?;
+        decode_one("SetReadState", &result, "m")
+    }
+
+    async fn set_paper_tags(&self, arxiv_id: &str, tags: &[String]) -> Result<PaperMetadata> {
+        let payload = json!({
+            "arxiv_id": split_version(arxiv_id).0,
+            "tags": encode_tags(tags)?,
+        });
+
+        let result = self.query("UpdatePaperTags", &payload).await?;
+        decode_one("UpdatePaperTags", &result, "m")
+    }
+
+    async fn add_citation(
+        &self,
+        from_arxiv_id: &str,
+        to_arxiv_id: &str,
+        context: &str,
+    ) -> Result<()> {
+        let from_arxiv_id = split_version(from_arxiv_id).0;
+        let to_arxiv_id = split_version(to_arxiv_id).0;
+        let payload = json!({
+            "from_arxiv_id": from_arxiv_id,
+            "to_arxiv_id": to_arxiv_id,
+            "context": context,
+        });
+
+        let existing = self.get_references(from_arxiv_id).await?;
+        let query = if existing
+            .iter()
+            .any(|link| link.paper.arxiv_id == to_arxiv_id)
+        {
+            "UpdateCitation"
+        } else {
+            "AddCitation"
+        };
+        self.query(query, &payload).await?;
+        Ok(())
+    }
+
+    async fn delete_citations(&self, arxiv_id: &str) -> Result<()> {
+        let payload = json!({
+            "arxiv_id": split_version(arxiv_id).0,
+        });
+
+        self.query("DeleteCitations", &payload).await?;
+        Ok(())
+    }
+
+    async fn get_references(&self, arxiv_id: &str) -> Result<Vec<CitationLink>> {
+        self.citation_links("GetCitedPapers", arxiv_id, "p2").await
+    }
+
+    async fn get_cited_by(&self, arxiv_id: &str) -> Result<Vec<CitationLink>> {
+        self.citation_links("GetCitingPapers", arxiv_id, "p1").await
+    }
+}
+
+/// Turns a non-success response into [`DbError::Status`].
+async fn check_status(response: reqwest::Response) -> Result<reqwest::Response, DbError> {
+    let status = response.status();
+    if status.is_success() {
+        return Ok(response);
+    }
+    Err(DbError::Status {
+        status: status.as_u16(),
+        body: response.text().await.unwrap_or_default(),
+    })
+}
+
+fn paper_payload(paper: &Paper, embedding: Vec<f32>, model: &ModelInfo) -> Result<Value> {
+    check_vector(&embedding, model)?;
+
+    Ok(json!({
+        "arxiv_id": paper.base_id(),
+        "version": paper.version().unwrap_or(0),
+        "title": paper.title,
+        "authors": paper.authors_string(),
+        "published": paper.published.to_rfc3339(),
+        "updated": paper.updated.to_rfc3339(),
+        "categories": paper.categories_string(),
+        "abstract_preview": paper.abstract_preview(200),
+        "embedding": embedding,
+        "embedding_model": model.name,
+        "embedding_version": model.version,
+        "embedding_dim": model.dimension,
+    }))
+}
+
+fn metadata_payload(paper: &Paper) -> Value {
+    json!({
+        "arxiv_id": paper.base_id(),
+        "abstract": paper.abstract_text,
+        "comment": paper.comment.as_deref().unwrap_or(""),
+        "journal_ref": paper.journal_ref.as_deref().unwrap_or(""),
+        "doi": paper.doi.as_deref().unwrap_or(""),
+        "pdf_url": paper.pdf_url,
+    })
+}
+
+/// Fails if a search returned any vectors from a model other than `model`.
+/// Records stored before provenance was tracked have no model and count as
+/// mismatched.
+fn check_provenance<'a>(
+    model: &ModelInfo,
+    mismatched: impl Iterator<Item = (&'a String, &'a String)>,
+) -> Result<()> {
+    let mismatched: Vec<String> = mismatched
+        .map(|(arxiv_id, other)| {
+            let other = if other.is_empty() {
+                "unknown model"
+            } else {
+                other
+            };
+            format!("{} ({})", arxiv_id, other)
+        })
+        .collect();
+
+    if !mismatched.is_empty() {
+        bail!(
+            "Search returned vectors from a different embedding model than {}: {}; \
+             run the re-embed job to migrate them",
+            model,
+            mismatched.join(", ")
+        );
     }
+    Ok(())
 }
@@ -0,0 +1,119 @@
+use std::fmt;
+use std::time::Duration;
+
+use super::client::DbClient;
+use super::schema::SchemaReport;
+use crate::config::HelixDbConfig;
+
+/// Overall result of a [`HealthReport`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HealthStatus {
+    /// Reachable and serving every query this build needs
+    Healthy,
+    /// Reachable, but the deployed queries or schema do not match
+    Degraded,
+    Unreachable,
+}
+
+/// What a health check found out about a HelixDB server.
+#[derive(Debug)]
+pub struct HealthReport {
+    /// `endpoint:port`
+    pub address: String,
+    pub status: HealthStatus,
+    /// Round trip of a trivial query
+    pub latency: Option<Duration>,
+    pub papers: Option<u64>,
+    pub schema: Option<SchemaReport>,
+    /// Why the server is unreachable or degraded
+    pub error: Option<String>,
+}
+
+impl HealthReport {
+    pub fn is_healthy(&self) -> bool {
+        self.status == HealthStatus::Healthy
+    }
+
+    fn new(address: &str, status: HealthStatus) -> Self {
+        Self {
+            address: address.to_string(),
+            status,
+            latency: None,
+            papers: None,
+            schema: None,
+            error: None,
+        }
+    }
+}
+
+impl fmt::Display for HealthReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.status {
+            HealthStatus::Healthy => write!(f, "HelixDB at {} is healthy", self.address)?,
+            HealthStatus::Degraded => write!(f, "HelixDB at {} is degraded", self.address)?,
+            HealthStatus::Unreachable => write!(f, "HelixDB at {} is unreachable", self.address)?,
+        }
+        if let Some(latency) = self.latency {
+            write!(f, " ({} ms", latency.as_millis())?;
+            if let Some(papers) = self.papers {
+                write!(f, ", {} papers", papers)?;
+            }
+            write!(f, ")")?;
+        }
+        if let Some(error) = &self.error {
+            write!(f, ": {}", error)?;
+        }
+        if let Some(schema) = &self.schema {
+            write!(f, "\n{}", schema)?;
+        }
+        Ok(())
+    }
+}
+
+/// Checks the HelixDB server described by `config`.
+pub async fn check(config: &HelixDbConfig) -> HealthReport {
+    let address = format!("{}:{}", config.endpoint, config.port);
+    match DbClient::new(config) {
+        Ok(client) => check_client(&client).await,
+        Err(e) => HealthReport {
+            error: Some(format!("{:#}", e)),
+            ..HealthReport::new(&address, HealthStatus::Unreachable)
+        },
+    }
+}
+
+/// Pings the server, then compares its queries and schema version against
+/// this build. Never fails; problems are reported in the result.
+pub async fn check_client(client: &DbClient) -> HealthReport {
+    let mut report = HealthReport::new(client.address(), HealthStatus::Unreachable);
+
+    match client.ping().await {
+        Ok(latency) => report.latency = Some(latency),
+        Err(e) => {
+            report.error = Some(format!("{:#}", e));
+            return report;
+        }
+    }
+    report.papers = client.count_papers().await.ok();
+
+    report.status = match client.check_schema().await {
+        Ok(schema) => {
+            let status = if !schema.queries_ok() {
+                report.error = Some("deployed queries do not match this build".to_string());
+                HealthStatus::Degraded
+            } else if schema.is_newer() {
+                report.error = Some("schema was written by a newer build".to_string());
+                HealthStatus::Degraded
+            } else {
+                HealthStatus::Healthy
+            };
+            report.schema = Some(schema);
+            status
+        }
+        Err(e) => {
+            report.error = Some(format!("{:#}", e));
+            HealthStatus::Degraded
+        }
+    };
+    report
+}
@@ -0,0 +1,1025 @@
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+use super::migrations::upgrade_metadata;
+use super::models::{
+    ChunkSearchResult, CitationLink, Collection, CollectionPaper, EmbeddingCoverage, NewCollection,
+    PaperMetadata, ReadState, SearchResult, StoredChunk, StoredPaper, UpsertOutcome,
+};
+use super::store::{check_manual, check_vector, prepare_collection, PaperStore};
+use super::tags::encode_tags;
+use crate::arxiv::models::{split_version, Paper};
+use crate::embeddings::backend::ModelInfo;
+use crate::embeddings::chunking::ChunkEmbedding;
+
+/// Version 2 normalized tags and added reading states.
+const FORMAT_VERSION: u32 = 2;
+
+/// Shortest time between two rewrites of the library file. Changes made in
+/// between are kept in memory until the next save or [`LocalStore::flush`].
+const MIN_SAVE_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Everything the local store holds, serialized as one JSON document.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Library {
+    version: u32,
+    next_id: u64,
+    papers: BTreeMap<String, LocalPaper>,
+    metadata: BTreeMap<String, PaperMetadata>,
+    chunks: BTreeMap<String, Vec<LocalChunk>>,
+    collections: BTreeMap<String, LocalCollection>,
+    citations: Vec<Citation>,
+    /// Precomputed `SimilarTo` edges: arXiv ID to neighbors and scores
+    #[serde(default)]
+    similarities: BTreeMap<String, Vec<(String, f32)>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct LocalPaper {
+    paper: StoredPaper,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LocalChunk {
+    chunk: StoredChunk,
+    embedding: Vec<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct LocalCollection {
+    collection: Collection,
+    /// arXiv IDs with the time each was added
+    papers: Vec<(String, String)>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Citation {
+    from: String,
+    to: String,
+    context: String,
+}
+
+impl Library {
+    fn next_id(&mut self) -> String {
+        self.next_id += 1;
+        format!("local-{}", self.next_id)
+    }
+
+    fn paper(&self, arxiv_id: &str) -> Result<&LocalPaper> {
+        self.papers
+            .get(arxiv_id)
+            .ok_or_else(|| anyhow!("Paper {} is not in the library", arxiv_id))
+    }
+
+    fn collection_mut(&mut self, name: &str) -> Result<&mut LocalCollection> {
+        self.collections
+            .get_mut(name)
+            .ok_or_else(|| anyhow!("Collection {:?} does not exist", name))
+    }
+
+    fn metadata_mut(&mut self, arxiv_id: &str) -> Result<&mut PaperMetadata> {
+        self.metadata
+            .get_mut(arxiv_id)
+            .ok_or_else(|| anyhow!("No metadata stored for paper {}", arxiv_id))
+    }
+
+    /// Inserts or overwrites the vector for `paper`, keeping its node ID.
+    fn put_paper(&mut self, paper: &Paper, embedding: Vec<f32>, model: &ModelInfo) -> StoredPaper {
+        let arxiv_id = paper.base_id().to_string();
+        let id = match self.papers.get(&arxiv_id) {
+            Some(existing) => existing.paper.id.clone(),
+            None => self.next_id(),
+        };
+        let stored = StoredPaper {
+            id,
+            arxiv_id: arxiv_id.clone(),
+            version: paper.version().unwrap_or(0),
+            title: paper.title.clone(),
+            authors: paper.authors_string(),
+            published: paper.published.to_rfc3339(),
+            updated: paper.updated.to_rfc3339(),
+            categories: paper.categories_string(),
+            abstract_preview: paper.abstract_preview(200),
+            embedding_model: model.name.clone(),
+            embedding_version: model.version.clone(),
+            embedding_dim: model.dimension as u32,
+        };
+        self.papers.insert(
+            arxiv_id,
+            LocalPaper {
+                paper: stored.clone(),
+                embedding,
+            },
+        );
+        stored
+    }
+
+    /// Inserts or refreshes the arXiv fields of a paper's metadata, keeping
+    /// the user's flags and tags.
+    fn put_metadata(&mut self, paper: &Paper) -> PaperMetadata {
+        let arxiv_id = paper.base_id().to_string();
+        let previous = self.metadata.get(&arxiv_id).cloned();
+        let metadata = PaperMetadata {
+            abstract_text: paper.abstract_text.clone(),
+            comment: paper.comment.clone().unwrap_or_default(),
+            journal_ref: paper.journal_ref.clone().unwrap_or_default(),
+            doi: paper.doi.clone().unwrap_or_default(),
+            pdf_url: paper.pdf_url.clone(),
+            ..previous.unwrap_or_else(|| PaperMetadata {
+                id: self.next_id(),
+                arxiv_id: arxiv_id.clone(),
+                abstract_text: String::new(),
+                comment: String::new(),
+                journal_ref: String::new(),
+                doi: String::new(),
+                pdf_url: String::new(),
+                saved: false,
+                read: false,
+                read_state: ReadState::Unread,
+                reading_started_at: String::new(),
+                read_at: String::new(),
+                tags: String::new(),
+                created_at: chrono::Utc::now().to_rfc3339(),
+            })
+        };
+        self.metadata.insert(arxiv_id, metadata.clone());
+        metadata
+    }
+
+    fn new_chunk(
+        &mut self,
+        arxiv_id: &str,
+        chunk: &ChunkEmbedding,
+        model: &ModelInfo,
+    ) -> LocalChunk {
+        LocalChunk {
+            chunk: StoredChunk {
+                id: self.next_id(),
+                arxiv_id: arxiv_id.to_string(),
+                chunk_index: chunk.index as u32,
+                text: chunk.text.clone(),
+                embedding_model: model.name.clone(),
+                embedding_version: model.version.clone(),
+                embedding_dim: model.dimension as u32,
+            },
+            embedding: chunk.embedding.clone(),
+        }
+    }
+
+    /// Replaces all chunk vectors of a paper.
+    fn put_chunks(&mut self, arxiv_id: &str, chunks: &[ChunkEmbedding], model: &ModelInfo) {
+        let stored: Vec<LocalChunk> = chunks
+            .iter()
+            .map(|chunk| self.new_chunk(arxiv_id, chunk, model))
+            .collect();
+        if stored.is_empty() {
+            self.chunks.remove(arxiv_id);
+        } else {
+            self.chunks.insert(arxiv_id.to_string(), stored);
+        }
+    }
+
+    fn citation_links<'a>(
+        &self,
+        ends: impl Iterator<Item = (&'a String, &'a String)>,
+    ) -> Vec<CitationLink> {
+        ends.filter_map(|(arxiv_id, context)| {
+            self.papers.get(arxiv_id).map(|local| CitationLink {
+                paper: local.paper.clone(),
+                context: context.clone(),
+            })
+        })
+        .collect()
+    }
+}
+
+/// Embedded paper store kept in a single JSON file.
+///
+/// The whole library is held in memory and rewritten atomically after
+/// changes. A change following a quiet period is saved at once; during a
+/// burst of changes, such as an ingestion run, saves are spaced at least
+/// [`MIN_SAVE_INTERVAL`] and ten times the last save's duration apart, so
+/// that rewriting a growing file takes a bounded share of the time instead
+/// of growing with the square of the papers stored. Changes not yet saved
+/// are written by [`flush`](PaperStore::flush) and when the store is
+/// dropped. Vector search is a brute-force cosine scan, which is fast
+/// enough for personal libraries of tens of thousands of papers.
+pub struct LocalStore {
+    path: PathBuf,
+    library: Mutex<Library>,
+    /// Only locked while holding `library`
+    saves: Mutex<SaveState>,
+}
+
+#[derive(Debug, Default)]
+struct SaveState {
+    /// Changes made since the last save
+    dirty: bool,
+    saved_at: Option<Instant>,
+    last_save_took: Duration,
+}
+
+impl LocalStore {
+    pub fn open(path: &Path) -> Result<Self> {
+        let library = match std::fs::read_to_string(path) {
+            Ok(json) => {
+                let mut library: Library = serde_json::from_str(&json)
+                    .with_context(|| format!("Failed to parse local library {:?}", path))?;
+                if library.version > FORMAT_VERSION {
+                    bail!(
+                        "Local library {:?} has format version {}, newer than {}",
+                        path,
+                        library.version,
+                        FORMAT_VERSION
+                    );
+                }
+                if library.version < FORMAT_VERSION {
+                    // Upgraded in memory, written back with the next change
+                    let upgraded = library
+                        .metadata
+                        .values_mut()
+                        .map(upgrade_metadata)
+                        .filter(|changed| *changed)
+                        .count();
+                    tracing::info!(
+                        "Upgraded local library {:?} from format version {} ({} papers changed)",
+                        path,
+                        library.version,
+                        upgraded
+                    );
+                    library.version = FORMAT_VERSION;
+                }
+                library
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Library {
+                version: FORMAT_VERSION,
+                ..Library::default()
+            },
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+        };
+
+        tracing::info!(
+            "Opened local library {:?} ({} papers)",
+            path,
+            library.papers.len()
+        );
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            library: Mutex::new(library),
+            saves: Mutex::new(SaveState::default()),
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Replaces the whole library with a copy of `source`, leaving out
+    /// passage vectors. Returns the number of papers copied.
+    ///
+    /// Papers whose record is unchanged since the last copy keep the vector
+    /// copied then; similarities and references are fetched for every
+    /// paper, since they change without the paper itself changing.
+    pub async fn mirror(&self, source: &dyn PaperStore) -> Result<usize> {
+        let mut copied = self.mirrored_papers()?;
+        let mut library = Library {
+            version: FORMAT_VERSION,
+            ..Library::default()
+        };
+
+        for (paper, metadata) in source.list_papers().await? {
+            let arxiv_id = paper.arxiv_id.clone();
+            let local = match copied.remove(&arxiv_id) {
+                Some(local) if local.paper == paper => local,
+                _ => match source.get_paper_vector(&arxiv_id).await? {
+                    Some(embedding) => LocalPaper { paper, embedding },
+                    None => continue,
+                },
+            };
+
+            let similar: Vec<(String, f32)> = source
+                .get_similarities(&arxiv_id)
+                .await?
+                .into_iter()
+                .map(|hit| (hit.paper.arxiv_id, hit.similarity_score))
+                .collect();
+            if !similar.is_empty() {
+                library.similarities.insert(arxiv_id.clone(), similar);
+            }
+            for link in source.get_references(&arxiv_id).await? {
+                library.citations.push(Citation {
+                    from: arxiv_id.clone(),
+                    to: link.paper.arxiv_id,
+                    context: link.context,
+                });
+            }
+
+            library.papers.insert(arxiv_id.clone(), local);
+            library.metadata.insert(arxiv_id, metadata);
+        }
+
+        for collection in source.get_all_collections().await? {
+            let papers = if collection.is_smart() {
+                Vec::new()
+            } else {
+                source
+                    .get_collection_papers(&collection.name)
+                    .await?
+                    .into_iter()
+                    .map(|member| (member.paper.arxiv_id, member.added_at.unwrap_or_default()))
+                    .collect()
+            };
+            library.collections.insert(
+                collection.name.clone(),
+                LocalCollection { collection, papers },
+            );
+        }
+
+        let papers = library.papers.len();
+        self.write(|current| {
+            *current = library;
+            Ok(())
+        })?;
+        self.flush().await?;
+        Ok(papers)
+    }
+
+    /// Every paper held with its vector, as [`mirror`](Self::mirror)
+    /// copied it.
+    fn mirrored_papers(&self) -> Result<BTreeMap<String, LocalPaper>> {
+        Ok(self.lock()?.papers.clone())
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Library>> {
+        self.library
+            .lock()
+            .map_err(|_| anyhow!("Local library lock poisoned"))
+    }
+
+    /// Applies `change` and persists the library if it succeeds.
+    fn write<T>(&self, change: impl FnOnce(&mut Library) -> Result<T>) -> Result<T> {
+        let mut library = self.lock()?;
+        let result = change(&mut library)?;
+        self.changed(&library)?;
+        Ok(result)
+    }
+
+    /// Records a change to `library`, which must be locked, and saves it
+    /// unless the last save was too recent.
+    fn changed(&self, library: &Library) -> Result<()> {
+        let mut saves = self.saves()?;
+        saves.dirty = true;
+        let due = saves.saved_at.is_none_or(|saved_at| {
+            saved_at.elapsed() >= MIN_SAVE_INTERVAL.max(saves.last_save_took * 10)
+        });
+        if due {
+            self.save_now(library, &mut saves)?;
+        }
+        Ok(())
+    }
+
+    /// Saves `library`, which must be locked, if it has unsaved changes.
+    fn save_pending(&self, library: &Library) -> Result<()> {
+        let mut saves = self.saves()?;
+        if saves.dirty {
+            self.save_now(library, &mut saves)?;
+        }
+        Ok(())
+    }
+
+    fn save_now(&self, library: &Library, saves: &mut SaveState) -> Result<()> {
+        let started = Instant::now();
+        self.save(library)?;
+        *saves = SaveState {
+            dirty: false,
+            saved_at: Some(Instant::now()),
+            last_save_took: started.elapsed(),
+        };
+        Ok(())
+    }
+
+    fn saves(&self) -> Result<MutexGuard<'_, SaveState>> {
+        self.saves
+            .lock()
+            .map_err(|_| anyhow!("Local library lock poisoned"))
+    }
+
+    fn save(&self, library: &Library) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(library)?)?;
+        std::fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to replace {:?}", self.path))?;
+        Ok(())
+    }
+}
+
+impl Drop for LocalStore {
+    fn drop(&mut self) {
+        let saved = self.lock().and_then(|library| self.save_pending(&library));
+        if let Err(e) = saved {
+            tracing::error!("Failed to save local library {:?}: {:#}", self.path, e);
+        }
+    }
+}
+
+#[async_trait]
+impl PaperStore for LocalStore {
+    fn backend_name(&self) -> &str {
+        "local"
+    }
+
+    async fn flush(&self) -> Result<()> {
+        let library = self.lock()?;
+        self.save_pending(&library)
+    }
+
+    async fn add_paper(
+        &self,
+        paper: &Paper,
+        embedding: Vec<f32>,
+        model: &ModelInfo,
+    ) -> Result<StoredPaper> {
+        check_vector(&embedding, model)?;
+        self.write(|library| Ok(library.put_paper(paper, embedding, model)))
+    }
+
+    async fn upsert_paper(
+        &self,
+        paper: &Paper,
+        embedding: Vec<f32>,
+        chunks: &[ChunkEmbedding],
+        model: &ModelInfo,
+    ) -> Result<UpsertOutcome> {
+        check_vector(&embedding, model)?;
+        for chunk in chunks {
+            check_vector(&chunk.embedding, model)?;
+        }
+
+        // Deciding and writing under one lock, so that concurrent upserts
+        // of the same paper cannot both insert it
+        let mut library = self.lock()?;
+        let outcome = match library.papers.get(paper.base_id()) {
+            None => UpsertOutcome::Inserted,
+            Some(existing) if existing.paper.is_outdated_by(paper) => UpsertOutcome::Updated,
+            Some(_) => return Ok(UpsertOutcome::Skipped),
+        };
+
+        library.put_paper(paper, embedding, model);
+        library.put_metadata(paper);
+        library.put_chunks(paper.base_id(), chunks, model);
+        self.changed(&library)?;
+        Ok(outcome)
+    }
+
+    async fn add_paper_metadata(&self, paper: &Paper) -> Result<PaperMetadata> {
+        self.write(|library| Ok(library.put_metadata(paper)))
+    }
+
+    async fn add_paper_chunks(
+        &self,
+        arxiv_id: &str,
+        chunks: &[ChunkEmbedding],
+        model: &ModelInfo,
+    ) -> Result<Vec<StoredChunk>> {
+        for chunk in chunks {
+            check_vector(&chunk.embedding, model)?;
+        }
+
+        let arxiv_id = split_version(arxiv_id).0;
+        self.write(|library| {
+            let mut stored = Vec::with_capacity(chunks.len());
+            for chunk in chunks {
+                let local = library.new_chunk(arxiv_id, chunk, model);
+                stored.push(local.chunk.clone());
+                library
+                    .chunks
+                    .entry(arxiv_id.to_string())
+                    .or_default()
+                    .push(local);
+            }
+            Ok(stored)
+        })
+    }
+
+    async fn delete_paper_chunks(&self, arxiv_id: &str) -> Result<()> {
+        self.write(|library| {
+            library.chunks.remove(split_version(arxiv_id).0);
+            Ok(())
+        })
+    }
+
+    async fn get_paper_by_arxiv_id(&self, arxiv_id: &str) -> Result<Option<StoredPaper>> {
+        Ok(self
+            .lock()?
+            .papers
+            .get(split_version(arxiv_id).0)
+            .map(|local| local.paper.clone()))
+    }
+
+    async fn search_papers(
+        &self,
+        query_embedding: Vec<f32>,
+        model: &ModelInfo,
+        min_similarity: f32,
+        limit: u32,
+    ) -> Result<Vec<SearchResult>> {
+        check_vector(&query_embedding, model)?;
+
+        let library = self.lock()?;
+        let hits = top_k(
+            library
+                .papers
+                .values()
+                .filter(|local| local.paper.embedded_with(model))
+                .map(|local| (local, cosine(&query_embedding, &local.embedding))),
+            min_similarity,
+            limit,
+        );
+
+        Ok(hits
+            .into_iter()
+            .map(|(local, similarity_score)| SearchResult {
+                paper: local.paper.clone(),
+                similarity_score,
+            })
+            .collect())
+    }
+
+    async fn search_paper_chunks(
+        &self,
+        query_embedding: Vec<f32>,
+        model: &ModelInfo,
+        min_similarity: f32,
+        limit: u32,
+    ) -> Result<Vec<ChunkSearchResult>> {
+        check_vector(&query_embedding, model)?;
+
+        let library = self.lock()?;
+        let hits = top_k(
+            library
+                .chunks
+                .values()
+                .flatten()
+                .filter(|local| local.chunk.embedded_with(model))
+                .map(|local| (local, cosine(&query_embedding, &local.embedding))),
+            min_similarity,
+            limit,
+        );
+
+        Ok(hits
+            .into_iter()
+            .map(|(local, similarity_score)| ChunkSearchResult {
+                chunk: local.chunk.clone(),
+                similarity_score,
+            })
+            .collect())
+    }
+
+    /// Nearest neighbours of the paper's own vector, among papers embedded
+    /// with the same model.
+    async fn find_similar_papers(&self, arxiv_id: &str, limit: u32) -> Result<Vec<StoredPaper>> {
+        let arxiv_id = split_version(arxiv_id).0;
+        let library = self.lock()?;
+        let target = library.paper(arxiv_id)?;
+
+        // Papers ingested before similarities were precomputed fall back to
+        // a direct scan
+        if let Some(similar) = library.similarities.get(arxiv_id) {
+            return Ok(similar
+                .iter()
+                .filter_map(|(id, _)| library.papers.get(id))
+                .take(limit as usize)
+                .map(|local| local.paper.clone())
+                .collect());
+        }
+
+        let hits = top_k(
+            library
+                .papers
+                .values()
+                .filter(|local| local.paper.arxiv_id != arxiv_id)
+                .filter(|local| {
+                    local.paper.embedding_model == target.paper.embedding_model
+                        && local.paper.embedding_version == target.paper.embedding_version
+                })
+                .map(|local| (local, cosine(&target.embedding, &local.embedding))),
+            f32::MIN,
+            limit,
+        );
+
+        Ok(hits
+            .into_iter()
+            .map(|(local, _)| local.paper.clone())
+            .collect())
+    }
+
+    async fn get_paper_vector(&self, arxiv_id: &str) -> Result<Option<Vec<f32>>> {
+        Ok(self
+            .lock()?
+            .papers
+            .get(split_version(arxiv_id).0)
+            .map(|local| local.embedding.clone()))
+    }
+
+    async fn get_similarities(&self, arxiv_id: &str) -> Result<Vec<SearchResult>> {
+        let library = self.lock()?;
+        let mut results: Vec<SearchResult> = library
+            .similarities
+            .get(split_version(arxiv_id).0)
+            .into_iter()
+            .flatten()
+            .filter_map(|(id, score)| {
+                library.papers.get(id).map(|local| SearchResult {
+                    paper: local.paper.clone(),
+                    similarity_score: *score,
+                })
+            })
+            .collect();
+        results.sort_by(|a, b| b.similarity_score.total_cmp(&a.similarity_score));
+        Ok(results)
+    }
+
+    async fn replace_similarities(&self, arxiv_id: &str, similar: &[(String, f32)]) -> Result<()> {
+        let arxiv_id = split_version(arxiv_id).0;
+        self.write(|library| {
+            library.paper(arxiv_id)?;
+            for (id, _) in similar {
+                library.paper(id)?;
+            }
+            let mut similar = similar.to_vec();
+            similar.sort_by(|a, b| b.1.total_cmp(&a.1));
+            library.similarities.insert(arxiv_id.to_string(), similar);
+            Ok(())
+        })
+    }
+
+    async fn embedding_coverage(&self, model: &ModelInfo) -> Result<EmbeddingCoverage> {
+        let library = self.lock()?;
+        Ok(EmbeddingCoverage {
+            matching: library
+                .papers
+                .values()
+                .filter(|local| local.paper.embedded_with(model))
+                .count() as u64,
+            total: library.papers.len() as u64,
+        })
+    }
+
+    async fn list_stale_papers(
+        &self,
+        model: &ModelInfo,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<(StoredPaper, PaperMetadata)>> {
+        let library = self.lock()?;
+
+        // BTreeMap iteration is already ordered by arXiv ID
+        Ok(library
+            .papers
+            .values()
+            .filter(|local| !local.paper.embedded_with(model))
+            .filter_map(|local| {
+                library
+                    .metadata
+                    .get(&local.paper.arxiv_id)
+                    .map(|metadata| (local.paper.clone(), metadata.clone()))
+            })
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect())
+    }
+
+    async fn replace_paper_embedding(
+        &self,
+        arxiv_id: &str,
+        embedding: Vec<f32>,
+        model: &ModelInfo,
+    ) -> Result<StoredPaper> {
+        check_vector(&embedding, model)?;
+
+        let arxiv_id = split_version(arxiv_id).0;
+        self.write(|library| {
+            let local = library
+                .papers
+                .get_mut(arxiv_id)
+                .ok_or_else(|| anyhow!("Paper {} is not in the library", arxiv_id))?;
+            local.embedding = embedding;
+            local.paper.embedding_model = model.name.clone();
+            local.paper.embedding_version = model.version.clone();
+            local.paper.embedding_dim = model.dimension as u32;
+            Ok(local.paper.clone())
+        })
+    }
+
+    async fn overwrite_paper(&self, paper: &Paper) -> Result<StoredPaper> {
+        self.write(|library| {
+            let local = library
+                .papers
+                .get(paper.base_id())
+                .ok_or_else(|| anyhow!("Paper {} is not in the library", paper.base_id()))?;
+            let model = ModelInfo {
+                name: local.paper.embedding_model.clone(),
+                version: local.paper.embedding_version.clone(),
+                dimension: local.paper.embedding_dim as usize,
+            };
+            let embedding = local.embedding.clone();
+
+            let stored = library.put_paper(paper, embedding, &model);
+            library.put_metadata(paper);
+            Ok(stored)
+        })
+    }
+
+    async fn create_collection(&self, collection: &NewCollection) -> Result<Collection> {
+        self.write(|library| {
+            let existing: Vec<Collection> = library
+                .collections
+                .values()
+                .map(|local| local.collection.clone())
+                .collect();
+            let collection = Collection {
+                id: library.next_id(),
+                ..prepare_collection(collection, &existing)?
+            };
+            library.collections.insert(
+                collection.name.clone(),
+                LocalCollection {
+                    collection: collection.clone(),
+                    papers: Vec::new(),
+                },
+            );
+            Ok(collection)
+        })
+    }
+
+    async fn get_all_collections(&self) -> Result<Vec<Collection>> {
+        Ok(self
+            .lock()?
+            .collections
+            .values()
+            .map(|local| local.collection.clone())
+            .collect())
+    }
+
+    async fn get_collection(&self, name: &str) -> Result<Option<Collection>> {
+        Ok(self
+            .lock()?
+            .collections
+            .get(name)
+            .map(|local| local.collection.clone()))
+    }
+
+    async fn rename_collection(&self, name: &str, new_name: &str) -> Result<Collection> {
+        let new_name = new_name.trim();
+        if new_name.is_empty() {
+            bail!("Collection name cannot be empty");
+        }
+        self.write(|library| {
+            library.collection_mut(name)?;
+            if name == new_name {
+                return Ok(library.collection_mut(name)?.collection.clone());
+            }
+            if library.collections.contains_key(new_name) {
+                bail!("Collection {:?} already exists", new_name);
+            }
+
+            let mut local = library
+                .collections
+                .remove(name)
+                .expect("collection checked above");
+            local.collection.name = new_name.to_string();
+            let renamed = local.collection.clone();
+            library.collections.insert(new_name.to_string(), local);
+
+            for child in library.collections.values_mut() {
+                if child.collection.parent == name {
+                    child.collection.parent = new_name.to_string();
+                }
+            }
+            Ok(renamed)
+        })
+    }
+
+    async fn delete_collection(&self, name: &str) -> Result<()> {
+        self.write(|library| {
+            let removed = library
+                .collections
+                .remove(name)
+                .ok_or_else(|| anyhow!("Collection {:?} does not exist", name))?;
+            for child in library.collections.values_mut() {
+                if child.collection.parent == name {
+                    child.collection.parent = removed.collection.parent.clone();
+                }
+            }
+            Ok(())
+        })
+    }
+
+    async fn get_collection_papers(&self, collection_name: &str) -> Result<Vec<CollectionPaper>> {
+        let library = self.lock()?;
+        let collection = library
+            .collections
+            .get(collection_name)
+            .ok_or_else(|| anyhow!("Collection {:?} does not exist", collection_name))?;
+
+        if let Some(filter) = collection.collection.smart_filter()? {
+            return Ok(library
+                .papers
+                .values()
+                .filter(|local| {
+                    filter.matches(&local.paper, library.metadata.get(&local.paper.arxiv_id))
+                })
+                .map(|local| CollectionPaper {
+                    paper: local.paper.clone(),
+                    added_at: None,
+                })
+                .collect());
+        }
+
+        Ok(collection
+            .papers
+            .iter()
+            .filter_map(|(arxiv_id, added_at)| {
+                library.papers.get(arxiv_id).map(|local| CollectionPaper {
+                    paper: local.paper.clone(),
+                    added_at: Some(added_at.clone()),
+                })
+            })
+            .collect())
+    }
+
+    async fn add_paper_to_collection(&self, arxiv_id: &str, collection_name: &str) -> Result<()> {
+        let arxiv_id = split_version(arxiv_id).0;
+        self.write(|library| {
+            library.paper(arxiv_id)?;
+            let collection = library.collection_mut(collection_name)?;
+            check_manual(&collection.collection)?;
+            if !collection.papers.iter().any(|(id, _)| id == arxiv_id) {
+                collection
+                    .papers
+                    .push((arxiv_id.to_string(), chrono::Utc::now().to_rfc3339()));
+            }
+            Ok(())
+        })
+    }
+
+    async fn remove_paper_from_collection(
+        &self,
+        arxiv_id: &str,
+        collection_name: &str,
+    ) -> Result<()> {
+        let arxiv_id = split_version(arxiv_id).0;
+        self.write(|library| {
+            let collection = library.collection_mut(collection_name)?;
+            check_manual(&collection.collection)?;
+            collection.papers.retain(|(id, _)| id != arxiv_id);
+            Ok(())
+        })
+    }
+
+    async fn mark_paper_saved(&self, arxiv_id: &str, saved: bool) -> Result<PaperMetadata> {
+        self.write(|library| {
+            let metadata = library.metadata_mut(split_version(arxiv_id).0)?;
+            metadata.saved = saved;
+            Ok(metadata.clone())
+        })
+    }
+
+    async fn list_papers(&self) -> Result<Vec<(StoredPaper, PaperMetadata)>> {
+        let library = self.lock()?;
+        Ok(library
+            .papers
+            .values()
+            .filter_map(|local| {
+                library
+                    .metadata
+                    .get(&local.paper.arxiv_id)
+                    .map(|metadata| (local.paper.clone(), metadata.clone()))
+            })
+            .collect())
+    }
+
+    async fn get_paper_metadata(&self, arxiv_id: &str) -> Result<Option<PaperMetadata>> {
+        Ok(self
+            .lock()?
+            .metadata
+            .get(split_version(arxiv_id).0)
+            .cloned())
+    }
+
+    async fn set_read_state(&self, arxiv_id: &str, state: ReadState) -> Result<PaperMetadata> {
+        self.write(|library| {
+            let metadata = library.metadata_mut(split_version(arxiv_id).0)?;
+            metadata.set_state(state, chrono::Utc::now());
+            Ok(metadata.clone())
+        })
+    }
+
+    async fn set_paper_tags(&self, arxiv_id: &str, tags: &[String]) -> Result<PaperMetadata> {
+        let tags = encode_tags(tags)?;
+        self.write(|library| {
+            let metadata = library.metadata_mut(split_version(arxiv_id).0)?;
+            metadata.tags = tags;
+            Ok(metadata.clone())
+        })
+    }
+
+    async fn add_citation(
+        &self,
+        from_arxiv_id: &str,
+        to_arxiv_id: &str,
+        context: &str,
+    ) -> Result<()> {
+        let (from, to) = (split_version(from_arxiv_id).0, split_version(to_arxiv_id).0);
+        self.write(|library| {
+            library.paper(from)?;
+            library.paper(to)?;
+            match library
+                .citations
+                .iter_mut()
+                .find(|citation| citation.from == from && citation.to == to)
+            {
+                Some(citation) => citation.context = context.to_string(),
+                None => library.citations.push(Citation {
+                    from: from.to_string(),
+                    to: to.to_string(),
+                    context: context.to_string(),
+                }),
+            }
+            Ok(())
+        })
+    }
+
+    async fn delete_citations(&self, arxiv_id: &str) -> Result<()> {
+        let arxiv_id = split_version(arxiv_id).0;
+        self.write(|library| {
+            library
+                .citations
+                .retain(|citation| citation.from != arxiv_id);
+            Ok(())
+        })
+    }
+
+    async fn get_references(&self, arxiv_id: &str) -> Result<Vec<CitationLink>> {
+        let arxiv_id = split_version(arxiv_id).0;
+        let library = self.lock()?;
+        Ok(library.citation_links(
+            library
+                .citations
+                .iter()
+                .filter(|citation| citation.from == arxiv_id)
+                .map(|citation| (&citation.to, &citation.context)),
+        ))
+    }
+
+    async fn get_cited_by(&self, arxiv_id: &str) -> Result<Vec<CitationLink>> {
+        let arxiv_id = split_version(arxiv_id).0;
+        let library = self.lock()?;
+        Ok(library.citation_links(
+            library
+                .citations
+                .iter()
+                .filter(|citation| citation.to == arxiv_id)
+                .map(|citation| (&citation.from, &citation.context)),
+        ))
+    }
+}
+
+/// Keeps the `limit` best-scoring items at or above `min_similarity`,
+/// best first.
+fn top_k<T>(
+    scored: impl Iterator<Item = (T, f32)>,
+    min_similarity: f32,
+    limit: u32,
+) -> Vec<(T, f32)> {
+    let mut hits: Vec<(T, f32)> = scored
+        .filter(|(_, score)| *score >= min_similarity)
+        .collect();
+    hits.sort_by(|a, b| b.1.total_cmp(&a.1));
+    hits.truncate(limit as usize);
+    hits
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return f32::MIN;
+    }
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm_a: f32 = a.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norm_b: f32 = b.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a * norm_b)
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::Result;
+use futures::future::BoxFuture;
+
+use super::models::{PaperMetadata, ReadState};
+use super::store::PaperStore;
+use super::tags::{join_tags, split_tags};
+
+/// A change to stored data, applied once when a store is upgraded to
+/// `version`.
+pub struct Migration {
+    pub version: u32,
+    pub description: &'static str,
+    pub apply: for<'a> fn(&'a dyn PaperStore) -> BoxFuture<'a, Result<()>>,
+}
+
+/// Every migration, in version order. Version 1 is the original schema.
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 2,
+        description: "embedding provenance on papers and chunks",
+        apply: |store| Box::pin(embedding_provenance(store)),
+    },
+    Migration {
+        version: 3,
+        description: "normalized tags and reading states",
+        apply: |store| Box::pin(tags_and_read_states(store)),
+    },
+];
+
+/// Migrations needed to bring a store at version `from` up to date.
+pub fn pending(from: u32) -> impl Iterator<Item = &'static Migration> {
+    MIGRATIONS.iter().filter(move |m| m.version > from)
+}
+
+/// Papers stored before embeddings recorded their model cannot be fixed in
+/// place, since re-embedding needs the embedding backend.
+async fn embedding_provenance(store: &dyn PaperStore) -> Result<()> {
+    let unknown = store
+        .list_papers()
+        .await?
+        .iter()
+        .filter(|(paper, _)| paper.embedding_model.is_empty())
+        .count();
+    if unknown > 0 {
+        tracing::warn!(
+            "{} papers have embeddings from an unknown model; run the reembed example to fix them",
+            unknown
+        );
+    }
+    Ok(())
+}
+
+async fn tags_and_read_states(store: &dyn PaperStore) -> Result<()> {
+    let mut upgraded = 0;
+    for (paper, metadata) in store.list_papers().await? {
+        let mut target = metadata.clone();
+        if !upgrade_metadata(&mut target) {
+            continue;
+        }
+        if target.tags != metadata.tags {
+            store
+                .set_paper_tags(&paper.arxiv_id, &split_tags(&target.tags))
+                .await?;
+        }
+        if target.read_state != metadata.read_state {
+            store
+                .set_read_state(&paper.arxiv_id, target.read_state)
+                .await?;
+        }
+        upgraded += 1;
+    }
+    tracing::info!("Upgraded tags and reading state of {} papers", upgraded);
+    Ok(())
+}
+
+/// Brings metadata written before version 3 to the current form: tags
+/// normalized and deduplicated, and the legacy `read` flag reflected in
+/// `read_state`. Returns whether anything changed.
+pub fn upgrade_metadata(metadata: &mut PaperMetadata) -> bool {
+    let mut changed = false;
+
+    let tags = join_tags(&split_tags(&metadata.tags));
+    if tags != metadata.tags {
+        metadata.tags = tags;
+        changed = true;
+    }
+    if metadata.read && metadata.read_state == ReadState::Unread {
+        metadata.read_state = ReadState::Read;
+        changed = true;
+    }
+    changed
+}
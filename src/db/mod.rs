@@ -1,2 +1,12 @@
 pub mod client;
+pub mod health;
+pub mod local;
+pub mod migrations;
 pub mod models;
+pub mod offline;
+pub mod reembed;
+pub mod schema;
+pub mod similarity;
+pub mod snapshot;
+pub mod store;
+pub mod tags;
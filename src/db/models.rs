@@ -1,17 +1,688 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct SearchResult {
+use super::tags::{normalize_tag, split_tags};
+use crate::arxiv::models::Paper;
+use crate::embeddings::backend::ModelInfo;
+
+/// A `V::Paper` record as stored in HelixDB.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredPaper {
+    /// HelixDB node ID
+    #[serde(default)]
+    pub id: String,
+    /// arXiv ID without version suffix
     pub arxiv_id: String,
+    /// Latest arXiv version stored, 0 if unknown
+    #[serde(default)]
+    pub version: u32,
     pub title: String,
+    /// Comma-separated author names
+    #[serde(default)]
     pub authors: String,
+    #[serde(default)]
+    pub published: String,
+    /// arXiv's last-updated timestamp, RFC 3339
+    #[serde(default)]
+    pub updated: String,
+    /// Comma-separated arXiv categories
+    #[serde(default)]
+    pub categories: String,
+    #[serde(default)]
+    pub abstract_preview: String,
+    /// Empty for vectors stored before provenance was recorded
+    #[serde(default)]
+    pub embedding_model: String,
+    #[serde(default)]
+    pub embedding_version: String,
+    #[serde(default)]
+    pub embedding_dim: u32,
+}
+
+impl StoredPaper {
+    pub fn author_list(&self) -> Vec<String> {
+        split_list(&self.authors)
+    }
+
+    pub fn category_list(&self) -> Vec<String> {
+        split_list(&self.categories)
+    }
+
+    pub fn published_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.published)
+            .ok()
+            .map(|date| date.with_timezone(&Utc))
+    }
+
+    pub fn updated_at(&self) -> Option<DateTime<Utc>> {
+        DateTime::parse_from_rfc3339(&self.updated)
+            .ok()
+            .map(|date| date.with_timezone(&Utc))
+    }
+
+    /// Whether arXiv has a newer version or revision of this paper than the
+    /// one stored.
+    pub fn is_outdated_by(&self, paper: &Paper) -> bool {
+        if paper.version().unwrap_or(0) > self.version {
+            return true;
+        }
+        match self.updated_at() {
+            Some(updated) => paper.updated > updated,
+            None => true,
+        }
+    }
+
+    /// Whether this vector was produced by `model`.
+    pub fn embedded_with(&self, model: &ModelInfo) -> bool {
+        self.embedding_model == model.name && self.embedding_version == model.version
+    }
+
+    /// Rebuilds an arXiv paper from the stored vector and its metadata.
+    pub fn to_paper(&self, metadata: &PaperMetadata) -> Paper {
+        let published = self.published_at().unwrap_or_default();
+        let updated = self.updated_at().unwrap_or(published);
+        let arxiv_id = if self.version > 0 {
+            format!("{}v{}", self.arxiv_id, self.version)
+        } else {
+            self.arxiv_id.clone()
+        };
+        let categories = self.category_list();
+        let optional = |value: &str| (!value.is_empty()).then(|| value.to_string());
+
+        Paper {
+            arxiv_id,
+            title: self.title.clone(),
+            authors: self.author_list(),
+            abstract_text: metadata.abstract_text.clone(),
+            published,
+            updated,
+            primary_category: categories.first().cloned().unwrap_or_default(),
+            categories,
+            pdf_url: metadata.pdf_url.clone(),
+            comment: optional(&metadata.comment),
+            journal_ref: optional(&metadata.journal_ref),
+            doi: optional(&metadata.doi),
+        }
+    }
+}
+
+/// An `N::PaperMetadata` record.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaperMetadata {
+    #[serde(default)]
+    pub id: String,
+    pub arxiv_id: String,
+    #[serde(rename = "abstract", default)]
+    pub abstract_text: String,
+    #[serde(default)]
+    pub comment: String,
+    #[serde(default)]
+    pub journal_ref: String,
+    #[serde(default)]
+    pub doi: String,
+    #[serde(default)]
+    pub pdf_url: String,
+    #[serde(default)]
+    pub saved: bool,
+    /// Mirrors `read_state == Read`, kept for older records and queries
+    #[serde(default)]
+    pub read: bool,
+    #[serde(default)]
+    pub read_state: ReadState,
+    /// When the paper was last moved to `Reading`, empty if never
+    #[serde(default)]
+    pub reading_started_at: String,
+    /// When the paper was last marked `Read`, empty if not read
+    #[serde(default)]
+    pub read_at: String,
+    /// Comma-separated normalized tags, see [`super::tags`]
+    #[serde(default)]
+    pub tags: String,
+    #[serde(default)]
+    pub created_at: String,
+}
+
+impl PaperMetadata {
+    pub fn tag_list(&self) -> Vec<String> {
+        split_tags(&self.tags)
+    }
+
+    pub fn has_tag(&self, tag: &str) -> bool {
+        normalize_tag(tag).is_ok_and(|tag| self.tag_list().contains(&tag))
+    }
+
+    /// The reading state, treating records that only carry the legacy
+    /// `read` flag as read.
+    pub fn state(&self) -> ReadState {
+        if self.read && self.read_state == ReadState::Unread {
+            ReadState::Read
+        } else {
+            self.read_state
+        }
+    }
+
+    /// Moves the paper to `state`, stamping the transition with `now`.
+    pub fn set_state(&mut self, state: ReadState, now: DateTime<Utc>) {
+        match state {
+            ReadState::Unread => {
+                self.reading_started_at.clear();
+                self.read_at.clear();
+            }
+            ReadState::Reading => {
+                if self.state() != ReadState::Reading {
+                    self.reading_started_at = now.to_rfc3339();
+                }
+                self.read_at.clear();
+            }
+            ReadState::Read => {
+                if self.state() != ReadState::Read {
+                    self.read_at = now.to_rfc3339();
+                }
+            }
+        }
+        self.read_state = state;
+        self.read = state == ReadState::Read;
+    }
+}
+
+/// Where the user is with a paper.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReadState {
+    #[default]
+    Unread,
+    Reading,
+    Read,
+}
+
+impl ReadState {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ReadState::Unread => "unread",
+            ReadState::Reading => "reading",
+            ReadState::Read => "read",
+        }
+    }
+}
+
+impl std::fmt::Display for ReadState {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ReadState {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().as_str() {
+            "unread" => Ok(ReadState::Unread),
+            "reading" => Ok(ReadState::Reading),
+            "read" => Ok(ReadState::Read),
+            other => Err(anyhow!("Unknown read state {:?}", other)),
+        }
+    }
+}
+
+/// A `V::PaperChunk` record.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredChunk {
+    #[serde(default)]
+    pub id: String,
+    pub arxiv_id: String,
+    pub chunk_index: u32,
+    pub text: String,
+    #[serde(default)]
+    pub embedding_model: String,
+    #[serde(default)]
+    pub embedding_version: String,
+    #[serde(default)]
+    pub embedding_dim: u32,
+}
+
+impl StoredChunk {
+    pub fn embedded_with(&self, model: &ModelInfo) -> bool {
+        self.embedding_model == model.name && self.embedding_version == model.version
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SearchResult {
+    pub paper: StoredPaper,
+    /// Cosine similarity to the query, higher is closer
+    pub similarity_score: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChunkSearchResult {
+    pub chunk: StoredChunk,
     pub similarity_score: f32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Collection {
+    #[serde(default)]
     pub id: String,
     pub name: String,
+    #[serde(default)]
     pub description: String,
+    /// Name of the enclosing collection, empty for top-level collections
+    #[serde(default)]
+    pub parent: String,
+    /// JSON-encoded [`SmartFilter`] for smart collections, empty otherwise
+    #[serde(default)]
+    pub filter: String,
+    #[serde(default)]
     pub created_at: String,
 }
+
+impl Collection {
+    pub fn parent(&self) -> Option<&str> {
+        (!self.parent.is_empty()).then_some(self.parent.as_str())
+    }
+
+    pub fn is_smart(&self) -> bool {
+        !self.filter.is_empty()
+    }
+
+    /// The rule selecting this collection's papers, if it is a smart
+    /// collection.
+    pub fn smart_filter(&self) -> Result<Option<SmartFilter>> {
+        if self.filter.is_empty() {
+            return Ok(None);
+        }
+        serde_json::from_str(&self.filter)
+            .map(Some)
+            .with_context(|| format!("Collection {:?} has an invalid filter", self.name))
+    }
+}
+
+/// Parameters for creating a collection.
+#[derive(Debug, Clone, Default)]
+pub struct NewCollection {
+    pub name: String,
+    pub description: String,
+    /// Nest the collection under this one
+    pub parent: Option<String>,
+    /// Make this a smart collection whose papers are selected by the filter
+    pub filter: Option<SmartFilter>,
+}
+
+impl NewCollection {
+    pub fn new(name: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            ..Self::default()
+        }
+    }
+}
+
+/// Rule for a smart collection. A paper matches when it satisfies every
+/// criterion that is set; list criteria match if any entry matches.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SmartFilter {
+    /// arXiv categories, e.g. `cs.LG`; a trailing `.*` matches an archive
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub categories: Vec<String>,
+    /// Case-insensitive substrings of an author name
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub authors: Vec<String>,
+    /// Case-insensitive substrings of the title or abstract
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub keywords: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub saved: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub read: Option<bool>,
+    /// Only papers first published on or after this date
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub published_after: Option<DateTime<Utc>>,
+}
+
+impl SmartFilter {
+    pub fn matches(&self, paper: &StoredPaper, metadata: Option<&PaperMetadata>) -> bool {
+        let categories = paper.category_list();
+        if !self.categories.is_empty()
+            && !self.categories.iter().any(|wanted| {
+                categories
+                    .iter()
+                    .any(|category| match wanted.strip_suffix(".*") {
+                        Some(archive) => category.split('.').next() == Some(archive),
+                        None => category == wanted,
+                    })
+            })
+        {
+            return false;
+        }
+
+        let authors = paper.authors.to_lowercase();
+        if !self.authors.is_empty()
+            && !self
+                .authors
+                .iter()
+                .any(|author| authors.contains(&author.to_lowercase()))
+        {
+            return false;
+        }
+
+        if !self.keywords.is_empty() {
+            let text = format!(
+                "{} {}",
+                paper.title,
+                metadata
+                    .map(|m| m.abstract_text.as_str())
+                    .unwrap_or(&paper.abstract_preview)
+            )
+            .to_lowercase();
+            if !self
+                .keywords
+                .iter()
+                .any(|keyword| text.contains(&keyword.to_lowercase()))
+            {
+                return false;
+            }
+        }
+
+        if let Some(saved) = self.saved {
+            if metadata.map(|m| m.saved).unwrap_or(false) != saved {
+                return false;
+            }
+        }
+        if let Some(read) = self.read {
+            if metadata.map(|m| m.read).unwrap_or(false) != read {
+                return false;
+            }
+        }
+
+        if let Some(after) = self.published_after {
+            if paper
+                .published_at()
+                .is_none_or(|published| published < after)
+            {
+                return false;
+            }
+        }
+
+        true
+    }
+}
+
+/// A paper listed in a collection.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectionPaper {
+    pub paper: StoredPaper,
+    /// When the paper was added; `None` for smart collections
+    pub added_at: Option<String>,
+}
+
+/// One end of an `E::Cites` edge, with the sentence the citation appears in.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CitationLink {
+    pub paper: StoredPaper,
+    #[serde(default)]
+    pub context: String,
+}
+
+/// A collection with its nested collections, see [`collection_tree`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct CollectionNode {
+    pub collection: Collection,
+    pub children: Vec<CollectionNode>,
+}
+
+/// Arranges a flat list of collections into a forest by their `parent`
+/// names. Collections whose parent is missing become roots.
+pub fn collection_tree(collections: &[Collection]) -> Vec<CollectionNode> {
+    fn build(parent: &str, collections: &[Collection], depth: usize) -> Vec<CollectionNode> {
+        // Guard against cycles in hand-edited data
+        if depth > collections.len() {
+            return Vec::new();
+        }
+        collections
+            .iter()
+            .filter(|collection| collection.parent == parent)
+            .map(|collection| CollectionNode {
+                collection: collection.clone(),
+                children: build(&collection.name, collections, depth + 1),
+            })
+            .collect()
+    }
+
+    let names: std::collections::HashSet<&str> =
+        collections.iter().map(|c| c.name.as_str()).collect();
+    let mut roots = build("", collections, 0);
+    for orphan in collections
+        .iter()
+        .filter(|c| !c.parent.is_empty() && !names.contains(c.parent.as_str()))
+    {
+        roots.push(CollectionNode {
+            collection: orphan.clone(),
+            children: build(&orphan.name, collections, 1),
+        });
+    }
+    roots
+}
+
+/// What [`PaperStore::upsert_paper`](super::store::PaperStore::upsert_paper)
+/// did with a paper.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    Inserted,
+    /// arXiv reported a newer version or `updated` timestamp
+    Updated,
+    /// Already stored and up to date
+    Skipped,
+}
+
+/// Running totals of upsert outcomes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct UpsertReport {
+    pub inserted: usize,
+    pub updated: usize,
+    pub skipped: usize,
+    pub failed: usize,
+}
+
+impl UpsertReport {
+    pub fn record(&mut self, outcome: UpsertOutcome) {
+        match outcome {
+            UpsertOutcome::Inserted => self.inserted += 1,
+            UpsertOutcome::Updated => self.updated += 1,
+            UpsertOutcome::Skipped => self.skipped += 1,
+        }
+    }
+
+    pub fn total(&self) -> usize {
+        self.inserted + self.updated + self.skipped + self.failed
+    }
+}
+
+impl std::fmt::Display for UpsertReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} inserted, {} updated, {} skipped, {} failed",
+            self.inserted, self.updated, self.skipped, self.failed
+        )
+    }
+}
+
+/// How many stored paper vectors were produced by a given model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EmbeddingCoverage {
+    pub matching: u64,
+    pub total: u64,
+}
+
+impl EmbeddingCoverage {
+    pub fn is_complete(&self) -> bool {
+        self.matching == self.total
+    }
+
+    pub fn stale(&self) -> u64 {
+        self.total.saturating_sub(self.matching)
+    }
+}
+
+/// Pulls the records returned under `key` (the query's `RETURN` variable)
+/// out of a HelixDB response and decodes each one as `T`.
+///
+/// A single object is treated as a one-element list. If the response holds
+/// exactly one field it is used regardless of its name.
+pub(crate) fn decode_rows<T: DeserializeOwned>(
+    query: &str,
+    response: &Value,
+    key: &str,
+) -> Result<Vec<T>> {
+    let rows = field(query, response, key)?;
+    let items: Vec<&Value> = match rows {
+        Value::Array(items) => items.iter().collect(),
+        Value::Null => Vec::new(),
+        other => vec![other],
+    };
+
+    items
+        .into_iter()
+        .enumerate()
+        .map(|(i, item)| {
+            T::deserialize(item).map_err(|e| {
+                anyhow!(
+                    "HelixDB {} returned an unexpected record at {}[{}]: {} (got {})",
+                    query,
+                    key,
+                    i,
+                    e,
+                    preview(item)
+                )
+            })
+        })
+        .collect()
+}
+
+/// Like [`decode_rows`], for queries that return exactly one record.
+pub(crate) fn decode_one<T: DeserializeOwned>(
+    query: &str,
+    response: &Value,
+    key: &str,
+) -> Result<T> {
+    decode_rows(query, response, key)?
+        .into_iter()
+        .next()
+        .with_context(|| format!("HelixDB {} returned no {} record", query, key))
+}
+
+/// Decodes search hits, reading the score HelixDB attaches to each vector.
+///
+/// Servers report either a similarity (`similarity_score`, `score`,
+/// `similarity`) or a cosine `distance`, which is converted.
+pub(crate) fn decode_scored<T: DeserializeOwned>(
+    query: &str,
+    response: &Value,
+    key: &str,
+) -> Result<Vec<(T, f32)>> {
+    let records: Vec<Value> = decode_rows(query, response, key)?;
+
+    records
+        .into_iter()
+        .enumerate()
+        .map(|(i, record)| {
+            let score = ["similarity_score", "score", "similarity"]
+                .iter()
+                .find_map(|name| record.get(*name).and_then(Value::as_f64))
+                .or_else(|| {
+                    record
+                        .get("distance")
+                        .and_then(Value::as_f64)
+                        .map(|distance| 1.0 - distance)
+                })
+                .ok_or_else(|| {
+                    anyhow!(
+                        "HelixDB {} returned a hit without a similarity score at {}[{}] (got {})",
+                        query,
+                        key,
+                        i,
+                        preview(&record)
+                    )
+                })?;
+
+            let item = T::deserialize(&record).map_err(|e| {
+                anyhow!(
+                    "HelixDB {} returned an unexpected record at {}[{}]: {} (got {})",
+                    query,
+                    key,
+                    i,
+                    e,
+                    preview(&record)
+                )
+            })?;
+
+            Ok((item, score as f32))
+        })
+        .collect()
+}
+
+/// Extracts the single number a `COUNT` query returns.
+pub(crate) fn decode_count(query: &str, response: &Value) -> Result<u64> {
+    match response {
+        Value::Number(n) => n.as_u64(),
+        Value::Object(fields) if fields.len() == 1 => {
+            fields.values().next().and_then(Value::as_u64)
+        }
+        _ => None,
+    }
+    .ok_or_else(|| {
+        anyhow!(
+            "HelixDB {} returned {} where a count was expected",
+            query,
+            preview(response)
+        )
+    })
+}
+
+fn field<'a>(query: &str, response: &'a Value, key: &str) -> Result<&'a Value> {
+    let Value::Object(fields) = response else {
+        return Err(anyhow!(
+            "HelixDB {} returned {} where an object was expected",
+            query,
+            preview(response)
+        ));
+    };
+
+    fields
+        .get(key)
+        .or_else(|| {
+            (fields.len() == 1)
+                .then(|| fields.values().next())
+                .flatten()
+        })
+        .ok_or_else(|| {
+            anyhow!(
+                "HelixDB {} response has no {:?} field (fields: {})",
+                query,
+                key,
+                fields.keys().cloned().collect::<Vec<_>>().join(", ")
+            )
+        })
+}
+
+/// Shortens a JSON value for error messages.
+fn preview(value: &Value) -> String {
+    let text = value.to_string();
+    if text.chars().count() > 200 {
+        format!("{}...", text.chars().take(200).collect::<String>())
+    } else {
+        text
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|item| !item.is_empty())
+        .map(str::to_string)
+        .collect()
+}
@@ -0,0 +1,716 @@
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use super::client::{is_unavailable, DbClient};
+use super::local::LocalStore;
+use super::models::{
+    ChunkSearchResult, CitationLink, Collection, CollectionPaper, EmbeddingCoverage, NewCollection,
+    PaperMetadata, ReadState, SearchResult, SmartFilter, StoredChunk, StoredPaper, UpsertOutcome,
+};
+use super::store::PaperStore;
+use crate::arxiv::models::Paper;
+use crate::config::OfflineConfig;
+use crate::embeddings::backend::ModelInfo;
+use crate::embeddings::chunking::ChunkEmbedding;
+
+const CACHE_FILE: &str = "cache.json";
+const QUEUE_FILE: &str = "pending-writes.json";
+
+/// A library edit made while HelixDB was offline, replayed when it returns.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum PendingWrite {
+    MarkSaved {
+        arxiv_id: String,
+        saved: bool,
+    },
+    SetReadState {
+        arxiv_id: String,
+        state: ReadState,
+    },
+    SetTags {
+        arxiv_id: String,
+        tags: Vec<String>,
+    },
+    CreateCollection {
+        name: String,
+        description: String,
+        parent: Option<String>,
+        filter: Option<SmartFilter>,
+    },
+    RenameCollection {
+        name: String,
+        new_name: String,
+    },
+    DeleteCollection {
+        name: String,
+    },
+    AddToCollection {
+        arxiv_id: String,
+        collection: String,
+    },
+    RemoveFromCollection {
+        arxiv_id: String,
+        collection: String,
+    },
+    AddCitation {
+        from: String,
+        to: String,
+        context: String,
+    },
+    DeleteCitations {
+        arxiv_id: String,
+    },
+    ReplaceSimilarities {
+        arxiv_id: String,
+        similar: Vec<(String, f32)>,
+    },
+}
+
+impl PendingWrite {
+    pub async fn apply(&self, store: &dyn PaperStore) -> Result<()> {
+        match self {
+            PendingWrite::MarkSaved { arxiv_id, saved } => {
+                store.mark_paper_saved(arxiv_id, *saved).await?;
+            }
+            PendingWrite::SetReadState { arxiv_id, state } => {
+                store.set_read_state(arxiv_id, *state).await?;
+            }
+            PendingWrite::SetTags { arxiv_id, tags } => {
+                store.set_paper_tags(arxiv_id, tags).await?;
+            }
+            PendingWrite::CreateCollection {
+                name,
+                description,
+                parent,
+                filter,
+            } => {
+                let collection = NewCollection {
+                    name: name.clone(),
+                    description: description.clone(),
+                    parent: parent.clone(),
+                    filter: filter.clone(),
+                };
+                store.create_collection(&collection).await?;
+            }
+            PendingWrite::RenameCollection { name, new_name } => {
+                store.rename_collection(name, new_name).await?;
+            }
+            PendingWrite::DeleteCollection { name } => store.delete_collection(name).await?,
+            PendingWrite::AddToCollection {
+                arxiv_id,
+                collection,
+            } => store.add_paper_to_collection(arxiv_id, collection).await?,
+            PendingWrite::RemoveFromCollection {
+                arxiv_id,
+                collection,
+            } => {
+                store
+                    .remove_paper_from_collection(arxiv_id, collection)
+                    .await?
+            }
+            PendingWrite::AddCitation { from, to, context } => {
+                store.add_citation(from, to, context).await?
+            }
+            PendingWrite::DeleteCitations { arxiv_id } => store.delete_citations(arxiv_id).await?,
+            PendingWrite::ReplaceSimilarities { arxiv_id, similar } => {
+                store.replace_similarities(arxiv_id, similar).await?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes waiting for HelixDB, persisted so they survive a restart.
+struct WriteQueue {
+    path: PathBuf,
+    pending: Mutex<Vec<PendingWrite>>,
+}
+
+impl WriteQueue {
+    fn open(path: PathBuf) -> Result<Self> {
+        let pending = match std::fs::read_to_string(&path) {
+            Ok(json) => serde_json::from_str(&json)
+                .with_context(|| format!("Failed to parse write queue {:?}", path))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {:?}", path)),
+        };
+        Ok(Self {
+            path,
+            pending: Mutex::new(pending),
+        })
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, Vec<PendingWrite>>> {
+        self.pending
+            .lock()
+            .map_err(|_| anyhow!("Write queue lock poisoned"))
+    }
+
+    fn len(&self) -> usize {
+        self.lock().map(|pending| pending.len()).unwrap_or(0)
+    }
+
+    fn snapshot(&self) -> Result<Vec<PendingWrite>> {
+        Ok(self.lock()?.clone())
+    }
+
+    fn push(&self, write: PendingWrite) -> Result<()> {
+        let mut pending = self.lock()?;
+        pending.push(write);
+        self.save(&pending)
+    }
+
+    /// Drops the first `count` writes, once they have been replayed.
+    fn drain(&self, count: usize) -> Result<()> {
+        let mut pending = self.lock()?;
+        let count = count.min(pending.len());
+        pending.drain(..count);
+        self.save(&pending)
+    }
+
+    fn save(&self, pending: &[PendingWrite]) -> Result<()> {
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec(pending)?)?;
+        std::fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to replace {:?}", self.path))?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ReplayReport {
+    pub applied: usize,
+    /// Writes HelixDB rejected, such as edits to papers deleted meanwhile
+    pub dropped: Vec<(PendingWrite, String)>,
+    /// Writes still queued because HelixDB went away again
+    pub remaining: usize,
+}
+
+impl std::fmt::Display for ReplayReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} applied, {} dropped, {} remaining",
+            self.applied,
+            self.dropped.len(),
+            self.remaining
+        )
+    }
+}
+
+/// HelixDB with a local fallback, so the app keeps working through outages.
+///
+/// While HelixDB answers, every call goes to it. When a call fails because
+/// the server is unavailable, the store switches to offline: reads are
+/// served from a [`LocalStore`] cache mirroring the HelixDB library, and
+/// library edits (saved, reading state, tags, collections, citations) are
+/// applied to the cache and queued on disk. Ingestion and re-embedding need
+/// the database and fail while offline. Every `reconnect_interval` the store
+/// tries HelixDB again; once it answers, the queue is replayed in order and
+/// the cache is refreshed in the background.
+pub struct OfflineStore {
+    primary: DbClient,
+    cache: Arc<LocalStore>,
+    queue: Arc<WriteQueue>,
+    online: AtomicBool,
+    reconnect_interval: Duration,
+    last_attempt: Mutex<Instant>,
+    reconnecting: tokio::sync::Mutex<()>,
+    refreshing: Arc<AtomicBool>,
+}
+
+/// Runs a read on HelixDB, or on the cache while offline.
+macro_rules! read {
+    ($self:ident . $method:ident ( $($arg:expr),* )) => {{
+        if $self.ensure_online().await {
+            match $self.primary.$method($($arg),*).await {
+                Err(e) if is_unavailable(&e) => $self.go_offline(&e),
+                result => return result,
+            }
+        }
+        $self.cache.$method($($arg),*).await
+    }};
+}
+
+/// Runs a library edit on HelixDB and the cache, or only on the cache while
+/// offline, queueing `$write` for replay.
+macro_rules! edit {
+    ($self:ident . $method:ident ( $($arg:expr),* ), $write:expr) => {{
+        if $self.ensure_online().await {
+            match $self.primary.$method($($arg),*).await {
+                Err(e) if is_unavailable(&e) => $self.go_offline(&e),
+                Err(e) => return Err(e),
+                Ok(result) => {
+                    if let Err(e) = $self.cache.$method($($arg),*).await {
+                        tracing::debug!("Offline cache not updated: {}", e);
+                    }
+                    return Ok(result);
+                }
+            }
+        }
+        let result = $self.cache.$method($($arg),*).await?;
+        $self.queue.push($write)?;
+        Ok(result)
+    }};
+}
+
+/// Runs a write that only HelixDB can take.
+macro_rules! online_only {
+    ($self:ident . $method:ident ( $($arg:expr),* )) => {{
+        if !$self.ensure_online().await {
+            bail!(
+                "HelixDB at {} is offline; {} needs the database",
+                $self.primary.address(),
+                stringify!($method)
+            );
+        }
+        let result = $self.primary.$method($($arg),*).await;
+        if let Err(e) = &result {
+            if is_unavailable(e) {
+                $self.go_offline(e);
+            }
+        }
+        result
+    }};
+}
+
+impl OfflineStore {
+    /// Wraps `primary`, keeping the cache and queue in `config.dir`.
+    /// `online` is whether HelixDB answered at startup.
+    pub fn open(primary: DbClient, config: &OfflineConfig, online: bool) -> Result<Self> {
+        let cache = LocalStore::open(&config.dir.join(CACHE_FILE))?;
+        let queue = WriteQueue::open(config.dir.join(QUEUE_FILE))?;
+        if queue.len() > 0 {
+            tracing::info!("{} offline writes waiting for HelixDB", queue.len());
+        }
+
+        Ok(Self {
+            primary,
+            cache: Arc::new(cache),
+            queue: Arc::new(queue),
+            online: AtomicBool::new(online),
+            reconnect_interval: Duration::from_secs(config.reconnect_interval_secs),
+            last_attempt: Mutex::new(Instant::now()),
+            reconnecting: tokio::sync::Mutex::new(()),
+            refreshing: Arc::new(AtomicBool::new(false)),
+        })
+    }
+
+    /// Whether an earlier session left a cache to work from.
+    pub fn has_cache(config: &OfflineConfig) -> bool {
+        config.dir.join(CACHE_FILE).exists()
+    }
+
+    pub fn is_online(&self) -> bool {
+        self.online.load(Ordering::Relaxed)
+    }
+
+    pub fn pending_writes(&self) -> usize {
+        self.queue.len()
+    }
+
+    pub fn cache_path(&self) -> &Path {
+        self.cache.path()
+    }
+
+    /// Replays queued writes and starts refreshing the cache from HelixDB
+    /// in the background. Returns `false`, leaving the store offline, if
+    /// HelixDB is still unavailable.
+    pub async fn reconnect(&self) -> Result<bool> {
+        let _guard = self.reconnecting.lock().await;
+        self.touch_last_attempt();
+
+        if let Err(e) = self.primary.ping().await {
+            tracing::debug!("HelixDB still offline: {:#}", e);
+            self.online.store(false, Ordering::Relaxed);
+            return Ok(false);
+        }
+
+        let report = self.replay().await?;
+        if report.remaining > 0 {
+            self.online.store(false, Ordering::Relaxed);
+            return Ok(false);
+        }
+        if report.applied > 0 || !report.dropped.is_empty() {
+            tracing::info!("Replayed offline writes: {}", report);
+        }
+        for (write, reason) in &report.dropped {
+            tracing::warn!("Dropped offline write {:?}: {}", write, reason);
+        }
+
+        self.online.store(true, Ordering::Relaxed);
+        self.spawn_refresh();
+        Ok(true)
+    }
+
+    /// Copies the HelixDB library into the cache. Skipped while writes are
+    /// queued, since they only exist in the cache.
+    pub async fn refresh_cache(&self) -> Result<usize> {
+        refresh(&self.primary, &self.cache, &self.queue).await
+    }
+
+    /// Refreshes the cache in a background task, unless one is running.
+    fn spawn_refresh(&self) {
+        if self.refreshing.swap(true, Ordering::AcqRel) {
+            return;
+        }
+        let primary = self.primary.clone();
+        let cache = self.cache.clone();
+        let queue = self.queue.clone();
+        let refreshing = self.refreshing.clone();
+        tokio::spawn(async move {
+            if let Err(e) = refresh(&primary, &cache, &queue).await {
+                tracing::warn!("Could not refresh the offline cache: {:#}", e);
+            }
+            refreshing.store(false, Ordering::Release);
+        });
+    }
+
+    /// Sends queued writes to HelixDB in order, stopping if it goes away.
+    async fn replay(&self) -> Result<ReplayReport> {
+        let pending = self.queue.snapshot()?;
+        let mut report = ReplayReport::default();
+
+        let mut done = 0;
+        for write in &pending {
+            match write.apply(&self.primary).await {
+                Ok(()) => report.applied += 1,
+                Err(e) if is_unavailable(&e) => break,
+                Err(e) => report.dropped.push((write.clone(), format!("{:#}", e))),
+            }
+            done += 1;
+        }
+
+        self.queue.drain(done)?;
+        report.remaining = pending.len() - done;
+        Ok(report)
+    }
+
+    /// Whether to try HelixDB, reconnecting first if it was offline and the
+    /// reconnect interval has passed.
+    async fn ensure_online(&self) -> bool {
+        if self.is_online() {
+            return true;
+        }
+        let due = self
+            .last_attempt
+            .lock()
+            .map(|last| last.elapsed() >= self.reconnect_interval)
+            .unwrap_or(true);
+        if !due {
+            return false;
+        }
+        match self.reconnect().await {
+            Ok(online) => online,
+            Err(e) => {
+                tracing::warn!("Reconnecting to HelixDB failed: {:#}", e);
+                false
+            }
+        }
+    }
+
+    fn go_offline(&self, error: &anyhow::Error) {
+        if self.online.swap(false, Ordering::Relaxed) {
+            tracing::warn!(
+                "HelixDB went offline ({:#}); working from the local cache",
+                error
+            );
+        }
+        self.touch_last_attempt();
+    }
+
+    fn touch_last_attempt(&self) {
+        if let Ok(mut last) = self.last_attempt.lock() {
+            *last = Instant::now();
+        }
+    }
+}
+
+/// Copies the HelixDB library into `cache`, see [`LocalStore::mirror`].
+async fn refresh(primary: &DbClient, cache: &LocalStore, queue: &WriteQueue) -> Result<usize> {
+    if queue.len() > 0 {
+        bail!("Cannot refresh the offline cache while writes are queued");
+    }
+    let papers = cache.mirror(primary).await?;
+
+    // HelixDB went away during the copy, and the edits queued meanwhile
+    // were made to the cache it replaced
+    for write in queue.snapshot()? {
+        if let Err(e) = write.apply(cache).await {
+            tracing::debug!("Offline cache not updated: {}", e);
+        }
+    }
+
+    tracing::info!("Offline cache holds {} papers", papers);
+    Ok(papers)
+}
+
+#[async_trait]
+impl PaperStore for OfflineStore {
+    fn backend_name(&self) -> &str {
+        if self.is_online() {
+            "helixdb"
+        } else {
+            "helixdb (offline)"
+        }
+    }
+
+    async fn flush(&self) -> Result<()> {
+        self.cache.flush().await
+    }
+
+    async fn add_paper(
+        &self,
+        paper: &Paper,
+        embedding: Vec<f32>,
+        model: &ModelInfo,
+    ) -> Result<StoredPaper> {
+        online_only!(self.add_paper(paper, embedding, model))
+    }
+
+    async fn add_paper_metadata(&self, paper: &Paper) -> Result<PaperMetadata> {
+        online_only!(self.add_paper_metadata(paper))
+    }
+
+    async fn upsert_paper(
+        &self,
+        paper: &Paper,
+        embedding: Vec<f32>,
+        chunks: &[ChunkEmbedding],
+        model: &ModelInfo,
+    ) -> Result<UpsertOutcome> {
+        online_only!(self.upsert_paper(paper, embedding, chunks, model))
+    }
+
+    async fn add_paper_chunks(
+        &self,
+        arxiv_id: &str,
+        chunks: &[ChunkEmbedding],
+        model: &ModelInfo,
+    ) -> Result<Vec<StoredChunk>> {
+        online_only!(self.add_paper_chunks(arxiv_id, chunks, model))
+    }
+
+    async fn delete_paper_chunks(&self, arxiv_id: &str) -> Result<()> {
+        online_only!(self.delete_paper_chunks(arxiv_id))
+    }
+
+    async fn get_paper_by_arxiv_id(&self, arxiv_id: &str) -> Result<Option<StoredPaper>> {
+        read!(self.get_paper_by_arxiv_id(arxiv_id))
+    }
+
+    async fn search_papers(
+        &self,
+        query_embedding: Vec<f32>,
+        model: &ModelInfo,
+        min_similarity: f32,
+        limit: u32,
+    ) -> Result<Vec<SearchResult>> {
+        read!(self.search_papers(query_embedding.clone(), model, min_similarity, limit))
+    }
+
+    async fn search_paper_chunks(
+        &self,
+        query_embedding: Vec<f32>,
+        model: &ModelInfo,
+        min_similarity: f32,
+        limit: u32,
+    ) -> Result<Vec<ChunkSearchResult>> {
+        read!(self.search_paper_chunks(query_embedding.clone(), model, min_similarity, limit))
+    }
+
+    async fn find_similar_papers(&self, arxiv_id: &str, limit: u32) -> Result<Vec<StoredPaper>> {
+        read!(self.find_similar_papers(arxiv_id, limit))
+    }
+
+    async fn get_paper_vector(&self, arxiv_id: &str) -> Result<Option<Vec<f32>>> {
+        read!(self.get_paper_vector(arxiv_id))
+    }
+
+    async fn get_similarities(&self, arxiv_id: &str) -> Result<Vec<SearchResult>> {
+        read!(self.get_similarities(arxiv_id))
+    }
+
+    async fn replace_similarities(&self, arxiv_id: &str, similar: &[(String, f32)]) -> Result<()> {
+        edit!(
+            self.replace_similarities(arxiv_id, similar),
+            PendingWrite::ReplaceSimilarities {
+                arxiv_id: arxiv_id.to_string(),
+                similar: similar.to_vec(),
+            }
+        )
+    }
+
+    async fn embedding_coverage(&self, model: &ModelInfo) -> Result<EmbeddingCoverage> {
+        read!(self.embedding_coverage(model))
+    }
+
+    async fn list_stale_papers(
+        &self,
+        model: &ModelInfo,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<(StoredPaper, PaperMetadata)>> {
+        read!(self.list_stale_papers(model, offset, limit))
+    }
+
+    async fn replace_paper_embedding(
+        &self,
+        arxiv_id: &str,
+        embedding: Vec<f32>,
+        model: &ModelInfo,
+    ) -> Result<StoredPaper> {
+        online_only!(self.replace_paper_embedding(arxiv_id, embedding, model))
+    }
+
+    async fn overwrite_paper(&self, paper: &Paper) -> Result<StoredPaper> {
+        online_only!(self.overwrite_paper(paper))
+    }
+
+    async fn create_collection(&self, collection: &NewCollection) -> Result<Collection> {
+        edit!(
+            self.create_collection(collection),
+            PendingWrite::CreateCollection {
+                name: collection.name.clone(),
+                description: collection.description.clone(),
+                parent: collection.parent.clone(),
+                filter: collection.filter.clone(),
+            }
+        )
+    }
+
+    async fn get_all_collections(&self) -> Result<Vec<Collection>> {
+        read!(self.get_all_collections())
+    }
+
+    async fn get_collection(&self, name: &str) -> Result<Option<Collection>> {
+        read!(self.get_collection(name))
+    }
+
+    async fn rename_collection(&self, name: &str, new_name: &str) -> Result<Collection> {
+        edit!(
+            self.rename_collection(name, new_name),
+            PendingWrite::RenameCollection {
+                name: name.to_string(),
+                new_name: new_name.to_string(),
+            }
+        )
+    }
+
+    async fn delete_collection(&self, name: &str) -> Result<()> {
+        edit!(
+            self.delete_collection(name),
+            PendingWrite::DeleteCollection {
+                name: name.to_string(),
+            }
+        )
+    }
+
+    async fn get_collection_papers(&self, collection_name: &str) -> Result<Vec<CollectionPaper>> {
+        read!(self.get_collection_papers(collection_name))
+    }
+
+    async fn add_paper_to_collection(&self, arxiv_id: &str, collection_name: &str) -> Result<()> {
+        edit!(
+            self.add_paper_to_collection(arxiv_id, collection_name),
+            PendingWrite::AddToCollection {
+                arxiv_id: arxiv_id.to_string(),
+                collection: collection_name.to_string(),
+            }
+        )
+    }
+
+    async fn remove_paper_from_collection(
+        &self,
+        arxiv_id: &str,
+        collection_name: &str,
+    ) -> Result<()> {
+        edit!(
+            self.remove_paper_from_collection(arxiv_id, collection_name),
+            PendingWrite::RemoveFromCollection {
+                arxiv_id: arxiv_id.to_string(),
+                collection: collection_name.to_string(),
+            }
+        )
+    }
+
+    async fn list_papers(&self) -> Result<Vec<(StoredPaper, PaperMetadata)>> {
+        read!(self.list_papers())
+    }
+
+    async fn get_paper_metadata(&self, arxiv_id: &str) -> Result<Option<PaperMetadata>> {
+        read!(self.get_paper_metadata(arxiv_id))
+    }
+
+    async fn mark_paper_saved(&self, arxiv_id: &str, saved: bool) -> Result<PaperMetadata> {
+        edit!(
+            self.mark_paper_saved(arxiv_id, saved),
+            PendingWrite::MarkSaved {
+                arxiv_id: arxiv_id.to_string(),
+                saved,
+            }
+        )
+    }
+
+    async fn set_read_state(&self, arxiv_id: &str, state: ReadState) -> Result<PaperMetadata> {
+        edit!(
+            self.set_read_state(arxiv_id, state),
+            PendingWrite::SetReadState {
+                arxiv_id: arxiv_id.to_string(),
+                state,
+            }
+        )
+    }
+
+    async fn set_paper_tags(&self, arxiv_id: &str, tags: &[String]) -> Result<PaperMetadata> {
+        edit!(
+            self.set_paper_tags(arxiv_id, tags),
+            PendingWrite::SetTags {
+                arxiv_id: arxiv_id.to_string(),
+                tags: tags.to_vec(),
+            }
+        )
+    }
+
+    async fn add_citation(
+        &self,
+        from_arxiv_id: &str,
+        to_arxiv_id: &str,
+        context: &str,
+    ) -> Result<()> {
+        edit!(
+            self.add_citation(from_arxiv_id, to_arxiv_id, context),
+            PendingWrite::AddCitation {
+                from: from_arxiv_id.to_string(),
+                to: to_arxiv_id.to_string(),
+                context: context.to_string(),
+            }
+        )
+    }
+
+    async fn delete_citations(&self, arxiv_id: &str) -> Result<()> {
+        edit!(
+            self.delete_citations(arxiv_id),
+            PendingWrite::DeleteCitations {
+                arxiv_id: arxiv_id.to_string(),
+            }
+        )
+    }
+
+    async fn get_references(&self, arxiv_id: &str) -> Result<Vec<CitationLink>> {
+        read!(self.get_references(arxiv_id))
+    }
+
+    async fn get_cited_by(&self, arxiv_id: &str) -> Result<Vec<CitationLink>> {
+        read!(self.get_cited_by(arxiv_id))
+    }
+}
@@ -0,0 +1,119 @@
+use anyhow::Result;
+
+use super::store::PaperStore;
+use crate::arxiv::models::Paper;
+use crate::embeddings::backend::ModelInfo;
+use crate::embeddings::chunking::DocumentEmbedder;
+
+/// Progress reported by [`ReembedJob::run`] after each page of papers.
+#[derive(Debug, Clone, Copy)]
+pub struct ReembedProgress {
+    pub reembedded: usize,
+    pub failed: usize,
+    /// Papers still carrying vectors from another model
+    pub remaining: u64,
+}
+
+#[derive(Debug, Default)]
+pub struct ReembedReport {
+    pub reembedded: usize,
+    /// arXiv IDs that could not be migrated, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+/// Regenerates the vectors of every stored paper whose embedding came from a
+/// model other than the embedder's, swapping each one in place.
+///
+/// Each paper is migrated independently, so the job can be interrupted at
+/// any point: running it again picks up the papers that are still stale.
+pub struct ReembedJob<'a> {
+    db: &'a dyn PaperStore,
+    embedder: &'a DocumentEmbedder,
+    page_size: u32,
+}
+
+impl<'a> ReembedJob<'a> {
+    pub fn new(db: &'a dyn PaperStore, embedder: &'a DocumentEmbedder) -> Self {
+        Self {
+            db,
+            embedder,
+            page_size: 32,
+        }
+    }
+
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    pub fn target_model(&self) -> ModelInfo {
+        self.embedder.backend().model_info()
+    }
+
+    pub async fn run(&self, on_progress: &mut dyn FnMut(ReembedProgress)) -> Result<ReembedReport> {
+        let model = self.target_model();
+        let mut report = ReembedReport::default();
+        // Failed papers whose vector was not replaced, so still listed first
+        let mut stale_failures = 0u32;
+
+        tracing::info!("Re-embedding stored papers with {}", model);
+
+        loop {
+            let page = self
+                .db
+                .list_stale_papers(&model, stale_failures, self.page_size)
+                .await?;
+            let papers: Vec<Paper> = page
+                .iter()
+                .map(|(paper, metadata)| paper.to_paper(metadata))
+                .collect();
+            if papers.is_empty() {
+                break;
+            }
+
+            let documents = self.embedder.embed_papers(&papers).await?;
+            for (paper, document) in papers.iter().zip(documents) {
+                let result = async {
+                    self.db.delete_paper_chunks(&paper.arxiv_id).await?;
+                    self.db
+                        .add_paper_chunks(&paper.arxiv_id, &document.chunks, &model)
+                        .await?;
+                    self.db
+                        .replace_paper_embedding(&paper.arxiv_id, document.embedding, &model)
+                        .await?;
+                    anyhow::Ok(())
+                }
+                .await;
+
+                match result {
+                    Ok(()) => report.reembedded += 1,
+                    Err(e) => {
+                        tracing::warn!("Failed to re-embed {}: {}", paper.arxiv_id, e);
+                        // The vector is swapped last, but a failed response does
+                        // not prove the swap did not happen
+                        if !self.is_replaced(&paper.arxiv_id, &model).await {
+                            stale_failures += 1;
+                        }
+                        report.failed.push((paper.arxiv_id.clone(), e.to_string()));
+                    }
+                }
+            }
+
+            let coverage = self.db.embedding_coverage(&model).await?;
+            on_progress(ReembedProgress {
+                reembedded: report.reembedded,
+                failed: report.failed.len(),
+                remaining: coverage.stale(),
+            });
+        }
+
+        Ok(report)
+    }
+
+    async fn is_replaced(&self, arxiv_id: &str, model: &ModelInfo) -> bool {
+        matches!(
+            self.db.get_paper_by_arxiv_id(arxiv_id).await,
+            Ok(Some(stored)) if stored.embedded_with(model)
+        )
+    }
+}
@@ -0,0 +1,655 @@
+use anyhow::{bail, Result};
+use serde_json::Value;
+use std::fmt;
+
+/// Version of the HelixDB schema and queries this build expects. Bump it
+/// together with the header of `helix/schema.hx` and add a migration in
+/// [`super::migrations`] whenever stored data changes shape.
+pub const SCHEMA_VERSION: u32 = 3;
+
+/// Type of an HQL query parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamType {
+    String,
+    Bool,
+    U32,
+    F32,
+    /// `Vec<F32>`
+    Vector,
+}
+
+impl ParamType {
+    /// Parses an HQL type name such as `U32` or `Vec<F32>`.
+    pub fn from_hql(name: &str) -> Option<Self> {
+        match name.replace(' ', "").as_str() {
+            "String" => Some(ParamType::String),
+            "Bool" => Some(ParamType::Bool),
+            "U32" => Some(ParamType::U32),
+            "F32" => Some(ParamType::F32),
+            "Vec<F32>" | "[F32]" => Some(ParamType::Vector),
+            _ => None,
+        }
+    }
+
+    fn accepts(self, value: &Value) -> bool {
+        match self {
+            ParamType::String => value.is_string(),
+            ParamType::Bool => value.is_boolean(),
+            ParamType::U32 => value.as_u64().is_some_and(|n| n <= u32::MAX as u64),
+            ParamType::F32 => value.is_number(),
+            ParamType::Vector => value
+                .as_array()
+                .is_some_and(|items| items.iter().all(Value::is_number)),
+        }
+    }
+}
+
+impl fmt::Display for ParamType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            ParamType::String => "String",
+            ParamType::Bool => "Bool",
+            ParamType::U32 => "U32",
+            ParamType::F32 => "F32",
+            ParamType::Vector => "Vec<F32>",
+        })
+    }
+}
+
+/// Name and parameters of an HQL query the client calls.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuerySpec {
+    pub name: &'static str,
+    /// Whether the query only reads, so that calling it changes nothing
+    pub read_only: bool,
+    pub params: &'static [(&'static str, ParamType)],
+}
+
+impl QuerySpec {
+    /// Fails unless `payload` has exactly this query's parameters with
+    /// values of the right types.
+    pub fn check_payload(&self, payload: &Value) -> Result<()> {
+        let Some(fields) = payload.as_object() else {
+            bail!("Query {} expects an object of parameters", self.name);
+        };
+
+        for (param, kind) in self.params {
+            match fields.get(*param) {
+                None => bail!("Query {} is missing parameter {}", self.name, param),
+                Some(value) if !kind.accepts(value) => bail!(
+                    "Query {} expects {} for parameter {}, got {}",
+                    self.name,
+                    kind,
+                    param,
+                    value
+                ),
+                Some(_) => {}
+            }
+        }
+        if let Some(extra) = fields
+            .keys()
+            .find(|key| !self.params.iter().any(|(param, _)| param == key))
+        {
+            bail!("Query {} has no parameter {}", self.name, extra);
+        }
+        Ok(())
+    }
+}
+
+/// Looks up a query in [`QUERIES`].
+pub fn find_query(name: &str) -> Option<&'static QuerySpec> {
+    QUERIES.iter().find(|spec| spec.name == name)
+}
+
+/// Fails unless `name` is a registered query and `payload` matches it.
+pub fn check_call(name: &str, payload: &Value) -> Result<()> {
+    match find_query(name) {
+        Some(spec) => spec.check_payload(payload),
+        None => bail!("Query {} is not in the query registry", name),
+    }
+}
+
+/// A query definition parsed from an `.hx` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HqlQuery {
+    pub name: String,
+    /// Parameter names with their HQL types, as written
+    pub params: Vec<(String, String)>,
+}
+
+/// Extracts the `QUERY Name (params) =>` headers from HQL source.
+pub fn parse_hql_queries(hql: &str) -> Vec<HqlQuery> {
+    let mut queries = Vec::new();
+    let mut rest = hql;
+
+    while let Some(start) = rest.find("QUERY ") {
+        rest = &rest[start + "QUERY ".len()..];
+        let Some(open) = rest.find('(') else {
+            break;
+        };
+        let Some(close) = rest[open..].find(')').map(|i| open + i) else {
+            break;
+        };
+
+        let params = rest[open + 1..close]
+            .split(',')
+            .filter_map(|param| {
+                let (name, kind) = param.split_once(':')?;
+                Some((name.trim().to_string(), kind.trim().to_string()))
+            })
+            .collect();
+        queries.push(HqlQuery {
+            name: rest[..open].trim().to_string(),
+            params,
+        });
+        rest = &rest[close..];
+    }
+    queries
+}
+
+/// The schema version declared in the header of a `schema.hx` file.
+pub fn parse_schema_version(schema: &str) -> Option<u32> {
+    schema.lines().find_map(|line| {
+        line.trim()
+            .strip_prefix("//")?
+            .trim()
+            .strip_prefix("Schema version:")?
+            .trim()
+            .parse()
+            .ok()
+    })
+}
+
+/// A disagreement between the registry and a set of query definitions.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryIssue {
+    /// Registered, but not defined
+    Missing(String),
+    /// Defined with different parameters
+    Mismatched { name: String, detail: String },
+    /// Defined, but not registered
+    Unregistered(String),
+}
+
+impl fmt::Display for QueryIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            QueryIssue::Missing(name) => write!(f, "{}: missing", name),
+            QueryIssue::Mismatched { name, detail } => write!(f, "{}: {}", name, detail),
+            QueryIssue::Unregistered(name) => write!(f, "{}: not used by this build", name),
+        }
+    }
+}
+
+/// Compares the registry against HQL definitions, such as the contents of
+/// `helix/queries.hx`.
+pub fn check_hql(hql: &str) -> Vec<QueryIssue> {
+    let defined = parse_hql_queries(hql);
+    let mut issues = Vec::new();
+
+    for spec in QUERIES {
+        let Some(query) = defined.iter().find(|query| query.name == spec.name) else {
+            issues.push(QueryIssue::Missing(spec.name.to_string()));
+            continue;
+        };
+
+        let expected: Vec<(&str, ParamType)> = spec.params.to_vec();
+        let actual: Vec<(&str, Option<ParamType>)> = query
+            .params
+            .iter()
+            .map(|(name, kind)| (name.as_str(), ParamType::from_hql(kind)))
+            .collect();
+        let matches = expected.len() == actual.len()
+            && expected
+                .iter()
+                .zip(&actual)
+                .all(|((a, ta), (b, tb))| a == b && Some(*ta) == *tb);
+        if !matches {
+            issues.push(QueryIssue::Mismatched {
+                name: spec.name.to_string(),
+                detail: format!(
+                    "expected ({}), defined ({})",
+                    format_params(expected.iter().map(|(n, t)| (*n, t.to_string()))),
+                    format_params(query.params.iter().map(|(n, t)| (n.as_str(), t.clone())))
+                ),
+            });
+        }
+    }
+
+    for query in &defined {
+        if find_query(&query.name).is_none() {
+            issues.push(QueryIssue::Unregistered(query.name.clone()));
+        }
+    }
+    issues
+}
+
+fn format_params<'a>(params: impl Iterator<Item = (&'a str, String)>) -> String {
+    params
+        .map(|(name, kind)| format!("{}: {}", name, kind))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Interprets the response to calling `spec` with no parameters.
+pub fn probe_issue(spec: &QuerySpec, status: u16, body: &str) -> Option<QueryIssue> {
+    let mismatched = |detail: String| {
+        Some(QueryIssue::Mismatched {
+            name: spec.name.to_string(),
+            detail,
+        })
+    };
+    let known = |field: &str| spec.params.iter().any(|(param, _)| *param == field);
+
+    match status {
+        404 => Some(QueryIssue::Missing(spec.name.to_string())),
+        200 if !spec.params.is_empty() => {
+            mismatched("takes no parameters on the server".to_string())
+        }
+        200 => None,
+        _ if spec.params.is_empty() => mismatched(format!("failed without parameters: {}", body)),
+        _ => {
+            if let Some(field) = quoted_field(body, "missing field").filter(|f| !known(f)) {
+                mismatched(format!("server expects parameter {}", field))
+            } else if let Some(field) = quoted_field(body, "unknown field").filter(|f| known(f)) {
+                mismatched(format!("server does not take parameter {}", field))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// The name in a serde error such as ``missing field `title` ``.
+fn quoted_field<'a>(body: &'a str, prefix: &str) -> Option<&'a str> {
+    let rest = &body[body.find(prefix)? + prefix.len()..];
+    let rest = rest.trim_start().strip_prefix('`')?;
+    Some(&rest[..rest.find('`')?])
+}
+
+/// Result of checking a HelixDB server against this build.
+#[derive(Debug, Default)]
+pub struct SchemaReport {
+    /// Highest applied schema version, `None` if never recorded
+    pub server_version: Option<u32>,
+    pub issues: Vec<QueryIssue>,
+    /// Write queries, which are not probed on the server
+    pub unchecked: usize,
+}
+
+impl SchemaReport {
+    /// Whether every query this build calls is deployed as expected.
+    pub fn queries_ok(&self) -> bool {
+        !self
+            .issues
+            .iter()
+            .any(|issue| !matches!(issue, QueryIssue::Unregistered(_)))
+    }
+
+    /// Whether the stored data is newer than this build understands.
+    pub fn is_newer(&self) -> bool {
+        self.server_version.is_some_and(|v| v > SCHEMA_VERSION)
+    }
+}
+
+impl fmt::Display for SchemaReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.server_version {
+            Some(version) => write!(f, "schema v{} (expected v{})", version, SCHEMA_VERSION)?,
+            None => write!(
+                f,
+                "schema version not recorded (expected v{})",
+                SCHEMA_VERSION
+            )?,
+        }
+        if self.issues.is_empty() {
+            write!(
+                f,
+                ", all {} read queries present",
+                QUERIES.len() - self.unchecked
+            )
+        } else {
+            for issue in &self.issues {
+                write!(f, "\n  {}", issue)?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Every HQL query `DbClient` calls, with its parameters. Must match
+/// `helix/queries.hx`; `test_query_registry_matches_helix_queries` in
+/// `tests/db_test.rs` keeps the two in sync.
+pub const QUERIES: &[QuerySpec] = &[
+    QuerySpec {
+        name: "AddPaper",
+        read_only: false,
+        params: &[
+            ("arxiv_id", ParamType::String),
+            ("version", ParamType::U32),
+            ("title", ParamType::String),
+            ("authors", ParamType::String),
+            ("published", ParamType::String),
+            ("updated", ParamType::String),
+            ("categories", ParamType::String),
+            ("abstract_preview", ParamType::String),
+            ("embedding", ParamType::Vector),
+            ("embedding_model", ParamType::String),
+            ("embedding_version", ParamType::String),
+            ("embedding_dim", ParamType::U32),
+        ],
+    },
+    QuerySpec {
+        name: "UpdatePaper",
+        read_only: false,
+        params: &[
+            ("arxiv_id", ParamType::String),
+            ("version", ParamType::U32),
+            ("title", ParamType::String),
+            ("authors", ParamType::String),
+            ("published", ParamType::String),
+            ("updated", ParamType::String),
+            ("categories", ParamType::String),
+            ("abstract_preview", ParamType::String),
+            ("embedding", ParamType::Vector),
+            ("embedding_model", ParamType::String),
+            ("embedding_version", ParamType::String),
+            ("embedding_dim", ParamType::U32),
+        ],
+    },
+    QuerySpec {
+        name: "AddPaperChunk",
+        read_only: false,
+        params: &[
+            ("arxiv_id", ParamType::String),
+            ("chunk_index", ParamType::U32),
+            ("text", ParamType::String),
+            ("embedding", ParamType::Vector),
+            ("embedding_model", ParamType::String),
+            ("embedding_version", ParamType::String),
+            ("embedding_dim", ParamType::U32),
+        ],
+    },
+    QuerySpec {
+        name: "DeletePaperChunks",
+        read_only: false,
+        params: &[("arxiv_id", ParamType::String)],
+    },
+    QuerySpec {
+        name: "AddPaperMetadata",
+        read_only: false,
+        params: &[
+            ("arxiv_id", ParamType::String),
+            ("abstract", ParamType::String),
+            ("comment", ParamType::String),
+            ("journal_ref", ParamType::String),
+            ("doi", ParamType::String),
+            ("pdf_url", ParamType::String),
+            ("created_at", ParamType::String),
+        ],
+    },
+    QuerySpec {
+        name: "UpdatePaperMetadata",
+        read_only: false,
+        params: &[
+            ("arxiv_id", ParamType::String),
+            ("abstract", ParamType::String),
+            ("comment", ParamType::String),
+            ("journal_ref", ParamType::String),
+            ("doi", ParamType::String),
+            ("pdf_url", ParamType::String),
+        ],
+    },
+    QuerySpec {
+        name: "LinkPaperMetadata",
+        read_only: false,
+        params: &[
+            ("paper_id", ParamType::String),
+            ("metadata_id", ParamType::String),
+        ],
+    },
+    QuerySpec {
+        name: "SearchPapers",
+        read_only: true,
+        params: &[
+            ("query_embedding", ParamType::Vector),
+            ("embedding_model", ParamType::String),
+            ("embedding_version", ParamType::String),
+            ("min_similarity", ParamType::F32),
+            ("limit", ParamType::U32),
+        ],
+    },
+    QuerySpec {
+        name: "SearchPaperChunks",
+        read_only: true,
+        params: &[
+            ("query_embedding", ParamType::Vector),
+            ("embedding_model", ParamType::String),
+            ("embedding_version", ParamType::String),
+            ("min_similarity", ParamType::F32),
+            ("limit", ParamType::U32),
+        ],
+    },
+    QuerySpec {
+        name: "GetPaperByArxivId",
+        read_only: true,
+        params: &[("arxiv_id", ParamType::String)],
+    },
+    QuerySpec {
+        name: "GetPaperWithMetadata",
+        read_only: true,
+        params: &[("arxiv_id", ParamType::String)],
+    },
+    QuerySpec {
+        name: "CountPapersWithModel",
+        read_only: true,
+        params: &[
+            ("embedding_model", ParamType::String),
+            ("embedding_version", ParamType::String),
+        ],
+    },
+    QuerySpec {
+        name: "ListStalePapers",
+        read_only: true,
+        params: &[
+            ("embedding_model", ParamType::String),
+            ("embedding_version", ParamType::String),
+            ("offset", ParamType::U32),
+            ("limit", ParamType::U32),
+        ],
+    },
+    QuerySpec {
+        name: "ReplacePaperEmbedding",
+        read_only: false,
+        params: &[
+            ("arxiv_id", ParamType::String),
+            ("embedding", ParamType::Vector),
+            ("embedding_model", ParamType::String),
+            ("embedding_version", ParamType::String),
+            ("embedding_dim", ParamType::U32),
+        ],
+    },
+    QuerySpec {
+        name: "FindSimilarPapers",
+        read_only: true,
+        params: &[("arxiv_id", ParamType::String), ("limit", ParamType::U32)],
+    },
+    QuerySpec {
+        name: "GetSimilarities",
+        read_only: true,
+        params: &[("arxiv_id", ParamType::String)],
+    },
+    QuerySpec {
+        name: "AddSimilarity",
+        read_only: false,
+        params: &[
+            ("from_arxiv_id", ParamType::String),
+            ("to_arxiv_id", ParamType::String),
+            ("score", ParamType::F32),
+        ],
+    },
+    QuerySpec {
+        name: "DeleteSimilarities",
+        read_only: false,
+        params: &[("arxiv_id", ParamType::String)],
+    },
+    QuerySpec {
+        name: "GetPaperVector",
+        read_only: true,
+        params: &[("arxiv_id", ParamType::String)],
+    },
+    QuerySpec {
+        name: "CreateCollection",
+        read_only: false,
+        params: &[
+            ("name", ParamType::String),
+            ("description", ParamType::String),
+            ("parent", ParamType::String),
+            ("filter", ParamType::String),
+            ("created_at", ParamType::String),
+        ],
+    },
+    QuerySpec {
+        name: "GetAllCollections",
+        read_only: true,
+        params: &[],
+    },
+    QuerySpec {
+        name: "GetCollection",
+        read_only: true,
+        params: &[("name", ParamType::String)],
+    },
+    QuerySpec {
+        name: "RenameCollection",
+        read_only: false,
+        params: &[("name", ParamType::String), ("new_name", ParamType::String)],
+    },
+    QuerySpec {
+        name: "ReparentCollections",
+        read_only: false,
+        params: &[
+            ("parent", ParamType::String),
+            ("new_parent", ParamType::String),
+        ],
+    },
+    QuerySpec {
+        name: "DeleteCollection",
+        read_only: false,
+        params: &[("name", ParamType::String)],
+    },
+    QuerySpec {
+        name: "GetCollectionPapers",
+        read_only: true,
+        params: &[("collection_name", ParamType::String)],
+    },
+    QuerySpec {
+        name: "AddPaperToCollection",
+        read_only: false,
+        params: &[
+            ("arxiv_id", ParamType::String),
+            ("collection_name", ParamType::String),
+            ("added_at", ParamType::String),
+        ],
+    },
+    QuerySpec {
+        name: "RemovePaperFromCollection",
+        read_only: false,
+        params: &[
+            ("arxiv_id", ParamType::String),
+            ("collection_name", ParamType::String),
+        ],
+    },
+    QuerySpec {
+        name: "ListPapers",
+        read_only: true,
+        params: &[("offset", ParamType::U32), ("limit", ParamType::U32)],
+    },
+    QuerySpec {
+        name: "MarkPaperSaved",
+        read_only: false,
+        params: &[("arxiv_id", ParamType::String), ("saved", ParamType::Bool)],
+    },
+    QuerySpec {
+        name: "MarkPaperRead",
+        read_only: false,
+        params: &[("arxiv_id", ParamType::String), ("read", ParamType::Bool)],
+    },
+    QuerySpec {
+        name: "SetReadState",
+        read_only: false,
+        params: &[
+            ("arxiv_id", ParamType::String),
+            ("read", ParamType::Bool),
+            ("read_state", ParamType::String),
+            ("reading_started_at", ParamType::String),
+            ("read_at", ParamType::String),
+        ],
+    },
+    QuerySpec {
+        name: "GetPaperMetadata",
+        read_only: true,
+        params: &[("arxiv_id", ParamType::String)],
+    },
+    QuerySpec {
+        name: "UpdatePaperTags",
+        read_only: false,
+        params: &[("arxiv_id", ParamType::String), ("tags", ParamType::String)],
+    },
+    QuerySpec {
+        name: "AddCitation",
+        read_only: false,
+        params: &[
+            ("from_arxiv_id", ParamType::String),
+            ("to_arxiv_id", ParamType::String),
+            ("context", ParamType::String),
+        ],
+    },
+    QuerySpec {
+        name: "UpdateCitation",
+        read_only: false,
+        params: &[
+            ("from_arxiv_id", ParamType::String),
+            ("to_arxiv_id", ParamType::String),
+            ("context", ParamType::String),
+        ],
+    },
+    QuerySpec {
+        name: "DeleteCitations",
+        read_only: false,
+        params: &[("arxiv_id", ParamType::String)],
+    },
+    QuerySpec {
+        name: "GetCitedPapers",
+        read_only: true,
+        params: &[("arxiv_id", ParamType::String)],
+    },
+    QuerySpec {
+        name: "GetCitingPapers",
+        read_only: true,
+        params: &[("arxiv_id", ParamType::String)],
+    },
+    QuerySpec {
+        name: "CountPapers",
+        read_only: true,
+        params: &[],
+    },
+    QuerySpec {
+        name: "CountSavedPapers",
+        read_only: true,
+        params: &[],
+    },
+    QuerySpec {
+        name: "GetSchemaVersion",
+        read_only: true,
+        params: &[],
+    },
+    QuerySpec {
+        name: "SetSchemaVersion",
+        read_only: false,
+        params: &[
+            ("version", ParamType::U32),
+            ("description", ParamType::String),
+            ("applied_at", ParamType::String),
+        ],
+    },
+];
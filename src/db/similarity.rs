@@ -0,0 +1,202 @@
+use anyhow::{anyhow, Result};
+
+use super::store::PaperStore;
+use crate::config::SimilarPapersConfig;
+use crate::embeddings::backend::ModelInfo;
+
+/// Scores closer than this are treated as unchanged.
+const SCORE_EPSILON: f32 = 1e-5;
+
+/// Progress reported by [`SimilarityJob`] after each paper.
+#[derive(Debug, Clone, Copy)]
+pub struct SimilarityProgress {
+    pub processed: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Default)]
+pub struct SimilarityReport {
+    /// Papers whose own neighbor list was written
+    pub linked: usize,
+    /// Papers whose neighbor list was already up to date
+    pub unchanged: usize,
+    /// Existing papers that gained one of the processed papers as neighbor
+    pub neighbors_updated: usize,
+    /// Papers embedded with a different model, left alone
+    pub skipped: usize,
+    /// arXiv IDs that could not be processed, with the reason
+    pub failed: Vec<(String, String)>,
+}
+
+impl std::fmt::Display for SimilarityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} linked, {} unchanged, {} neighbors updated, {} skipped, {} failed",
+            self.linked,
+            self.unchanged,
+            self.neighbors_updated,
+            self.skipped,
+            self.failed.len()
+        )
+    }
+}
+
+/// Precomputes `SimilarTo` edges so "similar papers" is a graph lookup.
+///
+/// For each paper, a k-NN search over vectors from the same model links it
+/// to its `top_k` nearest papers scoring at least `min_similarity`. The
+/// paper is then offered to each of those neighbors, which keep it if it
+/// beats their current weakest link. Every write replaces a paper's whole
+/// neighbor list, so running the job twice gives the same graph.
+pub struct SimilarityJob<'a> {
+    db: &'a dyn PaperStore,
+    model: ModelInfo,
+    top_k: usize,
+    min_similarity: f32,
+}
+
+impl<'a> SimilarityJob<'a> {
+    pub fn new(db: &'a dyn PaperStore, model: ModelInfo, config: &SimilarPapersConfig) -> Self {
+        Self {
+            db,
+            model,
+            top_k: config.top_k.max(1),
+            min_similarity: config.min_similarity,
+        }
+    }
+
+    pub fn with_top_k(mut self, top_k: usize) -> Self {
+        self.top_k = top_k.max(1);
+        self
+    }
+
+    pub fn with_min_similarity(mut self, min_similarity: f32) -> Self {
+        self.min_similarity = min_similarity;
+        self
+    }
+
+    /// Links the given papers, typically the ones just ingested.
+    pub async fn run(
+        &self,
+        arxiv_ids: &[String],
+        on_progress: &mut dyn FnMut(SimilarityProgress),
+    ) -> Result<SimilarityReport> {
+        let mut report = SimilarityReport::default();
+
+        for (i, arxiv_id) in arxiv_ids.iter().enumerate() {
+            if let Err(e) = self.link_paper(arxiv_id, &mut report).await {
+                tracing::warn!("Failed to link similar papers for {}: {}", arxiv_id, e);
+                report.failed.push((arxiv_id.clone(), e.to_string()));
+            }
+            on_progress(SimilarityProgress {
+                processed: i + 1,
+                total: arxiv_ids.len(),
+            });
+        }
+
+        Ok(report)
+    }
+
+    /// Recomputes the neighbors of every paper embedded with the job's model.
+    pub async fn run_all(
+        &self,
+        on_progress: &mut dyn FnMut(SimilarityProgress),
+    ) -> Result<SimilarityReport> {
+        let arxiv_ids: Vec<String> = self
+            .db
+            .list_papers()
+            .await?
+            .into_iter()
+            .filter(|(paper, _)| paper.embedded_with(&self.model))
+            .map(|(paper, _)| paper.arxiv_id)
+            .collect();
+        self.run(&arxiv_ids, on_progress).await
+    }
+
+    async fn link_paper(&self, arxiv_id: &str, report: &mut SimilarityReport) -> Result<()> {
+        let paper = self
+            .db
+            .get_paper_by_arxiv_id(arxiv_id)
+            .await?
+            .ok_or_else(|| anyhow!("Paper {} is not stored", arxiv_id))?;
+        if !paper.embedded_with(&self.model) {
+            report.skipped += 1;
+            return Ok(());
+        }
+        let vector = self
+            .db
+            .get_paper_vector(&paper.arxiv_id)
+            .await?
+            .ok_or_else(|| anyhow!("Paper {} has no stored vector", arxiv_id))?;
+
+        // One extra result, since the paper finds itself
+        let neighbors: Vec<(String, f32)> = self
+            .db
+            .search_papers(
+                vector,
+                &self.model,
+                self.min_similarity,
+                self.top_k as u32 + 1,
+            )
+            .await?
+            .into_iter()
+            .filter(|hit| hit.paper.arxiv_id != paper.arxiv_id)
+            .take(self.top_k)
+            .map(|hit| (hit.paper.arxiv_id, hit.similarity_score))
+            .collect();
+
+        if self.update(&paper.arxiv_id, neighbors.clone()).await? {
+            report.linked += 1;
+        } else {
+            report.unchanged += 1;
+        }
+
+        // Similarity is symmetric, so offer this paper to its neighbors
+        for (neighbor, score) in neighbors {
+            let mut theirs: Vec<(String, f32)> = self
+                .db
+                .get_similarities(&neighbor)
+                .await?
+                .into_iter()
+                .map(|hit| (hit.paper.arxiv_id, hit.similarity_score))
+                .filter(|(id, _)| *id != paper.arxiv_id)
+                .collect();
+            theirs.push((paper.arxiv_id.clone(), score));
+            if self.update(&neighbor, theirs).await? {
+                report.neighbors_updated += 1;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the best `top_k` of `candidates` as the neighbors of
+    /// `arxiv_id`, unless they already are. Returns whether anything was
+    /// written.
+    async fn update(&self, arxiv_id: &str, mut candidates: Vec<(String, f32)>) -> Result<bool> {
+        candidates.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        candidates.truncate(self.top_k);
+
+        let mut current: Vec<(String, f32)> = self
+            .db
+            .get_similarities(arxiv_id)
+            .await?
+            .into_iter()
+            .map(|hit| (hit.paper.arxiv_id, hit.similarity_score))
+            .collect();
+        current.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        let unchanged = current.len() == candidates.len()
+            && current
+                .iter()
+                .zip(&candidates)
+                .all(|(a, b)| a.0 == b.0 && (a.1 - b.1).abs() < SCORE_EPSILON);
+        if unchanged {
+            return Ok(false);
+        }
+
+        self.db.replace_similarities(arxiv_id, &candidates).await?;
+        Ok(true)
+    }
+}
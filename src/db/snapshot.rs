@@ -0,0 +1,561 @@
+use anyhow::{anyhow, bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufWriter, Write};
+use std::path::Path;
+
+use super::models::{collection_tree, Collection, CollectionNode, NewCollection, PaperMetadata};
+use super::models::{ReadState, StoredPaper, UpsertOutcome, UpsertReport};
+use super::store::PaperStore;
+use crate::embeddings::backend::ModelInfo;
+use crate::embeddings::chunking::DocumentEmbedder;
+
+/// Identifies a library snapshot in its header line.
+pub const SNAPSHOT_FORMAT: &str = "arxiv-tui-library";
+
+/// Version of the snapshot layout written by this build. Snapshots with a
+/// newer version are refused on import.
+pub const SNAPSHOT_VERSION: u32 = 1;
+
+/// One line of a library snapshot.
+///
+/// A snapshot is a JSON Lines file: a header, then every paper, then the
+/// collections (parents before their children), then the citation edges.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum SnapshotRecord {
+    Header(SnapshotHeader),
+    Paper(Box<PaperRecord>),
+    Collection(CollectionRecord),
+    Citation(CitationRecord),
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SnapshotHeader {
+    pub format: String,
+    pub version: u32,
+    pub exported_at: String,
+    /// Backend the snapshot was taken from
+    pub source: String,
+    pub papers: usize,
+    /// Whether paper records carry their vectors
+    pub vectors: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PaperRecord {
+    pub paper: StoredPaper,
+    pub metadata: PaperMetadata,
+    /// Produced by the model named in `paper`; absent unless exported with
+    /// vectors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CollectionRecord {
+    pub collection: Collection,
+    /// arXiv IDs of the papers filed by hand, oldest first; empty for smart
+    /// collections
+    #[serde(default)]
+    pub papers: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CitationRecord {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub context: String,
+}
+
+#[derive(Debug, Default)]
+pub struct ExportReport {
+    pub papers: usize,
+    pub vectors: usize,
+    pub collections: usize,
+    pub citations: usize,
+}
+
+impl std::fmt::Display for ExportReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} papers ({} with vectors), {} collections, {} citations",
+            self.papers, self.vectors, self.collections, self.citations
+        )
+    }
+}
+
+/// Writes a store's library to a snapshot.
+///
+/// Passage vectors and `SimilarTo` edges are derived data and are not
+/// exported; re-run ingestion and the similarity job after importing if
+/// they are needed.
+pub struct LibraryExport<'a> {
+    db: &'a dyn PaperStore,
+    include_vectors: bool,
+}
+
+impl<'a> LibraryExport<'a> {
+    pub fn new(db: &'a dyn PaperStore) -> Self {
+        Self {
+            db,
+            include_vectors: true,
+        }
+    }
+
+    /// Leaves out paper vectors, for a much smaller file. Importing such a
+    /// snapshot needs an embedder.
+    pub fn without_vectors(mut self) -> Self {
+        self.include_vectors = false;
+        self
+    }
+
+    /// Writes the snapshot to `path`, replacing it only once complete.
+    pub async fn write_file(&self, path: &Path) -> Result<ExportReport> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        let file = std::fs::File::create(&tmp_path)
+            .with_context(|| format!("Failed to create {:?}", tmp_path))?;
+        let report = self.write(BufWriter::new(file)).await?;
+        std::fs::rename(&tmp_path, path)
+            .with_context(|| format!("Failed to replace {:?}", path))?;
+        Ok(report)
+    }
+
+    pub async fn write<W: Write>(&self, mut out: W) -> Result<ExportReport> {
+        let papers = self.db.list_papers().await?;
+        let mut report = ExportReport::default();
+
+        write_record(
+            &mut out,
+            &SnapshotRecord::Header(SnapshotHeader {
+                format: SNAPSHOT_FORMAT.to_string(),
+                version: SNAPSHOT_VERSION,
+                exported_at: chrono::Utc::now().to_rfc3339(),
+                source: self.db.backend_name().to_string(),
+                papers: papers.len(),
+                vectors: self.include_vectors,
+            }),
+        )?;
+
+        let mut citations = Vec::new();
+        for (mut paper, mut metadata) in papers {
+            // Node IDs are only meaningful to the store they came from
+            paper.id.clear();
+            metadata.id.clear();
+
+            let vector = if self.include_vectors {
+                self.db.get_paper_vector(&paper.arxiv_id).await?
+            } else {
+                None
+            };
+            if vector.is_some() {
+                report.vectors += 1;
+            }
+
+            for link in self.db.get_references(&paper.arxiv_id).await? {
+                citations.push(CitationRecord {
+                    from: paper.arxiv_id.clone(),
+                    to: link.paper.arxiv_id,
+                    context: link.context,
+                });
+            }
+
+            write_record(
+                &mut out,
+                &SnapshotRecord::Paper(Box::new(PaperRecord {
+                    paper,
+                    metadata,
+                    vector,
+                })),
+            )?;
+            report.papers += 1;
+        }
+
+        let collections = self.db.get_all_collections().await?;
+        for mut collection in flatten(collection_tree(&collections)) {
+            let papers = if collection.is_smart() {
+                Vec::new()
+            } else {
+                self.db
+                    .get_collection_papers(&collection.name)
+                    .await?
+                    .into_iter()
+                    .map(|entry| entry.paper.arxiv_id)
+                    .collect()
+            };
+            collection.id.clear();
+            write_record(
+                &mut out,
+                &SnapshotRecord::Collection(CollectionRecord { collection, papers }),
+            )?;
+            report.collections += 1;
+        }
+
+        for citation in citations {
+            write_record(&mut out, &SnapshotRecord::Citation(citation))?;
+            report.citations += 1;
+        }
+
+        out.flush()?;
+        Ok(report)
+    }
+}
+
+/// What to do with papers and collections that are already in the store.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ConflictPolicy {
+    /// Keep the store's version untouched
+    #[default]
+    Skip,
+    /// Replace the arXiv fields, metadata, vector (if the snapshot has
+    /// one), saved flag, read state, tags, citations and collection
+    /// contents with the snapshot's
+    Overwrite,
+    /// Keep the store's version, adding the snapshot's tags and collection
+    /// entries to it
+    MergeTags,
+}
+
+impl ConflictPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ConflictPolicy::Skip => "skip",
+            ConflictPolicy::Overwrite => "overwrite",
+            ConflictPolicy::MergeTags => "merge-tags",
+        }
+    }
+}
+
+impl std::fmt::Display for ConflictPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ConflictPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_lowercase().replace('_', "-").as_str() {
+            "skip" => Ok(ConflictPolicy::Skip),
+            "overwrite" => Ok(ConflictPolicy::Overwrite),
+            "merge-tags" | "merge" => Ok(ConflictPolicy::MergeTags),
+            other => Err(anyhow!(
+                "Unknown conflict policy {:?} (expected skip, overwrite or merge-tags)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct ImportReport {
+    /// `updated` counts existing papers overwritten or merged into
+    pub papers: UpsertReport,
+    pub collections_created: usize,
+    /// Papers filed into collections
+    pub collection_papers: usize,
+    pub citations: usize,
+    /// Papers, collections or citations that could not be imported, with
+    /// the reason
+    pub failed: Vec<(String, String)>,
+}
+
+impl std::fmt::Display for ImportReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "papers: {}; {} collections created, {} papers filed, {} citations, {} errors",
+            self.papers,
+            self.collections_created,
+            self.collection_papers,
+            self.citations,
+            self.failed.len()
+        )
+    }
+}
+
+/// Restores a snapshot into any store backend.
+///
+/// Papers missing from the store are inserted with their vector, or embedded
+/// again if the snapshot was exported without vectors. Papers and
+/// collections already present are handled according to the
+/// [`ConflictPolicy`]. Read states are restored but their timestamps are
+/// those of the import.
+pub struct LibraryImport<'a> {
+    db: &'a dyn PaperStore,
+    policy: ConflictPolicy,
+    embedder: Option<&'a DocumentEmbedder>,
+}
+
+impl<'a> LibraryImport<'a> {
+    pub fn new(db: &'a dyn PaperStore, policy: ConflictPolicy) -> Self {
+        Self {
+            db,
+            policy,
+            embedder: None,
+        }
+    }
+
+    /// Embeds papers that have no vector in the snapshot.
+    pub fn with_embedder(mut self, embedder: &'a DocumentEmbedder) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+
+    pub async fn read_file(&self, path: &Path) -> Result<ImportReport> {
+        let file =
+            std::fs::File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+        self.read(std::io::BufReader::new(file))
+            .await
+            .with_context(|| format!("Failed to import {:?}", path))
+    }
+
+    pub async fn read<R: BufRead>(&self, input: R) -> Result<ImportReport> {
+        let mut report = ImportReport::default();
+        let mut header = false;
+
+        for (index, line) in input.lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record: SnapshotRecord = serde_json::from_str(&line)
+                .with_context(|| format!("Invalid record on line {}", index + 1))?;
+
+            match record {
+                SnapshotRecord::Header(h) => {
+                    check_header(&h)?;
+                    header = true;
+                }
+                _ if !header => bail!("Not a library snapshot: missing header line"),
+                SnapshotRecord::Paper(record) => {
+                    let arxiv_id = record.paper.arxiv_id.clone();
+                    match self.import_paper(*record).await {
+                        Ok(outcome) => report.papers.record(outcome),
+                        Err(e) => {
+                            report.papers.failed += 1;
+                            report.failed.push((arxiv_id, format!("{:#}", e)));
+                        }
+                    }
+                }
+                SnapshotRecord::Collection(record) => {
+                    let name = record.collection.name.clone();
+                    if let Err(e) = self.import_collection(record, &mut report).await {
+                        report.failed.push((name, format!("{:#}", e)));
+                    }
+                }
+                SnapshotRecord::Citation(record) => {
+                    match self
+                        .db
+                        .add_citation(&record.from, &record.to, &record.context)
+                        .await
+                    {
+                        Ok(()) => report.citations += 1,
+                        Err(e) => report.failed.push((
+                            format!("{} -> {}", record.from, record.to),
+                            format!("{:#}", e),
+                        )),
+                    }
+                }
+            }
+        }
+
+        if !header {
+            bail!("Not a library snapshot: missing header line");
+        }
+        Ok(report)
+    }
+
+    async fn import_paper(&self, record: PaperRecord) -> Result<UpsertOutcome> {
+        let PaperRecord {
+            paper,
+            metadata,
+            vector,
+        } = record;
+        let existing = self.db.get_paper_metadata(&paper.arxiv_id).await?;
+
+        let Some(current) = existing else {
+            let (vector, model) = self.vector_for(&paper, &metadata, vector).await?;
+            self.db
+                .upsert_paper(&paper.to_paper(&metadata), vector, &[], &model)
+                .await?;
+            self.restore_state(&paper.arxiv_id, &metadata, None).await?;
+            return Ok(UpsertOutcome::Inserted);
+        };
+
+        match self.policy {
+            ConflictPolicy::Skip => Ok(UpsertOutcome::Skipped),
+            ConflictPolicy::MergeTags => {
+                let mut tags = current.tag_list();
+                let before = tags.len();
+                for tag in metadata.tag_list() {
+                    if !tags.contains(&tag) {
+                        tags.push(tag);
+                    }
+                }
+                if tags.len() == before {
+                    return Ok(UpsertOutcome::Skipped);
+                }
+                self.db.set_paper_tags(&paper.arxiv_id, &tags).await?;
+                Ok(UpsertOutcome::Updated)
+            }
+            ConflictPolicy::Overwrite => {
+                self.db.overwrite_paper(&paper.to_paper(&metadata)).await?;
+                if let Some(vector) = vector {
+                    let model = snapshot_model(&paper, vector.len());
+                    self.db
+                        .replace_paper_embedding(&paper.arxiv_id, vector, &model)
+                        .await?;
+                }
+                self.db.delete_citations(&paper.arxiv_id).await?;
+                self.restore_state(&paper.arxiv_id, &metadata, Some(&current))
+                    .await?;
+                Ok(UpsertOutcome::Updated)
+            }
+        }
+    }
+
+    /// The snapshot's vector, or a fresh one from the embedder.
+    async fn vector_for(
+        &self,
+        paper: &StoredPaper,
+        metadata: &PaperMetadata,
+        vector: Option<Vec<f32>>,
+    ) -> Result<(Vec<f32>, ModelInfo)> {
+        if let Some(vector) = vector {
+            let model = snapshot_model(paper, vector.len());
+            return Ok((vector, model));
+        }
+        let Some(embedder) = self.embedder else {
+            bail!("The snapshot has no vector for this paper; import with an embedder");
+        };
+        let document = embedder.embed_paper(&paper.to_paper(metadata)).await?;
+        Ok((document.embedding, embedder.backend().model_info()))
+    }
+
+    /// Applies the saved flag, read state and tags of `metadata` where they
+    /// differ from `current`.
+    async fn restore_state(
+        &self,
+        arxiv_id: &str,
+        metadata: &PaperMetadata,
+        current: Option<&PaperMetadata>,
+    ) -> Result<()> {
+        let saved = current.is_some_and(|c| c.saved);
+        let state = current.map(|c| c.state()).unwrap_or(ReadState::Unread);
+        let tags = current.map(|c| c.tag_list()).unwrap_or_default();
+
+        if metadata.saved != saved {
+            self.db.mark_paper_saved(arxiv_id, metadata.saved).await?;
+        }
+        if metadata.state() != state {
+            self.db.set_read_state(arxiv_id, metadata.state()).await?;
+        }
+        if metadata.tag_list() != tags {
+            self.db
+                .set_paper_tags(arxiv_id, &metadata.tag_list())
+                .await?;
+        }
+        Ok(())
+    }
+
+    async fn import_collection(
+        &self,
+        record: CollectionRecord,
+        report: &mut ImportReport,
+    ) -> Result<()> {
+        let CollectionRecord { collection, papers } = record;
+        let existing = self.db.get_collection(&collection.name).await?;
+
+        let current: Vec<String> = match existing {
+            None => {
+                self.db
+                    .create_collection(&NewCollection {
+                        name: collection.name.clone(),
+                        description: collection.description.clone(),
+                        parent: collection.parent().map(str::to_string),
+                        filter: collection.smart_filter()?,
+                    })
+                    .await?;
+                report.collections_created += 1;
+                Vec::new()
+            }
+            Some(_) if self.policy == ConflictPolicy::Skip => return Ok(()),
+            Some(existing) if existing.is_smart() => return Ok(()),
+            Some(_) => self
+                .db
+                .get_collection_papers(&collection.name)
+                .await?
+                .into_iter()
+                .map(|entry| entry.paper.arxiv_id)
+                .collect(),
+        };
+
+        for arxiv_id in papers.iter().filter(|id| !current.contains(id)) {
+            match self
+                .db
+                .add_paper_to_collection(arxiv_id, &collection.name)
+                .await
+            {
+                Ok(()) => report.collection_papers += 1,
+                Err(e) => report.failed.push((
+                    format!("{} in {}", arxiv_id, collection.name),
+                    format!("{:#}", e),
+                )),
+            }
+        }
+
+        if self.policy == ConflictPolicy::Overwrite {
+            for arxiv_id in current.iter().filter(|id| !papers.contains(id)) {
+                self.db
+                    .remove_paper_from_collection(arxiv_id, &collection.name)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn check_header(header: &SnapshotHeader) -> Result<()> {
+    if header.format != SNAPSHOT_FORMAT {
+        bail!("Not a library snapshot: format is {:?}", header.format);
+    }
+    if header.version > SNAPSHOT_VERSION {
+        bail!(
+            "Snapshot version {} is newer than this build supports ({}); upgrade arxiv-tui",
+            header.version,
+            SNAPSHOT_VERSION
+        );
+    }
+    Ok(())
+}
+
+/// The model a snapshot vector was produced by, as recorded on the paper.
+fn snapshot_model(paper: &StoredPaper, dimension: usize) -> ModelInfo {
+    ModelInfo {
+        name: paper.embedding_model.clone(),
+        version: paper.embedding_version.clone(),
+        dimension,
+    }
+}
+
+/// Collections in depth-first order, parents before their children.
+fn flatten(nodes: Vec<CollectionNode>) -> Vec<Collection> {
+    let mut collections = Vec::new();
+    for node in nodes {
+        collections.push(node.collection);
+        collections.extend(flatten(node.children));
+    }
+    collections
+}
+
+fn write_record<W: Write>(out: &mut W, record: &SnapshotRecord) -> Result<()> {
+    serde_json::to_writer(&mut *out, record)?;
+    out.write_all(b"\n")?;
+    Ok(())
+}
@@ -0,0 +1,437 @@
+use anyhow::{anyhow, bail, Result};
+use async_trait::async_trait;
+use std::sync::Arc;
+
+use super::client::DbClient;
+use super::health::{self, HealthStatus};
+use super::local::LocalStore;
+use super::models::{
+    ChunkSearchResult, CitationLink, Collection, CollectionPaper, EmbeddingCoverage, NewCollection,
+    PaperMetadata, ReadState, SearchResult, StoredChunk, StoredPaper, UpsertOutcome,
+};
+use super::offline::OfflineStore;
+use super::tags::{count_tags, normalize_tag, TagCount, TagExpr};
+use crate::arxiv::models::Paper;
+use crate::config::{Config, StoreBackendKind};
+use crate::embeddings::backend::ModelInfo;
+use crate::embeddings::chunking::ChunkEmbedding;
+
+/// Persistent storage for papers, their vectors and the user's library.
+///
+/// Implemented by [`DbClient`] for HelixDB and by [`LocalStore`] for an
+/// embedded single-file database. [`OfflineStore`] combines the two to ride
+/// out HelixDB outages.
+#[async_trait]
+pub trait PaperStore: Send + Sync {
+    /// Short name of the backend, for logs and the status bar.
+    fn backend_name(&self) -> &str;
+
+    /// Writes out changes the store has buffered. Stores that persist every
+    /// change as it is made have nothing to do.
+    async fn flush(&self) -> Result<()> {
+        Ok(())
+    }
+
+    // ===== Papers =====
+
+    async fn add_paper(
+        &self,
+        paper: &Paper,
+        embedding: Vec<f32>,
+        model: &ModelInfo,
+    ) -> Result<StoredPaper>;
+
+    async fn add_paper_metadata(&self, paper: &Paper) -> Result<PaperMetadata>;
+
+    /// Stores a paper keyed by its arXiv ID without version: inserts it with
+    /// its metadata and chunks if new, replaces the vector, arXiv fields and
+    /// chunks if arXiv reports a newer version or `updated` timestamp, and
+    /// leaves it alone otherwise. Saved/read flags and tags are preserved.
+    async fn upsert_paper(
+        &self,
+        paper: &Paper,
+        embedding: Vec<f32>,
+        chunks: &[ChunkEmbedding],
+        model: &ModelInfo,
+    ) -> Result<UpsertOutcome>;
+
+    async fn add_paper_chunks(
+        &self,
+        arxiv_id: &str,
+        chunks: &[ChunkEmbedding],
+        model: &ModelInfo,
+    ) -> Result<Vec<StoredChunk>>;
+
+    async fn delete_paper_chunks(&self, arxiv_id: &str) -> Result<()>;
+
+    /// Returns `None` if no paper with this ID is stored. A version suffix
+    /// on `arxiv_id` is ignored.
+    async fn get_paper_by_arxiv_id(&self, arxiv_id: &str) -> Result<Option<StoredPaper>>;
+
+    // ===== Search =====
+
+    /// Searches only vectors produced by `model`, ordered by descending
+    /// similarity.
+    async fn search_papers(
+        &self,
+        query_embedding: Vec<f32>,
+        model: &ModelInfo,
+        min_similarity: f32,
+        limit: u32,
+    ) -> Result<Vec<SearchResult>>;
+
+    async fn search_paper_chunks(
+        &self,
+        query_embedding: Vec<f32>,
+        model: &ModelInfo,
+        min_similarity: f32,
+        limit: u32,
+    ) -> Result<Vec<ChunkSearchResult>>;
+
+    /// Papers linked to `arxiv_id` by precomputed `SimilarTo` edges, most
+    /// similar first. See [`super::similarity::SimilarityJob`].
+    async fn find_similar_papers(&self, arxiv_id: &str, limit: u32) -> Result<Vec<StoredPaper>>;
+
+    // ===== Similar papers =====
+
+    /// The stored vector of a paper, `None` if the paper is not stored.
+    async fn get_paper_vector(&self, arxiv_id: &str) -> Result<Option<Vec<f32>>>;
+
+    /// The `SimilarTo` edges leaving `arxiv_id` with their scores, most
+    /// similar first.
+    async fn get_similarities(&self, arxiv_id: &str) -> Result<Vec<SearchResult>>;
+
+    /// Replaces every `SimilarTo` edge leaving `arxiv_id` with edges to the
+    /// given papers and scores.
+    async fn replace_similarities(&self, arxiv_id: &str, similar: &[(String, f32)]) -> Result<()>;
+
+    // ===== Embedding migrations =====
+
+    /// Counts stored paper vectors produced by `model` against all papers.
+    async fn embedding_coverage(&self, model: &ModelInfo) -> Result<EmbeddingCoverage>;
+
+    /// Returns up to `limit` papers, with metadata, whose vectors were not
+    /// produced by `model`, ordered by arXiv ID.
+    async fn list_stale_papers(
+        &self,
+        model: &ModelInfo,
+        offset: u32,
+        limit: u32,
+    ) -> Result<Vec<(StoredPaper, PaperMetadata)>>;
+
+    async fn replace_paper_embedding(
+        &self,
+        arxiv_id: &str,
+        embedding: Vec<f32>,
+        model: &ModelInfo,
+    ) -> Result<StoredPaper>;
+
+    /// Replaces the arXiv fields and metadata of a stored paper whatever
+    /// its version, keeping its vector, chunks, saved/read flags and tags.
+    async fn overwrite_paper(&self, paper: &Paper) -> Result<StoredPaper>;
+
+    // ===== Collections =====
+
+    /// Fails if a collection with the same name exists or the parent does
+    /// not.
+    async fn create_collection(&self, collection: &NewCollection) -> Result<Collection>;
+
+    async fn get_all_collections(&self) -> Result<Vec<Collection>>;
+
+    /// Returns `None` if no collection has this name.
+    async fn get_collection(&self, name: &str) -> Result<Option<Collection>>;
+
+    /// Renames a collection, keeping its papers and nested collections.
+    async fn rename_collection(&self, name: &str, new_name: &str) -> Result<Collection>;
+
+    /// Deletes a collection without touching its papers. Nested collections
+    /// move up to the deleted collection's parent.
+    async fn delete_collection(&self, name: &str) -> Result<()>;
+
+    /// Papers in the collection, oldest addition first. Smart collections
+    /// are evaluated against the whole library on every call.
+    async fn get_collection_papers(&self, collection_name: &str) -> Result<Vec<CollectionPaper>>;
+
+    /// Adding a paper that is already in the collection does nothing. Smart
+    /// collections cannot be edited by hand.
+    async fn add_paper_to_collection(&self, arxiv_id: &str, collection_name: &str) -> Result<()>;
+
+    async fn remove_paper_from_collection(
+        &self,
+        arxiv_id: &str,
+        collection_name: &str,
+    ) -> Result<()>;
+
+    // ===== Paper status =====
+
+    /// Every stored paper with its metadata, ordered by arXiv ID.
+    async fn list_papers(&self) -> Result<Vec<(StoredPaper, PaperMetadata)>>;
+
+    /// Returns `None` if no metadata is stored for the paper.
+    async fn get_paper_metadata(&self, arxiv_id: &str) -> Result<Option<PaperMetadata>>;
+
+    async fn mark_paper_saved(&self, arxiv_id: &str, saved: bool) -> Result<PaperMetadata>;
+
+    /// Moves a paper between unread, reading and read, recording when it
+    /// started being read and when it was finished.
+    async fn set_read_state(&self, arxiv_id: &str, state: ReadState) -> Result<PaperMetadata>;
+
+    async fn mark_paper_read(&self, arxiv_id: &str, read: bool) -> Result<PaperMetadata> {
+        let state = if read {
+            ReadState::Read
+        } else {
+            ReadState::Unread
+        };
+        self.set_read_state(arxiv_id, state).await
+    }
+
+    async fn papers_in_state(&self, state: ReadState) -> Result<Vec<(StoredPaper, PaperMetadata)>> {
+        let mut papers = self.list_papers().await?;
+        papers.retain(|(_, metadata)| metadata.state() == state);
+        Ok(papers)
+    }
+
+    // ===== Tags =====
+
+    /// Replaces a paper's tags; they are normalized and deduplicated.
+    async fn set_paper_tags(&self, arxiv_id: &str, tags: &[String]) -> Result<PaperMetadata>;
+
+    async fn add_tag(&self, arxiv_id: &str, tag: &str) -> Result<PaperMetadata> {
+        let tag = normalize_tag(tag)?;
+        let mut tags = self.require_metadata(arxiv_id).await?.tag_list();
+        if !tags.contains(&tag) {
+            tags.push(tag);
+        }
+        self.set_paper_tags(arxiv_id, &tags).await
+    }
+
+    async fn remove_tag(&self, arxiv_id: &str, tag: &str) -> Result<PaperMetadata> {
+        let tag = normalize_tag(tag)?;
+        let mut tags = self.require_metadata(arxiv_id).await?.tag_list();
+        tags.retain(|t| *t != tag);
+        self.set_paper_tags(arxiv_id, &tags).await
+    }
+
+    /// Renames a tag on every paper, merging it into `new_tag` where both
+    /// are present. Returns the number of papers changed.
+    async fn rename_tag(&self, tag: &str, new_tag: &str) -> Result<usize> {
+        let (tag, new_tag) = (normalize_tag(tag)?, normalize_tag(new_tag)?);
+        if tag == new_tag {
+            return Ok(0);
+        }
+        let mut changed = 0;
+        for (paper, metadata) in self.list_papers().await? {
+            let tags = metadata.tag_list();
+            if !tags.contains(&tag) {
+                continue;
+            }
+            let mut renamed: Vec<String> = Vec::with_capacity(tags.len());
+            for t in tags {
+                let t = if t == tag { new_tag.clone() } else { t };
+                if !renamed.contains(&t) {
+                    renamed.push(t);
+                }
+            }
+            self.set_paper_tags(&paper.arxiv_id, &renamed).await?;
+            changed += 1;
+        }
+        Ok(changed)
+    }
+
+    /// Removes a tag from every paper. Returns the number of papers changed.
+    async fn delete_tag(&self, tag: &str) -> Result<usize> {
+        let tag = normalize_tag(tag)?;
+        let mut changed = 0;
+        for (paper, metadata) in self.list_papers().await? {
+            let mut tags = metadata.tag_list();
+            if tags.contains(&tag) {
+                tags.retain(|t| *t != tag);
+                self.set_paper_tags(&paper.arxiv_id, &tags).await?;
+                changed += 1;
+            }
+        }
+        Ok(changed)
+    }
+
+    /// All tags in use, most used first.
+    async fn list_tags(&self) -> Result<Vec<TagCount>> {
+        let papers = self.list_papers().await?;
+        Ok(count_tags(papers.iter().map(|(_, m)| m.tags.as_str())))
+    }
+
+    /// Papers whose tags satisfy `expr`, e.g. `ml and not survey`.
+    async fn find_papers_by_tags(&self, expr: &TagExpr) -> Result<Vec<StoredPaper>> {
+        Ok(self
+            .list_papers()
+            .await?
+            .into_iter()
+            .filter(|(_, metadata)| expr.matches(&metadata.tag_list()))
+            .map(|(paper, _)| paper)
+            .collect())
+    }
+
+    async fn require_metadata(&self, arxiv_id: &str) -> Result<PaperMetadata> {
+        self.get_paper_metadata(arxiv_id)
+            .await?
+            .ok_or_else(|| anyhow!("No metadata stored for paper {}", arxiv_id))
+    }
+
+    // ===== Citations =====
+
+    /// Records that `from_arxiv_id` cites `to_arxiv_id`. Both papers must be
+    /// stored; citing the same paper again only updates the context.
+    async fn add_citation(
+        &self,
+        from_arxiv_id: &str,
+        to_arxiv_id: &str,
+        context: &str,
+    ) -> Result<()>;
+
+    /// Drops every citation made by `arxiv_id`, before re-indexing it.
+    async fn delete_citations(&self, arxiv_id: &str) -> Result<()>;
+
+    /// Papers cited by `arxiv_id`, with the citing sentence.
+    async fn get_references(&self, arxiv_id: &str) -> Result<Vec<CitationLink>>;
+
+    /// Papers that cite `arxiv_id`, with the citing sentence.
+    async fn get_cited_by(&self, arxiv_id: &str) -> Result<Vec<CitationLink>>;
+
+    /// Papers cited by `arxiv_id`.
+    async fn get_cited_papers(&self, arxiv_id: &str) -> Result<Vec<StoredPaper>> {
+        let links = self.get_references(arxiv_id).await?;
+        Ok(links.into_iter().map(|link| link.paper).collect())
+    }
+
+    /// Papers that cite `arxiv_id`.
+    async fn get_citing_papers(&self, arxiv_id: &str) -> Result<Vec<StoredPaper>> {
+        let links = self.get_cited_by(arxiv_id).await?;
+        Ok(links.into_iter().map(|link| link.paper).collect())
+    }
+}
+
+/// Opens the store selected by `storage.backend`.
+///
+/// With `auto`, HelixDB is used when an endpoint is configured, answers
+/// within the connect timeout and serves the queries this build needs;
+/// otherwise papers go to the local library file. When offline mode is on
+/// and an earlier session left a cache, an unreachable HelixDB is worked
+/// around from that cache instead, see [`OfflineStore`].
+pub async fn open(config: &Config) -> Result<Arc<dyn PaperStore>> {
+    let store: Arc<dyn PaperStore> = match config.storage.backend {
+        StoreBackendKind::Local => Arc::new(LocalStore::open(&config.storage.library_path)?),
+        StoreBackendKind::Helix => connect_helix(config).await?,
+        StoreBackendKind::Auto => {
+            if config.helixdb.endpoint.is_empty() {
+                tracing::info!("No HelixDB endpoint configured; using local store");
+                Arc::new(LocalStore::open(&config.storage.library_path)?)
+            } else {
+                match connect_helix(config).await {
+                    Ok(store) => store,
+                    Err(e) => {
+                        tracing::warn!("{:#}; using local store", e);
+                        Arc::new(LocalStore::open(&config.storage.library_path)?)
+                    }
+                }
+            }
+        }
+    };
+
+    tracing::info!("Paper store ready: {}", store.backend_name());
+    Ok(store)
+}
+
+/// Connects to HelixDB, checks it against this build and applies pending
+/// schema migrations.
+async fn connect_helix(config: &Config) -> Result<Arc<dyn PaperStore>> {
+    let offline = &config.helixdb.offline;
+    let client = DbClient::new(&config.helixdb)?;
+
+    let report = health::check_client(&client).await;
+    match report.status {
+        HealthStatus::Healthy => tracing::debug!("{}", report),
+        HealthStatus::Unreachable if offline.enabled && OfflineStore::has_cache(offline) => {
+            tracing::warn!("{}; working offline from {:?}", report, offline.dir);
+            return Ok(Arc::new(OfflineStore::open(client, offline, false)?));
+        }
+        HealthStatus::Unreachable => bail!("{}", report),
+        HealthStatus::Degraded => bail!("{} (deploy helix/ and restart)", report),
+    }
+
+    if let Some(version) = client.migrate().await?.last() {
+        tracing::info!(
+            "Migrated HelixDB at {} to schema v{}",
+            client.address(),
+            version
+        );
+    }
+    if !offline.enabled {
+        return Ok(Arc::new(client));
+    }
+
+    // Replay writes left by an offline session and refresh the cache in
+    // the background; the store serves HelixDB directly meanwhile
+    let store = Arc::new(OfflineStore::open(client, offline, true)?);
+    let background = store.clone();
+    tokio::spawn(async move {
+        if let Err(e) = background.reconnect().await {
+            tracing::warn!("Could not replay offline writes: {:#}", e);
+        }
+    });
+    Ok(store)
+}
+
+/// Fails if `embedding` does not have the dimension `model` produces.
+pub(crate) fn check_vector(embedding: &[f32], model: &ModelInfo) -> Result<()> {
+    if embedding.len() != model.dimension {
+        bail!(
+            "Vector has {} dimensions but model {} produces {}",
+            embedding.len(),
+            model,
+            model.dimension
+        );
+    }
+    Ok(())
+}
+
+/// Validates a new collection against the existing ones and builds its
+/// stored form.
+pub(crate) fn prepare_collection(
+    collection: &NewCollection,
+    existing: &[Collection],
+) -> Result<Collection> {
+    let name = collection.name.trim();
+    if name.is_empty() {
+        bail!("Collection name cannot be empty");
+    }
+    if existing.iter().any(|c| c.name == name) {
+        bail!("Collection {:?} already exists", name);
+    }
+    let parent = collection.parent.clone().unwrap_or_default();
+    if !parent.is_empty() && !existing.iter().any(|c| c.name == parent) {
+        bail!("Parent collection {:?} does not exist", parent);
+    }
+    let filter = match &collection.filter {
+        Some(filter) => serde_json::to_string(filter)?,
+        None => String::new(),
+    };
+
+    Ok(Collection {
+        id: String::new(),
+        name: name.to_string(),
+        description: collection.description.clone(),
+        parent,
+        filter,
+        created_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+/// Fails unless papers can be added to or removed from `collection` by hand.
+pub(crate) fn check_manual(collection: &Collection) -> Result<()> {
+    if collection.is_smart() {
+        bail!(
+            "Collection {:?} is a smart collection; its papers are chosen by its filter",
+            collection.name
+        );
+    }
+    Ok(())
+}
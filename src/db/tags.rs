@@ -0,0 +1,254 @@
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Canonical form of a tag: trimmed, lowercase, with inner whitespace
+/// collapsed to single dashes. Fails on empty tags and on commas, which
+/// separate tags in storage.
+pub fn normalize_tag(tag: &str) -> Result<String> {
+    let tag = tag
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("-")
+        .to_lowercase();
+    if tag.is_empty() {
+        bail!("Tag cannot be empty");
+    }
+    if tag.contains(',') {
+        bail!("Tag {:?} cannot contain a comma", tag);
+    }
+    Ok(tag)
+}
+
+/// Splits a stored comma-separated tag string, dropping blanks and
+/// duplicates.
+pub fn split_tags(tags: &str) -> Vec<String> {
+    let mut list: Vec<String> = Vec::new();
+    for tag in tags.split(',').filter_map(|tag| normalize_tag(tag).ok()) {
+        if !list.contains(&tag) {
+            list.push(tag);
+        }
+    }
+    list
+}
+
+/// Inverse of [`split_tags`].
+pub fn join_tags(tags: &[String]) -> String {
+    tags.join(",")
+}
+
+/// Normalizes and deduplicates `tags` into their stored form.
+pub fn encode_tags(tags: &[String]) -> Result<String> {
+    let mut list: Vec<String> = Vec::with_capacity(tags.len());
+    for tag in tags {
+        let tag = normalize_tag(tag)?;
+        if !list.contains(&tag) {
+            list.push(tag);
+        }
+    }
+    Ok(join_tags(&list))
+}
+
+/// A tag and the number of papers carrying it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// Counts tags over the given tag lists, most used first.
+pub fn count_tags<'a>(tag_lists: impl Iterator<Item = &'a str>) -> Vec<TagCount> {
+    let mut counts: std::collections::BTreeMap<String, usize> = Default::default();
+    for tags in tag_lists {
+        for tag in split_tags(tags) {
+            *counts.entry(tag).or_default() += 1;
+        }
+    }
+
+    let mut counts: Vec<TagCount> = counts
+        .into_iter()
+        .map(|(tag, count)| TagCount { tag, count })
+        .collect();
+    counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+    counts
+}
+
+/// Boolean query over a paper's tags.
+///
+/// Parsed from expressions like `ml and (nlp or vision) and not survey`.
+/// `&`, `|` and `!` work as well as the keywords, a leading `-` negates a
+/// tag, and tags next to each other are combined with `and`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagExpr {
+    Tag(String),
+    Not(Box<TagExpr>),
+    And(Vec<TagExpr>),
+    Or(Vec<TagExpr>),
+}
+
+impl TagExpr {
+    pub fn parse(input: &str) -> Result<Self> {
+        let tokens = tokenize(input);
+        if tokens.is_empty() {
+            bail!("Tag expression is empty");
+        }
+        let mut parser = Parser { tokens, pos: 0 };
+        let expr = parser.or()?;
+        if let Some(token) = parser.peek() {
+            bail!("Unexpected {:?} in tag expression", token);
+        }
+        Ok(expr)
+    }
+
+    pub fn matches(&self, tags: &[String]) -> bool {
+        match self {
+            TagExpr::Tag(tag) => tags.contains(tag),
+            TagExpr::Not(inner) => !inner.matches(tags),
+            TagExpr::And(all) => all.iter().all(|expr| expr.matches(tags)),
+            TagExpr::Or(any) => any.iter().any(|expr| expr.matches(tags)),
+        }
+    }
+
+    /// Matches against a stored comma-separated tag string.
+    pub fn matches_str(&self, tags: &str) -> bool {
+        self.matches(&split_tags(tags))
+    }
+}
+
+impl fmt::Display for TagExpr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn join(f: &mut fmt::Formatter, exprs: &[TagExpr], op: &str) -> fmt::Result {
+            write!(f, "(")?;
+            for (i, expr) in exprs.iter().enumerate() {
+                if i > 0 {
+                    write!(f, " {} ", op)?;
+                }
+                write!(f, "{}", expr)?;
+            }
+            write!(f, ")")
+        }
+
+        match self {
+            TagExpr::Tag(tag) => write!(f, "{}", tag),
+            TagExpr::Not(inner) => write!(f, "not {}", inner),
+            TagExpr::And(all) => join(f, all, "and"),
+            TagExpr::Or(any) => join(f, any, "or"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Tag(String),
+    And,
+    Or,
+    Not,
+    Open,
+    Close,
+}
+
+fn tokenize(input: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    let mut word = String::new();
+
+    fn flush(word: &mut String, tokens: &mut Vec<Token>) {
+        if word.is_empty() {
+            return;
+        }
+        let mut rest = word.as_str();
+        while let Some(stripped) = rest.strip_prefix('-') {
+            tokens.push(Token::Not);
+            rest = stripped;
+        }
+        match rest.to_lowercase().as_str() {
+            "" => {}
+            "and" => tokens.push(Token::And),
+            "or" => tokens.push(Token::Or),
+            "not" => tokens.push(Token::Not),
+            tag => tokens.push(Token::Tag(tag.to_string())),
+        }
+        word.clear();
+    }
+
+    for c in input.chars() {
+        let token = match c {
+            '(' => Some(Token::Open),
+            ')' => Some(Token::Close),
+            '&' => Some(Token::And),
+            '|' => Some(Token::Or),
+            '!' => Some(Token::Not),
+            c if c.is_whitespace() => None,
+            c => {
+                word.push(c);
+                continue;
+            }
+        };
+        flush(&mut word, &mut tokens);
+        tokens.extend(token);
+    }
+    flush(&mut word, &mut tokens);
+    tokens
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    fn or(&mut self) -> Result<TagExpr> {
+        let mut any = vec![self.and()?];
+        while self.peek() == Some(&Token::Or) {
+            self.pos += 1;
+            any.push(self.and()?);
+        }
+        Ok(if any.len() == 1 {
+            any.remove(0)
+        } else {
+            TagExpr::Or(any)
+        })
+    }
+
+    fn and(&mut self) -> Result<TagExpr> {
+        let mut all = vec![self.unary()?];
+        loop {
+            match self.peek() {
+                Some(Token::And) => self.pos += 1,
+                Some(Token::Tag(_)) | Some(Token::Not) | Some(Token::Open) => {}
+                _ => break,
+            }
+            all.push(self.unary()?);
+        }
+        Ok(if all.len() == 1 {
+            all.remove(0)
+        } else {
+            TagExpr::And(all)
+        })
+    }
+
+    fn unary(&mut self) -> Result<TagExpr> {
+        match self.next() {
+            Some(Token::Not) => Ok(TagExpr::Not(Box::new(self.unary()?))),
+            Some(Token::Open) => {
+                let expr = self.or()?;
+                if self.next() != Some(Token::Close) {
+                    bail!("Missing ')' in tag expression");
+                }
+                Ok(expr)
+            }
+            Some(Token::Tag(tag)) => Ok(TagExpr::Tag(normalize_tag(&tag)?)),
+            Some(token) => bail!("Unexpected {:?} in tag expression", token),
+            None => bail!("Tag expression ends unexpectedly"),
+        }
+    }
+}
@@ -0,0 +1,181 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::cache::CachedBackend;
+use super::chunking::word_spans;
+use super::hash::HashBackend;
+use super::onnx::OnnxBackend;
+use super::openai::OpenAiBackend;
+use crate::config::{EmbeddingBackendKind, EmbeddingsConfig, StorageConfig};
+
+/// A source of text embeddings.
+///
+/// Implementations return one L2-normalized vector per input text, in input
+/// order.
+#[async_trait]
+pub trait EmbeddingBackend: Send + Sync {
+    /// Short identifier of the model behind this backend.
+    fn model_name(&self) -> &str;
+
+    /// Identifies the exact weights behind `model_name`, e.g. a hash of the
+    /// model file. Cached vectors are only reused when this matches.
+    fn fingerprint(&self) -> &str {
+        self.model_name()
+    }
+
+    fn dimension(&self) -> usize;
+
+    /// Provenance recorded alongside every vector this backend produces.
+    fn model_info(&self) -> ModelInfo {
+        ModelInfo {
+            name: self.model_name().to_string(),
+            version: self.fingerprint().to_string(),
+            dimension: self.dimension(),
+        }
+    }
+
+    /// Preferred number of texts per [`EmbeddingBackend::embed_batch`] call.
+    fn batch_size(&self) -> usize;
+
+    /// Number of tokens the model reads before truncating its input.
+    fn max_tokens(&self) -> usize {
+        usize::MAX
+    }
+
+    /// Byte ranges of the tokens in `text`, used to split long inputs into
+    /// chunks. Defaults to whitespace-separated words.
+    fn token_spans(&self, text: &str) -> Result<Vec<(usize, usize)>> {
+        Ok(word_spans(text))
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>>;
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_batch(&[text])
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("Embedding backend returned no vector"))
+    }
+
+    /// Embeds `texts` in chunks of [`EmbeddingBackend::batch_size`],
+    /// returning vectors in input order.
+    async fn embed_all(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        self.embed_all_with_progress(texts, &mut |_| {}).await
+    }
+
+    /// Like [`EmbeddingBackend::embed_all`], calling `on_progress` after
+    /// every chunk.
+    async fn embed_all_with_progress(
+        &self,
+        texts: &[&str],
+        on_progress: &mut (dyn FnMut(BatchProgress) + Send),
+    ) -> Result<Vec<Vec<f32>>> {
+        let started = Instant::now();
+        let mut embeddings = Vec::with_capacity(texts.len());
+
+        for batch in texts.chunks(self.batch_size().max(1)) {
+            embeddings.extend(self.embed_batch(batch).await?);
+            on_progress(BatchProgress {
+                completed: embeddings.len(),
+                total: texts.len(),
+                elapsed: started.elapsed(),
+            });
+        }
+
+        Ok(embeddings)
+    }
+}
+
+/// Identifies the vector space an embedding belongs to. Vectors are only
+/// comparable when all three fields match.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelInfo {
+    pub name: String,
+    /// Fingerprint of the exact weights, see [`EmbeddingBackend::fingerprint`]
+    pub version: String,
+    pub dimension: usize,
+}
+
+impl std::fmt::Display for ModelInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let version: String = self.version.chars().take(12).collect();
+        write!(f, "{}@{} ({}d)", self.name, version, self.dimension)
+    }
+}
+
+/// Progress reported by [`EmbeddingBackend::embed_all_with_progress`] after
+/// each batch completes.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchProgress {
+    pub completed: usize,
+    pub total: usize,
+    pub elapsed: Duration,
+}
+
+impl BatchProgress {
+    pub fn texts_per_second(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.completed as f64 / secs
+        } else {
+            0.0
+        }
+    }
+}
+
+/// Builds the backend selected by `config.backend`.
+pub fn from_config(config: &EmbeddingsConfig) -> Result<Arc<dyn EmbeddingBackend>> {
+    let backend: Arc<dyn EmbeddingBackend> = match config.backend {
+        EmbeddingBackendKind::Onnx => Arc::new(OnnxBackend::new(config)?),
+        EmbeddingBackendKind::Hash => {
+            tracing::warn!("Using hash embedding backend: search results will not be semantic");
+            Arc::new(HashBackend::new(config.dimension.unwrap_or(384)))
+        }
+        EmbeddingBackendKind::OpenAi => Arc::new(OpenAiBackend::new(config)?),
+    };
+
+    tracing::info!(
+        "Embedding backend ready: {} ({} dimensions)",
+        backend.model_name(),
+        backend.dimension()
+    );
+
+    Ok(backend)
+}
+
+/// Like [`from_config`], but answers repeated texts from the on-disk
+/// embedding cache when `config.cache` is enabled.
+pub fn from_config_with_cache(
+    config: &EmbeddingsConfig,
+    storage: &StorageConfig,
+) -> Result<Arc<dyn EmbeddingBackend>> {
+    let backend = from_config(config)?;
+    if !config.cache {
+        return Ok(backend);
+    }
+    Ok(Arc::new(CachedBackend::open(backend, storage)?))
+}
+
+pub fn normalize(embedding: &mut [f32]) {
+    let magnitude: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    if magnitude > 0.0 {
+        for x in embedding.iter_mut() {
+            *x /= magnitude;
+        }
+    }
+}
+
+/// Fails unless every vector has `dimension` entries.
+pub(crate) fn check_dimensions(embeddings: &[Vec<f32>], dimension: usize) -> Result<()> {
+    if let Some(embedding) = embeddings.iter().find(|e| e.len() != dimension) {
+        anyhow::bail!(
+            "Model produced {}-dimensional embeddings but {} were configured",
+            embedding.len(),
+            dimension
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,391 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use super::backend::EmbeddingBackend;
+use crate::config::StorageConfig;
+
+const MAGIC: &[u8; 4] = b"AXEC";
+const FORMAT_VERSION: u32 = 1;
+const HEADER_LEN: u64 = 8;
+/// Fraction of the size limit the cache shrinks to when it overflows, so
+/// that compaction does not run on every insert.
+const COMPACT_TARGET: f64 = 0.9;
+/// Largest vector the cache stores. A bigger dimension read back from disk
+/// can only come from a corrupt record, so it is not allocated.
+const MAX_DIMENSION: usize = 65_536;
+
+type Key = [u8; 32];
+
+struct Entry {
+    vector: Vec<f32>,
+    last_used: u64,
+}
+
+/// Persistent store of embedding vectors keyed by model and text.
+///
+/// Records are appended to a single binary file as
+/// `key (32 bytes) | dimension (u32 LE) | dimension * f32 LE`; later records
+/// for the same key win. When the cache grows past its size limit the least
+/// recently used vectors are dropped and the file is rewritten.
+pub struct EmbeddingCache {
+    path: PathBuf,
+    entries: HashMap<Key, Entry>,
+    max_bytes: u64,
+    size_bytes: u64,
+    clock: u64,
+    writer: Option<BufWriter<File>>,
+}
+
+impl EmbeddingCache {
+    pub fn open(path: &Path, max_bytes: u64) -> Result<Self> {
+        let mut cache = Self {
+            path: path.to_path_buf(),
+            entries: HashMap::new(),
+            max_bytes,
+            size_bytes: 0,
+            clock: 0,
+            writer: None,
+        };
+
+        match File::open(path) {
+            Ok(file) => {
+                if let Err(e) = cache.read_records(file) {
+                    tracing::warn!("Discarding unreadable embedding cache {:?}: {}", path, e);
+                    cache.entries.clear();
+                    cache.size_bytes = 0;
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => return Err(e).with_context(|| format!("Failed to open {:?}", path)),
+        }
+
+        // Rewrite so the file holds exactly the live entries with a valid
+        // header, then append from there.
+        if cache.size_bytes > cache.max_bytes {
+            cache.evict();
+        }
+        cache.rewrite()?;
+
+        Ok(cache)
+    }
+
+    /// Derives the cache key for `text` embedded by the model identified by
+    /// `model_id` and `fingerprint`.
+    pub fn key(model_id: &str, fingerprint: &str, text: &str) -> Key {
+        let text_hash = Sha256::digest(text.as_bytes());
+        let mut hasher = Sha256::new();
+        hasher.update(model_id.as_bytes());
+        hasher.update([0]);
+        hasher.update(fingerprint.as_bytes());
+        hasher.update([0]);
+        hasher.update(text_hash);
+        hasher.finalize().into()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn size_bytes(&self) -> u64 {
+        self.size_bytes
+    }
+
+    pub fn get(&mut self, key: &Key) -> Option<Vec<f32>> {
+        self.clock += 1;
+        let clock = self.clock;
+        self.entries.get_mut(key).map(|entry| {
+            entry.last_used = clock;
+            entry.vector.clone()
+        })
+    }
+
+    pub fn insert(&mut self, key: Key, vector: Vec<f32>) -> Result<()> {
+        if vector.len() > MAX_DIMENSION {
+            bail!(
+                "Cannot cache a {}-dimensional vector; the limit is {}",
+                vector.len(),
+                MAX_DIMENSION
+            );
+        }
+        self.clock += 1;
+
+        let writer = match &mut self.writer {
+            Some(writer) => writer,
+            None => bail!("Embedding cache {:?} is not writable", self.path),
+        };
+        write_record(writer, &key, &vector)?;
+
+        self.size_bytes += record_len(vector.len());
+        if let Some(old) = self.entries.insert(
+            key,
+            Entry {
+                vector,
+                last_used: self.clock,
+            },
+        ) {
+            self.size_bytes -= record_len(old.vector.len());
+        }
+
+        if self.size_bytes > self.max_bytes {
+            self.evict();
+            self.rewrite()?;
+        }
+
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        if let Some(writer) = &mut self.writer {
+            writer.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn clear(&mut self) -> Result<()> {
+        self.entries.clear();
+        self.size_bytes = 0;
+        self.rewrite()
+    }
+
+    fn read_records(&mut self, file: File) -> Result<()> {
+        let mut reader = BufReader::new(file);
+
+        let mut header = [0u8; HEADER_LEN as usize];
+        reader.read_exact(&mut header)?;
+        if &header[..4] != MAGIC {
+            bail!("not an embedding cache file");
+        }
+        let version = u32::from_le_bytes(header[4..8].try_into()?);
+        if version != FORMAT_VERSION {
+            bail!("unsupported cache format version {}", version);
+        }
+
+        loop {
+            let mut key = [0u8; 32];
+            match reader.read_exact(&mut key) {
+                Ok(()) => {}
+                Err(e) if e.kind() == ErrorKind::UnexpectedEof => break,
+                Err(e) => return Err(e.into()),
+            }
+
+            let mut dim = [0u8; 4];
+            let mut data = Vec::new();
+            let complete = reader.read_exact(&mut dim).is_ok() && {
+                let dimension = u32::from_le_bytes(dim) as usize;
+                dimension <= MAX_DIMENSION && {
+                    data.resize(dimension * 4, 0);
+                    reader.read_exact(&mut data).is_ok()
+                }
+            };
+            if !complete {
+                // Torn or corrupt write from an interrupted run; keep what
+                // came before
+                tracing::warn!("Ignoring truncated record at end of {:?}", self.path);
+                break;
+            }
+
+            let vector: Vec<f32> = data
+                .chunks_exact(4)
+                .map(|bytes| f32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                .collect();
+
+            self.clock += 1;
+            self.size_bytes += record_len(vector.len());
+            if let Some(old) = self.entries.insert(
+                key,
+                Entry {
+                    vector,
+                    last_used: self.clock,
+                },
+            ) {
+                self.size_bytes -= record_len(old.vector.len());
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Drops least recently used entries until the cache is comfortably
+    /// below its limit.
+    fn evict(&mut self) {
+        let target = (self.max_bytes as f64 * COMPACT_TARGET) as u64;
+
+        let mut by_age: Vec<(u64, Key)> = self
+            .entries
+            .iter()
+            .map(|(key, entry)| (entry.last_used, *key))
+            .collect();
+        by_age.sort_unstable();
+
+        let mut evicted = 0;
+        for (_, key) in by_age {
+            if self.size_bytes <= target {
+                break;
+            }
+            if let Some(entry) = self.entries.remove(&key) {
+                self.size_bytes -= record_len(entry.vector.len());
+                evicted += 1;
+            }
+        }
+
+        tracing::debug!("Evicted {} vectors from embedding cache", evicted);
+    }
+
+    /// Writes all live entries to a fresh file, oldest first, and reopens
+    /// it for appending.
+    fn rewrite(&mut self) -> Result<()> {
+        self.writer = None;
+
+        if let Some(parent) = self.path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp_path = self.path.with_extension("tmp");
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            writer.write_all(MAGIC)?;
+            writer.write_all(&FORMAT_VERSION.to_le_bytes())?;
+
+            let mut ordered: Vec<(&Key, &Entry)> = self.entries.iter().collect();
+            ordered.sort_unstable_by_key(|(_, entry)| entry.last_used);
+            for (key, entry) in ordered {
+                write_record(&mut writer, key, &entry.vector)?;
+            }
+            writer.flush()?;
+        }
+        std::fs::rename(&tmp_path, &self.path)
+            .with_context(|| format!("Failed to replace {:?}", self.path))?;
+
+        let file = OpenOptions::new().append(true).open(&self.path)?;
+        self.writer = Some(BufWriter::new(file));
+        Ok(())
+    }
+}
+
+impl Drop for EmbeddingCache {
+    fn drop(&mut self) {
+        if let Err(e) = self.flush() {
+            tracing::warn!("Failed to flush embedding cache: {}", e);
+        }
+    }
+}
+
+fn record_len(dimension: usize) -> u64 {
+    32 + 4 + 4 * dimension as u64
+}
+
+fn write_record(writer: &mut impl Write, key: &Key, vector: &[f32]) -> Result<()> {
+    writer.write_all(key)?;
+    writer.write_all(&(vector.len() as u32).to_le_bytes())?;
+    for value in vector {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+/// Wraps a backend so that previously seen texts are answered from an
+/// [`EmbeddingCache`] instead of being embedded again.
+pub struct CachedBackend {
+    inner: Arc<dyn EmbeddingBackend>,
+    cache: Mutex<EmbeddingCache>,
+}
+
+impl CachedBackend {
+    pub fn new(inner: Arc<dyn EmbeddingBackend>, cache: EmbeddingCache) -> Self {
+        Self {
+            inner,
+            cache: Mutex::new(cache),
+        }
+    }
+
+    /// Opens the cache file under `storage.cache_dir`, bounded by
+    /// `storage.max_cache_size_mb`.
+    pub fn open(inner: Arc<dyn EmbeddingBackend>, storage: &StorageConfig) -> Result<Self> {
+        let path = storage.cache_dir.join("embeddings.bin");
+        let max_bytes = storage.max_cache_size_mb * 1024 * 1024;
+        let cache = EmbeddingCache::open(&path, max_bytes)?;
+
+        tracing::info!(
+            "Embedding cache at {:?}: {} vectors ({} KB)",
+            path,
+            cache.len(),
+            cache.size_bytes() / 1024
+        );
+
+        Ok(Self::new(inner, cache))
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, EmbeddingCache>> {
+        self.cache
+            .lock()
+            .map_err(|_| anyhow::anyhow!("Embedding cache lock poisoned"))
+    }
+}
+
+#[async_trait]
+impl EmbeddingBackend for CachedBackend {
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn fingerprint(&self) -> &str {
+        self.inner.fingerprint()
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn batch_size(&self) -> usize {
+        self.inner.batch_size()
+    }
+
+    fn max_tokens(&self) -> usize {
+        self.inner.max_tokens()
+    }
+
+    fn token_spans(&self, text: &str) -> Result<Vec<(usize, usize)>> {
+        self.inner.token_spans(text)
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let keys: Vec<Key> = texts
+            .iter()
+            .map(|text| EmbeddingCache::key(self.model_name(), self.fingerprint(), text))
+            .collect();
+
+        let mut results: Vec<Option<Vec<f32>>> = {
+            let mut cache = self.lock()?;
+            keys.iter().map(|key| cache.get(key)).collect()
+        };
+
+        let missing: Vec<usize> = (0..texts.len()).filter(|&i| results[i].is_none()).collect();
+        if !missing.is_empty() {
+            let missing_texts: Vec<&str> = missing.iter().map(|&i| texts[i]).collect();
+            let vectors = self.inner.embed_batch(&missing_texts).await?;
+
+            let mut cache = self.lock()?;
+            for (&i, vector) in missing.iter().zip(vectors) {
+                cache.insert(keys[i], vector.clone())?;
+                results[i] = Some(vector);
+            }
+            cache.flush()?;
+        }
+
+        results
+            .into_iter()
+            .map(|vector| {
+                vector.ok_or_else(|| anyhow::anyhow!("Embedding backend returned too few vectors"))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,212 @@
+use anyhow::{bail, Result};
+use std::sync::Arc;
+
+use super::backend::{normalize, BatchProgress, EmbeddingBackend};
+use crate::arxiv::models::Paper;
+use crate::config::{ChunkingConfig, PoolingStrategy};
+
+/// A window of a longer text, small enough for the model to read whole.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TextChunk {
+    pub index: usize,
+    pub text: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChunkEmbedding {
+    pub index: usize,
+    pub text: String,
+    pub embedding: Vec<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DocumentEmbedding {
+    /// Pooled, normalized vector for the whole paper
+    pub embedding: Vec<f32>,
+    /// Per-chunk vectors; empty unless `store_chunks` is enabled
+    pub chunks: Vec<ChunkEmbedding>,
+}
+
+/// Embeds papers whose text may exceed the model's input length by
+/// splitting it into overlapping chunks and pooling the chunk vectors.
+pub struct DocumentEmbedder {
+    backend: Arc<dyn EmbeddingBackend>,
+    config: ChunkingConfig,
+}
+
+impl DocumentEmbedder {
+    pub fn new(backend: Arc<dyn EmbeddingBackend>, config: &ChunkingConfig) -> Self {
+        Self {
+            backend,
+            config: config.clone(),
+        }
+    }
+
+    pub fn backend(&self) -> &Arc<dyn EmbeddingBackend> {
+        &self.backend
+    }
+
+    pub fn chunk(&self, text: &str) -> Result<Vec<TextChunk>> {
+        let spans = self.backend.token_spans(text)?;
+        let max_tokens = self.config.max_tokens.min(self.backend.max_tokens());
+        Ok(chunk_text(text, &spans, max_tokens, self.config.overlap))
+    }
+
+    pub async fn embed_paper(&self, paper: &Paper) -> Result<DocumentEmbedding> {
+        self.embed_papers(std::slice::from_ref(paper))
+            .await?
+            .pop()
+            .ok_or_else(|| anyhow::anyhow!("No embedding produced for {}", paper.arxiv_id))
+    }
+
+    /// Embeds all chunks of all `papers` in as few backend batches as
+    /// possible, returning one document embedding per paper in input order.
+    pub async fn embed_papers(&self, papers: &[Paper]) -> Result<Vec<DocumentEmbedding>> {
+        self.embed_papers_with_progress(papers, &mut |_| {}).await
+    }
+
+    /// Like [`DocumentEmbedder::embed_papers`]; progress is counted in
+    /// chunks rather than papers.
+    pub async fn embed_papers_with_progress(
+        &self,
+        papers: &[Paper],
+        on_progress: &mut (dyn FnMut(BatchProgress) + Send),
+    ) -> Result<Vec<DocumentEmbedding>> {
+        let title_weighted = self.config.pooling == PoolingStrategy::TitleWeighted;
+
+        let mut texts = Vec::new();
+        let mut plans = Vec::with_capacity(papers.len());
+        for paper in papers {
+            let chunks = if title_weighted {
+                texts.push(paper.title.clone());
+                self.chunk(&paper.abstract_text)?
+            } else {
+                self.chunk(&paper.text_for_embedding())?
+            };
+            texts.extend(chunks.iter().map(|chunk| chunk.text.clone()));
+            plans.push(chunks);
+        }
+
+        let text_refs: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let vectors = self
+            .backend
+            .embed_all_with_progress(&text_refs, on_progress)
+            .await?;
+        if vectors.len() != texts.len() {
+            bail!(
+                "Embedding backend returned {} vectors for {} chunks",
+                vectors.len(),
+                texts.len()
+            );
+        }
+
+        let mut vectors = vectors.into_iter();
+        let mut documents = Vec::with_capacity(plans.len());
+        for chunks in plans {
+            let title_vector = if title_weighted { vectors.next() } else { None };
+            let chunk_vectors: Vec<Vec<f32>> = vectors.by_ref().take(chunks.len()).collect();
+
+            let mut embedding = mean(&chunk_vectors, self.backend.dimension());
+            if let Some(title_vector) = title_vector {
+                for (value, title) in embedding.iter_mut().zip(title_vector) {
+                    *value += self.config.title_weight * title;
+                }
+            }
+            normalize(&mut embedding);
+
+            let chunks = if self.config.store_chunks {
+                chunks
+                    .into_iter()
+                    .zip(chunk_vectors)
+                    .map(|(chunk, embedding)| ChunkEmbedding {
+                        index: chunk.index,
+                        text: chunk.text,
+                        embedding,
+                    })
+                    .collect()
+            } else {
+                Vec::new()
+            };
+
+            documents.push(DocumentEmbedding { embedding, chunks });
+        }
+
+        Ok(documents)
+    }
+}
+
+/// Splits `text` into windows of at most `max_tokens` tokens, repeating
+/// `overlap` tokens between neighbouring windows. `spans` are the byte
+/// ranges of the tokens in `text`.
+pub fn chunk_text(
+    text: &str,
+    spans: &[(usize, usize)],
+    max_tokens: usize,
+    overlap: usize,
+) -> Vec<TextChunk> {
+    let max_tokens = max_tokens.max(1);
+    if spans.len() <= max_tokens {
+        return vec![TextChunk {
+            index: 0,
+            text: text.trim().to_string(),
+        }];
+    }
+
+    let stride = max_tokens - overlap.min(max_tokens - 1);
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    loop {
+        let end = (start + max_tokens).min(spans.len());
+        let from = spans[start].0;
+        let to = spans[end - 1].1;
+        chunks.push(TextChunk {
+            index: chunks.len(),
+            text: text.get(from..to).unwrap_or_default().to_string(),
+        });
+
+        if end == spans.len() {
+            break;
+        }
+        start += stride;
+    }
+
+    chunks
+}
+
+/// Byte ranges of the whitespace-separated words in `text`.
+pub fn word_spans(text: &str) -> Vec<(usize, usize)> {
+    let mut spans = Vec::new();
+    let mut start = None;
+
+    for (i, c) in text.char_indices() {
+        match (c.is_whitespace(), start) {
+            (true, Some(s)) => {
+                spans.push((s, i));
+                start = None;
+            }
+            (false, None) => start = Some(i),
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        spans.push((s, text.len()));
+    }
+
+    spans
+}
+
+fn mean(vectors: &[Vec<f32>], dimension: usize) -> Vec<f32> {
+    let mut pooled = vec![0.0f32; dimension];
+    for vector in vectors {
+        for (sum, value) in pooled.iter_mut().zip(vector) {
+            *sum += value;
+        }
+    }
+    if !vectors.is_empty() {
+        for value in pooled.iter_mut() {
+            *value /= vectors.len() as f32;
+        }
+    }
+    pooled
+}
@@ -1,87 +0,0 @@
-use anyhow::Result;
-use std::collections::hash_map::DefaultHasher;
-use std::hash::{Hash, Hasher};
-
-use crate::config::EmbeddingsConfig;
-
-pub struct EmbeddingGenerator {
-    dimension: usize,
-    _batch_size: usize,
-}
-
-impl EmbeddingGenerator {
-    pub fn new(config: &EmbeddingsConfig) -> Result<Self> {
-        // TODO: Implement actual ONNX model loading
-        // For now, this is a placeholder that generates deterministic random embeddings
-        tracing::warn!(
-            "EmbeddingGenerator is using placeholder implementation. \
-             Real embeddings require ONNX model at {:?}",
-            config.model_path
-        );
-
-        Ok(Self {
-            dimension: config.dimension,
-            _batch_size: config.batch_size,
-        })
-    }
-
-    pub fn generate_embedding(&self, text: &str) -> Result<Vec<f32>> {
-        // Placeholder: Generate deterministic "embedding" from text hash
-        // TODO: Replace with actual ONNX inference
-        let mut hasher = DefaultHasher::new();
-        text.hash(&mut hasher);
-        let hash = hasher.finish();
-
-        let mut embedding = Vec::with_capacity(self.dimension);
-        let mut seed = hash;
-
-        for _ in 0..self.dimension {
-            // Simple LCG for deterministic randomness
-            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
-            let value = ((seed >> 16) & 0x7fff) as f32 / 32768.0 - 0.5;
-            embedding.push(value);
-        }
-
-        // Normalize
-        Self::normalize(&mut embedding);
-
-        Ok(embedding)
-    }
-
-    pub fn batch_generate(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
-        texts.iter()
-            .map(|text| self.generate_embedding(text))
-            .collect()
-    }
-
-    pub fn normalize(embedding: &mut [f32]) {
-        let magnitude: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
-        if magnitude > 0.0 {
-            for x in embedding.iter_mut() {
-                *x /= magnitude;
-            }
-        }
-    }
-}
-
-// TODO: Implement real ONNX-based embedding generation
-// This will require:
-// 1. Loading the ONNX model with ort 2.0 API
-// 2. Loading the tokenizer
-// 3. Tokenizing input text
-// 4. Running inference
-// 5. Extracting and normalizing embeddings
-//
-// Example implementation outline:
-// ```
-// use ort::{GraphOptimizationLevel, Session};
-// use tokenizers::Tokenizer;
-//
-// let session = Session::builder()?
-//     .with_optimization_level(GraphOptimizationLevel::Level3)?
-//     .commit_from_file(&config.model_path)?;
-//
-// let tokenizer = Tokenizer::from_file(&tokenizer_path)?;
-// let encoding = tokenizer.encode(text, true)?;
-// let outputs = session.run(inputs)?;
-// ```
@@ -0,0 +1,62 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+use super::backend::{normalize, EmbeddingBackend};
+
+/// Hashes text into deterministic pseudo-random vectors.
+///
+/// The output carries no semantic meaning; this backend exists for tests and
+/// for running the pipeline without a model.
+pub struct HashBackend {
+    dimension: usize,
+}
+
+impl HashBackend {
+    pub fn new(dimension: usize) -> Self {
+        Self { dimension }
+    }
+
+    fn hash_embedding(&self, text: &str) -> Vec<f32> {
+        let mut hasher = DefaultHasher::new();
+        text.hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let mut embedding = Vec::with_capacity(self.dimension);
+        let mut seed = hash;
+
+        for _ in 0..self.dimension {
+            // Simple LCG for deterministic randomness
+            seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+            let value = ((seed >> 16) & 0x7fff) as f32 / 32768.0 - 0.5;
+            embedding.push(value);
+        }
+
+        normalize(&mut embedding);
+        embedding
+    }
+}
+
+#[async_trait]
+impl EmbeddingBackend for HashBackend {
+    fn model_name(&self) -> &str {
+        "hash"
+    }
+
+    fn fingerprint(&self) -> &str {
+        "hash-lcg-v1"
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn batch_size(&self) -> usize {
+        64
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| self.hash_embedding(text)).collect())
+    }
+}
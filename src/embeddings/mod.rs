@@ -1,2 +1,7 @@
-pub mod generator;
+pub mod backend;
+pub mod cache;
+pub mod chunking;
+pub mod hash;
 pub mod models;
+pub mod onnx;
+pub mod openai;
@@ -1,16 +1,484 @@
-// Model-related utilities and types for embeddings
-// This can include download utilities for ONNX models
-
-use anyhow::Result;
-use std::path::Path;
-
-pub async fn download_model(model_name: &str, output_path: &Path) -> Result<()> {
-    // TODO: Implement model download from HuggingFace or other source
-    // For now, users will need to manually download the model
-    tracing::warn!(
-        "Model download not yet implemented. Please download {} manually to {:?}",
-        model_name,
-        output_path
-    );
-    Ok(())
+// Model registry and downloads for local ONNX embedding models
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use crate::config::EmbeddingsConfig;
+
+/// Local file name of the ONNX graph inside a model directory.
+pub const MODEL_FILE: &str = "model.onnx";
+/// Local file name of the tokenizer inside a model directory.
+pub const TOKENIZER_FILE: &str = "tokenizer.json";
+
+const REGISTRY_FILE: &str = "registry.json";
+
+/// Sequence length assumed for models loaded from an explicit `model_path`.
+pub const DEFAULT_MAX_SEQ_LEN: usize = 256;
+
+#[derive(Debug, Clone, Copy)]
+pub struct ModelFile {
+    /// Name of the file inside the local model directory
+    pub local_name: &'static str,
+    /// Path of the file inside the upstream repository
+    pub remote_path: &'static str,
+    /// Pinned SHA-256, when known. It takes precedence over any checksum
+    /// the server publishes, so a mirror cannot vouch for a changed file.
+    pub sha256: Option<&'static str>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ModelSpec {
+    pub name: &'static str,
+    /// HuggingFace repository the files are fetched from
+    pub repo: &'static str,
+    pub dimension: usize,
+    pub max_seq_len: usize,
+    pub files: &'static [ModelFile],
+}
+
+const fn onnx_files() -> &'static [ModelFile] {
+    &[
+        ModelFile {
+            local_name: MODEL_FILE,
+            remote_path: "onnx/model.onnx",
+            sha256: None,
+        },
+        ModelFile {
+            local_name: TOKENIZER_FILE,
+            remote_path: "tokenizer.json",
+            sha256: None,
+        },
+    ]
+}
+
+/// Models the manager knows how to install.
+pub const KNOWN_MODELS: &[ModelSpec] = &[
+    ModelSpec {
+        name: "all-MiniLM-L6-v2",
+        repo: "sentence-transformers/all-MiniLM-L6-v2",
+        dimension: 384,
+        max_seq_len: 256,
+        files: onnx_files(),
+    },
+    ModelSpec {
+        name: "bge-small-en-v1.5",
+        repo: "BAAI/bge-small-en-v1.5",
+        dimension: 384,
+        max_seq_len: 512,
+        files: onnx_files(),
+    },
+    ModelSpec {
+        name: "e5-small-v2",
+        repo: "intfloat/e5-small-v2",
+        dimension: 384,
+        max_seq_len: 512,
+        files: onnx_files(),
+    },
+    ModelSpec {
+        name: "all-mpnet-base-v2",
+        repo: "sentence-transformers/all-mpnet-base-v2",
+        dimension: 768,
+        max_seq_len: 384,
+        files: onnx_files(),
+    },
+];
+
+pub fn find_model(name: &str) -> Option<&'static ModelSpec> {
+    KNOWN_MODELS.iter().find(|spec| spec.name == name)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstalledModel {
+    pub name: String,
+    pub dimension: usize,
+    pub max_seq_len: usize,
+    /// SHA-256 of each installed file, keyed by local file name
+    pub files: BTreeMap<String, String>,
+    pub installed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ModelStatus {
+    pub spec: &'static ModelSpec,
+    pub installed: Option<InstalledModel>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Registry {
+    models: BTreeMap<String, InstalledModel>,
+}
+
+/// Model file locations and shape, resolved from [`EmbeddingsConfig`].
+#[derive(Debug, Clone)]
+pub struct ResolvedModel {
+    pub name: String,
+    pub model_path: PathBuf,
+    pub tokenizer_path: PathBuf,
+    pub dimension: usize,
+    pub max_seq_len: usize,
+}
+
+/// Installs, lists and removes embedding models under a models directory.
+///
+/// Each model lives in `<models_dir>/<name>/`; `registry.json` records what
+/// is installed together with the checksum of every file.
+pub struct ModelManager {
+    models_dir: PathBuf,
+    base_url: String,
+    client: Client,
+    allow_unverified: bool,
+}
+
+impl ModelManager {
+    pub fn new(models_dir: impl Into<PathBuf>, base_url: &str) -> Self {
+        Self {
+            models_dir: models_dir.into(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            client: Client::new(),
+            allow_unverified: false,
+        }
+    }
+
+    /// Accept downloads that have no pinned, published or sidecar checksum.
+    /// Their hash is still recorded for [`verify`](Self::verify), but
+    /// nothing vouches for the file itself.
+    pub fn allow_unverified(mut self, allow: bool) -> Self {
+        self.allow_unverified = allow;
+        self
+    }
+
+    pub fn from_config(config: &EmbeddingsConfig) -> Self {
+        Self::new(&config.models_dir, &config.model_base_url)
+    }
+
+    pub fn models_dir(&self) -> &Path {
+        &self.models_dir
+    }
+
+    pub fn model_dir(&self, name: &str) -> PathBuf {
+        self.models_dir.join(name)
+    }
+
+    /// All known models with their install state.
+    pub fn list(&self) -> Result<Vec<ModelStatus>> {
+        let registry = self.load_registry()?;
+        Ok(KNOWN_MODELS
+            .iter()
+            .map(|spec| ModelStatus {
+                spec,
+                installed: registry.models.get(spec.name).cloned(),
+            })
+            .collect())
+    }
+
+    pub fn installed(&self, name: &str) -> Result<Option<InstalledModel>> {
+        Ok(self.load_registry()?.models.remove(name))
+    }
+
+    /// Downloads every file of the known model `name`, see
+    /// [`install_spec`](Self::install_spec).
+    pub async fn install(&self, name: &str) -> Result<InstalledModel> {
+        let spec = find_model(name).ok_or_else(|| {
+            anyhow!(
+                "Unknown model {:?}; known models: {}",
+                name,
+                KNOWN_MODELS
+                    .iter()
+                    .map(|spec| spec.name)
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )
+        })?;
+        self.install_spec(spec).await
+    }
+
+    /// Downloads every file of `spec`, verifies its SHA-256 and records the
+    /// model in the registry. Files are only moved into place once all of
+    /// them have been verified; a file with no checksum to check against
+    /// fails the install unless unverified installs are allowed.
+    pub async fn install_spec(&self, spec: &ModelSpec) -> Result<InstalledModel> {
+        let model_dir = self.model_dir(spec.name);
+        std::fs::create_dir_all(&model_dir)
+            .with_context(|| format!("Failed to create {:?}", model_dir))?;
+
+        let mut files = BTreeMap::new();
+        let mut downloaded = Vec::new();
+        for file in spec.files {
+            let url = format!(
+                "{}/{}/resolve/main/{}",
+                self.base_url, spec.repo, file.remote_path
+            );
+            let part_path = model_dir.join(format!("{}.part", file.local_name));
+
+            let result = self.download_verified(&url, file, &part_path).await;
+            let sha256 = match result {
+                Ok(sha256) => sha256,
+                Err(e) => {
+                    for (part, _) in &downloaded {
+                        let _ = std::fs::remove_file(part);
+                    }
+                    let _ = std::fs::remove_file(&part_path);
+                    return Err(e);
+                }
+            };
+
+            files.insert(file.local_name.to_string(), sha256);
+            downloaded.push((part_path, model_dir.join(file.local_name)));
+        }
+
+        for (part_path, final_path) in downloaded {
+            std::fs::rename(&part_path, &final_path)
+                .with_context(|| format!("Failed to move {:?} into place", final_path))?;
+        }
+
+        let installed = InstalledModel {
+            name: spec.name.to_string(),
+            dimension: spec.dimension,
+            max_seq_len: spec.max_seq_len,
+            files,
+            installed_at: Utc::now(),
+        };
+
+        let mut registry = self.load_registry()?;
+        registry
+            .models
+            .insert(spec.name.to_string(), installed.clone());
+        self.save_registry(&registry)?;
+
+        tracing::info!("Installed model {} into {:?}", spec.name, model_dir);
+        Ok(installed)
+    }
+
+    /// Deletes an installed model. Returns `false` if it was not installed.
+    pub fn remove(&self, name: &str) -> Result<bool> {
+        let mut registry = self.load_registry()?;
+        let model_dir = self.managed_model_dir(name, &registry)?;
+        let was_installed = registry.models.remove(name).is_some();
+
+        if model_dir.exists() {
+            std::fs::remove_dir_all(&model_dir)
+                .with_context(|| format!("Failed to remove {:?}", model_dir))?;
+        }
+
+        self.save_registry(&registry)?;
+        Ok(was_installed)
+    }
+
+    /// Re-hashes the files of an installed model and compares them with the
+    /// registry.
+    pub fn verify(&self, name: &str) -> Result<InstalledModel> {
+        let mut registry = self.load_registry()?;
+        let model_dir = self.managed_model_dir(name, &registry)?;
+        let installed = registry
+            .models
+            .remove(name)
+            .ok_or_else(|| anyhow!("Model {:?} is not installed", name))?;
+
+        for (file, expected) in &installed.files {
+            let path = model_dir.join(file);
+            let actual = file_sha256(&path)?;
+            if &actual != expected {
+                bail!(
+                    "Checksum mismatch for {:?}: expected {}, found {}",
+                    path,
+                    expected,
+                    actual
+                );
+            }
+        }
+
+        Ok(installed)
+    }
+
+    /// Directory of a known or registered model. Anything else is refused
+    /// before it can name a path outside the models directory.
+    fn managed_model_dir(&self, name: &str, registry: &Registry) -> Result<PathBuf> {
+        let is_plain_name =
+            !name.is_empty() && !name.contains(['/', '\\']) && !name.contains("..") && name != ".";
+        if !is_plain_name {
+            bail!("Invalid model name {:?}", name);
+        }
+        if find_model(name).is_none() && !registry.models.contains_key(name) {
+            bail!("Unknown model {:?}", name);
+        }
+        Ok(self.model_dir(name))
+    }
+
+    async fn download_verified(&self, url: &str, file: &ModelFile, path: &Path) -> Result<String> {
+        tracing::info!("Downloading {}", url);
+        let mut response = self
+            .client
+            .get(url)
+            .send()
+            .await
+            .with_context(|| format!("Failed to download {}", url))?;
+        if !response.status().is_success() {
+            bail!(
+                "Download of {} failed with status {}",
+                url,
+                response.status()
+            );
+        }
+
+        // HuggingFace reports the SHA-256 of LFS files in this header
+        let header_sha256 = response
+            .headers()
+            .get("x-linked-etag")
+            .and_then(|value| value.to_str().ok())
+            .map(|value| value.trim_matches('"').to_lowercase())
+            .filter(|value| is_sha256(value));
+
+        let mut hasher = Sha256::new();
+        let mut out =
+            std::fs::File::create(path).with_context(|| format!("Failed to create {:?}", path))?;
+        while let Some(chunk) = response.chunk().await? {
+            hasher.update(&chunk);
+            out.write_all(&chunk)?;
+        }
+        out.flush()?;
+        let actual = format!("{:x}", hasher.finalize());
+
+        let expected = match file.sha256.map(str::to_string).or(header_sha256) {
+            Some(expected) => Some(expected),
+            None => self.fetch_checksum_sidecar(url).await,
+        };
+
+        match expected {
+            Some(expected) if expected != actual => bail!(
+                "Checksum mismatch for {}: expected {}, downloaded {}",
+                url,
+                expected,
+                actual
+            ),
+            Some(_) => {}
+            None if self.allow_unverified => tracing::warn!(
+                "No published checksum for {}; recording {} for later verification",
+                url,
+                actual
+            ),
+            None => bail!(
+                "No checksum available for {} (downloaded {}); pass --allow-unverified to install it anyway",
+                url,
+                actual
+            ),
+        }
+
+        Ok(actual)
+    }
+
+    /// Mirrors may publish `<file>.sha256` next to each file.
+    async fn fetch_checksum_sidecar(&self, url: &str) -> Option<String> {
+        let response = self
+            .client
+            .get(format!("{}.sha256", url))
+            .send()
+            .await
+            .ok()?;
+        if !response.status().is_success() {
+            return None;
+        }
+        let body = response.text().await.ok()?;
+        let checksum = body.split_whitespace().next()?.to_lowercase();
+        is_sha256(&checksum).then_some(checksum)
+    }
+
+    fn load_registry(&self) -> Result<Registry> {
+        let path = self.models_dir.join(REGISTRY_FILE);
+        if !path.exists() {
+            return Ok(Registry::default());
+        }
+        let json = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&json).with_context(|| format!("Failed to parse {:?}", path))
+    }
+
+    fn save_registry(&self, registry: &Registry) -> Result<()> {
+        std::fs::create_dir_all(&self.models_dir)?;
+        let json = serde_json::to_string_pretty(registry)?;
+        std::fs::write(self.models_dir.join(REGISTRY_FILE), json)?;
+        Ok(())
+    }
+}
+
+/// Works out which model files to load and what dimension they produce.
+///
+/// With `embeddings.model` set, files and dimension come from the installed
+/// model and a configured `dimension` must agree with it. Otherwise
+/// `model_path` is used and `dimension` must be given.
+pub fn resolve(config: &EmbeddingsConfig) -> Result<ResolvedModel> {
+    let Some(name) = &config.model else {
+        let dimension = config.dimension.ok_or_else(|| {
+            anyhow!(
+                "Set embeddings.model or embeddings.dimension for {:?}",
+                config.model_path
+            )
+        })?;
+        let tokenizer_path = config.tokenizer_path.clone().unwrap_or_else(|| {
+            config
+                .model_path
+                .parent()
+                .unwrap_or_else(|| Path::new("."))
+                .join(TOKENIZER_FILE)
+        });
+        let name = config
+            .model_path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .unwrap_or("onnx")
+            .to_string();
+
+        return Ok(ResolvedModel {
+            name,
+            model_path: config.model_path.clone(),
+            tokenizer_path,
+            dimension,
+            max_seq_len: DEFAULT_MAX_SEQ_LEN,
+        });
+    };
+
+    let manager = ModelManager::from_config(config);
+    let installed = manager.installed(name)?.ok_or_else(|| {
+        anyhow!(
+            "Embedding model {:?} is not installed; run `cargo run --example models -- install {}`",
+            name,
+            name
+        )
+    })?;
+
+    if let Some(dimension) = config.dimension {
+        if dimension != installed.dimension {
+            bail!(
+                "embeddings.dimension is {} but model {} produces {}-dimensional vectors",
+                dimension,
+                name,
+                installed.dimension
+            );
+        }
+    }
+
+    let model_dir = manager.model_dir(name);
+    Ok(ResolvedModel {
+        name: installed.name,
+        model_path: model_dir.join(MODEL_FILE),
+        tokenizer_path: model_dir.join(TOKENIZER_FILE),
+        dimension: installed.dimension,
+        max_seq_len: installed.max_seq_len,
+    })
+}
+
+pub fn file_sha256(path: &Path) -> Result<String> {
+    let mut file =
+        std::fs::File::open(path).with_context(|| format!("Failed to open {:?}", path))?;
+    let mut hasher = Sha256::new();
+    std::io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+fn is_sha256(value: &str) -> bool {
+    value.len() == 64 && value.chars().all(|c| c.is_ascii_hexdigit())
 }
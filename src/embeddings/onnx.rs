@@ -0,0 +1,272 @@
+use anyhow::{anyhow, bail, Context, Result};
+use async_trait::async_trait;
+use ort::session::{builder::GraphOptimizationLevel, Session};
+use ort::value::Tensor;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use tokenizers::{Tokenizer, TruncationParams};
+
+use super::backend::{check_dimensions, normalize, EmbeddingBackend};
+use super::models::{self, file_sha256};
+use crate::config::EmbeddingsConfig;
+
+/// Runs a sentence-transformers ONNX export locally.
+pub struct OnnxBackend {
+    model: Arc<OnnxModel>,
+    model_name: String,
+    /// SHA-256 of the model file
+    model_hash: String,
+    dimension: usize,
+    batch_size: usize,
+    max_seq_len: usize,
+}
+
+struct OnnxModel {
+    session: Mutex<Session>,
+    tokenizer: Tokenizer,
+    /// Same tokenizer without truncation, used to measure long inputs
+    span_tokenizer: Tokenizer,
+    uses_token_type_ids: bool,
+}
+
+impl OnnxBackend {
+    /// Loads the ONNX model and tokenizer referenced by `config`, either a
+    /// managed model (`embeddings.model`) or the files at `model_path`.
+    ///
+    /// Fails if either file is missing; select the `hash` backend when real
+    /// embeddings are not needed.
+    pub fn new(config: &EmbeddingsConfig) -> Result<Self> {
+        if config.device != "cpu" {
+            bail!(
+                "Unsupported embedding device {:?}: only \"cpu\" is available",
+                config.device
+            );
+        }
+
+        let resolved = models::resolve(config)?;
+        let model = OnnxModel::load(
+            &resolved.model_path,
+            &resolved.tokenizer_path,
+            resolved.max_seq_len,
+        )?;
+
+        tracing::info!("Loaded embedding model from {:?}", resolved.model_path);
+
+        let model_hash = file_sha256(&resolved.model_path)?;
+
+        Ok(Self {
+            model: Arc::new(model),
+            model_name: resolved.name,
+            model_hash,
+            dimension: resolved.dimension,
+            batch_size: config.batch_size.max(1),
+            max_seq_len: resolved.max_seq_len,
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingBackend for OnnxBackend {
+    fn model_name(&self) -> &str {
+        &self.model_name
+    }
+
+    fn fingerprint(&self) -> &str {
+        &self.model_hash
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    fn max_tokens(&self) -> usize {
+        // Leave room for the [CLS] and [SEP] tokens
+        self.max_seq_len.saturating_sub(2)
+    }
+
+    fn token_spans(&self, text: &str) -> Result<Vec<(usize, usize)>> {
+        self.model.token_spans(text)
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        let model = Arc::clone(&self.model);
+        let texts: Vec<String> = texts.iter().map(|text| text.to_string()).collect();
+
+        // Inference is CPU-bound; keep it off the async worker threads
+        let mut embeddings = tokio::task::spawn_blocking(move || {
+            let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+            model.embed_batch(&texts)
+        })
+        .await??;
+
+        check_dimensions(&embeddings, self.dimension)?;
+        for embedding in embeddings.iter_mut() {
+            normalize(embedding);
+        }
+
+        Ok(embeddings)
+    }
+}
+
+impl OnnxModel {
+    fn load(model_path: &Path, tokenizer_path: &Path, max_seq_len: usize) -> Result<Self> {
+        if max_seq_len <= 2 {
+            bail!(
+                "Sequence length {} for {:?} leaves no room for text after [CLS] and [SEP]",
+                max_seq_len,
+                model_path
+            );
+        }
+        if !model_path.exists() {
+            bail!(
+                "Embedding model not found at {:?}; see models/README.md or run `cargo run --example models -- install <name>`",
+                model_path
+            );
+        }
+        if !tokenizer_path.exists() {
+            bail!(
+                "Tokenizer not found at {:?}; see models/README.md or run `cargo run --example models -- install <name>`",
+                tokenizer_path
+            );
+        }
+
+        let session = Session::builder()?
+            .with_optimization_level(GraphOptimizationLevel::Level3)?
+            .commit_from_file(model_path)
+            .with_context(|| format!("Failed to load ONNX model from {:?}", model_path))?;
+
+        let uses_token_type_ids = session
+            .inputs
+            .iter()
+            .any(|input| input.name == "token_type_ids");
+
+        let mut span_tokenizer = Tokenizer::from_file(tokenizer_path)
+            .map_err(|e| anyhow!("Failed to load tokenizer from {:?}: {}", tokenizer_path, e))?;
+        span_tokenizer
+            .with_truncation(None)
+            .map_err(|e| anyhow!("Failed to configure tokenizer truncation: {}", e))?;
+        span_tokenizer.with_padding(None);
+
+        let mut tokenizer = span_tokenizer.clone();
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: max_seq_len,
+                ..Default::default()
+            }))
+            .map_err(|e| anyhow!("Failed to configure tokenizer truncation: {}", e))?;
+
+        Ok(Self {
+            session: Mutex::new(session),
+            tokenizer,
+            span_tokenizer,
+            uses_token_type_ids,
+        })
+    }
+
+    fn token_spans(&self, text: &str) -> Result<Vec<(usize, usize)>> {
+        let encoding = self
+            .span_tokenizer
+            .encode(text, false)
+            .map_err(|e| anyhow!("Failed to tokenize input: {}", e))?;
+        Ok(encoding.get_offsets().to_vec())
+    }
+
+    /// Runs one padded forward pass over `texts`.
+    fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(|e| anyhow!("Failed to tokenize input: {}", e))?;
+
+        let batch = encodings.len();
+        let seq_len = encodings
+            .iter()
+            .map(|encoding| encoding.get_ids().len())
+            .max()
+            .unwrap_or(0);
+        let shape = [batch, seq_len];
+
+        // Pad every sequence to the longest one in the batch; padded
+        // positions get a zero attention mask and are skipped when pooling.
+        let mut input_ids = vec![0i64; batch * seq_len];
+        let mut attention_mask = vec![0i64; batch * seq_len];
+        let mut token_type_ids = vec![0i64; batch * seq_len];
+        for (row, encoding) in encodings.iter().enumerate() {
+            let offset = row * seq_len;
+            for (i, &id) in encoding.get_ids().iter().enumerate() {
+                input_ids[offset + i] = id as i64;
+            }
+            for (i, &mask) in encoding.get_attention_mask().iter().enumerate() {
+                attention_mask[offset + i] = mask as i64;
+            }
+            for (i, &type_id) in encoding.get_type_ids().iter().enumerate() {
+                token_type_ids[offset + i] = type_id as i64;
+            }
+        }
+
+        let mut inputs = ort::inputs! {
+            "input_ids" => Tensor::from_array((shape, input_ids))?,
+            "attention_mask" => Tensor::from_array((shape, attention_mask.clone()))?,
+        };
+        if self.uses_token_type_ids {
+            inputs.push((
+                "token_type_ids".into(),
+                Tensor::from_array((shape, token_type_ids))?.into(),
+            ));
+        }
+
+        let mut session = self
+            .session
+            .lock()
+            .map_err(|_| anyhow!("ONNX session lock poisoned"))?;
+        let outputs = session.run(inputs)?;
+
+        // last_hidden_state: [batch, seq_len, hidden]
+        let (output_shape, hidden_states) = outputs[0].try_extract_tensor::<f32>()?;
+        if output_shape.len() != 3
+            || output_shape[0] as usize != batch
+            || output_shape[1] as usize != seq_len
+        {
+            bail!("Unexpected model output shape {:?}", output_shape);
+        }
+        let hidden = output_shape[2] as usize;
+
+        Ok(hidden_states
+            .chunks_exact(seq_len * hidden)
+            .zip(attention_mask.chunks_exact(seq_len))
+            .map(|(states, mask)| mean_pool(states, mask, hidden))
+            .collect())
+    }
+}
+
+/// Averages token embeddings, ignoring padding positions.
+fn mean_pool(hidden_states: &[f32], attention_mask: &[i64], hidden: usize) -> Vec<f32> {
+    let mut pooled = vec![0.0f32; hidden];
+    let mut count = 0.0f32;
+
+    for (token, &mask) in hidden_states.chunks_exact(hidden).zip(attention_mask) {
+        if mask == 0 {
+            continue;
+        }
+        for (sum, value) in pooled.iter_mut().zip(token) {
+            *sum += value;
+        }
+        count += 1.0;
+    }
+
+    if count > 0.0 {
+        for value in pooled.iter_mut() {
+            *value /= count;
+        }
+    }
+
+    pooled
+}
@@ -0,0 +1,122 @@
+use anyhow::{bail, Context, Result};
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use super::backend::{check_dimensions, normalize, EmbeddingBackend};
+use crate::config::EmbeddingsConfig;
+
+/// Calls an OpenAI-compatible `/v1/embeddings` endpoint.
+pub struct OpenAiBackend {
+    client: Client,
+    url: String,
+    model: String,
+    api_key: Option<String>,
+    dimension: usize,
+    batch_size: usize,
+}
+
+#[derive(Serialize)]
+struct EmbeddingsRequest<'a> {
+    model: &'a str,
+    input: &'a [&'a str],
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+impl OpenAiBackend {
+    pub fn new(config: &EmbeddingsConfig) -> Result<Self> {
+        let openai = &config.openai;
+        if openai.model.is_empty() {
+            bail!("embeddings.openai.model must be set to use the openai backend");
+        }
+        let Some(dimension) = config.dimension else {
+            bail!("embeddings.dimension must be set to use the openai backend");
+        };
+
+        Ok(Self {
+            client: Client::new(),
+            url: format!("{}/v1/embeddings", openai.base_url.trim_end_matches('/')),
+            model: openai.model.clone(),
+            api_key: (!openai.api_key.is_empty()).then(|| openai.api_key.clone()),
+            dimension,
+            batch_size: config.batch_size.max(1),
+        })
+    }
+}
+
+#[async_trait]
+impl EmbeddingBackend for OpenAiBackend {
+    fn model_name(&self) -> &str {
+        &self.model
+    }
+
+    fn fingerprint(&self) -> &str {
+        &self.url
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn batch_size(&self) -> usize {
+        self.batch_size
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut request = self.client.post(&self.url).json(&EmbeddingsRequest {
+            model: &self.model,
+            input: texts,
+        });
+        if let Some(api_key) = &self.api_key {
+            request = request.bearer_auth(api_key);
+        }
+
+        let response = request
+            .send()
+            .await
+            .with_context(|| format!("Failed to reach embeddings endpoint {}", self.url))?;
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            bail!("Embeddings endpoint returned {}: {}", status, body);
+        }
+
+        let mut body: EmbeddingsResponse = response
+            .json()
+            .await
+            .context("Failed to decode embeddings response")?;
+        if body.data.len() != texts.len() {
+            bail!(
+                "Embeddings endpoint returned {} vectors for {} inputs",
+                body.data.len(),
+                texts.len()
+            );
+        }
+
+        // The API does not promise to keep input order
+        body.data.sort_by_key(|item| item.index);
+        let mut embeddings: Vec<Vec<f32>> =
+            body.data.into_iter().map(|item| item.embedding).collect();
+
+        check_dimensions(&embeddings, self.dimension)?;
+        for embedding in embeddings.iter_mut() {
+            normalize(embedding);
+        }
+
+        Ok(embeddings)
+    }
+}
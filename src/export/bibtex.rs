@@ -0,0 +1,461 @@
+use anyhow::Result;
+use chrono::Datelike;
+
+use super::exporter::{abs_url, citation_keys, non_empty, AuthorName, Exporter};
+use crate::arxiv::models::{ArxivId, Paper};
+use crate::citations::extract::find_arxiv_ids;
+use crate::config::ExportFormat;
+
+/// Words skipped when picking the title word of a citation key.
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "by", "do", "for", "from", "how", "in", "is", "of", "on",
+    "or", "the", "to", "towards", "via", "what", "when", "why", "with",
+];
+
+/// Accented letters with their LaTeX accent command and base letter,
+/// position by position.
+const ACCENTS: &[(&str, &str, &str)] = &[
+    ("'", "áéíóúýćńśźÁÉÍÓÚÝĆŃŚŹ", "aeiouycnszAEIOUYCNSZ"),
+    ("`", "àèìòùÀÈÌÒÙ", "aeiouAEIOU"),
+    ("\"", "äëïöüÿÄËÏÖÜ", "aeiouyAEIOU"),
+    ("^", "âêîôûÂÊÎÔÛ", "aeiouAEIOU"),
+    ("~", "ãñõÃÑÕ", "anoANO"),
+    ("c", "çşÇŞ", "csCS"),
+    ("v", "čšžřěňČŠŽŘĚŇ", "cszrenCSZREN"),
+    ("r", "åÅ", "aA"),
+];
+
+/// Letters without a base letter, with their LaTeX and ASCII spellings.
+const SPECIAL_LETTERS: &[(char, &str, &str)] = &[
+    ('ß', "{\\ss}", "ss"),
+    ('æ', "{\\ae}", "ae"),
+    ('Æ', "{\\AE}", "AE"),
+    ('œ', "{\\oe}", "oe"),
+    ('Œ', "{\\OE}", "OE"),
+    ('ø', "{\\o}", "o"),
+    ('Ø', "{\\O}", "O"),
+    ('ł', "{\\l}", "l"),
+    ('Ł', "{\\L}", "L"),
+];
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryType {
+    /// A preprint known only by its arXiv ID
+    Misc,
+    /// A paper with a journal reference
+    Article,
+}
+
+impl EntryType {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            EntryType::Misc => "misc",
+            EntryType::Article => "article",
+        }
+    }
+}
+
+/// A BibTeX entry. Field values are stored as written, braces included.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BibEntry {
+    pub entry_type: EntryType,
+    pub key: String,
+    pub fields: Vec<(&'static str, String)>,
+}
+
+impl BibEntry {
+    /// The entry for an arXiv paper: `@article` when it has a journal
+    /// reference, `@misc` otherwise, both carrying the arXiv eprint fields.
+    pub fn from_paper(paper: &Paper) -> Self {
+        let journal_ref = non_empty(&paper.journal_ref);
+        let entry_type = if journal_ref.is_some() {
+            EntryType::Article
+        } else {
+            EntryType::Misc
+        };
+
+        let mut fields = vec![("title", format!("{{{}}}", protect_case(&paper.title)))];
+        if !paper.authors.is_empty() {
+            let authors: Vec<String> = paper.authors.iter().map(|a| escape_latex(a)).collect();
+            fields.push(("author", format!("{{{}}}", authors.join(" and "))));
+        }
+        if let Some(journal_ref) = journal_ref {
+            fields.push(("journal", format!("{{{}}}", escape_latex(journal_ref))));
+        }
+        fields.push(("year", format!("{{{}}}", paper.published.year())));
+        fields.push((
+            "month",
+            MONTHS[paper.published.month0() as usize].to_string(),
+        ));
+        fields.push(("eprint", format!("{{{}}}", paper.base_id())));
+        fields.push(("archivePrefix", "{arXiv}".to_string()));
+        if !paper.primary_category.is_empty() {
+            fields.push(("primaryClass", format!("{{{}}}", paper.primary_category)));
+        }
+        if let Some(doi) = non_empty(&paper.doi) {
+            fields.push(("doi", format!("{{{}}}", doi)));
+        }
+        fields.push(("url", format!("{{{}}}", abs_url(paper))));
+
+        Self {
+            entry_type,
+            key: citation_key(paper),
+            fields,
+        }
+    }
+
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| *field == name)
+            .map(|(_, value)| value.as_str())
+    }
+}
+
+impl std::fmt::Display for BibEntry {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "@{}{{{},", self.entry_type.as_str(), self.key)?;
+        for (name, value) in &self.fields {
+            writeln!(f, "  {} = {},", name, value)?;
+        }
+        write!(f, "}}")
+    }
+}
+
+pub struct BibtexExporter;
+
+impl Exporter for BibtexExporter {
+    fn format(&self) -> ExportFormat {
+        ExportFormat::Bibtex
+    }
+
+    fn export(&self, papers: &[Paper]) -> Result<String> {
+        Ok(bibliography(papers))
+    }
+}
+
+/// An entry read from a `.bib` file. Field names are lowercase and values
+/// have their outer braces or quotes removed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedEntry {
+    /// Lowercase, e.g. `article`
+    pub entry_type: String,
+    pub key: String,
+    pub fields: Vec<(String, String)>,
+}
+
+impl ParsedEntry {
+    pub fn field(&self, name: &str) -> Option<&str> {
+        self.fields
+            .iter()
+            .find(|(field, _)| field == name)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// The arXiv paper the entry cites: its `eprint` when it is an arXiv
+    /// eprint, else an arXiv ID, DOI or URL found in the other fields.
+    pub fn arxiv_id(&self) -> Option<ArxivId> {
+        let archive = self
+            .field("archiveprefix")
+            .or(self.field("eprinttype"))
+            .unwrap_or("arxiv");
+        let eprint = self
+            .field("eprint")
+            .filter(|_| archive.eq_ignore_ascii_case("arxiv"));
+        let direct = [eprint, self.field("arxiv"), self.field("arxivid")];
+        let mentioned = ["doi", "url", "journal", "note", "howpublished"].map(|f| self.field(f));
+        direct
+            .into_iter()
+            .flatten()
+            .find_map(|value| value.parse().ok())
+            .or_else(|| {
+                mentioned
+                    .into_iter()
+                    .flatten()
+                    .flat_map(find_arxiv_ids)
+                    .find_map(|id| id.parse().ok())
+            })
+    }
+}
+
+/// Reads the entries of a `.bib` file, skipping `@string`, `@preamble` and
+/// `@comment` blocks and anything it cannot make sense of.
+pub fn parse_bibliography(text: &str) -> Vec<ParsedEntry> {
+    let mut entries = Vec::new();
+    let mut rest = text;
+
+    while let Some(at) = rest.find('@') {
+        rest = &rest[at + 1..];
+        let type_end = rest
+            .find(|c: char| !c.is_ascii_alphanumeric())
+            .unwrap_or(rest.len());
+        let entry_type = rest[..type_end].to_lowercase();
+        let body = rest[type_end..].trim_start();
+        let Some(close) = body.chars().next().and_then(|open| match open {
+            '{' => Some('}'),
+            '(' => Some(')'),
+            _ => None,
+        }) else {
+            continue;
+        };
+        let Some(end) = closing_delimiter(body, close) else {
+            break;
+        };
+        let contents = &body[1..end];
+        rest = &body[end + 1..];
+
+        if entry_type.is_empty() || matches!(entry_type.as_str(), "string" | "preamble" | "comment")
+        {
+            continue;
+        }
+        let Some((key, fields)) = contents.split_once(',') else {
+            continue;
+        };
+        entries.push(ParsedEntry {
+            entry_type,
+            key: key.trim().to_string(),
+            fields: parse_fields(fields),
+        });
+    }
+
+    entries
+}
+
+/// Index of the delimiter closing the block that `body` opens, skipping
+/// nested braces.
+fn closing_delimiter(body: &str, close: char) -> Option<usize> {
+    let mut depth = 0i32;
+    for (i, c) in body.char_indices().skip(1) {
+        match c {
+            '{' => depth += 1,
+            '}' if depth > 0 => depth -= 1,
+            c if c == close && depth == 0 => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// `name = value` pairs; values may be braced, quoted, bare or joined
+/// with `#`.
+fn parse_fields(text: &str) -> Vec<(String, String)> {
+    let mut fields = Vec::new();
+    let mut chars = text.char_indices().peekable();
+
+    loop {
+        // Field name, up to `=`
+        let mut name = String::new();
+        for (_, c) in chars.by_ref() {
+            if c == '=' {
+                break;
+            }
+            if c != ',' {
+                name.push(c);
+            }
+        }
+        let name = name.trim().to_lowercase();
+        if name.is_empty() {
+            return fields;
+        }
+
+        // Value parts, up to the next `,` outside braces and quotes
+        let mut value = String::new();
+        let mut depth = 0i32;
+        let mut quoted = false;
+        for (_, c) in chars.by_ref() {
+            match c {
+                '{' => {
+                    if depth > 0 || quoted {
+                        value.push(c);
+                    }
+                    depth += 1;
+                }
+                '}' => {
+                    depth -= 1;
+                    if depth > 0 || quoted {
+                        value.push(c);
+                    }
+                }
+                '"' if depth == 0 => quoted = !quoted,
+                ',' if depth == 0 && !quoted => break,
+                '#' if depth == 0 && !quoted => {}
+                c if depth == 0 && !quoted && c.is_whitespace() => {}
+                c => value.push(c),
+            }
+        }
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+        fields.push((name, value));
+    }
+}
+
+/// A citation key in the usual `lastnameYEARword` form, e.g.
+/// `vaswani2017attention`: the first author's last name and the first
+/// title word that is not a stop word, folded to lowercase ASCII.
+///
+/// The key depends only on the paper, so exporting it again gives the same
+/// key. [`citation_keys`] disambiguates papers that share a key.
+pub fn citation_key(paper: &Paper) -> String {
+    let author = paper
+        .authors
+        .first()
+        .map(|name| key_part(&AuthorName::parse(name).family))
+        .filter(|part| !part.is_empty())
+        .unwrap_or_else(|| "anon".to_string());
+    let word = paper
+        .title
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '/')
+        .map(key_part)
+        .find(|word| !word.is_empty() && !STOP_WORDS.contains(&word.as_str()))
+        .unwrap_or_default();
+    format!("{}{}{}", author, paper.published.year(), word)
+}
+
+/// BibTeX entries for `papers`, separated by blank lines, with keys told
+/// apart by [`citation_keys`].
+pub fn bibliography(papers: &[Paper]) -> String {
+    papers
+        .iter()
+        .zip(citation_keys(papers))
+        .map(|(paper, key)| {
+            let mut entry = BibEntry::from_paper(paper);
+            entry.key = key;
+            format!("{}\n", entry)
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Escapes text for a BibTeX field.
+///
+/// arXiv metadata is itself written in TeX, so `$...$` math, control
+/// sequences such as `\emph` and balanced braces are kept. Other characters
+/// special to LaTeX are escaped, accented letters become accent commands
+/// and runs of whitespace collapse to one space.
+pub fn escape_latex(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let math = text.matches('$').count() - text.matches("\\$").count();
+    let keep_math = math > 0 && math % 2 == 0;
+    let keep_braces = braces_balanced(&text);
+
+    let mut out = String::with_capacity(text.len());
+    let mut in_math = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if in_math && c != '$' {
+            out.push(c);
+            continue;
+        }
+        match c {
+            '\\' => match chars.peek() {
+                Some(&next) if next.is_ascii_alphabetic() || "'\"`^~=.&%$#_{}\\".contains(next) => {
+                    out.push(c);
+                    out.push(next);
+                    chars.next();
+                }
+                _ => out.push_str("\\textbackslash{}"),
+            },
+            '$' if keep_math => {
+                in_math = !in_math;
+                out.push(c);
+            }
+            '{' | '}' if keep_braces => out.push(c),
+            '&' | '%' | '$' | '#' | '_' | '{' | '}' => {
+                out.push('\\');
+                out.push(c);
+            }
+            '~' => out.push_str("\\textasciitilde{}"),
+            '^' => out.push_str("\\textasciicircum{}"),
+            c if c.is_ascii() => out.push(c),
+            c => match latex_letter(c) {
+                Some(latex) => out.push_str(&latex),
+                None => out.push(c),
+            },
+        }
+    }
+    out
+}
+
+/// Escapes a title, bracing words with capitals after their first letter
+/// (`BERT`, `ImageNet`) so bibliography styles keep their case.
+fn protect_case(title: &str) -> String {
+    escape_latex(title)
+        .split(' ')
+        .map(|word| {
+            let plain = word
+                .chars()
+                .all(|c| c.is_alphanumeric() || "-:,.;?!()'".contains(c));
+            let inner_caps = word.chars().skip(1).any(|c| c.is_uppercase());
+            if plain && inner_caps {
+                format!("{{{}}}", word)
+            } else {
+                word.to_string()
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The LaTeX spelling of an accented or special letter.
+fn latex_letter(c: char) -> Option<String> {
+    if let Some((_, latex, _)) = SPECIAL_LETTERS.iter().find(|(letter, _, _)| *letter == c) {
+        return Some(latex.to_string());
+    }
+    let (command, base) = accent(c)?;
+    let base = match base {
+        // Accents go on a dotless i
+        'i' => "\\i".to_string(),
+        base => base.to_string(),
+    };
+    Some(if command.chars().all(char::is_alphabetic) {
+        format!("{{\\{}{{{}}}}}", command, base)
+    } else {
+        format!("{{\\{}{}}}", command, base)
+    })
+}
+
+fn accent(c: char) -> Option<(&'static str, char)> {
+    ACCENTS.iter().find_map(|(command, accented, base)| {
+        let position = accented.chars().position(|a| a == c)?;
+        Some((*command, base.chars().nth(position)?))
+    })
+}
+
+/// Lowercase ASCII letters and digits of a word, with accents dropped.
+fn key_part(word: &str) -> String {
+    let mut part = String::new();
+    for c in word.chars() {
+        if c.is_ascii_alphanumeric() {
+            part.push(c.to_ascii_lowercase());
+        } else if let Some((_, _, ascii)) = SPECIAL_LETTERS.iter().find(|(l, _, _)| *l == c) {
+            part.push_str(&ascii.to_lowercase());
+        } else if let Some((_, base)) = accent(c) {
+            part.push(base.to_ascii_lowercase());
+        }
+    }
+    part
+}
+
+/// `a`, `b`, ..., `z`, `aa`, `ab`, ...
+fn braces_balanced(text: &str) -> bool {
+    let mut depth = 0i32;
+    let mut escaped = false;
+    for c in text.chars() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '{' => depth += 1,
+            '}' => {
+                depth -= 1;
+                if depth < 0 {
+                    return false;
+                }
+            }
+            _ => {}
+        }
+    }
+    depth == 0
+}
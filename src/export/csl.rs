@@ -0,0 +1,86 @@
+use anyhow::Result;
+use chrono::Datelike;
+use serde_json::{json, Map, Value};
+
+use super::exporter::{abs_url, citation_keys, non_empty, single_line, AuthorName, Exporter};
+use crate::arxiv::models::Paper;
+use crate::config::ExportFormat;
+
+/// A CSL-JSON array, as read by Zotero, pandoc and citeproc. Preprints are
+/// `article` items published by arXiv, papers with a journal reference
+/// `article-journal` items.
+pub struct CslJsonExporter;
+
+impl Exporter for CslJsonExporter {
+    fn format(&self) -> ExportFormat {
+        ExportFormat::CslJson
+    }
+
+    fn export(&self, papers: &[Paper]) -> Result<String> {
+        let items: Vec<Value> = papers
+            .iter()
+            .zip(citation_keys(papers))
+            .map(|(paper, key)| item(paper, &key))
+            .collect();
+        Ok(serde_json::to_string_pretty(&items)? + "\n")
+    }
+}
+
+/// The CSL item for one paper, identified by `key`.
+pub fn item(paper: &Paper, key: &str) -> Value {
+    let journal_ref = non_empty(&paper.journal_ref);
+    let authors: Vec<Value> = paper
+        .authors
+        .iter()
+        .map(|author| {
+            let name = AuthorName::parse(author);
+            let mut value = json!({ "family": name.family, "given": name.given });
+            if !name.suffix.is_empty() {
+                value["suffix"] = json!(name.suffix);
+            }
+            value
+        })
+        .collect();
+
+    let mut item = Map::new();
+    item.insert("id".into(), json!(key));
+    let item_type = if journal_ref.is_some() {
+        "article-journal"
+    } else {
+        "article"
+    };
+    item.insert("type".into(), json!(item_type));
+    item.insert("title".into(), json!(single_line(&paper.title)));
+    item.insert("author".into(), Value::Array(authors));
+    item.insert(
+        "issued".into(),
+        json!({ "date-parts": [[
+            paper.published.year(),
+            paper.published.month(),
+            paper.published.day()
+        ]] }),
+    );
+    if !paper.abstract_text.trim().is_empty() {
+        item.insert("abstract".into(), json!(single_line(&paper.abstract_text)));
+    }
+    match journal_ref {
+        Some(journal_ref) => {
+            item.insert("container-title".into(), json!(single_line(journal_ref)));
+        }
+        None => {
+            item.insert("publisher".into(), json!("arXiv"));
+        }
+    }
+    item.insert("number".into(), json!(format!("arXiv:{}", paper.base_id())));
+    if let Some(doi) = non_empty(&paper.doi) {
+        item.insert("DOI".into(), json!(doi));
+    }
+    item.insert("URL".into(), json!(abs_url(paper)));
+    if !paper.categories.is_empty() {
+        item.insert("keyword".into(), json!(paper.categories.join(", ")));
+    }
+    if let Some(comment) = non_empty(&paper.comment) {
+        item.insert("note".into(), json!(single_line(comment)));
+    }
+    Value::Object(item)
+}
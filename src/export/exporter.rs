@@ -0,0 +1,187 @@
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use super::bibtex::{citation_key, BibtexExporter};
+use super::csl::CslJsonExporter;
+use super::markdown::MarkdownExporter;
+use super::ris::RisExporter;
+use crate::arxiv::models::Paper;
+use crate::config::{ExportConfig, ExportFormat};
+use crate::db::models::StoredPaper;
+use crate::db::store::PaperStore;
+
+/// Turns papers into a citation file.
+pub trait Exporter: Send + Sync {
+    fn format(&self) -> ExportFormat;
+
+    /// The whole file for `papers`, in order.
+    fn export(&self, papers: &[Paper]) -> Result<String>;
+}
+
+/// The exporter for `format`, set up from the `[export]` config.
+pub fn exporter(format: ExportFormat, config: &ExportConfig) -> Result<Box<dyn Exporter>> {
+    Ok(match format {
+        ExportFormat::Bibtex => Box::new(BibtexExporter),
+        ExportFormat::Ris => Box::new(RisExporter),
+        ExportFormat::CslJson => Box::new(CslJsonExporter),
+        ExportFormat::Markdown => Box::new(MarkdownExporter::new(&config.markdown_template)?),
+    })
+}
+
+/// Exports `papers` to `path`, creating its directory if needed.
+pub fn write_export(exporter: &dyn Exporter, papers: &[Paper], path: &Path) -> Result<()> {
+    let contents = exporter.export(papers)?;
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, contents).with_context(|| format!("Failed to write {:?}", path))
+}
+
+/// The citation key of each paper in `papers`, unique within the file.
+///
+/// Papers sharing a [`citation_key`] are told apart by a letter suffix,
+/// given in arXiv ID order so that adding a newer paper leaves existing
+/// keys alone: `smith2024deep`, `smith2024deepa`, `smith2024deepb`.
+pub fn citation_keys(papers: &[Paper]) -> Vec<String> {
+    let mut keys: Vec<String> = papers.iter().map(citation_key).collect();
+
+    let mut by_key: HashMap<String, Vec<usize>> = HashMap::new();
+    for (index, key) in keys.iter().enumerate() {
+        by_key.entry(key.clone()).or_default().push(index);
+    }
+    for indices in by_key.values_mut().filter(|indices| indices.len() > 1) {
+        indices.sort_by(|&a, &b| papers[a].base_id().cmp(papers[b].base_id()));
+        for (rank, &index) in indices.iter().enumerate().skip(1) {
+            keys[index].push_str(&key_suffix(rank - 1));
+        }
+    }
+    keys
+}
+
+/// `a`, `b`, ..., `z`, `aa`, `ab`, ...
+fn key_suffix(mut n: usize) -> String {
+    let mut suffix = String::new();
+    loop {
+        suffix.insert(0, (b'a' + (n % 26) as u8) as char);
+        if n < 26 {
+            return suffix;
+        }
+        n = n / 26 - 1;
+    }
+}
+
+/// A file in `dir` named after `name`, e.g. `Reading list` becomes
+/// `reading-list.bib`.
+pub fn export_path(dir: &Path, name: &str, format: ExportFormat) -> PathBuf {
+    let mut stem = String::new();
+    for c in name.trim().chars() {
+        if c.is_alphanumeric() || c == '.' {
+            stem.extend(c.to_lowercase());
+        } else if !stem.is_empty() && !stem.ends_with('-') {
+            stem.push('-');
+        }
+    }
+    let stem = stem.trim_end_matches('-');
+    let stem = if stem.is_empty() { "papers" } else { stem };
+    dir.join(format!("{}.{}", stem, format.extension()))
+}
+
+/// Rebuilds stored papers, such as search results, with their metadata.
+pub async fn load_papers(db: &dyn PaperStore, papers: &[StoredPaper]) -> Result<Vec<Paper>> {
+    let mut loaded = Vec::with_capacity(papers.len());
+    for paper in papers {
+        let metadata = db.require_metadata(&paper.arxiv_id).await?;
+        loaded.push(paper.to_paper(&metadata));
+    }
+    Ok(loaded)
+}
+
+/// Exports every paper of a collection to `path`, oldest addition first.
+/// Returns the number of papers written.
+pub async fn export_collection(
+    db: &dyn PaperStore,
+    exporter: &dyn Exporter,
+    collection_name: &str,
+    path: &Path,
+) -> Result<usize> {
+    let entries = db.get_collection_papers(collection_name).await?;
+    let stored: Vec<StoredPaper> = entries.into_iter().map(|entry| entry.paper).collect();
+    let papers = load_papers(db, &stored).await?;
+    write_export(exporter, &papers, path)?;
+    Ok(papers.len())
+}
+
+/// An author name written `Given Family`, as arXiv lists them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorName {
+    pub given: String,
+    pub family: String,
+    /// `Jr.`, `III` and the like
+    pub suffix: String,
+}
+
+impl AuthorName {
+    pub fn parse(name: &str) -> Self {
+        if let Some((family, given)) = name.split_once(',') {
+            return Self {
+                given: given.trim().to_string(),
+                family: family.trim().to_string(),
+                suffix: String::new(),
+            };
+        }
+
+        let mut words: Vec<&str> = name.split_whitespace().collect();
+        let suffix = match words.last() {
+            Some(word)
+                if words.len() > 1
+                    && matches!(*word, "Jr." | "Jr" | "Sr." | "Sr" | "II" | "III" | "IV") =>
+            {
+                words.pop().unwrap_or_default()
+            }
+            _ => "",
+        };
+        let mut family = vec![words.pop().unwrap_or_default()];
+        // Particles such as `van der` belong to the family name
+        while words.len() > 1
+            && words
+                .last()
+                .is_some_and(|w| w.starts_with(char::is_lowercase))
+        {
+            family.insert(0, words.pop().unwrap_or_default());
+        }
+        Self {
+            given: words.join(" "),
+            family: family.join(" "),
+            suffix: suffix.to_string(),
+        }
+    }
+
+    /// `Family, Given` or `Family, Given, Suffix`.
+    pub fn inverted(&self) -> String {
+        let mut name = self.family.clone();
+        for part in [&self.given, &self.suffix] {
+            if !part.is_empty() {
+                name.push_str(", ");
+                name.push_str(part);
+            }
+        }
+        name
+    }
+}
+
+/// Collapses line breaks and runs of spaces in arXiv metadata.
+pub(crate) fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+pub(crate) fn abs_url(paper: &Paper) -> String {
+    format!("https://arxiv.org/abs/{}", paper.base_id())
+}
+
+pub(crate) fn non_empty(value: &Option<String>) -> Option<&str> {
+    value
+        .as_deref()
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
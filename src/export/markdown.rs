@@ -0,0 +1,140 @@
+use anyhow::{bail, Result};
+use chrono::Datelike;
+
+use super::bibtex::citation_key;
+use super::exporter::{abs_url, single_line, Exporter};
+use crate::arxiv::models::Paper;
+use crate::config::ExportFormat;
+
+/// Placeholders a Markdown template may use.
+pub const PLACEHOLDERS: &[&str] = &[
+    "title",
+    "authors",
+    "year",
+    "published",
+    "arxiv_id",
+    "url",
+    "pdf_url",
+    "abstract",
+    "categories",
+    "primary_category",
+    "doi",
+    "journal_ref",
+    "comment",
+    "key",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+enum Segment {
+    Text(String),
+    Placeholder(&'static str),
+}
+
+/// Markdown notes, one per paper, rendered from a template such as
+/// `## {title}\n\n{authors} ({year})\n`.
+///
+/// `{name}` is replaced by the paper's field, see [`PLACEHOLDERS`]; missing
+/// optional fields render empty. `{{` and `}}` produce literal braces.
+pub struct MarkdownExporter {
+    template: Vec<Segment>,
+}
+
+impl MarkdownExporter {
+    /// Fails on unknown placeholders and unbalanced braces.
+    pub fn new(template: &str) -> Result<Self> {
+        let mut segments = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) => name.push(c),
+                            None => bail!("Unclosed {{{} in Markdown template", name),
+                        }
+                    }
+                    let Some(placeholder) = PLACEHOLDERS.iter().find(|p| **p == name.trim()) else {
+                        bail!(
+                            "Unknown placeholder {{{}}} in Markdown template (expected one of {})",
+                            name,
+                            PLACEHOLDERS.join(", ")
+                        );
+                    };
+                    if !text.is_empty() {
+                        segments.push(Segment::Text(std::mem::take(&mut text)));
+                    }
+                    segments.push(Segment::Placeholder(placeholder));
+                }
+                '}' => bail!("Unmatched }} in Markdown template; write }}}} for a brace"),
+                c => text.push(c),
+            }
+        }
+        if !text.is_empty() {
+            segments.push(Segment::Text(text));
+        }
+
+        Ok(Self { template: segments })
+    }
+
+    pub fn render(&self, paper: &Paper) -> String {
+        let mut out = String::new();
+        for segment in &self.template {
+            match segment {
+                Segment::Text(text) => out.push_str(text),
+                Segment::Placeholder(name) => out.push_str(&field(paper, name)),
+            }
+        }
+        out
+    }
+}
+
+impl Exporter for MarkdownExporter {
+    fn format(&self) -> ExportFormat {
+        ExportFormat::Markdown
+    }
+
+    fn export(&self, papers: &[Paper]) -> Result<String> {
+        Ok(papers
+            .iter()
+            .map(|paper| {
+                let note = self.render(paper);
+                let note = note.trim_matches('\n');
+                format!("{}\n", note)
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+fn field(paper: &Paper, name: &str) -> String {
+    let optional = |value: &Option<String>| value.as_deref().map(single_line).unwrap_or_default();
+    match name {
+        "title" => single_line(&paper.title),
+        "authors" => paper.authors_string(),
+        "year" => paper.published.year().to_string(),
+        "published" => paper.published.format("%Y-%m-%d").to_string(),
+        "arxiv_id" => paper.base_id().to_string(),
+        "url" => abs_url(paper),
+        "pdf_url" => paper.pdf_url.clone(),
+        "abstract" => single_line(&paper.abstract_text),
+        "categories" => paper.categories_string(),
+        "primary_category" => paper.primary_category.clone(),
+        "doi" => optional(&paper.doi),
+        "journal_ref" => optional(&paper.journal_ref),
+        "comment" => optional(&paper.comment),
+        "key" => citation_key(paper),
+        _ => String::new(),
+    }
+}
@@ -0,0 +1,5 @@
+pub mod bibtex;
+pub mod csl;
+pub mod exporter;
+pub mod markdown;
+pub mod ris;
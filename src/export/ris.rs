@@ -0,0 +1,73 @@
+use anyhow::Result;
+use chrono::Datelike;
+
+use super::exporter::{abs_url, citation_keys, non_empty, single_line, AuthorName, Exporter};
+use crate::arxiv::models::Paper;
+use crate::config::ExportFormat;
+
+/// RIS records, as read by Zotero, EndNote and Mendeley: `JOUR` for papers
+/// with a journal reference, `UNPB` (unpublished work) for preprints.
+pub struct RisExporter;
+
+impl Exporter for RisExporter {
+    fn format(&self) -> ExportFormat {
+        ExportFormat::Ris
+    }
+
+    fn export(&self, papers: &[Paper]) -> Result<String> {
+        Ok(papers
+            .iter()
+            .zip(citation_keys(papers))
+            .map(|(paper, key)| record(paper, &key))
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
+
+/// The RIS record for one paper with ID `key`, ending with its `ER` line.
+pub fn record(paper: &Paper, key: &str) -> String {
+    let journal_ref = non_empty(&paper.journal_ref);
+    let mut lines: Vec<(&str, String)> = Vec::new();
+
+    let entry_type = if journal_ref.is_some() {
+        "JOUR"
+    } else {
+        "UNPB"
+    };
+    lines.push(("TY", entry_type.to_string()));
+    lines.push(("ID", key.to_string()));
+    lines.push(("TI", single_line(&paper.title)));
+    for author in &paper.authors {
+        lines.push(("AU", AuthorName::parse(author).inverted()));
+    }
+    lines.push(("PY", paper.published.year().to_string()));
+    lines.push(("DA", paper.published.format("%Y/%m/%d/").to_string()));
+    if !paper.abstract_text.trim().is_empty() {
+        lines.push(("AB", single_line(&paper.abstract_text)));
+    }
+    if let Some(journal_ref) = journal_ref {
+        lines.push(("JO", single_line(journal_ref)));
+    }
+    if let Some(doi) = non_empty(&paper.doi) {
+        lines.push(("DO", doi.to_string()));
+    }
+    lines.push(("UR", abs_url(paper)));
+    if !paper.pdf_url.is_empty() {
+        lines.push(("L1", paper.pdf_url.clone()));
+    }
+    for category in &paper.categories {
+        lines.push(("KW", category.clone()));
+    }
+    if let Some(comment) = non_empty(&paper.comment) {
+        lines.push(("N1", single_line(comment)));
+    }
+    lines.push(("AN", format!("arXiv:{}", paper.base_id())));
+    lines.push(("DB", "arXiv.org".to_string()));
+
+    let mut record: String = lines
+        .into_iter()
+        .map(|(tag, value)| format!("{}  - {}\n", tag, value))
+        .collect();
+    record.push_str("ER  - \n");
+    record
+}
@@ -0,0 +1,147 @@
+use anyhow::Result;
+
+use super::pipeline::{IngestPipeline, IngestProgress, IngestReport};
+use super::source::StaticSource;
+use crate::arxiv::client::ArxivClient;
+use crate::arxiv::models::{split_version, ArxivId};
+use crate::config::IngestConfig;
+use crate::db::models::NewCollection;
+use crate::db::store::PaperStore;
+use crate::embeddings::chunking::DocumentEmbedder;
+
+#[derive(Debug, Default)]
+pub struct IdImportReport {
+    /// Distinct IDs asked for
+    pub requested: usize,
+    /// IDs arXiv returned no paper for
+    pub missing: Vec<ArxivId>,
+    pub ingest: IngestReport,
+    /// Papers added to the target collection, if one was set
+    pub collected: usize,
+}
+
+impl std::fmt::Display for IdImportReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} requested, {} missing; {}",
+            self.requested,
+            self.missing.len(),
+            self.ingest
+        )?;
+        if self.collected > 0 {
+            write!(f, ", {} added to the collection", self.collected)?;
+        }
+        Ok(())
+    }
+}
+
+/// Fetches papers by arXiv ID, in `id_list` batches, and stores them
+/// through the ingestion pipeline. Used to refresh stored papers, where
+/// papers already at the latest version are skipped, and to import the
+/// papers a BibTeX file cites.
+pub struct IdImport<'a> {
+    client: &'a ArxivClient,
+    store: &'a dyn PaperStore,
+    pipeline: IngestPipeline<'a>,
+    collection: Option<String>,
+}
+
+impl<'a> IdImport<'a> {
+    pub fn new(
+        client: &'a ArxivClient,
+        store: &'a dyn PaperStore,
+        embedder: &'a DocumentEmbedder,
+        config: &IngestConfig,
+    ) -> Self {
+        Self {
+            client,
+            store,
+            pipeline: IngestPipeline::new(store, embedder, config).without_checkpoint(),
+            collection: None,
+        }
+    }
+
+    /// Also adds every stored paper to `name`, creating the collection if
+    /// needed.
+    pub fn into_collection(mut self, name: &str) -> Self {
+        self.collection = Some(name.to_string());
+        self
+    }
+
+    pub async fn run(
+        &self,
+        ids: &[ArxivId],
+        on_progress: &mut (dyn FnMut(IngestProgress) + Send),
+    ) -> Result<IdImportReport> {
+        let fetched = self.client.get_by_ids(ids).await?;
+        for id in &fetched.missing {
+            tracing::warn!("arXiv has no paper {}", id);
+        }
+
+        let mut report = IdImportReport {
+            requested: fetched.papers.len() + fetched.missing.len(),
+            missing: fetched.missing,
+            ..IdImportReport::default()
+        };
+        let stored: Vec<String> = fetched
+            .papers
+            .iter()
+            .map(|paper| paper.base_id().to_string())
+            .collect();
+
+        let source = StaticSource::new("arxiv:id_list", fetched.papers);
+        report.ingest = self.pipeline.run(&source, on_progress).await?;
+
+        if let Some(name) = &self.collection {
+            if self.store.get_collection(name).await?.is_none() {
+                self.store
+                    .create_collection(&NewCollection::new(name))
+                    .await?;
+            }
+            for arxiv_id in &stored {
+                let failed = report
+                    .ingest
+                    .failures
+                    .iter()
+                    .any(|failure| &failure.arxiv_id == arxiv_id);
+                if !failed {
+                    self.store.add_paper_to_collection(arxiv_id, name).await?;
+                    report.collected += 1;
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// IDs of the stored papers, or of one collection's papers, to refresh.
+/// Versions are dropped so that arXiv returns the latest one; papers
+/// stored under something other than an arXiv ID are left out.
+pub async fn stored_ids(store: &dyn PaperStore, collection: Option<&str>) -> Result<Vec<ArxivId>> {
+    let arxiv_ids: Vec<String> = match collection {
+        Some(name) => store
+            .get_collection_papers(name)
+            .await?
+            .into_iter()
+            .map(|entry| entry.paper.arxiv_id)
+            .collect(),
+        None => store
+            .list_papers()
+            .await?
+            .into_iter()
+            .map(|(paper, _)| paper.arxiv_id)
+            .collect(),
+    };
+    Ok(arxiv_ids
+        .iter()
+        .filter_map(|id| match split_version(id).0.parse() {
+            Ok(id) => Some(id),
+            Err(e) => {
+                tracing::warn!("Not refreshing {}: {}", id, e);
+                None
+            }
+        })
+        .collect())
+}
@@ -0,0 +1,83 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+/// Progress of one ingestion run, saved after every page so an interrupted
+/// run can pick up where it stopped.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// [`super::source::PaperSource::key`] of the run
+    pub source: String,
+    /// Result offset of the next page to fetch
+    pub next_start: usize,
+    /// Papers written or already up to date, by arXiv ID without version
+    pub done: BTreeSet<String>,
+    /// Papers that could not be ingested, with the reason
+    pub failed: BTreeMap<String, String>,
+    /// Result offset of the first page with a failed paper
+    #[serde(default)]
+    pub retry_from: Option<usize>,
+    /// Whether the run reached the end of its source
+    pub finished: bool,
+    pub updated_at: String,
+}
+
+/// Every run recorded in a checkpoint file, keyed by source.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CheckpointFile {
+    runs: BTreeMap<String, Checkpoint>,
+}
+
+impl Checkpoint {
+    pub fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            ..Self::default()
+        }
+    }
+
+    /// The unfinished run of `source` recorded at `path`, or a fresh run if
+    /// there is none.
+    pub fn resume(path: &Path, source: &str) -> Result<Self> {
+        Ok(read(path)?
+            .runs
+            .remove(source)
+            .filter(|run| !run.finished)
+            .unwrap_or_else(|| Self::new(source)))
+    }
+
+    /// Where a resumed run starts: at the first page with a failed paper,
+    /// so that those papers are tried again, or else at the next page.
+    /// Papers already done are skipped on the way.
+    pub fn resume_start(&self) -> usize {
+        self.retry_from.map_or(self.next_start, |retry_from| {
+            retry_from.min(self.next_start)
+        })
+    }
+
+    /// Records this run at `path`, keeping the runs of other sources.
+    pub fn save(&mut self, path: &Path) -> Result<()> {
+        self.updated_at = chrono::Utc::now().to_rfc3339();
+        let mut file = read(path)?;
+        file.runs.insert(self.source.clone(), self.clone());
+
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let tmp_path = path.with_extension("tmp");
+        std::fs::write(&tmp_path, serde_json::to_vec_pretty(&file)?)?;
+        std::fs::rename(&tmp_path, path)
+            .with_context(|| format!("Failed to replace {:?}", path))?;
+        Ok(())
+    }
+}
+
+fn read(path: &Path) -> Result<CheckpointFile> {
+    match std::fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json)
+            .with_context(|| format!("Failed to parse ingest checkpoint {:?}", path)),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(CheckpointFile::default()),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {:?}", path)),
+    }
+}
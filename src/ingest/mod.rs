@@ -0,0 +1,4 @@
+pub mod by_id;
+pub mod checkpoint;
+pub mod pipeline;
+pub mod source;
@@ -0,0 +1,367 @@
+use anyhow::Result;
+use futures::stream::{self, StreamExt};
+use std::collections::HashSet;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+use super::checkpoint::Checkpoint;
+use super::source::PaperSource;
+use crate::arxiv::models::Paper;
+use crate::config::IngestConfig;
+use crate::db::models::{UpsertOutcome, UpsertReport};
+use crate::db::store::PaperStore;
+use crate::embeddings::backend::ModelInfo;
+use crate::embeddings::chunking::DocumentEmbedder;
+
+/// Step of the pipeline at which a paper or page failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IngestStage {
+    Fetch,
+    Embed,
+    Store,
+}
+
+impl std::fmt::Display for IngestStage {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        f.write_str(match self {
+            IngestStage::Fetch => "fetch",
+            IngestStage::Embed => "embed",
+            IngestStage::Store => "store",
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IngestFailure {
+    /// arXiv ID without version; empty when a whole page failed to fetch
+    pub arxiv_id: String,
+    pub stage: IngestStage,
+    pub error: String,
+}
+
+/// Progress reported by [`IngestPipeline`] after each page.
+#[derive(Debug, Clone, Copy)]
+pub struct IngestProgress {
+    pub pages: usize,
+    pub fetched: usize,
+    /// Papers inserted, updated or found up to date so far
+    pub stored: usize,
+    pub failed: usize,
+    pub elapsed: Duration,
+}
+
+#[derive(Debug, Default)]
+pub struct IngestReport {
+    pub pages: usize,
+    pub fetched: usize,
+    /// Papers seen earlier in this run, or by the run being resumed
+    pub duplicates: usize,
+    /// Store outcomes; `skipped` papers were already stored at this version
+    pub upserts: UpsertReport,
+    /// Inserted or updated papers, e.g. to link similar papers afterwards
+    pub changed: Vec<String>,
+    pub failures: Vec<IngestFailure>,
+    /// Result offset the run resumed from, if a checkpoint was found
+    pub resumed_from: Option<usize>,
+    /// Whether the source was exhausted or `max_papers` reached
+    pub complete: bool,
+    pub elapsed: Duration,
+}
+
+impl std::fmt::Display for IngestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(
+            f,
+            "{} pages, {} fetched, {} duplicates: {} in {:.1}s",
+            self.pages,
+            self.fetched,
+            self.duplicates,
+            self.upserts,
+            self.elapsed.as_secs_f64()
+        )?;
+        if !self.complete {
+            write!(f, " (incomplete, resume to continue)")?;
+        }
+        Ok(())
+    }
+}
+
+/// Result of ingesting one paper.
+struct PaperOutcome {
+    arxiv_id: String,
+    result: std::result::Result<UpsertOutcome, (IngestStage, String)>,
+}
+
+/// Fetches papers page by page, embeds them in batches and writes them to
+/// the store.
+///
+/// The next page is fetched while the current one is embedded and written.
+/// Within a page, up to `concurrency` batches of `batch_size` papers are in
+/// flight, each writing up to `write_concurrency` papers at once. Papers
+/// already stored at the same arXiv version are skipped before embedding.
+///
+/// After each page the run's progress is saved to the checkpoint file, so
+/// running the same source again after an interruption continues from the
+/// last completed page, going back to retry papers that failed.
+pub struct IngestPipeline<'a> {
+    store: &'a dyn PaperStore,
+    embedder: &'a DocumentEmbedder,
+    model: ModelInfo,
+    page_size: u32,
+    batch_size: usize,
+    concurrency: usize,
+    write_concurrency: usize,
+    max_papers: Option<usize>,
+    checkpoint_path: Option<PathBuf>,
+}
+
+impl<'a> IngestPipeline<'a> {
+    pub fn new(
+        store: &'a dyn PaperStore,
+        embedder: &'a DocumentEmbedder,
+        config: &IngestConfig,
+    ) -> Self {
+        Self {
+            store,
+            embedder,
+            model: embedder.backend().model_info(),
+            page_size: config.page_size.max(1),
+            batch_size: config.batch_size.max(1),
+            concurrency: config.concurrency.max(1),
+            write_concurrency: config.write_concurrency.max(1),
+            max_papers: None,
+            checkpoint_path: Some(config.checkpoint_path.clone()),
+        }
+    }
+
+    /// Stops after fetching `max_papers` papers.
+    pub fn with_max_papers(mut self, max_papers: usize) -> Self {
+        self.max_papers = Some(max_papers);
+        self
+    }
+
+    pub fn with_page_size(mut self, page_size: u32) -> Self {
+        self.page_size = page_size.max(1);
+        self
+    }
+
+    /// Neither resumes nor records progress.
+    pub fn without_checkpoint(mut self) -> Self {
+        self.checkpoint_path = None;
+        self
+    }
+
+    pub async fn run(
+        &self,
+        source: &dyn PaperSource,
+        on_progress: &mut (dyn FnMut(IngestProgress) + Send),
+    ) -> Result<IngestReport> {
+        let started = Instant::now();
+        let mut checkpoint = match &self.checkpoint_path {
+            Some(path) => Checkpoint::resume(path, &source.key())?,
+            None => Checkpoint::new(&source.key()),
+        };
+        let mut report = IngestReport::default();
+        let mut start = checkpoint.resume_start();
+        if checkpoint.next_start > 0 {
+            tracing::info!(
+                "Resuming ingestion of {} from result {}",
+                checkpoint.source,
+                start
+            );
+            report.resumed_from = Some(start);
+        }
+
+        let mut seen: HashSet<String> = HashSet::new();
+        let mut page = source.fetch_page(start, self.page_size).await;
+
+        loop {
+            let papers = match page {
+                Ok(papers) => papers,
+                Err(e) => {
+                    tracing::warn!("Failed to fetch {} from {}: {:#}", source.key(), start, e);
+                    report.failures.push(IngestFailure {
+                        arxiv_id: String::new(),
+                        stage: IngestStage::Fetch,
+                        error: format!("{:#}", e),
+                    });
+                    break;
+                }
+            };
+
+            let mut last = papers.len() < self.page_size as usize;
+            let mut papers = papers;
+            if let Some(max_papers) = self.max_papers {
+                papers.truncate(max_papers.saturating_sub(report.fetched));
+                last |= report.fetched + papers.len() >= max_papers;
+            }
+            let page_start = start;
+            start += papers.len();
+            if !papers.is_empty() {
+                report.pages += 1;
+            }
+            report.fetched += papers.len();
+
+            let fresh: Vec<Paper> = papers
+                .into_iter()
+                .filter(|paper| {
+                    let arxiv_id = paper.base_id();
+                    let duplicate =
+                        checkpoint.done.contains(arxiv_id) || !seen.insert(arxiv_id.to_string());
+                    if duplicate {
+                        report.duplicates += 1;
+                    }
+                    !duplicate
+                })
+                .collect();
+
+            // Fetch the next page while this one is embedded and written
+            let outcomes = if last {
+                page = Ok(Vec::new());
+                self.process_page(fresh).await
+            } else {
+                let (outcomes, next) = tokio::join!(
+                    self.process_page(fresh),
+                    source.fetch_page(start, self.page_size)
+                );
+                page = next;
+                outcomes
+            };
+
+            for outcome in outcomes {
+                match outcome.result {
+                    Ok(result) => {
+                        report.upserts.record(result);
+                        if result != UpsertOutcome::Skipped {
+                            report.changed.push(outcome.arxiv_id.clone());
+                        }
+                        checkpoint.failed.remove(&outcome.arxiv_id);
+                        checkpoint.done.insert(outcome.arxiv_id);
+                    }
+                    Err((stage, error)) => {
+                        report.upserts.failed += 1;
+                        checkpoint
+                            .failed
+                            .insert(outcome.arxiv_id.clone(), error.clone());
+                        checkpoint.retry_from = Some(
+                            checkpoint
+                                .retry_from
+                                .map_or(page_start, |retry_from| retry_from.min(page_start)),
+                        );
+                        report.failures.push(IngestFailure {
+                            arxiv_id: outcome.arxiv_id,
+                            stage,
+                            error,
+                        });
+                    }
+                }
+            }
+
+            if checkpoint.failed.is_empty() {
+                checkpoint.retry_from = None;
+            }
+            checkpoint.next_start = start;
+            checkpoint.finished = last;
+            if let Some(path) = &self.checkpoint_path {
+                // Papers are only recorded as done once the store has them
+                self.store.flush().await?;
+                checkpoint.save(path)?;
+            }
+
+            on_progress(IngestProgress {
+                pages: report.pages,
+                fetched: report.fetched,
+                stored: report.upserts.total() - report.upserts.failed,
+                failed: report.failures.len(),
+                elapsed: started.elapsed(),
+            });
+
+            if last {
+                report.complete = true;
+                break;
+            }
+        }
+
+        self.store.flush().await?;
+        report.changed.sort();
+        report.elapsed = started.elapsed();
+        Ok(report)
+    }
+
+    /// Skips papers that are up to date, then embeds and writes the rest.
+    async fn process_page(&self, papers: Vec<Paper>) -> Vec<PaperOutcome> {
+        let lookups: Vec<(Paper, Result<bool>)> = stream::iter(papers)
+            .map(|paper| async move {
+                let current = self
+                    .store
+                    .get_paper_by_arxiv_id(paper.base_id())
+                    .await
+                    .map(|stored| stored.is_some_and(|stored| !stored.is_outdated_by(&paper)));
+                (paper, current)
+            })
+            .buffered(self.write_concurrency)
+            .collect()
+            .await;
+
+        let mut outcomes = Vec::new();
+        let mut pending = Vec::new();
+        for (paper, current) in lookups {
+            match current {
+                Ok(true) => outcomes.push(PaperOutcome {
+                    arxiv_id: paper.base_id().to_string(),
+                    result: Ok(UpsertOutcome::Skipped),
+                }),
+                Ok(false) => pending.push(paper),
+                Err(e) => outcomes.push(PaperOutcome {
+                    arxiv_id: paper.base_id().to_string(),
+                    result: Err((IngestStage::Store, format!("{:#}", e))),
+                }),
+            }
+        }
+
+        let batches: Vec<Vec<Paper>> = pending
+            .chunks(self.batch_size)
+            .map(<[Paper]>::to_vec)
+            .collect();
+        let written: Vec<Vec<PaperOutcome>> = stream::iter(batches)
+            .map(|batch| self.process_batch(batch))
+            .buffer_unordered(self.concurrency)
+            .collect()
+            .await;
+        outcomes.extend(written.into_iter().flatten());
+        outcomes
+    }
+
+    async fn process_batch(&self, batch: Vec<Paper>) -> Vec<PaperOutcome> {
+        let documents = match self.embedder.embed_papers(&batch).await {
+            Ok(documents) => documents,
+            Err(e) => {
+                tracing::warn!("Failed to embed a batch of {} papers: {:#}", batch.len(), e);
+                let error = format!("{:#}", e);
+                return batch
+                    .iter()
+                    .map(|paper| PaperOutcome {
+                        arxiv_id: paper.base_id().to_string(),
+                        result: Err((IngestStage::Embed, error.clone())),
+                    })
+                    .collect();
+            }
+        };
+
+        stream::iter(batch.iter().zip(documents))
+            .map(|(paper, document)| async move {
+                let result = self
+                    .store
+                    .upsert_paper(paper, document.embedding, &document.chunks, &self.model)
+                    .await
+                    .map_err(|e| (IngestStage::Store, format!("{:#}", e)));
+                PaperOutcome {
+                    arxiv_id: paper.base_id().to_string(),
+                    result,
+                }
+            })
+            .buffer_unordered(self.write_concurrency)
+            .collect()
+            .await
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use async_trait::async_trait;
+
+use crate::arxiv::client::ArxivClient;
+use crate::arxiv::models::Paper;
+
+/// Where an ingestion run gets its papers, one page at a time.
+#[async_trait]
+pub trait PaperSource: Send + Sync {
+    /// Identifies what is fetched. A checkpoint only resumes a run with the
+    /// same key.
+    fn key(&self) -> String;
+
+    /// Up to `size` papers starting at result `start`. A shorter page is
+    /// the last one.
+    async fn fetch_page(&self, start: usize, size: u32) -> Result<Vec<Paper>>;
+}
+
+/// Pages through an arXiv search.
+pub struct ArxivSource<'a> {
+    client: &'a ArxivClient,
+    query: String,
+    newest_first: bool,
+}
+
+impl<'a> ArxivSource<'a> {
+    /// Results of an arXiv `search_query`, in relevance order.
+    pub fn search(client: &'a ArxivClient, query: &str) -> Self {
+        Self {
+            client,
+            query: query.to_string(),
+            newest_first: false,
+        }
+    }
+
+    /// Papers of an arXiv category such as `cs.LG`, newest first.
+    pub fn latest(client: &'a ArxivClient, category: &str) -> Self {
+        Self {
+            client,
+            query: format!("cat:{}", category),
+            newest_first: true,
+        }
+    }
+}
+
+#[async_trait]
+impl PaperSource for ArxivSource<'_> {
+    fn key(&self) -> String {
+        if self.newest_first {
+            format!("arxiv:{} newest", self.query)
+        } else {
+            format!("arxiv:{}", self.query)
+        }
+    }
+
+    async fn fetch_page(&self, start: usize, size: u32) -> Result<Vec<Paper>> {
+        Ok(self
+            .client
+            .search_page(&self.query, start, size, self.newest_first)
+            .await?)
+    }
+}
+
+/// Papers already in memory, such as a list imported from a file.
+pub struct StaticSource {
+    key: String,
+    papers: Vec<Paper>,
+}
+
+impl StaticSource {
+    pub fn new(key: &str, papers: Vec<Paper>) -> Self {
+        Self {
+            key: key.to_string(),
+            papers,
+        }
+    }
+}
+
+#[async_trait]
+impl PaperSource for StaticSource {
+    fn key(&self) -> String {
+        self.key.clone()
+    }
+
+    async fn fetch_page(&self, start: usize, size: u32) -> Result<Vec<Paper>> {
+        Ok(self
+            .papers
+            .iter()
+            .skip(start)
+            .take(size as usize)
+            .cloned()
+            .collect())
+    }
+}
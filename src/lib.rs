@@ -2,9 +2,12 @@
 
 pub mod app;
 pub mod arxiv;
+pub mod citations;
 pub mod config;
 pub mod core;
 pub mod db;
 pub mod embeddings;
+pub mod export;
+pub mod ingest;
 pub mod ui;
 pub mod utils;
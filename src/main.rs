@@ -1,14 +1,7 @@
 use anyhow::Result;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-mod app;
-mod arxiv;
-mod config;
-mod core;
-mod db;
-mod embeddings;
-mod ui;
-mod utils;
+use arxiv_tui::{app, config};
 
 #[tokio::main]
 async fn main() -> Result<()> {
@@ -37,13 +37,33 @@ impl Ui {
         }
 
         // Render footer
-        let footer = Paragraph::new("Press 'q' to quit | '/' to search | 'h' for help")
-            .block(Block::default().borders(Borders::ALL));
+        let footer_text = match &state.status_message {
+            Some(message) => message.clone(),
+            None => format!(
+                "Press 'q' to quit | '/' to search | 'e' to export ({}) | 'h' for help",
+                state.export_format
+            ),
+        };
+        let footer = Paragraph::new(footer_text).block(Block::default().borders(Borders::ALL));
         f.render_widget(footer, chunks[2]);
     }
 
-    fn render_search_view(&self, f: &mut Frame, _state: &AppState, area: ratatui::layout::Rect) {
-        let content = Paragraph::new("Search View - Coming Soon!")
+    fn render_search_view(&self, f: &mut Frame, state: &AppState, area: ratatui::layout::Rect) {
+        let mut lines = vec![format!(
+            "> {}{}",
+            state.search_query,
+            if state.search_editing { "_" } else { "" }
+        )];
+        lines.push(String::new());
+        for (i, paper) in state.search_results.iter().enumerate() {
+            let marker = if state.selected_paper.is_some() && i == state.selected_index {
+                ">"
+            } else {
+                " "
+            };
+            lines.push(format!("{} {} [{}]", marker, paper.title, paper.base_id()));
+        }
+        let content = Paragraph::new(lines.join("\n"))
             .block(Block::default().title("Search").borders(Borders::ALL));
         f.render_widget(content, area);
     }
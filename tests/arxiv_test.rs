@@ -1,5 +1,12 @@
-use arxiv_tui::arxiv::client::ArxivClient;
-use arxiv_tui::arxiv::models::Category;
+use arxiv_tui::arxiv::client::{ArxivClient, ID_LIST_CHUNK};
+use arxiv_tui::arxiv::error::{backoff, parse_retry_after, ArxivError};
+use arxiv_tui::arxiv::models::{ArxivId, Category};
+use arxiv_tui::arxiv::pager::SearchPager;
+use arxiv_tui::arxiv::parser::parse_feed;
+use arxiv_tui::arxiv::rate_limit::{Budget, RateLimiter};
+use arxiv_tui::config::ArxivConfig;
+use futures::TryStreamExt;
+use std::time::Duration;
 
 #[tokio::test]
 async fn test_search_papers() {
@@ -50,6 +57,104 @@ async fn test_get_paper_by_id() {
     assert!(paper.title.contains("Attention"));
 }
 
+#[tokio::test]
+async fn test_get_papers_by_ids() {
+    let mut server = mockito::Server::new_async().await;
+    let client = mock_client(&server, ArxivConfig::default());
+
+    // One more unique ID than fits a request, plus a duplicate, two
+    // versions of one paper and an ID arXiv does not know
+    let first: Vec<String> = std::iter::once(101)
+        .chain(1..ID_LIST_CHUNK)
+        .map(|n| format!("2401.{:05}", n))
+        .collect();
+    let mut requested: Vec<String> = first.clone();
+    requested
+        .extend(["1706.03762v5", "1706.03762", "1706.03762v5", "2401.99999"].map(String::from));
+    let ids: Vec<ArxivId> = requested.iter().map(|id| id.parse().unwrap()).collect();
+
+    // arXiv answers in its own order
+    let returned: Vec<String> = first.iter().rev().map(|id| format!("{}v1", id)).collect();
+    let returned: Vec<&str> = returned.iter().map(String::as_str).collect();
+    let chunks = [
+        server
+            .mock("GET", "/api/query")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded("id_list".into(), first.join(",")),
+                mockito::Matcher::UrlEncoded("max_results".into(), ID_LIST_CHUNK.to_string()),
+            ]))
+            .with_body(feed(ID_LIST_CHUNK, 0, &returned))
+            .expect(1)
+            .create_async()
+            .await,
+        server
+            .mock("GET", "/api/query")
+            .match_query(mockito::Matcher::AllOf(vec![
+                mockito::Matcher::UrlEncoded(
+                    "id_list".into(),
+                    "1706.03762v5,1706.03762,2401.99999".into(),
+                ),
+                mockito::Matcher::UrlEncoded("max_results".into(), "3".into()),
+            ]))
+            .with_body(feed(2, 0, &["1706.03762v7", "1706.03762v5"]))
+            .expect(1)
+            .create_async()
+            .await,
+    ];
+
+    let fetched = client
+        .get_by_ids(&ids)
+        .await
+        .expect("Should fetch papers by ID");
+    for chunk in &chunks {
+        chunk.assert_async().await;
+    }
+
+    // In input order, duplicates once, unknown IDs reported
+    let fetched_ids: Vec<&str> = fetched.papers.iter().map(|p| p.arxiv_id.as_str()).collect();
+    let mut expected: Vec<String> = first.iter().map(|id| format!("{}v1", id)).collect();
+    expected.extend(["1706.03762v5", "1706.03762v7"].map(String::from));
+    assert_eq!(fetched_ids, expected);
+    assert_eq!(fetched.missing, vec![ids[ids.len() - 1].clone()]);
+}
+
+#[test]
+fn test_arxiv_id_parsing() {
+    let id: ArxivId = "1706.03762v7".parse().unwrap();
+    assert_eq!(id.base(), "1706.03762");
+    assert_eq!(id.version(), Some(7));
+    assert_eq!(id.to_string(), "1706.03762v7");
+
+    for text in [
+        "arXiv:2401.12345",
+        "https://arxiv.org/abs/2401.12345",
+        "http://export.arxiv.org/pdf/2401.12345.pdf",
+        " 2401.12345 ",
+    ] {
+        let id: ArxivId = text.parse().unwrap();
+        assert_eq!(id.to_string(), "2401.12345", "{}", text);
+    }
+
+    // Old-style IDs are filed without their subject class
+    let old: ArxivId = "math.GT/0309136v1".parse().unwrap();
+    assert_eq!(old.to_string(), "math/0309136v1");
+    assert_eq!(
+        "hep-th/9901001".parse::<ArxivId>().unwrap().base(),
+        "hep-th/9901001"
+    );
+
+    for invalid in [
+        "",
+        "1706.037",
+        "17060.3762",
+        "cs/123",
+        "Math/0309136",
+        "10.1000/xyz",
+    ] {
+        assert!(invalid.parse::<ArxivId>().is_err(), "{:?}", invalid);
+    }
+}
+
 #[tokio::test]
 async fn test_get_latest_papers() {
     let client = ArxivClient::new(3000, 10);
@@ -112,3 +217,426 @@ async fn test_text_for_embedding() {
     assert!(embedding_text.contains(&paper.title));
     assert!(embedding_text.contains(&paper.abstract_text));
 }
+
+#[tokio::test]
+async fn test_search_pages() {
+    let mut server = mockito::Server::new_async().await;
+    let client = mock_client(&server, ArxivConfig::default());
+
+    // The limit cuts the second page short, then the pager is done
+    let pages = [
+        server
+            .mock("GET", "/api/query")
+            .match_query(page_query("cat:cs.AI", 0, 3))
+            .with_body(feed(42, 0, &["2401.00001", "2401.00002", "2401.00003"]))
+            .expect(1)
+            .create_async()
+            .await,
+        server
+            .mock("GET", "/api/query")
+            .match_query(page_query("cat:cs.AI", 3, 2))
+            .with_body(feed(42, 3, &["2401.00004", "2401.00005"]))
+            .expect(1)
+            .create_async()
+            .await,
+    ];
+    let mut pager = SearchPager::new(&client, "cat:cs.AI")
+        .with_page_size(3)
+        .with_limit(5);
+    let first = pager
+        .next_page()
+        .await
+        .expect("First page should load")
+        .expect("cs.AI has papers");
+    assert_eq!(first.start_index, 0);
+    assert_eq!(first.papers.len(), 3);
+    assert_eq!(pager.total_results(), Some(42));
+
+    let second = pager.next_page().await.unwrap().unwrap();
+    assert_eq!(second.start_index, 3);
+    assert_eq!(second.papers.len(), 2);
+    assert!(pager.next_page().await.unwrap().is_none());
+    for page in &pages {
+        page.assert_async().await;
+    }
+
+    // A page shorter than requested is the last, whatever totalResults says
+    let pages = [
+        server
+            .mock("GET", "/api/query")
+            .match_query(page_query("cat:cs.LG", 0, 2))
+            .with_body(feed(9, 0, &["2402.00001", "2402.00002"]))
+            .expect(1)
+            .create_async()
+            .await,
+        server
+            .mock("GET", "/api/query")
+            .match_query(page_query("cat:cs.LG", 2, 2))
+            .with_body(feed(9, 2, &["2402.00003"]))
+            .expect(1)
+            .create_async()
+            .await,
+    ];
+    let streamed: Vec<_> = SearchPager::new(&client, "cat:cs.LG")
+        .with_page_size(2)
+        .papers()
+        .try_collect()
+        .await
+        .expect("Stream should succeed");
+    let ids: Vec<_> = streamed.iter().map(|p| p.arxiv_id.as_str()).collect();
+    assert_eq!(ids, ["2402.00001", "2402.00002", "2402.00003"]);
+    for page in &pages {
+        page.assert_async().await;
+    }
+}
+
+#[test]
+fn test_parse_feed_counts() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:opensearch="http://a9.com/-/spec/opensearch/1.1/" xmlns:arxiv="http://arxiv.org/schemas/atom">
+  <title>arXiv Query</title>
+  <opensearch:itemsPerPage>1</opensearch:itemsPerPage>
+  <opensearch:totalResults>42</opensearch:totalResults>
+  <opensearch:startIndex>10</opensearch:startIndex>
+  <entry>
+    <id>http://arxiv.org/abs/1706.03762v7</id>
+    <updated>2023-08-02T00:41:18Z</updated>
+    <published>2017-06-12T17:57:34Z</published>
+    <title>Attention Is All You Need</title>
+    <summary>The dominant sequence transduction models...</summary>
+    <author><name>Ashish Vaswani</name></author>
+    <link href="http://arxiv.org/pdf/1706.03762v7" rel="related" type="application/pdf"/>
+    <arxiv:comment>15 pages, 5 figures</arxiv:comment>
+    <arxiv:primary_category term="cs.CL"/>
+    <category term="cs.CL"/>
+    <category term="cs.LG"/>
+  </entry>
+</feed>"#;
+
+    let page = parse_feed(xml).expect("Feed should parse");
+    assert_eq!(page.total_results, 42);
+    assert_eq!(page.start_index, 10);
+    assert_eq!(page.items_per_page, 1);
+    assert_eq!(page.papers.len(), 1);
+    assert_eq!(page.papers[0].arxiv_id, "1706.03762v7");
+    assert_eq!(
+        page.papers[0].comment.as_deref(),
+        Some("15 pages, 5 figures")
+    );
+    assert_eq!(page.papers[0].primary_category, "cs.CL");
+    assert_eq!(page.papers[0].categories, vec!["cs.CL", "cs.LG"]);
+
+    // Past the last result arXiv sends a feed without entries
+    let empty = r#"<feed xmlns="http://www.w3.org/2005/Atom">
+  <opensearch:totalResults>42</opensearch:totalResults>
+  <opensearch:startIndex>50</opensearch:startIndex>
+  <opensearch:itemsPerPage>10</opensearch:itemsPerPage>
+</feed>"#;
+    let page = parse_feed(empty).expect("Empty feed should parse");
+    assert!(page.papers.is_empty());
+    assert_eq!(page.total_results, 42);
+}
+
+#[test]
+fn test_error_retry_policy() {
+    let rate_limited = ArxivError::RateLimited {
+        retry_after: Some(Duration::from_secs(5)),
+    };
+    assert!(rate_limited.is_retryable());
+    assert_eq!(rate_limited.retry_after(), Some(Duration::from_secs(5)));
+    assert!(rate_limited.to_string().contains("retry after 5s"));
+
+    let unavailable = ArxivError::Status {
+        status: 503,
+        message: "Service Unavailable".to_string(),
+    };
+    assert!(unavailable.is_retryable());
+    assert_eq!(unavailable.retry_after(), None);
+
+    let bad_request = ArxivError::Status {
+        status: 400,
+        message: "incorrect id format for 1234".to_string(),
+    };
+    assert!(!bad_request.is_retryable());
+    assert!(!ArxivError::Parse("expected an Atom feed".to_string()).is_retryable());
+    assert!(!ArxivError::NotFound("2401.99999".to_string()).is_retryable());
+    assert!(!ArxivError::InvalidId("not-an-id".to_string()).is_retryable());
+}
+
+#[test]
+fn test_retry_after_and_backoff() {
+    assert_eq!(parse_retry_after("120"), Some(Duration::from_secs(120)));
+    assert_eq!(
+        parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"),
+        Some(Duration::ZERO)
+    );
+    assert_eq!(parse_retry_after("soon"), None);
+
+    let base = Duration::from_millis(100);
+    for attempt in 0..4 {
+        let full = base * 2u32.pow(attempt);
+        let delay = backoff(base, attempt);
+        assert!(
+            delay <= full && delay >= full / 2,
+            "{:?} for attempt {}",
+            delay,
+            attempt
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_unreachable_arxiv_is_a_network_error() {
+    let config = ArxivConfig {
+        connect_timeout_ms: 200,
+        retry_backoff_ms: 10_000,
+        ..ArxivConfig::default()
+    };
+    // Nothing listens on the discard port
+    let client = ArxivClient::from_config(&config)
+        .with_api_url("http://127.0.0.1:9/api/query")
+        .with_rate_limiters(
+            RateLimiter::new(Budget::every(Duration::ZERO)),
+            RateLimiter::new(Budget::every(Duration::ZERO)),
+        );
+
+    // Refused connections are not retried
+    let started = std::time::Instant::now();
+    match client.get_by_id("1706.03762").await {
+        Err(e @ ArxivError::Network(_)) => {
+            assert!(!e.is_retryable());
+            assert!(started.elapsed() < Duration::from_secs(5));
+        }
+        other => panic!(
+            "expected a network error, got {:?}",
+            other.map(|p| p.arxiv_id)
+        ),
+    }
+}
+
+#[tokio::test]
+async fn test_long_retry_after_fails_fast() {
+    let mut server = mockito::Server::new_async().await;
+    let throttled = server
+        .mock("GET", mockito::Matcher::Any)
+        .with_status(503)
+        .with_header("Retry-After", "3600")
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = mock_client(
+        &server,
+        ArxivConfig {
+            max_retry_after_ms: 60_000,
+            ..ArxivConfig::default()
+        },
+    );
+    match client.search("quantum", Some(5)).await {
+        Err(ArxivError::RateLimited { retry_after }) => {
+            assert_eq!(retry_after, Some(Duration::from_secs(3600)))
+        }
+        other => panic!("expected rate limiting, got {:?}", other.map(|p| p.len())),
+    }
+    throttled.assert_async().await;
+}
+
+/// A client for `server` that never waits on the process-wide limiters.
+fn mock_client(server: &mockito::Server, config: ArxivConfig) -> ArxivClient {
+    ArxivClient::from_config(&config)
+        .with_api_url(&format!("{}/api/query", server.url()))
+        .with_rate_limiters(
+            RateLimiter::new(Budget::every(Duration::ZERO)),
+            RateLimiter::new(Budget::every(Duration::ZERO)),
+        )
+}
+
+#[tokio::test]
+async fn test_unavailable_is_retried() {
+    let mut server = mockito::Server::new_async().await;
+    let unavailable = server
+        .mock("GET", mockito::Matcher::Any)
+        .with_status(503)
+        .expect(2)
+        .create_async()
+        .await;
+    let ok = server
+        .mock("GET", mockito::Matcher::Any)
+        .with_body(feed(1, 0, &["1706.03762v7"]))
+        .expect(1)
+        .create_async()
+        .await;
+
+    let client = mock_client(
+        &server,
+        ArxivConfig {
+            max_retries: 2,
+            retry_backoff_ms: 10,
+            ..ArxivConfig::default()
+        },
+    );
+    let papers = client.search("attention", Some(1)).await.unwrap();
+    assert_eq!(papers[0].arxiv_id, "1706.03762v7");
+    unavailable.assert_async().await;
+    ok.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_retry_after_is_honored() {
+    let mut server = mockito::Server::new_async().await;
+    let throttled = server
+        .mock("GET", mockito::Matcher::Any)
+        .with_status(503)
+        .with_header("Retry-After", "1")
+        .expect(1)
+        .create_async()
+        .await;
+    let ok = server
+        .mock("GET", mockito::Matcher::Any)
+        .with_body(feed(1, 0, &["1706.03762v7"]))
+        .expect(1)
+        .create_async()
+        .await;
+
+    // The backoff alone would wait far longer than arXiv asked for
+    let client = mock_client(
+        &server,
+        ArxivConfig {
+            retry_backoff_ms: 60_000,
+            ..ArxivConfig::default()
+        },
+    );
+    let started = std::time::Instant::now();
+    client.search("attention", Some(1)).await.unwrap();
+    let waited = started.elapsed();
+    assert!(
+        waited >= Duration::from_secs(1) && waited < Duration::from_secs(10),
+        "waited {:?}",
+        waited
+    );
+    throttled.assert_async().await;
+    ok.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_too_many_requests_is_rate_limited() {
+    let mut server = mockito::Server::new_async().await;
+    server
+        .mock("GET", mockito::Matcher::Any)
+        .with_status(429)
+        .create_async()
+        .await;
+
+    let client = mock_client(
+        &server,
+        ArxivConfig {
+            max_retries: 0,
+            ..ArxivConfig::default()
+        },
+    );
+    match client.search("attention", Some(1)).await {
+        Err(ArxivError::RateLimited { retry_after: None }) => {}
+        other => panic!("expected rate limiting, got {:?}", other.map(|p| p.len())),
+    }
+}
+
+#[tokio::test]
+async fn test_html_error_pages() {
+    let page = "<html><body><h1>Service temporarily down</h1></body></html>";
+    let mut server = mockito::Server::new_async().await;
+    let client = mock_client(
+        &server,
+        ArxivConfig {
+            max_retries: 0,
+            ..ArxivConfig::default()
+        },
+    );
+
+    // A proxy answering 200 with HTML is not a feed
+    let html = server
+        .mock("GET", mockito::Matcher::Any)
+        .with_header("content-type", "text/html")
+        .with_body(page)
+        .create_async()
+        .await;
+    match client.search("attention", Some(1)).await {
+        Err(ArxivError::Parse(message)) => assert!(message.contains("Service temporarily down")),
+        other => panic!("expected a parse error, got {:?}", other.map(|p| p.len())),
+    }
+    html.remove_async().await;
+
+    server
+        .mock("GET", mockito::Matcher::Any)
+        .with_status(500)
+        .with_header("content-type", "text/html")
+        .with_body(page)
+        .create_async()
+        .await;
+    match client.search("attention", Some(1)).await {
+        Err(ArxivError::Status { status, message }) => {
+            assert_eq!(status, 500);
+            assert!(message.contains("Service temporarily down"));
+        }
+        other => panic!("expected an HTTP error, got {:?}", other.map(|p| p.len())),
+    }
+}
+
+/// An Atom feed of `ids` at `start` out of `total` results.
+fn feed(total: usize, start: usize, ids: &[&str]) -> String {
+    let entries: String = ids
+        .iter()
+        .map(|id| {
+            format!(
+                r#"
+  <entry>
+    <id>http://arxiv.org/abs/{id}</id>
+    <updated>2024-01-02T00:00:00Z</updated>
+    <published>2024-01-01T00:00:00Z</published>
+    <title>Paper {id}</title>
+    <summary>Abstract of {id}</summary>
+    <author><name>A. Author</name></author>
+    <arxiv:primary_category term="cs.AI"/>
+    <category term="cs.AI"/>
+  </entry>"#
+            )
+        })
+        .collect();
+    format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom" xmlns:opensearch="http://a9.com/-/spec/opensearch/1.1/" xmlns:arxiv="http://arxiv.org/schemas/atom">
+  <opensearch:totalResults>{total}</opensearch:totalResults>
+  <opensearch:startIndex>{start}</opensearch:startIndex>
+  <opensearch:itemsPerPage>{}</opensearch:itemsPerPage>{entries}
+</feed>"#,
+        ids.len()
+    )
+}
+
+/// Matches the request for one page of `query`.
+fn page_query(query: &str, start: usize, max_results: u32) -> mockito::Matcher {
+    mockito::Matcher::AllOf(vec![
+        mockito::Matcher::UrlEncoded("search_query".into(), query.into()),
+        mockito::Matcher::UrlEncoded("start".into(), start.to_string()),
+        mockito::Matcher::UrlEncoded("max_results".into(), max_results.to_string()),
+    ])
+}
+
+#[tokio::test]
+async fn test_get_by_invalid_id() {
+    let mut server = mockito::Server::new_async().await;
+    let api = server
+        .mock("GET", mockito::Matcher::Any)
+        .expect(0)
+        .create_async()
+        .await;
+
+    let client = mock_client(&server, ArxivConfig::default());
+    match client.get_by_id("not-an-id").await {
+        Err(ArxivError::InvalidId(id)) => assert_eq!(id, "not-an-id"),
+        other => panic!(
+            "expected an invalid ID, got {:?}",
+            other.map(|p| p.arxiv_id)
+        ),
+    }
+    api.assert_async().await;
+}
@@ -0,0 +1,166 @@
+use arxiv_tui::arxiv::models::Paper;
+use arxiv_tui::citations::extract::{extract_references, find_arxiv_ids};
+use arxiv_tui::citations::graph::{co_cited, neighborhood, Direction};
+use arxiv_tui::citations::index::CitationIndexer;
+use arxiv_tui::citations::provider::FixtureProvider;
+use arxiv_tui::db::local::LocalStore;
+use arxiv_tui::db::store::PaperStore;
+use arxiv_tui::embeddings::backend::ModelInfo;
+use chrono::Utc;
+use std::path::PathBuf;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("arxiv-tui-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+fn paper(arxiv_id: &str, title: &str) -> Paper {
+    Paper {
+        arxiv_id: arxiv_id.to_string(),
+        title: title.to_string(),
+        authors: vec!["Ada Lovelace".to_string()],
+        abstract_text: format!("Abstract of {}", title),
+        published: Utc::now(),
+        updated: Utc::now(),
+        categories: vec!["cs.LG".to_string()],
+        pdf_url: format!("https://arxiv.org/pdf/{}", arxiv_id),
+        comment: None,
+        journal_ref: None,
+        doi: None,
+        primary_category: "cs.LG".to_string(),
+    }
+}
+
+#[test]
+fn test_extract_numbered_references_with_context() {
+    let text = "Introduction\n\nTransformers [1] changed NLP. Later work [2, 3] scaled them.\n\n\
+                References\n\
+                [1] A. Vaswani et al. \u{201c}Attention is all you need,\u{201d} arXiv:1706.03762v5.\n\
+                [2] J. Devlin. BERT. In NAACL, doi:10.18653/v1/N19-1423.\n\
+                [3] S. Hawking. Old paper,\n    hep-th/9901001.\n";
+
+    let references = extract_references(text);
+    assert_eq!(references.len(), 3);
+
+    assert_eq!(references[0].arxiv_id.as_deref(), Some("1706.03762"));
+    assert_eq!(
+        references[0].title.as_deref(),
+        Some("Attention is all you need")
+    );
+    assert_eq!(references[0].context, "Transformers [1] changed NLP.");
+
+    assert_eq!(references[1].doi.as_deref(), Some("10.18653/v1/n19-1423"));
+    assert_eq!(references[1].arxiv_id, None);
+
+    // Entries continue across lines
+    assert_eq!(references[2].arxiv_id.as_deref(), Some("hep-th/9901001"));
+    assert_eq!(references[2].context, "Later work [2, 3] scaled them.");
+}
+
+#[test]
+fn test_extract_latex_bibitems() {
+    let source = "We build on \\citep{vaswani,devlin} here.\n\
+                  \\begin{thebibliography}{9}\n\
+                  \\bibitem{vaswani} A.~Vaswani, ``Attention is all you need'', arXiv:1706.03762.\n\
+                  \\bibitem[Devlin(2019)]{devlin} J. Devlin, BERT.\n\
+                  \\end{thebibliography}";
+
+    let references = extract_references(source);
+    assert_eq!(references.len(), 2);
+    assert_eq!(references[0].label.as_deref(), Some("vaswani"));
+    assert_eq!(references[0].arxiv_id.as_deref(), Some("1706.03762"));
+    assert_eq!(references[1].label.as_deref(), Some("devlin"));
+    assert_eq!(references[1].context, "We build on […] here.");
+}
+
+#[test]
+fn test_find_arxiv_ids_skips_lookalikes() {
+    let ids = find_arxiv_ids(
+        "https://arxiv.org/abs/2401.01234v2, doi 10.48550/arXiv.2312.00001, \
+         pages 1234.5678, year 1999.12345, math.CO/0501001",
+    );
+    assert_eq!(ids, vec!["2401.01234", "2312.00001", "math.CO/0501001"]);
+}
+
+#[tokio::test]
+async fn test_index_fixture_references_and_traverse() {
+    let dir = temp_dir("citations");
+    let store = LocalStore::open(&dir.join("library.json")).unwrap();
+    let model = ModelInfo {
+        name: "minilm".to_string(),
+        version: "v1".to_string(),
+        dimension: 2,
+    };
+
+    let papers = [
+        paper("2401.00001", "Foundations of graph learning"),
+        paper("2401.00002", "Scaling graph networks"),
+        paper("2401.00003", "A survey of graph methods"),
+        paper("2401.00004", "Message passing revisited"),
+    ];
+    for paper in &papers {
+        store
+            .upsert_paper(paper, vec![1.0, 0.0], &[], &model)
+            .await
+            .unwrap();
+    }
+
+    // 3 cites 1 and 2, 4 cites 1 and 2 (by title) and an unknown arXiv paper
+    std::fs::write(
+        dir.join("2401.00003.json"),
+        r#"[
+            {"raw": "Foundations, arXiv:2401.00001", "context": "As shown in [1]."},
+            {"raw": "Scaling, arXiv:2401.00002v3"}
+        ]"#,
+    )
+    .unwrap();
+    std::fs::write(
+        dir.join("2401.00004.txt"),
+        "Body citing [1] and [2] and [3].\n\nReferences\n\
+         1. A. Author. \"Foundations of graph learning\". 2024.\n\
+         2. B. Author. Scaling graph networks. arXiv:2401.00002.\n\
+         3. C. Author. Elsewhere. arXiv:2402.09999.\n",
+    )
+    .unwrap();
+
+    let provider = FixtureProvider::new(&dir);
+    let indexer = CitationIndexer::new(&store, &provider);
+    let report = indexer.index_library().await.unwrap();
+    assert_eq!(report.papers, 4);
+    assert_eq!(report.linked, 4);
+    assert!(report.not_in_library.contains("2402.09999"));
+
+    // Re-indexing replaces rather than duplicates edges
+    let again = indexer.index_library().await.unwrap();
+    assert_eq!(again.linked, 4);
+    let cited_by = store.get_cited_by("2401.00001").await.unwrap();
+    assert_eq!(cited_by.len(), 2);
+    assert!(cited_by
+        .iter()
+        .any(|link| link.paper.arxiv_id == "2401.00003" && link.context == "As shown in [1]."));
+
+    let references = store.get_references("2401.00004").await.unwrap();
+    assert_eq!(references.len(), 2);
+
+    let co = co_cited(&store, "2401.00001", 5).await.unwrap();
+    assert_eq!(co.len(), 1);
+    assert_eq!(co[0].paper.arxiv_id, "2401.00002");
+    assert_eq!(co[0].strength, 2);
+
+    let near = neighborhood(&store, "2401.00003", 1, Direction::References, 10)
+        .await
+        .unwrap();
+    assert_eq!(near.len(), 2);
+    let far = neighborhood(&store, "2401.00003", 2, Direction::Both, 10)
+        .await
+        .unwrap();
+    let four = far
+        .iter()
+        .find(|n| n.paper.arxiv_id == "2401.00004")
+        .expect("2401.00004 shares references with 2401.00003");
+    assert_eq!(four.distance, 2);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,682 @@
+use arxiv_tui::arxiv::models::Paper;
+use arxiv_tui::config::{HelixDbConfig, OfflineConfig};
+use arxiv_tui::db::client::{is_unavailable, DbClient};
+use arxiv_tui::db::health::{self, HealthStatus};
+use arxiv_tui::db::local::LocalStore;
+use arxiv_tui::db::migrations::MIGRATIONS;
+use arxiv_tui::db::offline::OfflineStore;
+use arxiv_tui::db::schema::{self, QueryIssue, SCHEMA_VERSION};
+use arxiv_tui::db::store::PaperStore;
+use arxiv_tui::embeddings::backend::ModelInfo;
+use arxiv_tui::embeddings::chunking::ChunkEmbedding;
+use mockito::Matcher;
+use serde_json::json;
+
+/// Config for a mock HelixDB server, without retries.
+fn config(server: &mockito::Server) -> HelixDbConfig {
+    let (host, port) = server
+        .host_with_port()
+        .rsplit_once(':')
+        .map(|(h, p)| (h.to_string(), p.parse().unwrap()))
+        .unwrap();
+    HelixDbConfig {
+        endpoint: format!("http://{}", host),
+        port,
+        max_retries: 0,
+        ..HelixDbConfig::default()
+    }
+}
+
+/// Points a client at a mock HelixDB server.
+fn client(server: &mockito::Server) -> DbClient {
+    DbClient::new(&config(server)).expect("Client should build")
+}
+
+fn model() -> ModelInfo {
+    ModelInfo {
+        name: "all-MiniLM-L6-v2".to_string(),
+        version: "abc123".to_string(),
+        dimension: 3,
+    }
+}
+
+#[tokio::test]
+async fn test_search_refuses_vectors_from_other_models() {
+    let mut server = mockito::Server::new_async().await;
+    let _search = server
+        .mock("POST", "/SearchPapers")
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"{"results": [
+                {"arxiv_id": "2401.00001", "title": "A", "embedding_model": "all-MiniLM-L6-v2", "embedding_version": "abc123", "score": 0.9},
+                {"arxiv_id": "2401.00002", "title": "B", "embedding_model": "bge-small-en-v1.5", "embedding_version": "def456", "score": 0.8}
+            ]}"#,
+        )
+        .create_async()
+        .await;
+    let db = client(&server);
+
+    let err = db
+        .search_papers(vec![1.0, 0.0, 0.0], &model(), 0.5, 10)
+        .await
+        .expect_err("Mixed-model results should be rejected");
+    assert!(err.to_string().contains("2401.00002"));
+
+    // Query vectors of the wrong size never reach the server
+    assert!(db
+        .search_papers(vec![1.0, 0.0], &model(), 0.5, 10)
+        .await
+        .is_err());
+}
+
+#[tokio::test]
+async fn test_embedding_coverage_counts_stale_papers() {
+    let mut server = mockito::Server::new_async().await;
+    let _matching = server
+        .mock("POST", "/CountPapersWithModel")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"count": 7}"#)
+        .create_async()
+        .await;
+    let _total = server
+        .mock("POST", "/CountPapers")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"count": 10}"#)
+        .create_async()
+        .await;
+
+    let coverage = client(&server)
+        .embedding_coverage(&model())
+        .await
+        .expect("Coverage query should succeed");
+    assert_eq!(coverage.matching, 7);
+    assert_eq!(coverage.total, 10);
+    assert_eq!(coverage.stale(), 3);
+    assert!(!coverage.is_complete());
+}
+
+#[tokio::test]
+async fn test_search_results_are_typed_and_ranked() {
+    let mut server = mockito::Server::new_async().await;
+    let _search = server
+        .mock("POST", "/SearchPapers")
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"{"results": [
+                {"id": "n1", "arxiv_id": "2401.00001", "title": "Far", "authors": "A. One, B. Two",
+                 "embedding_model": "all-MiniLM-L6-v2", "embedding_version": "abc123", "distance": 0.6},
+                {"id": "n2", "arxiv_id": "2401.00002", "title": "Near", "authors": "C. Three",
+                 "embedding_model": "all-MiniLM-L6-v2", "embedding_version": "abc123", "distance": 0.1}
+            ]}"#,
+        )
+        .create_async()
+        .await;
+
+    let results = client(&server)
+        .search_papers(vec![1.0, 0.0, 0.0], &model(), 0.0, 10)
+        .await
+        .expect("Search should succeed");
+
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].paper.arxiv_id, "2401.00002");
+    assert!((results[0].similarity_score - 0.9).abs() < 1e-6);
+    assert_eq!(results[1].paper.author_list(), vec!["A. One", "B. Two"]);
+}
+
+#[tokio::test]
+async fn test_writes_send_unversioned_ids() {
+    let mut server = mockito::Server::new_async().await;
+    let paper = json!({"arxiv_id": "2401.00001", "version": 2, "title": "A"});
+    let chunk = json!({"arxiv_id": "2401.00001", "chunk_index": 0, "text": "Body"});
+    let mut mocks = Vec::new();
+    for (query, body) in [
+        ("AddPaperChunk", json!({ "chunk": chunk })),
+        ("DeletePaperChunks", json!({})),
+        ("ReplacePaperEmbedding", json!({ "v": paper })),
+        (
+            "MarkPaperSaved",
+            json!({"m": {"arxiv_id": "2401.00001", "saved": true}}),
+        ),
+    ] {
+        mocks.push(
+            server
+                .mock("POST", format!("/{}", query).as_str())
+                .match_body(Matcher::PartialJson(json!({"arxiv_id": "2401.00001"})))
+                .with_header("content-type", "application/json")
+                .with_body(body.to_string())
+                .expect(1)
+                .create_async()
+                .await,
+        );
+    }
+
+    let db = client(&server);
+    let chunks = [ChunkEmbedding {
+        index: 0,
+        text: "Body".to_string(),
+        embedding: vec![1.0, 0.0, 0.0],
+    }];
+    db.add_paper_chunks("2401.00001v2", &chunks, &model())
+        .await
+        .unwrap();
+    db.delete_paper_chunks("2401.00001v2").await.unwrap();
+    db.replace_paper_embedding("2401.00001v2", vec![0.0, 1.0, 0.0], &model())
+        .await
+        .unwrap();
+    assert!(
+        db.mark_paper_saved("2401.00001v2", true)
+            .await
+            .unwrap()
+            .saved
+    );
+
+    for mock in &mocks {
+        mock.assert_async().await;
+    }
+}
+
+#[tokio::test]
+async fn test_unexpected_response_shape_is_reported() {
+    let mut server = mockito::Server::new_async().await;
+    let _collections = server
+        .mock("POST", "/GetAllCollections")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"c": [{"description": "missing a name"}]}"#)
+        .create_async()
+        .await;
+
+    let err = client(&server)
+        .get_all_collections()
+        .await
+        .expect_err("A collection without a name should not decode");
+    let message = err.to_string();
+    assert!(message.contains("GetAllCollections"));
+    assert!(message.contains("name"));
+}
+
+#[test]
+fn test_query_registry_matches_helix_queries() {
+    let issues = schema::check_hql(include_str!("../helix/queries.hx"));
+    assert!(issues.is_empty(), "Registry out of sync: {:?}", issues);
+
+    assert_eq!(
+        schema::parse_schema_version(include_str!("../helix/schema.hx")),
+        Some(SCHEMA_VERSION)
+    );
+    assert_eq!(MIGRATIONS.last().map(|m| m.version), Some(SCHEMA_VERSION));
+}
+
+#[test]
+fn test_payloads_are_checked_against_the_registry() {
+    assert!(schema::check_call(
+        "MarkPaperSaved",
+        &json!({"arxiv_id": "2401.00001", "saved": true})
+    )
+    .is_ok());
+
+    let wrong_type = schema::check_call(
+        "MarkPaperSaved",
+        &json!({"arxiv_id": "2401.00001", "saved": "yes"}),
+    )
+    .expect_err("A string is not a Bool");
+    assert!(wrong_type.to_string().contains("saved"));
+    assert!(schema::check_call("MarkPaperSaved", &json!({"arxiv_id": "2401.00001"})).is_err());
+    assert!(schema::check_call("CountPapers", &json!({"limit": 3})).is_err());
+    assert!(schema::check_call("DropEverything", &json!({})).is_err());
+
+    let drifted = schema::check_hql(
+        "QUERY CountPapers () =>\n    RETURN 1\nQUERY MarkPaperSaved (arxiv_id: String, saved: U32) =>\n",
+    );
+    assert!(drifted.contains(&QueryIssue::Missing("SearchPapers".to_string())));
+    assert!(drifted.iter().any(
+        |issue| matches!(issue, QueryIssue::Mismatched { name, .. } if name == "MarkPaperSaved")
+    ));
+}
+
+#[tokio::test]
+async fn test_schema_check_reports_missing_and_mismatched_queries() {
+    let mut server = mockito::Server::new_async().await;
+    let mut mocks = Vec::new();
+    for (query, body) in [
+        ("GetAllCollections", r#"{"c": []}"#),
+        ("CountPapers", r#"{"count": 0}"#),
+        ("CountSavedPapers", r#"{"count": 0}"#),
+        (
+            "GetSchemaVersion",
+            r#"{"s": [{"version": 2}, {"version": 3}]}"#,
+        ),
+        ("GetCollection", r#"{"c": []}"#),
+    ] {
+        mocks.push(
+            server
+                .mock("POST", format!("/{}", query).as_str())
+                .with_header("content-type", "application/json")
+                .with_body(body)
+                .create_async()
+                .await,
+        );
+    }
+    mocks.push(
+        server
+            .mock("POST", "/GetSimilarities")
+            .with_status(404)
+            .create_async()
+            .await,
+    );
+    mocks.push(
+        server
+            .mock("POST", "/GetCitedPapers")
+            .with_status(500)
+            .with_body("missing field `depth`")
+            .create_async()
+            .await,
+    );
+    // Write queries are never probed
+    let mut writes = Vec::new();
+    for query in ["AddPaper", "DeleteCollection", "SetSchemaVersion"] {
+        writes.push(
+            server
+                .mock("POST", format!("/{}", query).as_str())
+                .expect(0)
+                .create_async()
+                .await,
+        );
+    }
+
+    let report = client(&server)
+        .check_schema()
+        .await
+        .expect("Schema check should succeed");
+
+    assert_eq!(report.server_version, Some(3));
+    assert!(!report.queries_ok());
+    assert!(report
+        .issues
+        .contains(&QueryIssue::Missing("GetSimilarities".to_string())));
+    let mismatched: Vec<&str> = report
+        .issues
+        .iter()
+        .filter_map(|issue| match issue {
+            QueryIssue::Mismatched { name, .. } => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(mismatched, vec!["GetCollection", "GetCitedPapers"]);
+    for write in writes {
+        write.assert_async().await;
+    }
+}
+
+#[tokio::test]
+async fn test_migrate_stamps_empty_database() {
+    let mut server = mockito::Server::new_async().await;
+    let _versions = server
+        .mock("POST", "/GetSchemaVersion")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"s": []}"#)
+        .create_async()
+        .await;
+    let _count = server
+        .mock("POST", "/CountPapers")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"count": 0}"#)
+        .create_async()
+        .await;
+    let stamp = server
+        .mock("POST", "/SetSchemaVersion")
+        .match_body(Matcher::PartialJson(json!({"version": SCHEMA_VERSION})))
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"info": {"version": 3}}"#)
+        .expect(1)
+        .create_async()
+        .await;
+
+    let applied = client(&server)
+        .migrate()
+        .await
+        .expect("Migration should succeed");
+    assert!(applied.is_empty());
+    stamp.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_migrate_upgrades_legacy_database() {
+    let metadata = json!({"arxiv_id": "2401.00001", "tags": "ML, Deep Learning,ml", "read": true});
+    let mut server = mockito::Server::new_async().await;
+    let _versions = server
+        .mock("POST", "/GetSchemaVersion")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"s": []}"#)
+        .create_async()
+        .await;
+    let _count = server
+        .mock("POST", "/CountPapers")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"count": 1}"#)
+        .create_async()
+        .await;
+    let _list = server
+        .mock("POST", "/ListPapers")
+        .with_header("content-type", "application/json")
+        .with_body(
+            json!({"v": [{"arxiv_id": "2401.00001", "title": "A"}], "m": [metadata]}).to_string(),
+        )
+        .create_async()
+        .await;
+    let _metadata = server
+        .mock("POST", "/GetPaperMetadata")
+        .with_header("content-type", "application/json")
+        .with_body(json!({ "m": [metadata] }).to_string())
+        .create_async()
+        .await;
+    let tags = server
+        .mock("POST", "/UpdatePaperTags")
+        .match_body(Matcher::PartialJson(json!({"tags": "ml,deep-learning"})))
+        .with_header("content-type", "application/json")
+        .with_body(json!({ "m": metadata }).to_string())
+        .expect(1)
+        .create_async()
+        .await;
+    let read_state = server
+        .mock("POST", "/SetReadState")
+        .match_body(Matcher::PartialJson(json!({"read_state": "read"})))
+        .with_header("content-type", "application/json")
+        .with_body(json!({ "m": metadata }).to_string())
+        .expect(1)
+        .create_async()
+        .await;
+    let stamps = server
+        .mock("POST", "/SetSchemaVersion")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"info": {}}"#)
+        .expect(2)
+        .create_async()
+        .await;
+
+    let applied = client(&server)
+        .migrate()
+        .await
+        .expect("Migration should succeed");
+    assert_eq!(applied, vec![2, 3]);
+    tags.assert_async().await;
+    read_state.assert_async().await;
+    stamps.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_transient_failures_are_retried_with_backoff() {
+    let mut server = mockito::Server::new_async().await;
+    let unavailable = server
+        .mock("POST", "/CountPapers")
+        .with_status(503)
+        .expect(2)
+        .create_async()
+        .await;
+    let _count = server
+        .mock("POST", "/CountPapers")
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"count": 4}"#)
+        .create_async()
+        .await;
+    let rejected = server
+        .mock("POST", "/GetAllCollections")
+        .with_status(400)
+        .with_body("bad request")
+        .expect(1)
+        .create_async()
+        .await;
+
+    let db = DbClient::new(&HelixDbConfig {
+        max_retries: 3,
+        retry_backoff_ms: 1,
+        ..config(&server)
+    })
+    .unwrap();
+
+    assert_eq!(db.count_papers().await.unwrap(), 4);
+    unavailable.assert_async().await;
+
+    // Rejected requests are not retried, and do not mean the server is down
+    let err = db.get_all_collections().await.unwrap_err();
+    assert!(!is_unavailable(&err));
+    rejected.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_health_check_reports_unreachable_server() {
+    let report = health::check(&HelixDbConfig {
+        endpoint: "http://127.0.0.1".to_string(),
+        port: 9,
+        connect_timeout_ms: 500,
+        ..HelixDbConfig::default()
+    })
+    .await;
+
+    assert_eq!(report.status, HealthStatus::Unreachable);
+    assert!(report.latency.is_none());
+    assert!(report.to_string().contains("unreachable"));
+}
+
+#[tokio::test]
+async fn test_offline_store_queues_edits_and_replays_them() {
+    let dir = std::env::temp_dir().join(format!("arxiv-tui-offline-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let offline = OfflineConfig {
+        enabled: true,
+        dir: dir.clone(),
+        reconnect_interval_secs: 0,
+    };
+
+    let paper = Paper {
+        arxiv_id: "2401.00001v1".to_string(),
+        title: "Cached".to_string(),
+        authors: vec!["Ada Lovelace".to_string()],
+        abstract_text: "Abstract".to_string(),
+        published: chrono::Utc::now(),
+        updated: chrono::Utc::now(),
+        categories: vec!["cs.LG".to_string()],
+        pdf_url: "https://arxiv.org/pdf/2401.00001v1".to_string(),
+        comment: None,
+        journal_ref: None,
+        doi: None,
+        primary_category: "cs.LG".to_string(),
+    };
+
+    // A cache left by an earlier session
+    {
+        let cache = LocalStore::open(&dir.join("cache.json")).unwrap();
+        cache
+            .add_paper(&paper, vec![1.0, 0.0, 0.0], &model())
+            .await
+            .unwrap();
+        cache.add_paper_metadata(&paper).await.unwrap();
+    }
+    assert!(OfflineStore::has_cache(&offline));
+
+    let mut server = mockito::Server::new_async().await;
+    let down = server
+        .mock("POST", Matcher::Any)
+        .with_status(503)
+        .create_async()
+        .await;
+    let store = OfflineStore::open(client(&server), &offline, true).unwrap();
+
+    // The failed write goes to the cache and the queue
+    let metadata = store.mark_paper_saved("2401.00001", true).await.unwrap();
+    assert!(metadata.saved);
+    assert!(!store.is_online());
+    assert_eq!(store.pending_writes(), 1);
+    assert_eq!(store.backend_name(), "helixdb (offline)");
+
+    // Reads keep working from the cache
+    let cached = store
+        .get_paper_metadata("2401.00001")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(cached.saved);
+    // Ingestion needs the database
+    assert!(store.add_paper_metadata(&paper).await.is_err());
+
+    // Queued writes survive a restart
+    drop(store);
+    let store = OfflineStore::open(client(&server), &offline, false).unwrap();
+    assert_eq!(store.pending_writes(), 1);
+
+    down.remove_async().await;
+    let mut mocks = Vec::new();
+    for (query, body) in [
+        ("CountPapers", r#"{"count": 1}"#),
+        ("ListPapers", r#"{"v": [], "m": []}"#),
+        ("GetAllCollections", r#"{"c": []}"#),
+    ] {
+        mocks.push(
+            server
+                .mock("POST", format!("/{}", query).as_str())
+                .with_header("content-type", "application/json")
+                .with_body(body)
+                .create_async()
+                .await,
+        );
+    }
+    let replayed = server
+        .mock("POST", "/MarkPaperSaved")
+        .match_body(Matcher::PartialJson(
+            json!({"arxiv_id": "2401.00001", "saved": true}),
+        ))
+        .with_header("content-type", "application/json")
+        .with_body(r#"{"m": {"arxiv_id": "2401.00001", "saved": true}}"#)
+        .expect(1)
+        .create_async()
+        .await;
+
+    assert!(store.reconnect().await.unwrap());
+    assert!(store.is_online());
+    assert_eq!(store.pending_writes(), 0);
+    replayed.assert_async().await;
+}
+
+#[tokio::test]
+async fn test_offline_cache_refresh_fetches_only_changed_papers() {
+    let dir = std::env::temp_dir().join(format!("arxiv-tui-refresh-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let offline = OfflineConfig {
+        enabled: true,
+        dir: dir.clone(),
+        reconnect_interval_secs: 0,
+    };
+
+    let mut server = mockito::Server::new_async().await;
+    let mut edges = Vec::new();
+    for (query, body) in [
+        ("GetSimilarities", r#"{"p2": [], "e": []}"#),
+        ("GetCitedPapers", r#"{"p2": [], "e": []}"#),
+        ("GetAllCollections", r#"{"c": []}"#),
+    ] {
+        edges.push(
+            server
+                .mock("POST", format!("/{}", query).as_str())
+                .with_header("content-type", "application/json")
+                .with_body(body)
+                .create_async()
+                .await,
+        );
+    }
+    let mut vectors = Vec::new();
+    for (arxiv_id, fetches) in [("2401.00001", 2), ("2401.00002", 1)] {
+        vectors.push(
+            server
+                .mock("POST", "/GetPaperVector")
+                .match_body(Matcher::PartialJson(json!({ "arxiv_id": arxiv_id })))
+                .with_header("content-type", "application/json")
+                .with_body(json!({"v": [{"data": [1.0, 0.0, 0.0]}]}).to_string())
+                .expect(fetches)
+                .create_async()
+                .await,
+        );
+    }
+
+    let store = OfflineStore::open(client(&server), &offline, true).unwrap();
+    let listings = [
+        vec![("2401.00001", 1)],
+        // Unchanged papers keep their cached copy
+        vec![("2401.00001", 1), ("2401.00002", 1)],
+        // A new version is fetched again
+        vec![("2401.00001", 2), ("2401.00002", 1)],
+    ];
+    let listing = |papers: &[(&str, u32)]| {
+        let (records, metadata): (Vec<_>, Vec<_>) = papers
+            .iter()
+            .map(|(arxiv_id, version)| {
+                (
+                    json!({"arxiv_id": arxiv_id, "version": version, "title": "A"}),
+                    json!({"arxiv_id": arxiv_id}),
+                )
+            })
+            .unzip();
+        json!({"v": records, "m": metadata}).to_string()
+    };
+    for papers in &listings {
+        let listed = server
+            .mock("POST", "/ListPapers")
+            .with_header("content-type", "application/json")
+            .with_body(listing(papers))
+            .create_async()
+            .await;
+        assert_eq!(store.refresh_cache().await.unwrap(), papers.len());
+        listed.remove_async().await;
+    }
+
+    for vector in &vectors {
+        vector.assert_async().await;
+    }
+
+    // Edges added to an unchanged paper still reach the cache
+    for edge in edges.drain(..2) {
+        edge.remove_async().await;
+    }
+    let neighbor = json!({"arxiv_id": "2401.00001", "version": 2, "title": "A"});
+    for (query, arxiv_id, body) in [
+        (
+            "GetSimilarities",
+            "2401.00002",
+            json!({"p2": [neighbor], "e": [{"similarity_score": 0.9}]}),
+        ),
+        (
+            "GetCitedPapers",
+            "2401.00002",
+            json!({"p2": [neighbor], "e": [{"context": "Builds on"}]}),
+        ),
+        ("GetSimilarities", "2401.00001", json!({"p2": [], "e": []})),
+        ("GetCitedPapers", "2401.00001", json!({"p2": [], "e": []})),
+    ] {
+        edges.push(
+            server
+                .mock("POST", format!("/{}", query).as_str())
+                .match_body(Matcher::PartialJson(json!({ "arxiv_id": arxiv_id })))
+                .with_header("content-type", "application/json")
+                .with_body(body.to_string())
+                .create_async()
+                .await,
+        );
+    }
+    server
+        .mock("POST", "/ListPapers")
+        .with_header("content-type", "application/json")
+        .with_body(listing(&listings[2]))
+        .create_async()
+        .await;
+    store.refresh_cache().await.unwrap();
+    for vector in &vectors {
+        vector.assert_async().await;
+    }
+
+    let cache = LocalStore::open(store.cache_path()).unwrap();
+    let similar = cache.get_similarities("2401.00002").await.unwrap();
+    assert_eq!(similar.len(), 1);
+    assert_eq!(similar[0].paper.arxiv_id, "2401.00001");
+    let references = cache.get_references("2401.00002").await.unwrap();
+    assert_eq!(references.len(), 1);
+    assert_eq!(references[0].context, "Builds on");
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,386 @@
+use arxiv_tui::config::{
+    ChunkingConfig, EmbeddingBackendKind, EmbeddingsConfig, OpenAiEmbeddingsConfig,
+};
+use arxiv_tui::embeddings::backend::{self, EmbeddingBackend};
+use arxiv_tui::embeddings::cache::EmbeddingCache;
+use arxiv_tui::embeddings::chunking::{chunk_text, word_spans};
+use arxiv_tui::embeddings::hash::HashBackend;
+use arxiv_tui::embeddings::models::{self, ModelFile, ModelManager, ModelSpec};
+use sha2::{Digest, Sha256};
+use std::path::PathBuf;
+
+fn config(backend: EmbeddingBackendKind) -> EmbeddingsConfig {
+    EmbeddingsConfig {
+        backend,
+        model: None,
+        model_path: PathBuf::from("./models/does-not-exist.onnx"),
+        tokenizer_path: None,
+        batch_size: 2,
+        device: "cpu".to_string(),
+        dimension: Some(3),
+        models_dir: PathBuf::from("./models"),
+        model_base_url: "https://huggingface.co".to_string(),
+        openai: OpenAiEmbeddingsConfig::default(),
+        chunking: ChunkingConfig::default(),
+        cache: false,
+    }
+}
+
+#[tokio::test]
+async fn test_hash_embeddings_are_deterministic_and_normalized() {
+    let backend = HashBackend::new(384);
+
+    let first = backend
+        .embed("attention is all you need")
+        .await
+        .expect("Hash embedding should succeed");
+    let second = backend
+        .embed("attention is all you need")
+        .await
+        .expect("Hash embedding should succeed");
+
+    assert_eq!(first.len(), 384);
+    assert_eq!(first, second);
+
+    let magnitude: f32 = first.iter().map(|x| x * x).sum::<f32>().sqrt();
+    assert!(
+        (magnitude - 1.0).abs() < 1e-4,
+        "Embedding should be unit length"
+    );
+}
+
+#[test]
+fn test_missing_model_fails_loudly() {
+    let err = backend::from_config(&config(EmbeddingBackendKind::Onnx))
+        .err()
+        .expect("Loading a missing model should fail");
+
+    assert!(err.to_string().contains("does-not-exist.onnx"));
+}
+
+#[test]
+fn test_model_without_room_for_text_is_rejected() {
+    let dir = std::env::temp_dir().join(format!("arxiv-tui-models-short-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    let registry = serde_json::json!({"models": {"tiny": {
+        "name": "tiny",
+        "dimension": 3,
+        "max_seq_len": 2,
+        "files": {},
+        "installed_at": "2024-01-01T00:00:00Z",
+    }}});
+    std::fs::write(dir.join("registry.json"), registry.to_string()).unwrap();
+
+    let mut config = config(EmbeddingBackendKind::Onnx);
+    config.model = Some("tiny".to_string());
+    config.models_dir = dir.clone();
+    config.dimension = None;
+    let err = backend::from_config(&config)
+        .err()
+        .expect("A model that only fits [CLS] and [SEP] should be refused");
+    assert!(err.to_string().contains("Sequence length 2"));
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_embed_all_preserves_input_order() {
+    let backend = HashBackend::new(64);
+    let texts = ["first abstract", "second abstract", "third abstract"];
+
+    let mut reported = Vec::new();
+    let batch = backend
+        .embed_all_with_progress(&texts, &mut |progress| reported.push(progress.completed))
+        .await
+        .expect("Batch generation should succeed");
+
+    assert_eq!(batch.len(), texts.len());
+    for (text, embedding) in texts.iter().zip(&batch) {
+        assert_eq!(embedding, &backend.embed(text).await.unwrap());
+    }
+    assert_eq!(reported.last(), Some(&texts.len()));
+}
+
+#[tokio::test]
+async fn test_openai_backend_orders_by_index() {
+    let mut server = mockito::Server::new_async().await;
+    let mock = server
+        .mock("POST", "/v1/embeddings")
+        .with_header("content-type", "application/json")
+        .with_body(
+            r#"{"data": [
+                {"index": 1, "embedding": [0.0, 2.0, 0.0]},
+                {"index": 0, "embedding": [3.0, 0.0, 0.0]}
+            ]}"#,
+        )
+        .create_async()
+        .await;
+
+    let mut cfg = config(EmbeddingBackendKind::OpenAi);
+    cfg.openai.base_url = server.url();
+    cfg.openai.model = "test-model".to_string();
+    let backend = backend::from_config(&cfg).expect("OpenAI backend should build");
+
+    let vectors = backend
+        .embed_batch(&["first", "second"])
+        .await
+        .expect("Embedding request should succeed");
+
+    mock.assert_async().await;
+    assert_eq!(backend.model_name(), "test-model");
+    assert_eq!(vectors, vec![vec![1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]]);
+}
+
+#[test]
+fn test_chunk_text_overlaps_windows() {
+    let text = "one two three four five six seven";
+    let chunks = chunk_text(text, &word_spans(text), 3, 1);
+
+    let texts: Vec<&str> = chunks.iter().map(|c| c.text.as_str()).collect();
+    assert_eq!(
+        texts,
+        ["one two three", "three four five", "five six seven"]
+    );
+    assert_eq!(chunks.last().unwrap().index, 2);
+
+    // Short texts stay whole
+    assert_eq!(
+        chunk_text("short text", &word_spans("short text"), 3, 1).len(),
+        1
+    );
+}
+
+#[test]
+fn test_embedding_cache_survives_reopen_and_evicts() {
+    let dir = std::env::temp_dir().join(format!("arxiv-tui-cache-test-{}", std::process::id()));
+    let path = dir.join("embeddings.bin");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let first = EmbeddingCache::key("model", "abc", "first");
+    let second = EmbeddingCache::key("model", "abc", "second");
+    assert_ne!(first, EmbeddingCache::key("model", "def", "first"));
+
+    {
+        let mut cache = EmbeddingCache::open(&path, 1024 * 1024).unwrap();
+        cache.insert(first, vec![0.5, -0.25]).unwrap();
+        cache.insert(second, vec![1.0, 0.0]).unwrap();
+    }
+
+    // Room for a single two-dimensional record (44 bytes)
+    let mut cache = EmbeddingCache::open(&path, 50).unwrap();
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.get(&first), None);
+    assert_eq!(cache.get(&second), Some(vec![1.0, 0.0]));
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_embedding_cache_drops_record_with_corrupt_dimension() {
+    let dir = std::env::temp_dir().join(format!("arxiv-tui-cache-corrupt-{}", std::process::id()));
+    let path = dir.join("embeddings.bin");
+    let _ = std::fs::remove_dir_all(&dir);
+
+    let key = EmbeddingCache::key("model", "abc", "kept");
+    {
+        let mut cache = EmbeddingCache::open(&path, 1024 * 1024).unwrap();
+        cache.insert(key, vec![0.5, -0.25]).unwrap();
+    }
+
+    // A record claiming a ~16 GiB vector must not be allocated
+    let mut tail = EmbeddingCache::key("model", "abc", "corrupt").to_vec();
+    tail.extend_from_slice(&u32::MAX.to_le_bytes());
+    let mut bytes = std::fs::read(&path).unwrap();
+    bytes.extend_from_slice(&tail);
+    std::fs::write(&path, bytes).unwrap();
+
+    let mut cache = EmbeddingCache::open(&path, 1024 * 1024).unwrap();
+    assert_eq!(cache.len(), 1);
+    assert_eq!(cache.get(&key), Some(vec![0.5, -0.25]));
+    assert!(cache.insert(key, vec![0.0; 100_000]).is_err());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[tokio::test]
+async fn test_model_manager_installs_verifies_and_removes() {
+    let mut server = mockito::Server::new_async().await;
+    let model_bytes = b"fake onnx graph";
+    let model_sha = format!("{:x}", Sha256::digest(model_bytes));
+    let model_mock = server
+        .mock(
+            "GET",
+            "/BAAI/bge-small-en-v1.5/resolve/main/onnx/model.onnx",
+        )
+        .with_header("x-linked-etag", &format!("\"{}\"", model_sha))
+        .with_body(model_bytes)
+        .expect(2)
+        .create_async()
+        .await;
+    let tokenizer_mock = server
+        .mock("GET", "/BAAI/bge-small-en-v1.5/resolve/main/tokenizer.json")
+        .with_body("{}")
+        .expect(2)
+        .create_async()
+        .await;
+    let _sidecar = server
+        .mock(
+            "GET",
+            "/BAAI/bge-small-en-v1.5/resolve/main/tokenizer.json.sha256",
+        )
+        .with_status(404)
+        .create_async()
+        .await;
+
+    let dir = std::env::temp_dir().join(format!("arxiv-tui-models-test-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let manager = ModelManager::new(&dir, &server.url());
+
+    // The tokenizer has no checksum, so it needs an explicit opt-in
+    let err = manager
+        .install("bge-small-en-v1.5")
+        .await
+        .expect_err("A file without a checksum should fail the install");
+    assert!(err.to_string().contains("No checksum available"));
+    assert!(manager.installed("bge-small-en-v1.5").unwrap().is_none());
+    assert!(!dir
+        .join("bge-small-en-v1.5")
+        .join(models::MODEL_FILE)
+        .exists());
+
+    let manager = manager.allow_unverified(true);
+    let installed = manager
+        .install("bge-small-en-v1.5")
+        .await
+        .expect("Install should succeed");
+    model_mock.assert_async().await;
+    tokenizer_mock.assert_async().await;
+    assert_eq!(installed.dimension, 384);
+    assert_eq!(installed.files.get(models::MODEL_FILE), Some(&model_sha));
+    assert!(manager.verify("bge-small-en-v1.5").is_ok());
+
+    // A configured dimension that disagrees with the model is rejected
+    let mut config = config(EmbeddingBackendKind::Onnx);
+    config.model = Some("bge-small-en-v1.5".to_string());
+    config.models_dir = dir.clone();
+    config.dimension = Some(768);
+    assert!(models::resolve(&config).is_err());
+    config.dimension = None;
+    let resolved = models::resolve(&config).expect("Installed model should resolve");
+    assert_eq!(resolved.dimension, 384);
+    assert_eq!(resolved.max_seq_len, 512);
+
+    assert!(manager.remove("bge-small-en-v1.5").unwrap());
+    assert!(manager.installed("bge-small-en-v1.5").unwrap().is_none());
+    assert!(!dir.join("bge-small-en-v1.5").exists());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_model_manager_refuses_unknown_names() {
+    let dir = std::env::temp_dir().join(format!("arxiv-tui-models-names-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(dir.join("models")).unwrap();
+    let manager = ModelManager::new(dir.join("models"), "http://localhost");
+
+    for name in [
+        "",
+        ".",
+        "..",
+        "../models",
+        "nested/model",
+        "nested\\model",
+        "not-a-model",
+    ] {
+        assert!(manager.remove(name).is_err(), "{:?}", name);
+        assert!(manager.verify(name).is_err(), "{:?}", name);
+    }
+    assert!(dir.join("models").exists());
+    // Known models that are not installed are simply not there
+    assert!(!manager.remove("e5-small-v2").unwrap());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_model_manager_rejects_checksum_mismatch() {
+    let mut server = mockito::Server::new_async().await;
+    let _model = server
+        .mock("GET", "/intfloat/e5-small-v2/resolve/main/onnx/model.onnx")
+        .with_header("x-linked-etag", &format!("\"{}\"", "0".repeat(64)))
+        .with_body("tampered")
+        .create_async()
+        .await;
+
+    let dir = std::env::temp_dir().join(format!("arxiv-tui-models-bad-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let manager = ModelManager::new(&dir, &server.url());
+
+    let err = manager
+        .install("e5-small-v2")
+        .await
+        .expect_err("Checksum mismatch should fail the install");
+    assert!(err.to_string().contains("Checksum mismatch"));
+    assert!(manager.installed("e5-small-v2").unwrap().is_none());
+    assert!(!dir.join("e5-small-v2").join(models::MODEL_FILE).exists());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[tokio::test]
+async fn test_model_manager_prefers_pinned_checksums() {
+    const PINNED: &[ModelFile] = &[ModelFile {
+        local_name: models::MODEL_FILE,
+        remote_path: "onnx/model.onnx",
+        // SHA-256 of "fake onnx graph"
+        sha256: Some("6140e1c3359508f9426dc105eeb358aef10466f3628a3d5a0119d90e68722783"),
+    }];
+    let spec = ModelSpec {
+        name: "pinned-test",
+        repo: "test/pinned",
+        dimension: 3,
+        max_seq_len: 16,
+        files: PINNED,
+    };
+
+    // The server vouches for a file that does not match the pin
+    let mut server = mockito::Server::new_async().await;
+    let tampered = server
+        .mock("GET", "/test/pinned/resolve/main/onnx/model.onnx")
+        .with_header(
+            "x-linked-etag",
+            &format!("\"{:x}\"", Sha256::digest(b"tampered")),
+        )
+        .with_body("tampered")
+        .create_async()
+        .await;
+
+    let dir = std::env::temp_dir().join(format!("arxiv-tui-models-pinned-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    let manager = ModelManager::new(&dir, &server.url());
+
+    let err = manager
+        .install_spec(&spec)
+        .await
+        .expect_err("A file that differs from its pin should fail the install");
+    assert!(err.to_string().contains("Checksum mismatch"));
+    assert!(manager.installed("pinned-test").unwrap().is_none());
+
+    // The pinned file installs without any published checksum
+    tampered.remove_async().await;
+    server
+        .mock("GET", "/test/pinned/resolve/main/onnx/model.onnx")
+        .with_body("fake onnx graph")
+        .create_async()
+        .await;
+    let installed = manager.install_spec(&spec).await.unwrap();
+    assert_eq!(
+        installed.files.get(models::MODEL_FILE).map(String::as_str),
+        PINNED[0].sha256
+    );
+    assert!(manager.verify("pinned-test").is_ok());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
@@ -0,0 +1,447 @@
+use arxiv_tui::arxiv::models::Paper;
+use arxiv_tui::config::{ExportConfig, ExportFormat};
+use arxiv_tui::db::local::LocalStore;
+use arxiv_tui::db::models::NewCollection;
+use arxiv_tui::db::store::PaperStore;
+use arxiv_tui::embeddings::backend::ModelInfo;
+use arxiv_tui::export::bibtex::{self, BibEntry, BibtexExporter, EntryType};
+use arxiv_tui::export::exporter::{self, export_path, AuthorName, Exporter};
+use arxiv_tui::export::markdown::MarkdownExporter;
+use chrono::{TimeZone, Utc};
+use std::path::{Path, PathBuf};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("arxiv-tui-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn attention() -> Paper {
+    let published = Utc.with_ymd_and_hms(2017, 6, 12, 17, 57, 34).unwrap();
+    Paper {
+        arxiv_id: "1706.03762v7".to_string(),
+        title: "Attention Is All You Need".to_string(),
+        authors: vec![
+            "Ashish Vaswani".to_string(),
+            "Noam Shazeer".to_string(),
+            "Łukasz Kaiser".to_string(),
+        ],
+        abstract_text: "The dominant sequence transduction models...".to_string(),
+        published,
+        updated: published,
+        categories: vec!["cs.CL".to_string(), "cs.LG".to_string()],
+        pdf_url: "https://arxiv.org/pdf/1706.03762v7".to_string(),
+        comment: Some("15 pages, 5 figures".to_string()),
+        journal_ref: None,
+        doi: None,
+        primary_category: "cs.CL".to_string(),
+    }
+}
+
+#[test]
+fn test_bibtex_misc_entry_for_preprint() {
+    let entry = BibEntry::from_paper(&attention());
+    assert_eq!(entry.entry_type, EntryType::Misc);
+    assert_eq!(entry.key, "vaswani2017attention");
+
+    assert_eq!(
+        entry.to_string(),
+        "@misc{vaswani2017attention,\n\
+         \x20 title = {Attention Is All You Need},\n\
+         \x20 author = {Ashish Vaswani and Noam Shazeer and {\\L}ukasz Kaiser},\n\
+         \x20 year = {2017},\n\
+         \x20 month = jun,\n\
+         \x20 eprint = {1706.03762},\n\
+         \x20 archivePrefix = {arXiv},\n\
+         \x20 primaryClass = {cs.CL},\n\
+         \x20 url = {https://arxiv.org/abs/1706.03762},\n\
+         }"
+    );
+}
+
+#[test]
+fn test_bibtex_article_with_journal_ref() {
+    let mut paper = attention();
+    paper.journal_ref = Some("Advances in NeurIPS 30 (2017) 5998-6008".to_string());
+    paper.doi = Some("10.5555/3295222.3295349".to_string());
+
+    let entry = BibEntry::from_paper(&paper);
+    assert_eq!(entry.entry_type, EntryType::Article);
+    assert_eq!(
+        entry.field("journal"),
+        Some("{Advances in NeurIPS 30 (2017) 5998-6008}")
+    );
+    assert_eq!(entry.field("doi"), Some("{10.5555/3295222.3295349}"));
+    assert_eq!(entry.field("eprint"), Some("{1706.03762}"));
+    assert!(entry
+        .to_string()
+        .starts_with("@article{vaswani2017attention,"));
+}
+
+#[test]
+fn test_bibtex_escapes_latex() {
+    assert_eq!(
+        bibtex::escape_latex("Q&A at 100% for C# in my_dir"),
+        "Q\\&A at 100\\% for C\\# in my\\_dir"
+    );
+    // Math, commands and balanced braces from arXiv metadata are kept
+    assert_eq!(
+        bibtex::escape_latex("An $O(n \\log n)$ bound for \\emph{sparse}\n  {GANs}"),
+        "An $O(n \\log n)$ bound for \\emph{sparse} {GANs}"
+    );
+    assert_eq!(
+        bibtex::escape_latex("Costs $5 and ~3^2"),
+        "Costs \\$5 and \\textasciitilde{}3\\textasciicircum{}2"
+    );
+    assert_eq!(
+        bibtex::escape_latex("an unmatched } brace"),
+        "an unmatched \\} brace"
+    );
+    assert_eq!(
+        bibtex::escape_latex("Gödel, Erdős, Müller, Çelik, Šimon, Jiří"),
+        "G{\\\"o}del, Erdős, M{\\\"u}ller, {\\c{C}}elik, {\\v{S}}imon, Ji{\\v{r}}{\\'\\i}"
+    );
+
+    let mut paper = attention();
+    paper.title = "BERT: Pre-training of Deep Bidirectional Transformers".to_string();
+    let entry = BibEntry::from_paper(&paper);
+    assert_eq!(
+        entry.field("title"),
+        Some("{{BERT:} Pre-training of Deep Bidirectional Transformers}")
+    );
+    assert_eq!(entry.key, "vaswani2017bert");
+}
+
+#[test]
+fn test_bibtex_keys_are_stable_and_unique() {
+    let mut first = attention();
+    first.authors = vec!["José Müller-Ñúñez".to_string()];
+    first.title = "On the Theory of Everything".to_string();
+    assert_eq!(bibtex::citation_key(&first), "mullernunez2017theory");
+
+    let mut second = first.clone();
+    second.arxiv_id = "1706.09999v1".to_string();
+    let mut third = first.clone();
+    third.arxiv_id = "1706.05000v2".to_string();
+
+    // Suffixes follow arXiv ID order, whatever the input order
+    let bib = bibtex::bibliography(&[second.clone(), first.clone(), third.clone()]);
+    let keys: Vec<&str> = bib
+        .lines()
+        .filter_map(|line| line.strip_prefix("@misc{"))
+        .map(|line| line.trim_end_matches(','))
+        .collect();
+    assert_eq!(
+        keys,
+        vec![
+            "mullernunez2017theoryb",
+            "mullernunez2017theory",
+            "mullernunez2017theorya"
+        ]
+    );
+    assert_eq!(bib.matches("\n\n@misc").count(), 2);
+    assert_eq!(bibtex::bibliography(&[]), "");
+
+    let mut anonymous = attention();
+    anonymous.authors.clear();
+    assert_eq!(bibtex::citation_key(&anonymous), "anon2017attention");
+}
+
+#[tokio::test]
+async fn test_bibtex_exports_collection() {
+    let dir = temp_dir("bibtex-collection");
+    let store = LocalStore::open(&dir.join("library.json")).unwrap();
+    let model = ModelInfo {
+        name: "minilm".to_string(),
+        version: "v1".to_string(),
+        dimension: 2,
+    };
+
+    let mut cited = attention();
+    cited.journal_ref = Some("NeurIPS 2017".to_string());
+    store
+        .upsert_paper(&cited, vec![1.0, 0.0], &[], &model)
+        .await
+        .unwrap();
+    store
+        .create_collection(&NewCollection::new("Thesis"))
+        .await
+        .unwrap();
+    store
+        .add_paper_to_collection("1706.03762", "Thesis")
+        .await
+        .unwrap();
+
+    let path = dir.join("refs").join("thesis.bib");
+    let count = exporter::export_collection(&store, &BibtexExporter, "Thesis", &path)
+        .await
+        .unwrap();
+    assert_eq!(count, 1);
+
+    let bib = std::fs::read_to_string(&path).unwrap();
+    assert!(bib.starts_with("@article{vaswani2017attention,\n"));
+    assert!(bib.contains("  journal = {NeurIPS 2017},\n"));
+    assert!(bib.contains("  eprint = {1706.03762},\n"));
+    assert!(
+        exporter::export_collection(&store, &BibtexExporter, "Missing", &path)
+            .await
+            .is_err()
+    );
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn test_parse_bibliography_finds_arxiv_ids() {
+    let bib = r#"@string{neurips = "Advances in Neural Information Processing Systems"}
+@comment{Exported from a reference manager}
+
+@misc{vaswani2017attention,
+  title = {Attention Is {All} You Need},
+  author = {Vaswani, Ashish and Shazeer, Noam},
+  year = 2017,
+  eprint = {1706.03762v5},
+  archivePrefix = {arXiv},
+}
+
+@article{devlin2018bert,
+  title = "{BERT}: Pre-training of Deep Bidirectional Transformers",
+  journal = {arXiv preprint arXiv:1810.04805},
+  year = {2018}
+}
+
+@inproceedings(he2016resnet,
+  title = {Deep Residual Learning},
+  booktitle = neurips # " 2016",
+  url = {https://arxiv.org/abs/1512.03385}
+)
+
+@misc{hu2021lora,
+  title = {{LoRA}: Low-Rank Adaptation of Large Language Models},
+  doi = {10.48550/arXiv.2106.09685},
+  note = {Published at ICLR 2022}
+}
+
+@article{lecun1998gradient,
+  title = {Gradient-based learning applied to document recognition},
+  journal = {Proceedings of the IEEE},
+  eprint = {hal-01234567},
+  eprinttype = {hal}
+}
+"#;
+
+    let entries = bibtex::parse_bibliography(bib);
+    let keys: Vec<&str> = entries.iter().map(|entry| entry.key.as_str()).collect();
+    assert_eq!(
+        keys,
+        vec![
+            "vaswani2017attention",
+            "devlin2018bert",
+            "he2016resnet",
+            "hu2021lora",
+            "lecun1998gradient"
+        ]
+    );
+    assert_eq!(entries[0].entry_type, "misc");
+    assert_eq!(
+        entries[0].field("title"),
+        Some("Attention Is {All} You Need")
+    );
+    assert_eq!(entries[0].field("year"), Some("2017"));
+    assert_eq!(
+        entries[1].field("title"),
+        Some("{BERT}: Pre-training of Deep Bidirectional Transformers")
+    );
+    assert_eq!(entries[2].field("booktitle"), Some("neurips 2016"));
+
+    let ids: Vec<Option<String>> = entries
+        .iter()
+        .map(|entry| entry.arxiv_id().map(|id| id.to_string()))
+        .collect();
+    assert_eq!(
+        ids,
+        vec![
+            Some("1706.03762v5".to_string()),
+            Some("1810.04805".to_string()),
+            Some("1512.03385".to_string()),
+            Some("2106.09685".to_string()),
+            None
+        ]
+    );
+
+    // Our own exports read back to the papers they came from
+    let exported = bibtex::parse_bibliography(&bibtex::bibliography(&[attention()]));
+    assert_eq!(exported.len(), 1);
+    assert_eq!(
+        exported[0].arxiv_id().map(|id| id.base().to_string()),
+        Some("1706.03762".to_string())
+    );
+}
+
+#[test]
+fn test_csl_and_ris_keys_are_unique() {
+    let first = attention();
+    let mut second = attention();
+    second.arxiv_id = "1706.01234v1".to_string();
+    let papers = [first, second];
+    assert_eq!(
+        exporter::citation_keys(&papers),
+        vec!["vaswani2017attentiona", "vaswani2017attention"]
+    );
+
+    let config = ExportConfig::default();
+    let json = exporter::exporter(ExportFormat::CslJson, &config)
+        .unwrap()
+        .export(&papers)
+        .unwrap();
+    let items: serde_json::Value = serde_json::from_str(&json).unwrap();
+    assert_eq!(items[0]["id"], "vaswani2017attentiona");
+    assert_eq!(items[1]["id"], "vaswani2017attention");
+
+    let ris = exporter::exporter(ExportFormat::Ris, &config)
+        .unwrap()
+        .export(&papers)
+        .unwrap();
+    let ids: Vec<&str> = ris
+        .lines()
+        .filter_map(|line| line.strip_prefix("ID  - "))
+        .collect();
+    assert_eq!(ids, vec!["vaswani2017attentiona", "vaswani2017attention"]);
+}
+
+#[test]
+fn test_ris_export() {
+    let mut paper = attention();
+    paper.authors.push("Martin Luther King Jr.".to_string());
+    let config = ExportConfig::default();
+    let ris = exporter::exporter(ExportFormat::Ris, &config)
+        .unwrap()
+        .export(&[paper.clone()])
+        .unwrap();
+
+    let lines: Vec<&str> = ris.lines().collect();
+    assert_eq!(lines[0], "TY  - UNPB");
+    assert_eq!(lines[1], "ID  - vaswani2017attention");
+    assert_eq!(lines[2], "TI  - Attention Is All You Need");
+    assert_eq!(lines[3], "AU  - Vaswani, Ashish");
+    assert!(lines.contains(&"AU  - King, Martin Luther, Jr."));
+    assert!(lines.contains(&"PY  - 2017"));
+    assert!(lines.contains(&"DA  - 2017/06/12/"));
+    assert!(lines.contains(&"UR  - https://arxiv.org/abs/1706.03762"));
+    assert!(lines.contains(&"KW  - cs.LG"));
+    assert!(lines.contains(&"N1  - 15 pages, 5 figures"));
+    assert!(lines.contains(&"AN  - arXiv:1706.03762"));
+    assert_eq!(*lines.last().unwrap(), "ER  - ");
+
+    paper.journal_ref = Some("NeurIPS 2017".to_string());
+    let ris = exporter::exporter(ExportFormat::Ris, &config)
+        .unwrap()
+        .export(&[paper.clone(), paper])
+        .unwrap();
+    assert!(ris.starts_with("TY  - JOUR\n"));
+    assert!(ris.contains("JO  - NeurIPS 2017\n"));
+    assert_eq!(ris.matches("ER  - \n\nTY  - JOUR").count(), 1);
+}
+
+#[test]
+fn test_csl_json_export() {
+    let mut published = attention();
+    published.journal_ref = Some("NeurIPS 2017".to_string());
+    published.doi = Some("10.5555/3295222.3295349".to_string());
+
+    let json = exporter::exporter(ExportFormat::CslJson, &ExportConfig::default())
+        .unwrap()
+        .export(&[attention(), published])
+        .unwrap();
+    let items: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+    let preprint = &items[0];
+    assert_eq!(preprint["id"], "vaswani2017attention");
+    assert_eq!(preprint["type"], "article");
+    assert_eq!(preprint["publisher"], "arXiv");
+    assert_eq!(preprint["number"], "arXiv:1706.03762");
+    assert_eq!(preprint["author"][0]["family"], "Vaswani");
+    assert_eq!(preprint["author"][0]["given"], "Ashish");
+    assert_eq!(preprint["author"][2]["family"], "Kaiser");
+    assert_eq!(
+        preprint["issued"]["date-parts"],
+        serde_json::json!([[2017, 6, 12]])
+    );
+    assert_eq!(preprint["URL"], "https://arxiv.org/abs/1706.03762");
+
+    let article = &items[1];
+    assert_eq!(article["type"], "article-journal");
+    assert_eq!(article["container-title"], "NeurIPS 2017");
+    assert_eq!(article["DOI"], "10.5555/3295222.3295349");
+    assert!(article.get("publisher").is_none());
+}
+
+#[test]
+fn test_markdown_export_uses_template() {
+    let exporter = MarkdownExporter::new("# {title}\n{authors} ({year}) {{{key}}}\n{doi}").unwrap();
+    let mut second = attention();
+    second.title = "Second\n  paper".to_string();
+    let markdown = exporter.export(&[attention(), second]).unwrap();
+    assert_eq!(
+        markdown,
+        "# Attention Is All You Need\n\
+         Ashish Vaswani, Noam Shazeer, Łukasz Kaiser (2017) {vaswani2017attention}\n\
+         \n\
+         # Second paper\n\
+         Ashish Vaswani, Noam Shazeer, Łukasz Kaiser (2017) {vaswani2017second}\n"
+    );
+
+    let config = ExportConfig::default();
+    let default = exporter::exporter(ExportFormat::Markdown, &config)
+        .unwrap()
+        .export(&[attention()])
+        .unwrap();
+    assert!(default.starts_with("## Attention Is All You Need\n"));
+    assert!(default.contains("[1706.03762](https://arxiv.org/abs/1706.03762)"));
+
+    assert!(MarkdownExporter::new("{titel}").is_err());
+    assert!(MarkdownExporter::new("{title").is_err());
+    assert!(MarkdownExporter::new("title}").is_err());
+}
+
+#[test]
+fn test_export_formats_and_paths() {
+    assert_eq!(
+        "csl-json".parse::<ExportFormat>().unwrap(),
+        ExportFormat::CslJson
+    );
+    assert_eq!("bib".parse::<ExportFormat>().unwrap(), ExportFormat::Bibtex);
+    assert_eq!(
+        "MD".parse::<ExportFormat>().unwrap(),
+        ExportFormat::Markdown
+    );
+    assert!("docx".parse::<ExportFormat>().is_err());
+    assert_eq!(ExportFormat::Markdown.next(), ExportFormat::Bibtex);
+
+    for format in ExportFormat::ALL {
+        let exporter = exporter::exporter(format, &ExportConfig::default()).unwrap();
+        assert_eq!(exporter.format(), format);
+    }
+
+    assert_eq!(
+        export_path(
+            Path::new("/tmp/out"),
+            "Reading list: 2024!",
+            ExportFormat::Ris
+        ),
+        PathBuf::from("/tmp/out/reading-list-2024.ris")
+    );
+    assert_eq!(
+        export_path(Path::new("/tmp/out"), "1706.03762", ExportFormat::Bibtex),
+        PathBuf::from("/tmp/out/1706.03762.bib")
+    );
+
+    assert_eq!(
+        AuthorName::parse("Jan van der Berg"),
+        AuthorName {
+            given: "Jan".to_string(),
+            family: "van der Berg".to_string(),
+            suffix: String::new(),
+        }
+    );
+}
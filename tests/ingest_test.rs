@@ -0,0 +1,449 @@
+use anyhow::{bail, Result};
+use arxiv_tui::arxiv::client::{ArxivClient, ID_LIST_CHUNK};
+use arxiv_tui::arxiv::models::{ArxivId, Paper};
+use arxiv_tui::arxiv::rate_limit::{Budget, RateLimiter};
+use arxiv_tui::config::{ArxivConfig, ChunkingConfig, IngestConfig};
+use arxiv_tui::db::local::LocalStore;
+use arxiv_tui::db::store::PaperStore;
+use arxiv_tui::embeddings::backend::EmbeddingBackend;
+use arxiv_tui::embeddings::chunking::DocumentEmbedder;
+use arxiv_tui::embeddings::hash::HashBackend;
+use arxiv_tui::ingest::by_id::{stored_ids, IdImport};
+use arxiv_tui::ingest::checkpoint::Checkpoint;
+use arxiv_tui::ingest::pipeline::{IngestPipeline, IngestStage};
+use arxiv_tui::ingest::source::{PaperSource, StaticSource};
+use async_trait::async_trait;
+use chrono::Utc;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("arxiv-tui-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+fn paper(arxiv_id: &str) -> Paper {
+    Paper {
+        arxiv_id: arxiv_id.to_string(),
+        title: format!("Paper {}", arxiv_id),
+        authors: vec!["Ada Lovelace".to_string()],
+        abstract_text: format!("Abstract of paper {}", arxiv_id),
+        published: Utc::now(),
+        updated: Utc::now(),
+        categories: vec!["cs.LG".to_string()],
+        pdf_url: format!("https://arxiv.org/pdf/{}", arxiv_id),
+        comment: None,
+        journal_ref: None,
+        doi: None,
+        primary_category: "cs.LG".to_string(),
+    }
+}
+
+fn papers(count: usize) -> Vec<Paper> {
+    (1..=count)
+        .map(|i| paper(&format!("2401.{:05}v1", i)))
+        .collect()
+}
+
+fn ingest_config(dir: &std::path::Path) -> IngestConfig {
+    IngestConfig {
+        page_size: 3,
+        batch_size: 2,
+        concurrency: 2,
+        write_concurrency: 2,
+        checkpoint_path: dir.join("checkpoint.json"),
+    }
+}
+
+fn embedder() -> DocumentEmbedder {
+    DocumentEmbedder::new(Arc::new(HashBackend::new(16)), &ChunkingConfig::default())
+}
+
+/// Serves a fixed list of papers but fails every page after the first one
+/// until `recovered` is set.
+struct FlakySource {
+    papers: Vec<Paper>,
+    recovered: AtomicBool,
+}
+
+#[async_trait]
+impl PaperSource for FlakySource {
+    fn key(&self) -> String {
+        "flaky".to_string()
+    }
+
+    async fn fetch_page(&self, start: usize, size: u32) -> Result<Vec<Paper>> {
+        if start > 0 && !self.recovered.load(Ordering::SeqCst) {
+            bail!("arXiv API error: 503 Service Unavailable");
+        }
+        Ok(self
+            .papers
+            .iter()
+            .skip(start)
+            .take(size as usize)
+            .cloned()
+            .collect())
+    }
+}
+
+#[tokio::test]
+async fn test_ingest_dedups_and_reports() {
+    let dir = temp_dir("ingest-dedup");
+    let store = LocalStore::open(&dir.join("library.json")).unwrap();
+    let embedder = embedder();
+
+    // The second page repeats a paper from the first, with another version
+    let mut source_papers = papers(6);
+    source_papers.insert(3, paper("2401.00002v2"));
+    let source = StaticSource::new("static", source_papers);
+
+    let mut pages = Vec::new();
+    let report = IngestPipeline::new(&store, &embedder, &ingest_config(&dir))
+        .run(&source, &mut |progress| pages.push(progress.pages))
+        .await
+        .unwrap();
+
+    assert_eq!(pages, vec![1, 2, 3]);
+    assert_eq!(report.pages, 3);
+    assert_eq!(report.fetched, 7);
+    assert_eq!(report.duplicates, 1);
+    assert_eq!(report.upserts.inserted, 6);
+    assert_eq!(report.upserts.failed, 0);
+    assert!(report.failures.is_empty());
+    assert!(report.complete);
+    assert_eq!(report.resumed_from, None);
+    assert_eq!(
+        report.changed,
+        vec![
+            "2401.00001",
+            "2401.00002",
+            "2401.00003",
+            "2401.00004",
+            "2401.00005",
+            "2401.00006"
+        ]
+    );
+    assert_eq!(store.list_papers().await.unwrap().len(), 6);
+    assert!(report
+        .to_string()
+        .starts_with("3 pages, 7 fetched, 1 duplicates"));
+
+    // Running again finds everything up to date
+    let rerun = IngestPipeline::new(&store, &embedder, &ingest_config(&dir))
+        .run(&source, &mut |_| {})
+        .await
+        .unwrap();
+    assert_eq!(rerun.resumed_from, None);
+    assert_eq!(rerun.upserts.skipped, 6);
+    assert!(rerun.changed.is_empty());
+}
+
+#[tokio::test]
+async fn test_ingest_resumes_from_checkpoint() {
+    let dir = temp_dir("ingest-resume");
+    let config = ingest_config(&dir);
+    let store = LocalStore::open(&dir.join("library.json")).unwrap();
+    let embedder = embedder();
+    let source = FlakySource {
+        papers: papers(7),
+        recovered: AtomicBool::new(false),
+    };
+
+    let first = IngestPipeline::new(&store, &embedder, &config)
+        .run(&source, &mut |_| {})
+        .await
+        .unwrap();
+    assert!(!first.complete);
+    assert_eq!(first.upserts.inserted, 3);
+    assert_eq!(first.failures.len(), 1);
+    assert_eq!(first.failures[0].stage, IngestStage::Fetch);
+
+    let checkpoint = Checkpoint::resume(&config.checkpoint_path, "flaky").unwrap();
+    assert_eq!(checkpoint.next_start, 3);
+    assert_eq!(checkpoint.done.len(), 3);
+    assert!(!checkpoint.finished);
+
+    source.recovered.store(true, Ordering::SeqCst);
+    let second = IngestPipeline::new(&store, &embedder, &config)
+        .run(&source, &mut |_| {})
+        .await
+        .unwrap();
+    assert!(second.complete);
+    assert_eq!(second.resumed_from, Some(3));
+    assert_eq!(second.fetched, 4);
+    assert_eq!(second.upserts.inserted, 4);
+    assert_eq!(store.list_papers().await.unwrap().len(), 7);
+
+    // A finished run is not resumed
+    let checkpoint = Checkpoint::resume(&config.checkpoint_path, "flaky").unwrap();
+    assert_eq!(checkpoint.next_start, 0);
+}
+
+/// Fails every batch with a paper about poison until `healed` is set.
+struct PoisonedBackend {
+    inner: HashBackend,
+    healed: AtomicBool,
+}
+
+#[async_trait]
+impl EmbeddingBackend for PoisonedBackend {
+    fn model_name(&self) -> &str {
+        self.inner.model_name()
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn batch_size(&self) -> usize {
+        self.inner.batch_size()
+    }
+
+    async fn embed_batch(&self, texts: &[&str]) -> Result<Vec<Vec<f32>>> {
+        if !self.healed.load(Ordering::SeqCst) && texts.iter().any(|t| t.contains("poison")) {
+            bail!("embedding model crashed");
+        }
+        self.inner.embed_batch(texts).await
+    }
+}
+
+#[tokio::test]
+async fn test_ingest_retries_failed_papers_on_resume() {
+    let dir = temp_dir("ingest-retry");
+    let config = ingest_config(&dir);
+    let store = LocalStore::open(&dir.join("library.json")).unwrap();
+    let backend = Arc::new(PoisonedBackend {
+        inner: HashBackend::new(16),
+        healed: AtomicBool::new(false),
+    });
+    let embedder = DocumentEmbedder::new(backend.clone(), &ChunkingConfig::default());
+    let mut source_papers = papers(7);
+    source_papers[1].abstract_text = "A study of poison".to_string();
+    let source = FlakySource {
+        papers: source_papers,
+        recovered: AtomicBool::new(false),
+    };
+
+    // The first batch of the first page fails, then the second page
+    let first = IngestPipeline::new(&store, &embedder, &config)
+        .run(&source, &mut |_| {})
+        .await
+        .unwrap();
+    assert_eq!(first.upserts.inserted, 1);
+    assert_eq!(first.upserts.failed, 2);
+    let checkpoint = Checkpoint::resume(&config.checkpoint_path, "flaky").unwrap();
+    assert_eq!(checkpoint.next_start, 3);
+    assert_eq!(checkpoint.failed.len(), 2);
+    assert_eq!(checkpoint.retry_from, Some(0));
+
+    // Resuming goes back for the failed papers and skips the done one
+    backend.healed.store(true, Ordering::SeqCst);
+    source.recovered.store(true, Ordering::SeqCst);
+    let second = IngestPipeline::new(&store, &embedder, &config)
+        .run(&source, &mut |_| {})
+        .await
+        .unwrap();
+    assert!(second.complete);
+    assert_eq!(second.resumed_from, Some(0));
+    assert_eq!(second.upserts.inserted, 6);
+    assert_eq!(second.duplicates, 1);
+    assert!(second.failures.is_empty());
+    assert_eq!(store.list_papers().await.unwrap().len(), 7);
+}
+
+#[tokio::test]
+async fn test_ingest_stops_at_max_papers() {
+    let dir = temp_dir("ingest-max");
+    let store = LocalStore::open(&dir.join("library.json")).unwrap();
+    let embedder = embedder();
+    let source = StaticSource::new("static", papers(10));
+
+    let report = IngestPipeline::new(&store, &embedder, &ingest_config(&dir))
+        .without_checkpoint()
+        .with_max_papers(4)
+        .run(&source, &mut |_| {})
+        .await
+        .unwrap();
+
+    assert!(report.complete);
+    assert_eq!(report.pages, 2);
+    assert_eq!(report.fetched, 4);
+    assert_eq!(report.upserts.inserted, 4);
+    assert!(!dir.join("checkpoint.json").exists());
+}
+
+/// A stand-in arXiv API answering `id_list` queries with a feed of the
+/// requested papers, in reverse order, leaving out `unknown` IDs. Versioned
+/// IDs are returned at that version, others at v2. Records each request's
+/// ID list.
+async fn fake_arxiv(
+    unknown: &'static [&'static str],
+) -> (ArxivClient, Arc<Mutex<Vec<Vec<String>>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/api/query", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let seen = requests.clone();
+    tokio::spawn(async move {
+        while let Ok((mut socket, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            while !request.ends_with(b"\r\n\r\n") {
+                match socket.read(&mut buf).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buf[..n]),
+                }
+            }
+            let request = String::from_utf8_lossy(&request);
+            let id_list = request
+                .split_whitespace()
+                .nth(1)
+                .and_then(|target| target.split_once("id_list="))
+                .map(|(_, rest)| rest.split('&').next().unwrap_or_default())
+                .unwrap_or_default();
+            let ids: Vec<String> = id_list
+                .split(',')
+                .map(|id| urlencoding::decode(id).unwrap().into_owned())
+                .collect();
+
+            let entries: String = ids
+                .iter()
+                .rev()
+                .filter(|id| !unknown.contains(&id.as_str()))
+                .map(|id| {
+                    let versioned = if id.contains('v') {
+                        id.clone()
+                    } else {
+                        format!("{}v2", id)
+                    };
+                    format!(
+                        "<entry><id>http://arxiv.org/abs/{0}</id>\
+                         <updated>2024-01-02T00:00:00Z</updated>\
+                         <published>2024-01-01T00:00:00Z</published>\
+                         <title>Paper {0}</title><summary>Abstract of {0}</summary>\
+                         <author><name>Ada Lovelace</name></author>\
+                         <arxiv:primary_category term=\"cs.LG\"/>\
+                         <category term=\"cs.LG\"/></entry>",
+                        versioned
+                    )
+                })
+                .collect();
+            seen.lock().unwrap().push(ids);
+
+            let body = format!(
+                "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\
+                 <feed xmlns=\"http://www.w3.org/2005/Atom\" \
+                 xmlns:arxiv=\"http://arxiv.org/schemas/atom\">{}</feed>",
+                entries
+            );
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/atom+xml\r\n\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            let _ = socket.write_all(response.as_bytes()).await;
+        }
+    });
+
+    let config = ArxivConfig {
+        max_retries: 0,
+        ..ArxivConfig::default()
+    };
+    let client = ArxivClient::from_config(&config)
+        .with_api_url(&url)
+        .with_rate_limiters(
+            RateLimiter::new(Budget::every(Duration::ZERO)),
+            RateLimiter::new(Budget::every(Duration::ZERO)),
+        );
+    (client, requests)
+}
+
+fn arxiv_ids(ids: &[&str]) -> Vec<ArxivId> {
+    ids.iter().map(|id| id.parse().unwrap()).collect()
+}
+
+#[tokio::test]
+async fn test_get_by_ids_chunks_and_keeps_order() {
+    let (client, requests) = fake_arxiv(&["2401.99999"]).await;
+
+    let found: Vec<String> = (1..=ID_LIST_CHUNK + 5)
+        .map(|i| format!("2401.{:05}", i))
+        .chain(["math/0309136".to_string()])
+        .collect();
+    let mut ids: Vec<&str> = found.iter().map(String::as_str).collect();
+    ids.insert(50, "2401.99999");
+    ids.insert(60, "2401.00003v1");
+    ids.pop();
+    ids.push("math.GT/0309136");
+
+    let fetched = client.get_by_ids(&arxiv_ids(&ids)).await.unwrap();
+
+    // IDs are asked for in chunks; an explicit version is its own request
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 2);
+    assert_eq!(requests[0].len(), ID_LIST_CHUNK);
+    assert_eq!(requests[1].len(), 8);
+    assert_eq!(requests[1].last().unwrap(), "math/0309136");
+
+    // The version asked for, or the latest
+    let mut expected: Vec<String> = found.iter().map(|id| format!("{}v2", id)).collect();
+    expected.insert(59, "2401.00003v1".to_string());
+    let fetched_ids: Vec<&str> = fetched.papers.iter().map(|p| p.arxiv_id.as_str()).collect();
+    assert_eq!(fetched_ids, expected);
+    assert_eq!(fetched.missing, arxiv_ids(&["2401.99999"]));
+}
+
+#[tokio::test]
+async fn test_id_import_refreshes_and_collects() {
+    let dir = temp_dir("ingest-by-id");
+    let store = LocalStore::open(&dir.join("library.json")).unwrap();
+    let embedder = embedder();
+    let (client, _) = fake_arxiv(&["2401.99999"]).await;
+
+    IngestPipeline::new(&store, &embedder, &ingest_config(&dir))
+        .without_checkpoint()
+        .run(&StaticSource::new("static", papers(3)), &mut |_| {})
+        .await
+        .unwrap();
+
+    // Refreshing asks for the latest versions, v2 on the fake arXiv
+    let ids = stored_ids(&store, None).await.unwrap();
+    assert_eq!(ids.len(), 3);
+    assert!(ids.iter().all(|id| id.version().is_none()));
+    let refresh = IdImport::new(&client, &store, &embedder, &ingest_config(&dir))
+        .run(&ids, &mut |_| {})
+        .await
+        .unwrap();
+    assert_eq!(refresh.requested, 3);
+    assert!(refresh.missing.is_empty());
+    assert_eq!(refresh.ingest.upserts.updated, 3);
+    assert!(!dir.join("checkpoint.json").exists());
+
+    let import = IdImport::new(&client, &store, &embedder, &ingest_config(&dir))
+        .into_collection("Cited")
+        .run(
+            &arxiv_ids(&["2401.00004", "2401.99999", "2401.00001"]),
+            &mut |_| {},
+        )
+        .await
+        .unwrap();
+    assert_eq!(import.requested, 3);
+    assert_eq!(import.missing, arxiv_ids(&["2401.99999"]));
+    assert_eq!(import.ingest.upserts.inserted, 1);
+    assert_eq!(import.ingest.upserts.skipped, 1);
+    assert_eq!(import.collected, 2);
+    assert!(import.to_string().starts_with("3 requested, 1 missing; "));
+
+    let cited = stored_ids(&store, Some("Cited")).await.unwrap();
+    let mut cited: Vec<String> = cited.iter().map(ToString::to_string).collect();
+    cited.sort();
+    assert_eq!(cited, vec!["2401.00001", "2401.00004"]);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
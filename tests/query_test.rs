@@ -0,0 +1,95 @@
+use arxiv_tui::arxiv::query::{Field, Query};
+use chrono::NaiveDate;
+
+fn parse(input: &str) -> String {
+    input
+        .parse::<Query>()
+        .unwrap_or_else(|e| panic!("{:?} should parse: {:#}", input, e))
+        .to_string()
+}
+
+#[test]
+fn test_builder_renders_search_query() {
+    let query = Query::author("hinton")
+        .and(Query::category("cs.LG").or(Query::category("stat.ML")))
+        .and_not(Query::title("a survey"));
+    assert_eq!(
+        query.to_string(),
+        "(au:hinton AND (cat:cs.LG OR cat:stat.ML)) ANDNOT ti:\"a survey\""
+    );
+
+    let from = NaiveDate::from_ymd_opt(2023, 1, 1)
+        .unwrap()
+        .and_hms_opt(0, 0, 0)
+        .unwrap();
+    let to = NaiveDate::from_ymd_opt(2023, 12, 31)
+        .unwrap()
+        .and_hms_opt(23, 59, 0)
+        .unwrap();
+    let query = Query::term(Field::Abstract, "diffusion").and(Query::submitted_between(from, to));
+    assert_eq!(
+        query.to_string(),
+        "abs:diffusion AND submittedDate:[202301010000 TO 202312312359]"
+    );
+}
+
+#[test]
+fn test_parse_friendly_syntax() {
+    assert_eq!(
+        parse("au:hinton cat:cs.LG after:2024-01"),
+        "au:hinton AND cat:cs.LG AND submittedDate:[202401010000 TO 999912312359]"
+    );
+    assert_eq!(
+        parse("title:\"attention is all you need\" author:vaswani"),
+        "ti:\"attention is all you need\" AND au:vaswani"
+    );
+    assert_eq!(
+        parse("\"graph neural\" networks"),
+        "all:\"graph neural\" AND all:networks"
+    );
+    assert_eq!(
+        parse("after:2023 before:2023-07"),
+        "submittedDate:[202301010000 TO 202306302359]"
+    );
+    assert_eq!(parse("cat:cs.CV"), "cat:cs.CV");
+}
+
+#[test]
+fn test_parse_operators_and_grouping() {
+    assert_eq!(
+        parse("transformer (cat:cs.CL OR cat:cs.LG) -vision"),
+        "(all:transformer AND (cat:cs.CL OR cat:cs.LG)) ANDNOT all:vision"
+    );
+    assert_eq!(
+        parse("ti:bert AND au:devlin OR ti:gpt"),
+        "(ti:bert AND au:devlin) OR ti:gpt"
+    );
+    assert_eq!(
+        parse("ti:llm NOT ti:survey NOT ti:review"),
+        "ti:llm ANDNOT (ti:survey OR ti:review)"
+    );
+    assert_eq!(parse("ti:llm ANDNOT abs:agent"), "ti:llm ANDNOT abs:agent");
+}
+
+#[test]
+fn test_parse_errors() {
+    for input in [
+        "",
+        "   ",
+        "-vision",
+        "foo:bar",
+        "ti:",
+        "(cat:cs.LG",
+        "cat:cs.LG)",
+        "\"unclosed phrase",
+        "after:2024-13",
+        "after:24",
+        "ti:bert OR",
+    ] {
+        assert!(
+            input.parse::<Query>().is_err(),
+            "{:?} should be rejected",
+            input
+        );
+    }
+}
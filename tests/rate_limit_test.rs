@@ -0,0 +1,102 @@
+use arxiv_tui::arxiv::client::ArxivClient;
+use arxiv_tui::arxiv::rate_limit::{Budget, RateLimiter};
+use std::time::{Duration, Instant};
+
+const INTERVAL: Duration = Duration::from_millis(100);
+
+#[tokio::test]
+async fn test_first_request_is_not_delayed() {
+    let limiter = RateLimiter::new(Budget::every(INTERVAL));
+
+    let started = Instant::now();
+    limiter.acquire().await;
+    assert!(started.elapsed() < INTERVAL / 2);
+
+    limiter.acquire().await;
+    assert!(started.elapsed() >= INTERVAL);
+}
+
+#[tokio::test]
+async fn test_clones_share_budget_across_tasks() {
+    let limiter = RateLimiter::new(Budget::every(INTERVAL));
+
+    let started = Instant::now();
+    let tasks: Vec<_> = (0..4)
+        .map(|_| {
+            let limiter = limiter.clone();
+            tokio::spawn(async move {
+                limiter.acquire().await;
+                started.elapsed()
+            })
+        })
+        .collect();
+
+    let mut sent = Vec::new();
+    for task in tasks {
+        sent.push(task.await.unwrap());
+    }
+    sent.sort();
+
+    // One immediately, the others one interval apart
+    assert!(sent[0] < INTERVAL / 2);
+    for (i, at) in sent.iter().enumerate().skip(1) {
+        assert!(*at >= INTERVAL * i as u32, "request {} sent at {:?}", i, at);
+    }
+}
+
+#[tokio::test]
+async fn test_burst_and_separate_budgets() {
+    let api = RateLimiter::new(Budget::every(INTERVAL).with_burst(3));
+    let pdf = RateLimiter::new(Budget::every(INTERVAL));
+
+    let started = Instant::now();
+    for _ in 0..3 {
+        api.acquire().await;
+    }
+    // Spending the API budget leaves the PDF budget untouched
+    pdf.acquire().await;
+    assert!(started.elapsed() < INTERVAL / 2);
+
+    api.acquire().await;
+    assert!(started.elapsed() >= INTERVAL);
+}
+
+#[tokio::test]
+async fn test_shared_limiters_by_name() {
+    let budget = Budget::every(INTERVAL);
+    let first = RateLimiter::shared("rate-limit-test", budget.with_burst(2));
+    let second = RateLimiter::shared("rate-limit-test", budget);
+
+    // A stricter budget tightens the limiter for every holder
+    assert_eq!(first.budget(), budget);
+
+    // Both draw from one bucket
+    let started = Instant::now();
+    first.acquire().await;
+    assert!(started.elapsed() < INTERVAL / 2);
+    second.acquire().await;
+    assert!(started.elapsed() >= INTERVAL);
+}
+
+#[tokio::test]
+async fn test_looser_budget_does_not_loosen_shared_limiter() {
+    let strict = Budget::every(INTERVAL);
+    let first = RateLimiter::shared("rate-limit-loose", strict);
+    let second = RateLimiter::shared(
+        "rate-limit-loose",
+        Budget::every(Duration::ZERO).with_burst(5),
+    );
+    assert_eq!(first.budget(), strict);
+    assert_eq!(second.budget(), strict);
+
+    let started = Instant::now();
+    second.acquire().await;
+    second.acquire().await;
+    assert!(started.elapsed() >= INTERVAL);
+
+    // Nor can a client built without a delay lift arXiv's limit for others
+    let _polite = ArxivClient::new(3000, 10);
+    let _unthrottled = ArxivClient::new(0, 10);
+    let api = RateLimiter::shared("arxiv-api", Budget::every(Duration::ZERO));
+    assert_eq!(api.budget().interval, Duration::from_millis(3000));
+}
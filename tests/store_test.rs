@@ -0,0 +1,795 @@
+use arxiv_tui::arxiv::models::Paper;
+use arxiv_tui::config::SimilarPapersConfig;
+use arxiv_tui::db::local::LocalStore;
+use arxiv_tui::db::models::{
+    collection_tree, NewCollection, ReadState, SmartFilter, UpsertOutcome,
+};
+use arxiv_tui::db::similarity::SimilarityJob;
+use arxiv_tui::db::snapshot::{
+    ConflictPolicy, LibraryExport, LibraryImport, SnapshotRecord, SNAPSHOT_VERSION,
+};
+use arxiv_tui::db::store::PaperStore;
+use arxiv_tui::db::tags::{TagCount, TagExpr};
+use arxiv_tui::embeddings::backend::ModelInfo;
+use chrono::Utc;
+use std::path::PathBuf;
+
+fn temp_library(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("arxiv-tui-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir.join("library.json")
+}
+
+fn paper(arxiv_id: &str, title: &str) -> Paper {
+    Paper {
+        arxiv_id: arxiv_id.to_string(),
+        title: title.to_string(),
+        authors: vec!["Ada Lovelace".to_string()],
+        abstract_text: format!("Abstract of {}", title),
+        published: Utc::now(),
+        updated: Utc::now(),
+        categories: vec!["cs.LG".to_string()],
+        pdf_url: format!("https://arxiv.org/pdf/{}", arxiv_id),
+        comment: None,
+        journal_ref: None,
+        doi: None,
+        primary_category: "cs.LG".to_string(),
+    }
+}
+
+fn model(name: &str) -> ModelInfo {
+    ModelInfo {
+        name: name.to_string(),
+        version: "v1".to_string(),
+        dimension: 2,
+    }
+}
+
+#[tokio::test]
+async fn test_local_store_ranks_and_filters_by_model() {
+    let path = temp_library("local-search");
+    let store = LocalStore::open(&path).unwrap();
+    let minilm = model("minilm");
+
+    store
+        .add_paper(&paper("2401.00001", "East"), vec![1.0, 0.0], &minilm)
+        .await
+        .unwrap();
+    store
+        .add_paper(&paper("2401.00002", "North-east"), vec![0.7, 0.7], &minilm)
+        .await
+        .unwrap();
+    store
+        .add_paper(
+            &paper("2401.00003", "Other model"),
+            vec![1.0, 0.0],
+            &model("bge"),
+        )
+        .await
+        .unwrap();
+
+    let results = store
+        .search_papers(vec![1.0, 0.1], &minilm, 0.0, 10)
+        .await
+        .unwrap();
+    let ids: Vec<&str> = results.iter().map(|r| r.paper.arxiv_id.as_str()).collect();
+    assert_eq!(ids, vec!["2401.00001", "2401.00002"]);
+    assert!(results[0].similarity_score > results[1].similarity_score);
+
+    let similar = store.find_similar_papers("2401.00001", 5).await.unwrap();
+    assert_eq!(similar.len(), 1);
+    assert_eq!(similar[0].arxiv_id, "2401.00002");
+
+    let coverage = store.embedding_coverage(&minilm).await.unwrap();
+    assert_eq!((coverage.matching, coverage.total), (2, 3));
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[tokio::test]
+async fn test_local_store_persists_library_across_reopen() {
+    let path = temp_library("local-persist");
+    let minilm = model("minilm");
+
+    {
+        let store = LocalStore::open(&path).unwrap();
+        for (id, title) in [("2401.00001", "Cited"), ("2401.00002", "Citing")] {
+            let paper = paper(id, title);
+            store
+                .add_paper(&paper, vec![1.0, 0.0], &minilm)
+                .await
+                .unwrap();
+            store.add_paper_metadata(&paper).await.unwrap();
+        }
+        store
+            .create_collection(&NewCollection::new("Reading list"))
+            .await
+            .unwrap();
+        store
+            .add_paper_to_collection("2401.00001", "Reading list")
+            .await
+            .unwrap();
+        store.mark_paper_read("2401.00002", true).await.unwrap();
+        store
+            .add_citation("2401.00002", "2401.00001", "as shown in [1]")
+            .await
+            .unwrap();
+    }
+
+    let store = LocalStore::open(&path).unwrap();
+    let collections = store.get_all_collections().await.unwrap();
+    assert_eq!(collections.len(), 1);
+    assert_eq!(collections[0].name, "Reading list");
+
+    let papers = store.get_collection_papers("Reading list").await.unwrap();
+    assert_eq!(papers[0].paper.arxiv_id, "2401.00001");
+    assert!(papers[0].added_at.is_some());
+
+    let cited = store.get_cited_papers("2401.00002").await.unwrap();
+    assert_eq!(cited[0].arxiv_id, "2401.00001");
+    let citing = store.get_citing_papers("2401.00001").await.unwrap();
+    assert_eq!(citing[0].arxiv_id, "2401.00002");
+
+    // Flags survive a metadata refresh
+    let metadata = store
+        .add_paper_metadata(&paper("2401.00002", "Citing"))
+        .await
+        .unwrap();
+    assert!(metadata.read);
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[tokio::test]
+async fn test_upsert_is_idempotent_and_applies_new_versions() {
+    let path = temp_library("local-upsert");
+    let store = LocalStore::open(&path).unwrap();
+    let minilm = model("minilm");
+
+    let v1 = paper("2401.00001v1", "First draft");
+    let outcome = store
+        .upsert_paper(&v1, vec![1.0, 0.0], &[], &minilm)
+        .await
+        .unwrap();
+    assert_eq!(outcome, UpsertOutcome::Inserted);
+    store.mark_paper_read("2401.00001", true).await.unwrap();
+
+    let again = store
+        .upsert_paper(&v1, vec![1.0, 0.0], &[], &minilm)
+        .await
+        .unwrap();
+    assert_eq!(again, UpsertOutcome::Skipped);
+
+    let mut v2 = paper("2401.00001v2", "Camera ready");
+    v2.updated = v1.updated + chrono::Duration::days(30);
+    let updated = store
+        .upsert_paper(&v2, vec![0.0, 1.0], &[], &minilm)
+        .await
+        .unwrap();
+    assert_eq!(updated, UpsertOutcome::Updated);
+
+    let stored = store
+        .get_paper_by_arxiv_id("2401.00001v2")
+        .await
+        .unwrap()
+        .expect("Paper should be stored under its base ID");
+    assert_eq!(stored.arxiv_id, "2401.00001");
+    assert_eq!(stored.version, 2);
+    assert_eq!(stored.title, "Camera ready");
+
+    let coverage = store.embedding_coverage(&minilm).await.unwrap();
+    assert_eq!(coverage.total, 1);
+
+    // Refreshing metadata keeps the read flag
+    let metadata = store.mark_paper_saved("2401.00001", true).await.unwrap();
+    assert!(metadata.read);
+
+    // Versioned IDs name the stored paper everywhere
+    let metadata = store.mark_paper_saved("2401.00001v2", false).await.unwrap();
+    assert!(!metadata.saved);
+    let replaced = store
+        .replace_paper_embedding("2401.00001v1", vec![1.0, 0.0], &model("bge"))
+        .await
+        .unwrap();
+    assert_eq!(replaced.embedding_model, "bge");
+    assert_eq!(
+        store.get_paper_vector("2401.00001").await.unwrap(),
+        Some(vec![1.0, 0.0])
+    );
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn test_concurrent_upserts_insert_once() {
+    let path = temp_library("local-upsert-race");
+    let store = std::sync::Arc::new(LocalStore::open(&path).unwrap());
+
+    let tasks: Vec<_> = (0..8)
+        .map(|_| {
+            let store = store.clone();
+            tokio::spawn(async move {
+                store
+                    .upsert_paper(
+                        &paper("2401.00001v1", "Raced"),
+                        vec![1.0, 0.0],
+                        &[],
+                        &model("minilm"),
+                    )
+                    .await
+                    .unwrap()
+            })
+        })
+        .collect();
+    let mut outcomes = Vec::new();
+    for task in tasks {
+        outcomes.push(task.await.unwrap());
+    }
+
+    let inserted = outcomes
+        .iter()
+        .filter(|outcome| **outcome == UpsertOutcome::Inserted)
+        .count();
+    assert_eq!(inserted, 1);
+    assert_eq!(store.list_papers().await.unwrap().len(), 1);
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[tokio::test]
+async fn test_bursts_of_writes_are_saved_together() {
+    let path = temp_library("local-batch");
+    let store = LocalStore::open(&path).unwrap();
+    let minilm = model("minilm");
+
+    for i in 1..=50 {
+        let paper = paper(&format!("2401.{:05}", i), "Batched");
+        store
+            .upsert_paper(&paper, vec![1.0, 0.0], &[], &minilm)
+            .await
+            .unwrap();
+    }
+    // The first change is saved at once, the rest of the burst is held back
+    let on_disk = LocalStore::open(&path).unwrap();
+    assert!(on_disk.list_papers().await.unwrap().len() < 50);
+    drop(on_disk);
+
+    store.flush().await.unwrap();
+    let on_disk = LocalStore::open(&path).unwrap();
+    assert_eq!(on_disk.list_papers().await.unwrap().len(), 50);
+    drop(on_disk);
+
+    // Dropping the store saves what is left
+    store.mark_paper_saved("2401.00001", true).await.unwrap();
+    drop(store);
+    let reopened = LocalStore::open(&path).unwrap();
+    let metadata = reopened.get_paper_metadata("2401.00001").await.unwrap();
+    assert!(metadata.unwrap().saved);
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[tokio::test]
+async fn test_collections_nest_rename_and_filter() {
+    let path = temp_library("local-collections");
+    let store = LocalStore::open(&path).unwrap();
+    let minilm = model("minilm");
+
+    let mut physics = paper("2401.00002", "Quantum walks");
+    physics.categories = vec!["quant-ph".to_string()];
+    for paper in [paper("2401.00001", "Deep nets"), physics] {
+        store
+            .upsert_paper(&paper, vec![1.0, 0.0], &[], &minilm)
+            .await
+            .unwrap();
+    }
+    store.mark_paper_saved("2401.00001", true).await.unwrap();
+
+    store
+        .create_collection(&NewCollection::new("Thesis"))
+        .await
+        .unwrap();
+    store
+        .create_collection(&NewCollection {
+            parent: Some("Thesis".to_string()),
+            ..NewCollection::new("Chapter 1")
+        })
+        .await
+        .unwrap();
+    store
+        .create_collection(&NewCollection {
+            filter: Some(SmartFilter {
+                categories: vec!["cs.*".to_string()],
+                saved: Some(true),
+                ..SmartFilter::default()
+            }),
+            ..NewCollection::new("Saved ML")
+        })
+        .await
+        .unwrap();
+    assert!(store
+        .create_collection(&NewCollection::new("Thesis"))
+        .await
+        .is_err());
+
+    // Manual membership
+    store
+        .add_paper_to_collection("2401.00002v3", "Chapter 1")
+        .await
+        .unwrap();
+    store
+        .add_paper_to_collection("2401.00002", "Chapter 1")
+        .await
+        .unwrap();
+    let papers = store.get_collection_papers("Chapter 1").await.unwrap();
+    assert_eq!(papers.len(), 1);
+    store
+        .remove_paper_from_collection("2401.00002", "Chapter 1")
+        .await
+        .unwrap();
+    assert!(store
+        .get_collection_papers("Chapter 1")
+        .await
+        .unwrap()
+        .is_empty());
+
+    // Smart collections are computed, not edited
+    let smart = store.get_collection_papers("Saved ML").await.unwrap();
+    assert_eq!(smart.len(), 1);
+    assert_eq!(smart[0].paper.arxiv_id, "2401.00001");
+    assert_eq!(smart[0].added_at, None);
+    assert!(store
+        .add_paper_to_collection("2401.00002", "Saved ML")
+        .await
+        .is_err());
+
+    // Renaming keeps children attached; deleting moves them up
+    let renamed = store.rename_collection("Thesis", "PhD").await.unwrap();
+    assert_eq!(renamed.name, "PhD");
+    let tree = collection_tree(&store.get_all_collections().await.unwrap());
+    let phd = tree
+        .iter()
+        .find(|node| node.collection.name == "PhD")
+        .unwrap();
+    assert_eq!(phd.children[0].collection.name, "Chapter 1");
+
+    store.delete_collection("PhD").await.unwrap();
+    let chapter = store.get_collection("Chapter 1").await.unwrap().unwrap();
+    assert_eq!(chapter.parent(), None);
+    assert!(store.get_collection("PhD").await.unwrap().is_none());
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[tokio::test]
+async fn test_tags_and_read_states() {
+    let path = temp_library("local-tags");
+    let store = LocalStore::open(&path).unwrap();
+    let minilm = model("minilm");
+
+    for id in ["2401.00001", "2401.00002", "2401.00003"] {
+        store
+            .upsert_paper(&paper(id, id), vec![1.0, 0.0], &[], &minilm)
+            .await
+            .unwrap();
+    }
+    store.add_tag("2401.00001", "Deep Learning").await.unwrap();
+    store.add_tag("2401.00001", "survey").await.unwrap();
+    store.add_tag("2401.00002", "deep learning").await.unwrap();
+    store.add_tag("2401.00002", "nlp").await.unwrap();
+    store.add_tag("2401.00003v2", "nlp").await.unwrap();
+    assert!(store.add_tag("2401.00003", "a,b").await.is_err());
+
+    let tags = store.list_tags().await.unwrap();
+    assert_eq!(
+        tags.iter()
+            .map(|t| (t.tag.as_str(), t.count))
+            .collect::<Vec<_>>(),
+        vec![("deep-learning", 2), ("nlp", 2), ("survey", 1)]
+    );
+
+    let expr = TagExpr::parse("deep-learning and not survey").unwrap();
+    let found = store.find_papers_by_tags(&expr).await.unwrap();
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].arxiv_id, "2401.00002");
+
+    // Renaming onto an existing tag merges them
+    assert_eq!(store.rename_tag("nlp", "deep learning").await.unwrap(), 2);
+    let metadata = store
+        .get_paper_metadata("2401.00002")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(metadata.tag_list(), vec!["deep-learning"]);
+    assert_eq!(store.delete_tag("survey").await.unwrap(), 1);
+    store
+        .remove_tag("2401.00003", "deep-learning")
+        .await
+        .unwrap();
+    let tags = store.list_tags().await.unwrap();
+    assert_eq!(
+        tags,
+        vec![TagCount {
+            tag: "deep-learning".to_string(),
+            count: 2
+        }]
+    );
+
+    let reading = store
+        .set_read_state("2401.00001", ReadState::Reading)
+        .await
+        .unwrap();
+    assert_eq!(reading.state(), ReadState::Reading);
+    assert!(!reading.reading_started_at.is_empty());
+    assert!(!reading.read);
+
+    let read = store
+        .set_read_state("2401.00001", ReadState::Read)
+        .await
+        .unwrap();
+    assert!(read.read);
+    assert!(!read.read_at.is_empty());
+    assert_eq!(read.reading_started_at, reading.reading_started_at);
+
+    let unread = store.mark_paper_read("2401.00001", false).await.unwrap();
+    assert_eq!(unread.state(), ReadState::Unread);
+    assert!(unread.read_at.is_empty());
+
+    store.mark_paper_read("2401.00002", true).await.unwrap();
+    let finished = store.papers_in_state(ReadState::Read).await.unwrap();
+    assert_eq!(finished.len(), 1);
+    assert_eq!(finished[0].0.arxiv_id, "2401.00002");
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+#[test]
+fn test_tag_expression_parsing() {
+    let expr = TagExpr::parse("ml (NLP | vision) -survey").unwrap();
+    let tags = |list: &[&str]| list.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+    assert!(expr.matches(&tags(&["ml", "nlp"])));
+    assert!(!expr.matches(&tags(&["ml", "vision", "survey"])));
+    assert!(!expr.matches(&tags(&["nlp"])));
+    assert!(expr.matches_str("vision, ml"));
+
+    assert!(TagExpr::parse("").is_err());
+    assert!(TagExpr::parse("(ml or").is_err());
+    assert!(TagExpr::parse("ml )").is_err());
+}
+
+#[tokio::test]
+async fn test_similarity_job_is_incremental_and_idempotent() {
+    let path = temp_library("local-similarity");
+    let store = LocalStore::open(&path).unwrap();
+    let minilm = model("minilm");
+    let config = SimilarPapersConfig {
+        top_k: 1,
+        min_similarity: 0.5,
+    };
+
+    for (id, vector) in [
+        ("2401.00001", vec![1.0, 0.0]),
+        ("2401.00002", vec![0.6, 0.8]),
+        ("2401.00003", vec![0.0, 1.0]),
+    ] {
+        store
+            .upsert_paper(&paper(id, id), vector, &[], &minilm)
+            .await
+            .unwrap();
+    }
+    let ids: Vec<String> = ["2401.00001", "2401.00002", "2401.00003"]
+        .iter()
+        .map(|id| id.to_string())
+        .collect();
+
+    let job = SimilarityJob::new(&store, minilm.clone(), &config);
+    let report = job.run(&ids, &mut |_| {}).await.unwrap();
+    assert!(report.failed.is_empty());
+    let similar = store.get_similarities("2401.00001").await.unwrap();
+    assert_eq!(similar.len(), 1);
+    assert_eq!(similar[0].paper.arxiv_id, "2401.00002");
+    assert!((similar[0].similarity_score - 0.6).abs() < 1e-4);
+
+    // Nothing changes on a second run
+    let again = job.run(&ids, &mut |_| {}).await.unwrap();
+    assert_eq!(again.linked, 0);
+    assert_eq!(again.neighbors_updated, 0);
+    assert_eq!(again.unchanged, 3);
+
+    // A closer paper displaces the existing neighbor of 2401.00001
+    store
+        .upsert_paper(
+            &paper("2401.00004", "Near twin"),
+            vec![0.99, 0.1],
+            &[],
+            &minilm,
+        )
+        .await
+        .unwrap();
+    let report = job
+        .run(&["2401.00004".to_string()], &mut |_| {})
+        .await
+        .unwrap();
+    assert_eq!(report.linked, 1);
+    assert_eq!(report.neighbors_updated, 1);
+    let similar = store.find_similar_papers("2401.00001", 5).await.unwrap();
+    assert_eq!(similar.len(), 1);
+    assert_eq!(similar[0].arxiv_id, "2401.00004");
+
+    // Pairs under the threshold are never linked
+    let strict = SimilarityJob::new(&store, minilm, &config).with_min_similarity(0.999);
+    strict.run(&ids, &mut |_| {}).await.unwrap();
+    assert!(store
+        .get_similarities("2401.00003")
+        .await
+        .unwrap()
+        .is_empty());
+
+    let _ = std::fs::remove_dir_all(path.parent().unwrap());
+}
+
+/// A small library: two papers with vectors, tags and reading states, a
+/// nested collection, a smart collection and a citation.
+async fn sample_library(store: &LocalStore) {
+    let minilm = model("minilm");
+    for (id, title) in [("2401.00001", "Cited"), ("2401.00002", "Citing")] {
+        store
+            .upsert_paper(&paper(id, title), vec![1.0, 0.0], &[], &minilm)
+            .await
+            .unwrap();
+    }
+    store
+        .set_paper_tags("2401.00001", &["ml".to_string(), "to-cite".to_string()])
+        .await
+        .unwrap();
+    store.mark_paper_saved("2401.00001", true).await.unwrap();
+    store
+        .set_read_state("2401.00002", ReadState::Reading)
+        .await
+        .unwrap();
+
+    store
+        .create_collection(&NewCollection::new("Thesis"))
+        .await
+        .unwrap();
+    store
+        .create_collection(&NewCollection {
+            parent: Some("Thesis".to_string()),
+            ..NewCollection::new("Background")
+        })
+        .await
+        .unwrap();
+    store
+        .add_paper_to_collection("2401.00001", "Background")
+        .await
+        .unwrap();
+    store
+        .create_collection(&NewCollection {
+            filter: Some(SmartFilter {
+                saved: Some(true),
+                ..SmartFilter::default()
+            }),
+            ..NewCollection::new("Saved")
+        })
+        .await
+        .unwrap();
+    store
+        .add_citation("2401.00002", "2401.00001", "as shown in [1]")
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+async fn test_snapshot_round_trips_library() {
+    let source_path = temp_library("snapshot-source");
+    let source = LocalStore::open(&source_path).unwrap();
+    sample_library(&source).await;
+
+    let mut archive = Vec::new();
+    let exported = LibraryExport::new(&source)
+        .write(&mut archive)
+        .await
+        .unwrap();
+    assert_eq!(
+        (
+            exported.papers,
+            exported.vectors,
+            exported.collections,
+            exported.citations
+        ),
+        (2, 2, 3, 1)
+    );
+
+    let lines: Vec<SnapshotRecord> = archive
+        .split(|&b| b == b'\n')
+        .filter(|line| !line.is_empty())
+        .map(|line| serde_json::from_slice(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 7);
+    match &lines[0] {
+        SnapshotRecord::Header(header) => assert_eq!(header.version, SNAPSHOT_VERSION),
+        other => panic!("Expected a header, got {:?}", other),
+    }
+    // Parents come before their children
+    let collections: Vec<&str> = lines
+        .iter()
+        .filter_map(|line| match line {
+            SnapshotRecord::Collection(c) => Some(c.collection.name.as_str()),
+            _ => None,
+        })
+        .collect();
+    let position = |name| collections.iter().position(|c| *c == name).unwrap();
+    assert!(position("Thesis") < position("Background"));
+
+    let target_path = temp_library("snapshot-target");
+    let target = LocalStore::open(&target_path).unwrap();
+    let imported = LibraryImport::new(&target, ConflictPolicy::Skip)
+        .read(archive.as_slice())
+        .await
+        .unwrap();
+    assert_eq!(imported.papers.inserted, 2);
+    assert_eq!(imported.collections_created, 3);
+    assert_eq!(imported.collection_papers, 1);
+    assert_eq!(imported.citations, 1);
+    assert!(imported.failed.is_empty(), "{:?}", imported.failed);
+
+    let metadata = target.require_metadata("2401.00001").await.unwrap();
+    assert_eq!(metadata.tag_list(), vec!["ml", "to-cite"]);
+    assert!(metadata.saved);
+    let reading = target.require_metadata("2401.00002").await.unwrap();
+    assert_eq!(reading.state(), ReadState::Reading);
+    assert_eq!(
+        target.get_paper_vector("2401.00001").await.unwrap(),
+        Some(vec![1.0, 0.0])
+    );
+    let stored = target
+        .get_paper_by_arxiv_id("2401.00001")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(stored.embedded_with(&model("minilm")));
+
+    let background = target.get_collection("Background").await.unwrap().unwrap();
+    assert_eq!(background.parent, "Thesis");
+    let saved = target.get_collection_papers("Saved").await.unwrap();
+    assert_eq!(saved.len(), 1);
+    let cited = target.get_cited_papers("2401.00002").await.unwrap();
+    assert_eq!(cited[0].arxiv_id, "2401.00001");
+
+    // Importing twice changes nothing
+    let again = LibraryImport::new(&target, ConflictPolicy::Skip)
+        .read(archive.as_slice())
+        .await
+        .unwrap();
+    assert_eq!(again.papers.skipped, 2);
+    assert_eq!(again.collections_created, 0);
+
+    let _ = std::fs::remove_dir_all(source_path.parent().unwrap());
+    let _ = std::fs::remove_dir_all(target_path.parent().unwrap());
+}
+
+#[tokio::test]
+async fn test_snapshot_import_conflict_policies() {
+    let source_path = temp_library("snapshot-policy-source");
+    let source = LocalStore::open(&source_path).unwrap();
+    sample_library(&source).await;
+    let mut archive = Vec::new();
+    LibraryExport::new(&source)
+        .write(&mut archive)
+        .await
+        .unwrap();
+
+    let target_path = temp_library("snapshot-policy-target");
+    let target = LocalStore::open(&target_path).unwrap();
+    target
+        .upsert_paper(
+            &paper("2401.00001", "Cited"),
+            vec![0.0, 1.0],
+            &[],
+            &model("minilm"),
+        )
+        .await
+        .unwrap();
+    target
+        .set_paper_tags("2401.00001", &["survey".to_string()])
+        .await
+        .unwrap();
+
+    let skipped = LibraryImport::new(&target, ConflictPolicy::Skip)
+        .read(archive.as_slice())
+        .await
+        .unwrap();
+    assert_eq!((skipped.papers.inserted, skipped.papers.skipped), (1, 1));
+    let metadata = target.require_metadata("2401.00001").await.unwrap();
+    assert_eq!(metadata.tag_list(), vec!["survey"]);
+
+    let merged = LibraryImport::new(&target, ConflictPolicy::MergeTags)
+        .read(archive.as_slice())
+        .await
+        .unwrap();
+    assert_eq!(merged.papers.updated, 1);
+    let metadata = target.require_metadata("2401.00001").await.unwrap();
+    assert_eq!(metadata.tag_list(), vec!["survey", "ml", "to-cite"]);
+    assert!(!metadata.saved);
+
+    let overwritten = LibraryImport::new(&target, ConflictPolicy::Overwrite)
+        .read(archive.as_slice())
+        .await
+        .unwrap();
+    assert_eq!(overwritten.papers.updated, 2);
+    let metadata = target.require_metadata("2401.00001").await.unwrap();
+    assert_eq!(metadata.tag_list(), vec!["ml", "to-cite"]);
+    assert!(metadata.saved);
+    assert_eq!(
+        target.get_paper_vector("2401.00001").await.unwrap(),
+        Some(vec![1.0, 0.0])
+    );
+    assert_eq!(
+        target.get_cited_papers("2401.00002").await.unwrap().len(),
+        1
+    );
+
+    // Snapshots without vectors need an embedder for new papers
+    let mut bare = Vec::new();
+    LibraryExport::new(&source)
+        .without_vectors()
+        .write(&mut bare)
+        .await
+        .unwrap();
+    let empty_path = temp_library("snapshot-policy-bare");
+    let empty = LocalStore::open(&empty_path).unwrap();
+    let report = LibraryImport::new(&empty, ConflictPolicy::Skip)
+        .read(bare.as_slice())
+        .await
+        .unwrap();
+    assert_eq!(report.papers.failed, 2);
+
+    // Overwriting from them replaces everything but the vector
+    let stale_path = temp_library("snapshot-policy-stale");
+    let stale = LocalStore::open(&stale_path).unwrap();
+    let draft = Paper {
+        comment: Some("Draft, do not cite".to_string()),
+        doi: Some("10.1000/draft".to_string()),
+        ..paper("2401.00001", "Draft title")
+    };
+    stale
+        .upsert_paper(&draft, vec![0.0, 1.0], &[], &model("minilm"))
+        .await
+        .unwrap();
+    let report = LibraryImport::new(&stale, ConflictPolicy::Overwrite)
+        .read(bare.as_slice())
+        .await
+        .unwrap();
+    assert_eq!((report.papers.updated, report.papers.failed), (1, 1));
+    let stored = stale
+        .get_paper_by_arxiv_id("2401.00001")
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(stored.title, "Cited");
+    assert!(stored.embedded_with(&model("minilm")));
+    let metadata = stale.require_metadata("2401.00001").await.unwrap();
+    assert_eq!(metadata.abstract_text, "Abstract of Cited");
+    assert_eq!((metadata.comment.as_str(), metadata.doi.as_str()), ("", ""));
+    assert!(metadata.saved);
+    assert_eq!(
+        stale.get_paper_vector("2401.00001").await.unwrap(),
+        Some(vec![0.0, 1.0])
+    );
+
+    // Newer snapshot layouts are refused
+    let newer = format!(
+        "{{\"type\":\"header\",\"format\":\"arxiv-tui-library\",\"version\":{},\
+         \"exported_at\":\"\",\"source\":\"local\",\"papers\":0,\"vectors\":false}}\n",
+        SNAPSHOT_VERSION + 1
+    );
+    assert!(LibraryImport::new(&empty, ConflictPolicy::Skip)
+        .read(newer.as_bytes())
+        .await
+        .is_err());
+
+    let _ = std::fs::remove_dir_all(source_path.parent().unwrap());
+    let _ = std::fs::remove_dir_all(target_path.parent().unwrap());
+    let _ = std::fs::remove_dir_all(empty_path.parent().unwrap());
+    let _ = std::fs::remove_dir_all(stale_path.parent().unwrap());
+}